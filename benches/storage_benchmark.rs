@@ -0,0 +1,82 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use rocksdb::{IteratorMode, Options, DB};
+use tempfile::TempDir;
+use uuid::Uuid;
+
+// Mirrors the transaction store's key layout: `tx_<id>` records plus
+// `sender_<address><timestamp><id>` index entries pointing at them
+
+const SENDER_COUNT: u8 = 100;
+
+/// Benchmark per-sender lookups as the rest of the table grows
+fn sender_lookup_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("sender_lookup");
+
+    // The queried sender always has 10 transactions; everyone else's grow
+    for others_per_sender in [10, 100, 1000].iter() {
+        let (_temp_dir, db) = create_test_db(*others_per_sender);
+        let total = SENDER_COUNT as usize * others_per_sender + 10;
+
+        group.bench_with_input(BenchmarkId::new("full_scan", total), &db, |b, db| {
+            b.iter(|| full_scan(db, black_box(&[0u8; 20])))
+        });
+        group.bench_with_input(BenchmarkId::new("index_prefix", total), &db, |b, db| {
+            b.iter(|| index_lookup(db, black_box(&[0u8; 20])))
+        });
+    }
+
+    group.finish();
+}
+
+fn create_test_db(others_per_sender: usize) -> (TempDir, DB) {
+    let temp_dir = TempDir::new().unwrap();
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    let db = DB::open(&opts, temp_dir.path()).unwrap();
+
+    for sender in 0..=SENDER_COUNT {
+        let count = if sender == 0 { 10 } else { others_per_sender };
+        for timestamp in 0..count as u64 {
+            let id = Uuid::new_v4();
+            let tx_key = format!("tx_{}", id).into_bytes();
+            db.put(&tx_key, test_record(sender, timestamp)).unwrap();
+
+            let mut index_key = b"sender_".to_vec();
+            index_key.extend_from_slice(&[sender; 20]);
+            index_key.extend_from_slice(&timestamp.to_be_bytes());
+            index_key.extend_from_slice(id.as_bytes());
+            db.put(&index_key, &tx_key).unwrap();
+        }
+    }
+
+    (temp_dir, db)
+}
+
+/// A stand-in transaction record: sender address, timestamp and padding
+fn test_record(sender: u8, timestamp: u64) -> Vec<u8> {
+    let mut record = vec![sender; 20];
+    record.extend_from_slice(&timestamp.to_be_bytes());
+    record.resize(200, 0);
+    record
+}
+
+fn full_scan(db: &DB, sender: &[u8; 20]) -> usize {
+    db.iterator(IteratorMode::Start)
+        .map(|item| item.unwrap())
+        .filter(|(key, value)| key.starts_with(b"tx_") && value[..20] == sender[..])
+        .count()
+}
+
+fn index_lookup(db: &DB, sender: &[u8; 20]) -> usize {
+    let mut prefix = b"sender_".to_vec();
+    prefix.extend_from_slice(sender);
+
+    db.prefix_iterator(&prefix)
+        .map(|item| item.unwrap())
+        .take_while(|(key, _)| key.starts_with(&prefix))
+        .filter(|(_, tx_key)| db.get(tx_key).unwrap().is_some())
+        .count()
+}
+
+criterion_group!(benches, sender_lookup_benchmark);
+criterion_main!(benches);
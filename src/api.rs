@@ -1,13 +1,28 @@
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use anyhow::{Result, anyhow};
+use ed25519_dalek::SigningKey;
+use futures::future::join_all;
+use hyper::server::conn::AddrStream;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::body::HttpBody;
+use hyper::{header, Body, Method, Request, Response, Server, StatusCode};
 use serde::{Deserialize, Serialize};
-use tokio::sync::RwLock;
+use tokio::sync::{Notify, RwLock};
+use tokio::task::JoinHandle;
 use uuid::Uuid;
 
-use crate::storage::Storage;
+use crate::storage::{Storage, StorageError};
+use crate::storage::audit_store::{AuditCursor, AuditRecord};
 use crate::mempool::Mempool;
-use crate::consensus::ConsensusEngine;
-use crate::types::{Block, Transaction, BlockHeight, Hash, NodeState};
+use crate::consensus::{ConsensusEngine, ConsensusError};
+use crate::network::{NetworkManager, TransactionBatcher};
+use crate::sync::SyncManager;
+use crate::types::{Address, Block, Transaction, BlockHeight, Hash, NodeState};
+use crate::validation::Validator;
 
 /// JSON-RPC request structure
 #[derive(Debug, Deserialize)]
@@ -29,6 +44,22 @@ pub struct JsonRpcResponse {
     pub id: Option<serde_json::Value>,
 }
 
+impl JsonRpcResponse {
+    /// Build an error response that never reached a method
+    fn error(id: Option<serde_json::Value>, code: i32, message: &str) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            result: None,
+            error: Some(JsonRpcError {
+                code,
+                message: message.to_string(),
+                data: None,
+            }),
+            id,
+        }
+    }
+}
+
 /// JSON-RPC error structure
 #[derive(Debug, Serialize)]
 pub struct JsonRpcError {
@@ -50,6 +81,56 @@ pub struct BlockInfo {
     pub size: usize,
 }
 
+impl BlockInfo {
+    /// Build the API view of a block
+    pub fn from_block(block: &Block) -> Self {
+        Self {
+            height: block.header.height,
+            hash: to_hex(&block.hash()),
+            previous_hash: to_hex(&block.header.previous_hash),
+            timestamp: block.header.timestamp,
+            proposer: block.header.proposer.clone(),
+            transaction_count: block.transactions.len(),
+            size: bincode::serialize(block).unwrap_or_default().len(),
+        }
+    }
+}
+
+/// Validator signature within a commit certificate
+#[derive(Debug, Serialize)]
+pub struct CertificateSignature {
+    pub validator_id: String,
+    pub public_key: String,
+    pub signature: String,
+}
+
+/// Commit certificate proving a block reached finality
+#[derive(Debug, Serialize)]
+pub struct CommitCertificate {
+    pub height: BlockHeight,
+    pub block_hash: String,
+    pub signed_payload: String,
+    pub signatures: Vec<CertificateSignature>,
+}
+
+/// Merkle inclusion proof of a transaction in a block
+#[derive(Debug, Serialize)]
+pub struct TransactionProof {
+    pub height: BlockHeight,
+    pub tx_id: String,
+    pub tx_hash: String,
+    pub merkle_root: String,
+    pub index: usize,
+    pub siblings: Vec<String>,
+}
+
+/// Parameters for `blockchain_getTransactionProof`
+#[derive(Debug, Deserialize)]
+pub struct TransactionProofParams {
+    pub height: BlockHeight,
+    pub tx_id: Uuid,
+}
+
 /// Transaction information for API responses
 #[derive(Debug, Serialize)]
 pub struct TransactionInfo {
@@ -63,6 +144,100 @@ pub struct TransactionInfo {
     pub status: String,
 }
 
+/// Account information for API responses
+#[derive(Debug, Serialize)]
+pub struct AccountInfo {
+    pub balance: u64,
+    pub nonce: u64,
+    pub code_hash: Option<String>,
+}
+
+/// Audit log entry for API responses
+#[derive(Debug, Serialize)]
+pub struct AuditRecordInfo {
+    pub block_height: BlockHeight,
+    pub tx_id: String,
+    pub address: String,
+    pub old_balance: u64,
+    pub new_balance: u64,
+    pub old_nonce: u64,
+    pub new_nonce: u64,
+}
+
+impl AuditRecordInfo {
+    fn from_record(record: &AuditRecord) -> Self {
+        Self {
+            block_height: record.block_height,
+            tx_id: record.tx_id.to_string(),
+            address: to_hex(&record.address),
+            old_balance: record.old_balance,
+            new_balance: record.new_balance,
+            old_nonce: record.old_nonce,
+            new_nonce: record.new_nonce,
+        }
+    }
+}
+
+/// A page of the audit log; pass `next` back as `from_height`/`from_index` to continue
+#[derive(Debug, Serialize)]
+pub struct AuditLogPage {
+    pub records: Vec<AuditRecordInfo>,
+    pub next: Option<AuditCursor>,
+}
+
+/// Largest audit log page served in one call
+const MAX_AUDIT_PAGE_SIZE: usize = 1000;
+
+/// Position and page size for reading the audit log, all optional
+#[derive(Debug, Default, Deserialize)]
+pub struct AuditLogParams {
+    #[serde(default)]
+    pub from_height: BlockHeight,
+    #[serde(default)]
+    pub from_index: u32,
+    pub limit: Option<usize>,
+}
+
+/// Signed transaction submitted over RPC, with binary fields hex-encoded
+///
+/// The id is covered by the signature, so the signer has to choose it.
+#[derive(Debug, Deserialize)]
+pub struct SendTransactionParams {
+    pub id: Uuid,
+    pub from: String,
+    pub to: String,
+    pub amount: u64,
+    pub fee: u64,
+    pub nonce: u64,
+    pub timestamp: u64,
+    pub signature: String,
+    pub public_key: String,
+    #[serde(default)]
+    pub data: String,
+}
+
+impl SendTransactionParams {
+    /// Decode the hex fields into a transaction
+    fn into_transaction(self) -> Result<Transaction, JsonRpcError> {
+        Ok(Transaction {
+            id: self.id,
+            from: parse_fixed_hex("from", &self.from)?,
+            to: parse_fixed_hex("to", &self.to)?,
+            amount: self.amount,
+            fee: self.fee,
+            nonce: self.nonce,
+            timestamp: self.timestamp,
+            signature: parse_fixed_hex("signature", &self.signature)?,
+            public_key: parse_fixed_hex("public_key", &self.public_key)?,
+            data: from_hex(&self.data).ok_or_else(|| JsonRpcError {
+                code: -32602,
+                message: "Invalid params: data must be hex-encoded".to_string(),
+                data: None,
+            })?,
+        })
+    }
+}
+
 /// Node status information
 #[derive(Debug, Serialize)]
 pub struct NodeStatus {
@@ -76,13 +251,195 @@ pub struct NodeStatus {
     pub is_syncing: bool,
 }
 
+/// Outcome of a single self-test check
+#[derive(Debug, Serialize)]
+pub struct SubsystemCheck {
+    pub subsystem: String,
+    pub status: String,
+    pub detail: String,
+}
+
+/// Result of the node self-test diagnostic
+#[derive(Debug, Serialize)]
+pub struct SelfTestReport {
+    pub passed: bool,
+    pub checks: Vec<SubsystemCheck>,
+}
+
+impl SubsystemCheck {
+    fn pass(subsystem: &str, detail: String) -> Self {
+        Self { subsystem: subsystem.to_string(), status: "pass".to_string(), detail }
+    }
+
+    fn fail(subsystem: &str, detail: String) -> Self {
+        Self { subsystem: subsystem.to_string(), status: "fail".to_string(), detail }
+    }
+
+    fn skipped(subsystem: &str, detail: String) -> Self {
+        Self { subsystem: subsystem.to_string(), status: "skipped".to_string(), detail }
+    }
+}
+
+/// Execution budgets for RPC methods
+#[derive(Debug, Clone)]
+pub struct RpcTimeouts {
+    pub read_timeout: Duration,
+    pub write_timeout: Duration,
+    pub method_overrides: HashMap<String, Duration>,
+}
+
+impl Default for RpcTimeouts {
+    fn default() -> Self {
+        Self {
+            read_timeout: Duration::from_secs(5),
+            write_timeout: Duration::from_secs(30),
+            method_overrides: HashMap::new(),
+        }
+    }
+}
+
+impl RpcTimeouts {
+    /// Get the budget for a method, preferring explicit overrides
+    pub fn for_method(&self, method: &str) -> Duration {
+        if let Some(timeout) = self.method_overrides.get(method) {
+            return *timeout;
+        }
+
+        if is_write_method(method) {
+            self.write_timeout
+        } else {
+            self.read_timeout
+        }
+    }
+}
+
+/// Whether a method changes node state rather than only reading it
+fn is_write_method(method: &str) -> bool {
+    method == "blockchain_sendTransaction"
+}
+
+/// Sustained requests per second for a class of methods, with bursts up to `burst`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub per_second: f64,
+    pub burst: u32,
+}
+
+/// Request limits applied to each client; `None` leaves a class of methods unlimited
+#[derive(Debug, Clone, Default)]
+pub struct RateLimitConfig {
+    pub read: Option<RateLimit>,
+    pub write: Option<RateLimit>,
+    /// Share one budget between all connections from an IP instead of limiting each connection
+    pub per_ip: bool,
+}
+
+/// Clients tracked before idle buckets are dropped
+const MAX_TRACKED_BUCKETS: usize = 4096;
+
+/// Largest request body the server reads, in bytes
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+/// Most calls one batch request may carry
+const MAX_BATCH_LEN: usize = 100;
+
+/// Tokens refilled continuously up to the burst size; each request takes one
+#[derive(Debug, Clone, Copy)]
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn full(limit: &RateLimit, now: Instant) -> Self {
+        Self { tokens: limit.burst.max(1) as f64, updated: now }
+    }
+
+    fn refill(&mut self, limit: &RateLimit, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * limit.per_second).min(limit.burst.max(1) as f64);
+        self.updated = now;
+    }
+
+    fn try_take(&mut self, limit: &RateLimit, now: Instant) -> bool {
+        self.refill(limit, now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Token buckets for each client and class of methods
+pub struct RpcRateLimiter {
+    config: RateLimitConfig,
+    buckets: Mutex<HashMap<(SocketAddr, bool), TokenBucket>>,
+}
+
+impl RpcRateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Take a token for a call to `method` from `client`, returning false if none are left
+    pub fn check(&self, client: SocketAddr, method: &str) -> bool {
+        self.check_at(client, method, Instant::now())
+    }
+
+    fn check_at(&self, client: SocketAddr, method: &str, now: Instant) -> bool {
+        let is_write = is_write_method(method);
+        let Some(limit) = self.limit(is_write) else {
+            return true;
+        };
+
+        // Connections are told apart by source port; per-IP limiting ignores it
+        let client = if self.config.per_ip { SocketAddr::new(client.ip(), 0) } else { client };
+
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= MAX_TRACKED_BUCKETS && !buckets.contains_key(&(client, is_write)) {
+            // A bucket that has refilled is indistinguishable from a new one
+            buckets.retain(|(_, is_write), bucket| match self.limit(*is_write) {
+                Some(limit) => {
+                    bucket.refill(&limit, now);
+                    bucket.tokens < limit.burst.max(1) as f64
+                }
+                None => false,
+            });
+        }
+
+        buckets
+            .entry((client, is_write))
+            .or_insert_with(|| TokenBucket::full(&limit, now))
+            .try_take(&limit, now)
+    }
+
+    fn limit(&self, is_write: bool) -> Option<RateLimit> {
+        if is_write { self.config.write } else { self.config.read }
+    }
+}
+
 /// JSON-RPC server for blockchain API
 pub struct JsonRpcServer {
     port: u16,
     storage: Arc<Storage>,
     mempool: Arc<Mempool>,
     consensus: Arc<ConsensusEngine>,
+    network: Option<Arc<NetworkManager>>,
+    gossip: Option<TransactionBatcher>,
+    sync: Option<SyncManager>,
+    validator: Option<Arc<Validator>>,
+    signing_key: Option<SigningKey>,
+    timeouts: RpcTimeouts,
+    rate_limits: RateLimitConfig,
     is_running: Arc<RwLock<bool>>,
+    shutdown_notify: Arc<Notify>,
+    local_addr: Option<SocketAddr>,
+    server_task: Option<JoinHandle<()>>,
 }
 
 impl JsonRpcServer {
@@ -92,65 +449,227 @@ impl JsonRpcServer {
         storage: Arc<Storage>,
         mempool: Arc<Mempool>,
         consensus: Arc<ConsensusEngine>,
+        timeouts: RpcTimeouts,
     ) -> Result<Self> {
         Ok(Self {
             port,
             storage,
             mempool,
             consensus,
+            network: None,
+            gossip: None,
+            sync: None,
+            validator: None,
+            signing_key: None,
+            timeouts,
+            rate_limits: RateLimitConfig::default(),
             is_running: Arc::new(RwLock::new(false)),
+            shutdown_notify: Arc::new(Notify::new()),
+            local_addr: None,
+            server_task: None,
         })
     }
 
+    /// Report peers from a network manager
+    pub fn with_network(mut self, network: Arc<NetworkManager>) -> Self {
+        self.network = Some(network);
+        self
+    }
+
+    /// Gossip accepted transactions to peers
+    pub fn with_gossip(mut self, gossip: TransactionBatcher) -> Self {
+        self.gossip = Some(gossip);
+        self
+    }
+
+    /// Report sync status from a sync manager
+    pub fn with_sync(mut self, sync: SyncManager) -> Self {
+        self.sync = Some(sync);
+        self
+    }
+
+    /// Check submitted transactions with the node's validator, sharing its limits and signature scheme
+    pub fn with_validator(mut self, validator: Arc<Validator>) -> Self {
+        self.validator = Some(validator);
+        self
+    }
+
+    /// Self-test signing with the node's key
+    pub fn with_signing_key(mut self, signing_key: SigningKey) -> Self {
+        self.signing_key = Some(signing_key);
+        self
+    }
+
+    /// Limit how fast each client may call methods
+    pub fn with_rate_limits(mut self, rate_limits: RateLimitConfig) -> Self {
+        self.rate_limits = rate_limits;
+        self
+    }
+
     /// Start the JSON-RPC server
     pub async fn start(&mut self) -> Result<()> {
+        let mut handler = JsonRpcHandler::new(
+            self.storage.clone(),
+            self.mempool.clone(),
+            self.consensus.clone(),
+            self.timeouts.clone(),
+        ).with_rate_limits(self.rate_limits.clone());
+        if let Some(network) = &self.network {
+            handler = handler.with_network(network.clone());
+        }
+        if let Some(gossip) = &self.gossip {
+            handler = handler.with_gossip(gossip.clone());
+        }
+        if let Some(sync) = &self.sync {
+            handler = handler.with_sync(sync.clone());
+        }
+        if let Some(validator) = &self.validator {
+            handler = handler.with_validator(validator.clone());
+        }
+        if let Some(signing_key) = &self.signing_key {
+            handler = handler.with_signing_key(signing_key.clone());
+        }
+        let handler = Arc::new(handler);
+
+        let make_service = make_service_fn(move |conn: &AddrStream| {
+            let handler = handler.clone();
+            let client = conn.remote_addr();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| serve_http(handler.clone(), client, request)))
+            }
+        });
+
+        let addr = SocketAddr::from(([0, 0, 0, 0], self.port));
+        let server = Server::try_bind(&addr)
+            .map_err(|e| anyhow!("Failed to bind JSON-RPC server to {}: {}", addr, e))?
+            .serve(make_service);
+        let local_addr = server.local_addr();
+        self.local_addr = Some(local_addr);
+
         {
             let mut is_running = self.is_running.write().await;
             *is_running = true;
         }
 
-        let storage = self.storage.clone();
-        let mempool = self.mempool.clone();
-        let consensus = self.consensus.clone();
+        // Stop accepting connections once the running flag is cleared
         let is_running = self.is_running.clone();
-        let port = self.port;
-
-        tokio::spawn(async move {
-            // This is a simplified HTTP server implementation
-            // In practice, you'd use jsonrpsee or similar library
-            
-            let handler = JsonRpcHandler::new(storage, mempool, consensus);
-            
-            // Placeholder for actual HTTP server
-            tracing::info!("JSON-RPC server would start on port {}", port);
-            
-            // Keep running until shutdown
+        let shutdown_notify = self.shutdown_notify.clone();
+        let server = server.with_graceful_shutdown(async move {
             loop {
-                tokio::time::sleep(std::time::Duration::from_secs(1)).await;
-                
-                let running = is_running.read().await;
-                if !*running {
+                shutdown_notify.notified().await;
+                if !*is_running.read().await {
                     break;
                 }
             }
         });
 
+        tracing::info!("JSON-RPC server listening on {}", local_addr);
+        self.server_task = Some(tokio::spawn(async move {
+            if let Err(e) = server.await {
+                tracing::error!("JSON-RPC server error: {}", e);
+            }
+        }));
+
         Ok(())
     }
 
+    /// Address the server is bound to, once started
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.local_addr
+    }
+
     /// Shutdown the JSON-RPC server
     pub async fn shutdown(&mut self) -> Result<()> {
-        let mut is_running = self.is_running.write().await;
-        *is_running = false;
+        {
+            let mut is_running = self.is_running.write().await;
+            *is_running = false;
+        }
+        self.shutdown_notify.notify_one();
+
+        // Let in-flight requests finish before returning
+        if let Some(task) = self.server_task.take() {
+            task.await.map_err(|e| anyhow!("JSON-RPC server task failed: {}", e))?;
+        }
         Ok(())
     }
 }
 
+/// Serve one HTTP request carrying a JSON-RPC call or batch
+async fn serve_http(
+    handler: Arc<JsonRpcHandler>,
+    client: SocketAddr,
+    request: Request<Body>,
+) -> Result<Response<Body>, Infallible> {
+    if request.method() != Method::POST {
+        return Ok(Response::builder()
+            .status(StatusCode::METHOD_NOT_ALLOWED)
+            .header(header::ALLOW, "POST")
+            .body(Body::empty())
+            .unwrap());
+    }
+
+    let too_large = || Response::builder()
+        .status(StatusCode::PAYLOAD_TOO_LARGE)
+        .body(Body::from(format!("Request body exceeds {} bytes", MAX_BODY_BYTES)))
+        .unwrap();
+
+    // Refuse a declared oversized body before reading any of it
+    let declared_length = request.headers().get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<usize>().ok());
+    if declared_length.is_some_and(|length| length > MAX_BODY_BYTES) {
+        return Ok(too_large());
+    }
+
+    let body = match read_body(request.into_body(), MAX_BODY_BYTES).await {
+        Ok(Some(body)) => body,
+        Ok(None) => return Ok(too_large()),
+        Err(e) => {
+            return Ok(Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .body(Body::from(format!("Failed to read request body: {}", e)))
+                .unwrap());
+        }
+    };
+
+    let response = match handler.handle_body(client, &body).await {
+        Ok(payload) => Response::builder()
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(payload)),
+        Err(e) => Response::builder()
+            .status(StatusCode::INTERNAL_SERVER_ERROR)
+            .body(Body::from(format!("Failed to encode response: {}", e))),
+    };
+
+    Ok(response.unwrap())
+}
+
+/// Read a request body, or `None` once it grows past `limit` bytes
+async fn read_body(mut body: Body, limit: usize) -> Result<Option<Vec<u8>>, hyper::Error> {
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk?;
+        if bytes.len() + chunk.len() > limit {
+            return Ok(None);
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(Some(bytes))
+}
+
 /// JSON-RPC method handler
 pub struct JsonRpcHandler {
     storage: Arc<Storage>,
     mempool: Arc<Mempool>,
     consensus: Arc<ConsensusEngine>,
+    network: Option<Arc<NetworkManager>>,
+    gossip: Option<TransactionBatcher>,
+    sync: Option<SyncManager>,
+    validator: Arc<Validator>,
+    signing_key: Option<SigningKey>,
+    timeouts: RpcTimeouts,
+    rate_limiter: RpcRateLimiter,
 }
 
 impl JsonRpcHandler {
@@ -158,32 +677,66 @@ impl JsonRpcHandler {
         storage: Arc<Storage>,
         mempool: Arc<Mempool>,
         consensus: Arc<ConsensusEngine>,
+        timeouts: RpcTimeouts,
     ) -> Self {
         Self {
+            validator: Arc::new(Validator::new((*storage).clone())),
+            signing_key: None,
             storage,
             mempool,
             consensus,
+            network: None,
+            gossip: None,
+            sync: None,
+            timeouts,
+            rate_limiter: RpcRateLimiter::new(RateLimitConfig::default()),
         }
     }
 
+    /// Report peers from a network manager
+    pub fn with_network(mut self, network: Arc<NetworkManager>) -> Self {
+        self.network = Some(network);
+        self
+    }
+
+    /// Gossip accepted transactions to peers
+    pub fn with_gossip(mut self, gossip: TransactionBatcher) -> Self {
+        self.gossip = Some(gossip);
+        self
+    }
+
+    /// Report sync status from a sync manager
+    pub fn with_sync(mut self, sync: SyncManager) -> Self {
+        self.sync = Some(sync);
+        self
+    }
+
+    /// Check submitted transactions with the node's validator, sharing its limits and signature scheme
+    pub fn with_validator(mut self, validator: Arc<Validator>) -> Self {
+        self.validator = validator;
+        self
+    }
+
+    /// Self-test signing with the node's key
+    pub fn with_signing_key(mut self, signing_key: SigningKey) -> Self {
+        self.signing_key = Some(signing_key);
+        self
+    }
+
+    /// Limit how fast each client may call methods
+    pub fn with_rate_limits(mut self, rate_limits: RateLimitConfig) -> Self {
+        self.rate_limiter = RpcRateLimiter::new(rate_limits);
+        self
+    }
+
     /// Handle JSON-RPC request
-    pub async fn handle_request(&self, request: JsonRpcRequest) -> JsonRpcResponse {
-        let result = match request.method.as_str() {
-            "blockchain_getBlockByHeight" => self.get_block_by_height(request.params).await,
-            "blockchain_getBlockByHash" => self.get_block_by_hash(request.params).await,
-            "blockchain_getLatestBlock" => self.get_latest_block().await,
-            "blockchain_getTransaction" => self.get_transaction(request.params).await,
-            "blockchain_sendTransaction" => self.send_transaction(request.params).await,
-            "blockchain_getBalance" => self.get_balance(request.params).await,
-            "blockchain_getNodeStatus" => self.get_node_status().await,
-            "blockchain_getPeers" => self.get_peers().await,
-            "blockchain_getMempoolInfo" => self.get_mempool_info().await,
-            _ => Err(JsonRpcError {
-                code: -32601,
-                message: "Method not found".to_string(),
-                data: None,
-            }),
-        };
+    pub async fn handle_request(&self, client: SocketAddr, request: JsonRpcRequest) -> JsonRpcResponse {
+        if !self.rate_limiter.check(client, &request.method) {
+            return JsonRpcResponse::error(request.id, -32005, "Rate limited");
+        }
+
+        let budget = self.timeouts.for_method(&request.method);
+        let result = run_with_timeout(budget, self.dispatch(&request.method, request.params)).await;
 
         match result {
             Ok(result) => JsonRpcResponse {
@@ -201,6 +754,70 @@ impl JsonRpcHandler {
         }
     }
 
+    /// Handle a raw request body holding a single call or a batch of calls
+    pub async fn handle_body(&self, client: SocketAddr, body: &[u8]) -> Result<Vec<u8>, serde_json::Error> {
+        let value: serde_json::Value = match serde_json::from_slice(body) {
+            Ok(value) => value,
+            Err(_) => return serde_json::to_vec(&JsonRpcResponse::error(None, -32700, "Parse error")),
+        };
+
+        match value {
+            serde_json::Value::Array(calls) if calls.is_empty() => {
+                serde_json::to_vec(&JsonRpcResponse::error(None, -32600, "Invalid Request"))
+            }
+            serde_json::Value::Array(calls) if calls.len() > MAX_BATCH_LEN => {
+                let message = format!("Invalid Request: batch of {} calls exceeds the limit of {}", calls.len(), MAX_BATCH_LEN);
+                serde_json::to_vec(&JsonRpcResponse::error(None, -32600, &message))
+            }
+            serde_json::Value::Array(calls) => {
+                let responses = join_all(calls.into_iter().map(|call| self.handle_call(client, call))).await;
+                serde_json::to_vec(&responses)
+            }
+            call => serde_json::to_vec(&self.handle_call(client, call).await),
+        }
+    }
+
+    /// Validate and handle one call from a request body
+    async fn handle_call(&self, client: SocketAddr, call: serde_json::Value) -> JsonRpcResponse {
+        let id = call.get("id").cloned();
+
+        match serde_json::from_value::<JsonRpcRequest>(call) {
+            Ok(request) if request.jsonrpc == "2.0" => self.handle_request(client, request).await,
+            _ => JsonRpcResponse::error(id, -32600, "Invalid Request"),
+        }
+    }
+
+    /// Dispatch a request to its method implementation
+    async fn dispatch(
+        &self,
+        method: &str,
+        params: Option<serde_json::Value>,
+    ) -> Result<serde_json::Value, JsonRpcError> {
+        match method {
+            "blockchain_getBlockByHeight" => self.get_block_by_height(params).await,
+            "blockchain_getBlockByHash" => self.get_block_by_hash(params).await,
+            "blockchain_getLatestBlock" => self.get_latest_block().await,
+            "blockchain_getTransaction" => self.get_transaction(params).await,
+            "blockchain_getTransactionReceipt" => self.get_transaction_receipt(params).await,
+            "blockchain_sendTransaction" => self.send_transaction(params).await,
+            "blockchain_getBalance" => self.get_balance(params).await,
+            "blockchain_getNodeStatus" => self.get_node_status().await,
+            "blockchain_getPeers" => self.get_peers().await,
+            "blockchain_getMempoolInfo" => self.get_mempool_info().await,
+            "blockchain_estimateFee" => self.estimate_fee(params).await,
+            "blockchain_selfTest" => self.run_self_test().await,
+            "blockchain_getReorgStats" => self.get_reorg_stats().await,
+            "blockchain_getCommitCertificate" => self.get_commit_certificate(params).await,
+            "blockchain_getTransactionProof" => self.get_transaction_proof(params).await,
+            "admin_getAuditLog" => self.get_audit_log(params).await,
+            _ => Err(JsonRpcError {
+                code: -32601,
+                message: "Method not found".to_string(),
+                data: None,
+            }),
+        }
+    }
+
     /// Get block by height
     async fn get_block_by_height(&self, params: Option<serde_json::Value>) -> Result<serde_json::Value, JsonRpcError> {
         let height: BlockHeight = params
@@ -221,54 +838,132 @@ impl JsonRpcHandler {
                 })
             }
             Ok(None) => Err(JsonRpcError {
-                code: -32000,
+                code: -32001,
                 message: "Block not found".to_string(),
                 data: None,
             }),
-            Err(e) => Err(JsonRpcError {
-                code: -32603,
-                message: format!("Internal error: {}", e),
-                data: None,
-            }),
+            Err(e) => Err(rpc_error(e)),
         }
     }
 
-    /// Get block by hash
-    async fn get_block_by_hash(&self, params: Option<serde_json::Value>) -> Result<serde_json::Value, JsonRpcError> {
-        let hash_str: String = params
-            .and_then(|p| p.as_str().map(|s| s.to_string()))
+    /// Get the commit certificate for a block
+    async fn get_commit_certificate(&self, params: Option<serde_json::Value>) -> Result<serde_json::Value, JsonRpcError> {
+        let height: BlockHeight = params
+            .and_then(|p| p.as_u64())
             .ok_or_else(|| JsonRpcError {
                 code: -32602,
-                message: "Invalid params: hash required".to_string(),
+                message: "Invalid params: height required".to_string(),
                 data: None,
             })?;
 
-        // Parse hash from hex string (simplified)
-        let hash = self.parse_hash(&hash_str)?;
-
-        match self.storage.blocks().get_block_by_hash(&hash) {
-            Ok(Some(block)) => {
-                let block_info = self.block_to_info(&block);
-                serde_json::to_value(block_info).map_err(|e| JsonRpcError {
-                    code: -32603,
-                    message: format!("Internal error: {}", e),
-                    data: None,
-                })
-            }
-            Ok(None) => Err(JsonRpcError {
-                code: -32000,
+        let block = match self.storage.blocks().get_block(height) {
+            Ok(Some(block)) => block,
+            Ok(None) => return Err(JsonRpcError {
+                code: -32001,
                 message: "Block not found".to_string(),
                 data: None,
             }),
-            Err(e) => Err(JsonRpcError {
-                code: -32603,
-                message: format!("Internal error: {}", e),
-                data: None,
-            }),
-        }
-    }
+            Err(e) => return Err(rpc_error(e)),
+        };
 
-    /// Get latest block
+        let certificate = CommitCertificate {
+            height,
+            block_hash: to_hex(&block.hash()),
+            signed_payload: to_hex(&block.commit_payload()),
+            signatures: block.signatures.iter()
+                .map(|sig| CertificateSignature {
+                    validator_id: sig.validator_id.clone(),
+                    public_key: to_hex(&sig.public_key),
+                    signature: to_hex(&sig.signature),
+                })
+                .collect(),
+        };
+
+        serde_json::to_value(certificate).map_err(|e| JsonRpcError {
+            code: -32603,
+            message: format!("Internal error: {}", e),
+            data: None,
+        })
+    }
+
+    /// Prove a transaction's inclusion in the block at a height
+    async fn get_transaction_proof(&self, params: Option<serde_json::Value>) -> Result<serde_json::Value, JsonRpcError> {
+        let params: TransactionProofParams = params
+            .ok_or_else(|| "height and tx_id required".to_string())
+            .and_then(|params| serde_json::from_value(params).map_err(|e| e.to_string()))
+            .map_err(|e| JsonRpcError {
+                code: -32602,
+                message: format!("Invalid params: {}", e),
+                data: None,
+            })?;
+
+        let block = match self.storage.blocks().get_block(params.height) {
+            Ok(Some(block)) => block,
+            Ok(None) => return Err(JsonRpcError {
+                code: -32001,
+                message: "Block not found".to_string(),
+                data: None,
+            }),
+            Err(e) => return Err(rpc_error(e)),
+        };
+
+        let not_found = || JsonRpcError {
+            code: -32001,
+            message: "Transaction not found in block".to_string(),
+            data: None,
+        };
+        let index = block.transactions.iter()
+            .position(|tx| tx.id == params.tx_id)
+            .ok_or_else(not_found)?;
+        let proof = block.merkle_proof(index).ok_or_else(not_found)?;
+
+        let result = TransactionProof {
+            height: params.height,
+            tx_id: params.tx_id.to_string(),
+            tx_hash: to_hex(&block.transactions[index].hash()),
+            merkle_root: to_hex(&block.header.merkle_root),
+            index: proof.index,
+            siblings: proof.siblings.iter().map(|sibling| to_hex(sibling)).collect(),
+        };
+
+        serde_json::to_value(result).map_err(|e| JsonRpcError {
+            code: -32603,
+            message: format!("Internal error: {}", e),
+            data: None,
+        })
+    }
+
+    /// Get block by hash
+    async fn get_block_by_hash(&self, params: Option<serde_json::Value>) -> Result<serde_json::Value, JsonRpcError> {
+        let hash_str: String = params
+            .and_then(|p| p.as_str().map(|s| s.to_string()))
+            .ok_or_else(|| JsonRpcError {
+                code: -32602,
+                message: "Invalid params: hash required".to_string(),
+                data: None,
+            })?;
+
+        let hash = parse_hash(&hash_str)?;
+
+        match self.storage.blocks().get_block_by_hash(&hash) {
+            Ok(Some(block)) => {
+                let block_info = self.block_to_info(&block);
+                serde_json::to_value(block_info).map_err(|e| JsonRpcError {
+                    code: -32603,
+                    message: format!("Internal error: {}", e),
+                    data: None,
+                })
+            }
+            Ok(None) => Err(JsonRpcError {
+                code: -32001,
+                message: "Block not found".to_string(),
+                data: None,
+            }),
+            Err(e) => Err(rpc_error(e)),
+        }
+    }
+
+    /// Get latest block
     async fn get_latest_block(&self) -> Result<serde_json::Value, JsonRpcError> {
         match self.storage.blocks().get_latest_block() {
             Ok(Some(block)) => {
@@ -280,15 +975,11 @@ impl JsonRpcHandler {
                 })
             }
             Ok(None) => Err(JsonRpcError {
-                code: -32000,
+                code: -32001,
                 message: "No blocks found".to_string(),
                 data: None,
             }),
-            Err(e) => Err(JsonRpcError {
-                code: -32603,
-                message: format!("Internal error: {}", e),
-                data: None,
-            }),
+            Err(e) => Err(rpc_error(e)),
         }
     }
 
@@ -328,36 +1019,155 @@ impl JsonRpcHandler {
                     })
                 } else {
                     Err(JsonRpcError {
-                        code: -32000,
+                        code: -32001,
                         message: "Transaction not found".to_string(),
                         data: None,
                     })
                 }
             }
-            Err(e) => Err(JsonRpcError {
+            Err(e) => Err(rpc_error(e)),
+        }
+    }
+
+    /// Get the receipt of a committed transaction
+    async fn get_transaction_receipt(&self, params: Option<serde_json::Value>) -> Result<serde_json::Value, JsonRpcError> {
+        let tx_id = params
+            .and_then(|p| p.as_str().map(|s| s.to_string()))
+            .ok_or_else(|| JsonRpcError {
+                code: -32602,
+                message: "Invalid params: transaction ID required".to_string(),
+                data: None,
+            })?;
+
+        let tx_id = Uuid::parse_str(&tx_id).map_err(|_| JsonRpcError {
+            code: -32602,
+            message: "Invalid transaction ID format".to_string(),
+            data: None,
+        })?;
+
+        match self.storage.receipts().get_receipt(&tx_id) {
+            Ok(Some(receipt)) => serde_json::to_value(receipt).map_err(|e| JsonRpcError {
                 code: -32603,
                 message: format!("Internal error: {}", e),
                 data: None,
             }),
+            // Pending transactions have no receipt yet
+            Ok(None) => Err(JsonRpcError {
+                code: -32001,
+                message: "Receipt not found".to_string(),
+                data: None,
+            }),
+            Err(e) => Err(rpc_error(e)),
         }
     }
 
     /// Send transaction
     async fn send_transaction(&self, params: Option<serde_json::Value>) -> Result<serde_json::Value, JsonRpcError> {
-        // This would parse transaction from params and add to mempool
-        // Simplified implementation
-        Err(JsonRpcError {
-            code: -32601,
-            message: "Method not implemented".to_string(),
+        let params: SendTransactionParams = params
+            .ok_or_else(|| "transaction required".to_string())
+            .and_then(|params| serde_json::from_value(params).map_err(|e| e.to_string()))
+            .map_err(|e| JsonRpcError {
+                code: -32602,
+                message: format!("Invalid params: {}", e),
+                data: None,
+            })?;
+        let tx = params.into_transaction()?;
+
+        let rejected = |message: String| JsonRpcError {
+            code: -32000,
+            message,
             data: None,
-        })
+        };
+
+        self.validator.quick_check_transaction(&tx)
+            .map_err(|e| rejected(format!("Transaction failed validation: {}", e)))?;
+
+        let already_committed = self.storage.transactions().get_transaction(&tx.id)
+            .map_err(rpc_error)?
+            .is_some();
+        if already_committed || self.mempool.contains(&tx.id) {
+            return Err(rejected(format!("Transaction {} is already known", tx.id)));
+        }
+
+        if tx.fee < self.mempool.min_fee() {
+            return Err(rejected(format!("Fee {} is below the minimum of {}", tx.fee, self.mempool.min_fee())));
+        }
+
+        let tx_id = tx.id;
+        match self.mempool.add_transaction(tx.clone()) {
+            Ok(true) => {
+                if let Some(gossip) = &self.gossip {
+                    gossip.push(tx);
+                }
+                Ok(serde_json::Value::String(tx_id.to_string()))
+            }
+            Ok(false) if self.mempool.is_full() => Err(rejected("Mempool is full".to_string())),
+            Ok(false) => match self.mempool.get_by_nonce(&tx.from, tx.nonce) {
+                Some(winner) => Err(rejected(format!(
+                    "Nonce {} is already pending as {} with fee {}", tx.nonce, winner.id, winner.fee
+                ))),
+                None => Err(rejected("Transaction rejected by mempool".to_string())),
+            },
+            Err(e) => Err(rpc_error(e)),
+        }
     }
 
     /// Get balance
+    ///
+    /// Takes an address, or `{"address", "min_confirmations"}` to only count blocks
+    /// buried at least that deep. Reading past state needs an archive node.
     async fn get_balance(&self, params: Option<serde_json::Value>) -> Result<serde_json::Value, JsonRpcError> {
-        // This would get account balance from state
-        // Simplified implementation
-        serde_json::to_value(0u64).map_err(|e| JsonRpcError {
+        let invalid_params = |message: &str| JsonRpcError {
+            code: -32602,
+            message: format!("Invalid params: {}", message),
+            data: None,
+        };
+
+        let (address, min_confirmations) = match &params {
+            Some(serde_json::Value::String(address)) => (address.as_str(), None),
+            Some(serde_json::Value::Object(fields)) => {
+                let address = fields.get("address")
+                    .and_then(|address| address.as_str())
+                    .ok_or_else(|| invalid_params("address required"))?;
+                let min_confirmations = match fields.get("min_confirmations") {
+                    None | Some(serde_json::Value::Null) => None,
+                    Some(value) => Some(value.as_u64()
+                        .ok_or_else(|| invalid_params("min_confirmations must be a non-negative integer"))?),
+                };
+                (address, min_confirmations)
+            }
+            _ => return Err(invalid_params("address required")),
+        };
+        let address = parse_address(address)?;
+
+        // The tip and the account are read at one point in time, so a block
+        // committing mid-request can't pair a new height with old state
+        let snapshot = self.storage.snapshot();
+        let account = match min_confirmations {
+            None | Some(0) => self.storage.state().get_account_in(&snapshot, &address).map_err(rpc_error)?,
+            Some(confirmations) => {
+                let latest = self.storage.get_latest_height_in(&snapshot).map_err(rpc_error)?;
+                let height = latest
+                    .and_then(|latest| latest.checked_sub(confirmations))
+                    .ok_or_else(|| JsonRpcError {
+                        code: -32000,
+                        message: format!("No block has {} confirmations yet", confirmations),
+                        data: None,
+                    })?;
+
+                self.storage.state().get_account_at_in(&snapshot, &address, height).map_err(rpc_error)?
+            }
+        };
+
+        // Accounts that were never touched read as empty
+        let account = account.unwrap_or_default();
+        let info = AccountInfo {
+            balance: account.balance,
+            nonce: account.nonce,
+            code_hash: account.code_hash.map(|hash| to_hex(&hash)),
+        };
+
+        serde_json::to_value(info).map_err(|e| JsonRpcError {
             code: -32603,
             message: format!("Internal error: {}", e),
             data: None,
@@ -368,16 +1178,24 @@ impl JsonRpcHandler {
     async fn get_node_status(&self) -> Result<serde_json::Value, JsonRpcError> {
         let stats = self.consensus.get_stats();
         let mempool_stats = self.mempool.get_stats();
+        let connected_peers = match &self.network {
+            Some(network) => network.get_stats().await.connected_peers,
+            None => 0,
+        };
+        let is_syncing = match &self.sync {
+            Some(sync) => sync.is_lagging().map_err(rpc_error)?,
+            None => false,
+        };
 
         let status = NodeStatus {
-            node_id: "blockchain-node".to_string(), // Would get from config
-            is_validator: true, // Would get from config
+            node_id: self.consensus.node_id().clone(),
+            is_validator: self.consensus.is_validator(),
             current_height: stats.current_height,
             current_view: stats.current_view,
             current_round: stats.current_round,
-            connected_peers: 0, // Would get from network
+            connected_peers,
             mempool_size: mempool_stats.total_transactions,
-            is_syncing: false, // Would determine from sync status
+            is_syncing,
         };
 
         serde_json::to_value(status).map_err(|e| JsonRpcError {
@@ -389,8 +1207,12 @@ impl JsonRpcHandler {
 
     /// Get peers
     async fn get_peers(&self) -> Result<serde_json::Value, JsonRpcError> {
-        // Would get from network manager
-        serde_json::to_value(Vec::<String>::new()).map_err(|e| JsonRpcError {
+        let peers = match &self.network {
+            Some(network) => network.get_connected_peers().await,
+            None => Vec::new(),
+        };
+
+        serde_json::to_value(peers).map_err(|e| JsonRpcError {
             code: -32603,
             message: format!("Internal error: {}", e),
             data: None,
@@ -407,25 +1229,227 @@ impl JsonRpcHandler {
         })
     }
 
+    /// Suggest a fee for inclusion within a target number of blocks
+    async fn estimate_fee(&self, params: Option<serde_json::Value>) -> Result<serde_json::Value, JsonRpcError> {
+        let target_blocks = params
+            .and_then(|p| p.as_u64())
+            .filter(|target| *target > 0)
+            .ok_or_else(|| JsonRpcError {
+                code: -32602,
+                message: "Invalid params: target block count of at least 1 required".to_string(),
+                data: None,
+            })?;
+
+        let fee = self.mempool.estimate_fee(target_blocks, self.consensus.max_transactions_per_block());
+        Ok(serde_json::json!({
+            "target_blocks": target_blocks,
+            "fee": fee,
+            "pending": self.mempool.size(),
+        }))
+    }
+
+    /// Get observed reorg depths
+    async fn get_reorg_stats(&self) -> Result<serde_json::Value, JsonRpcError> {
+        let stats = self.consensus.get_reorg_stats();
+        serde_json::to_value(stats).map_err(|e| JsonRpcError {
+            code: -32603,
+            message: format!("Internal error: {}", e),
+            data: None,
+        })
+    }
+
+    /// Get a page of the state mutation audit log
+    async fn get_audit_log(&self, params: Option<serde_json::Value>) -> Result<serde_json::Value, JsonRpcError> {
+        if !self.storage.audit_log_enabled() {
+            return Err(JsonRpcError {
+                code: -32000,
+                message: "Audit log is not enabled on this node".to_string(),
+                data: None,
+            });
+        }
+
+        let params: AuditLogParams = match params {
+            Some(params) => serde_json::from_value(params).map_err(|e| JsonRpcError {
+                code: -32602,
+                message: format!("Invalid params: {}", e),
+                data: None,
+            })?,
+            None => AuditLogParams::default(),
+        };
+        let from = AuditCursor { height: params.from_height, index: params.from_index };
+        let limit = params.limit.unwrap_or(100).min(MAX_AUDIT_PAGE_SIZE);
+
+        let page = self.storage.audit().get_records(from, limit).map_err(rpc_error)?;
+
+        let page = AuditLogPage {
+            records: page.records.iter().map(AuditRecordInfo::from_record).collect(),
+            next: page.next,
+        };
+        serde_json::to_value(page).map_err(|e| JsonRpcError {
+            code: -32603,
+            message: format!("Internal error: {}", e),
+            data: None,
+        })
+    }
+
+    /// Run the self-test diagnostic
+    async fn run_self_test(&self) -> Result<serde_json::Value, JsonRpcError> {
+        let report = self.self_test().await;
+        serde_json::to_value(report).map_err(|e| JsonRpcError {
+            code: -32603,
+            message: format!("Internal error: {}", e),
+            data: None,
+        })
+    }
+
+    /// Exercise each core subsystem and report a pass/fail per component
+    pub async fn self_test(&self) -> SelfTestReport {
+        let checks = vec![
+            self.check_storage(),
+            Self::check_hashing(),
+            self.check_signing(),
+            self.check_mempool(),
+            self.check_consensus(),
+            self.check_network().await,
+        ];
+
+        SelfTestReport {
+            passed: checks.iter().all(|check| check.status != "fail"),
+            checks,
+        }
+    }
+
+    /// Write and read back a scratch metadata entry
+    fn check_storage(&self) -> SubsystemCheck {
+        let nonce = Uuid::new_v4().to_string();
+
+        if let Err(e) = self.storage.put_metadata("self_test", &nonce) {
+            return SubsystemCheck::fail("storage", format!("Write failed: {}", e));
+        }
+
+        match self.storage.get_metadata::<String>("self_test") {
+            Ok(Some(value)) if value == nonce => {
+                SubsystemCheck::pass("storage", "Scratch entry round-tripped".to_string())
+            }
+            Ok(_) => SubsystemCheck::fail("storage", "Scratch entry mismatch".to_string()),
+            Err(e) => SubsystemCheck::fail("storage", format!("Read failed: {}", e)),
+        }
+    }
+
+    /// Hash a known vector and compare against the expected SHA-256 digest
+    fn check_hashing() -> SubsystemCheck {
+        use sha2::{Digest, Sha256};
+
+        let digest: Hash = Sha256::digest(b"abc").into();
+        let expected: Hash = [
+            0xba, 0x78, 0x16, 0xbf, 0x8f, 0x01, 0xcf, 0xea, 0x41, 0x41, 0x40, 0xde, 0x5d, 0xae, 0x22, 0x23,
+            0xb0, 0x03, 0x61, 0xa3, 0x96, 0x17, 0x7a, 0x9c, 0xb4, 0x10, 0xff, 0x61, 0xf2, 0x00, 0x15, 0xad,
+        ];
+
+        if digest == expected {
+            SubsystemCheck::pass("hashing", "SHA-256 test vector matched".to_string())
+        } else {
+            SubsystemCheck::fail("hashing", "SHA-256 test vector mismatch".to_string())
+        }
+    }
+
+    /// Sign and verify a message with the node's key, which must be the one its validator entry names
+    fn check_signing(&self) -> SubsystemCheck {
+        use ed25519_dalek::{Signer, Verifier};
+
+        let Some(signing_key) = &self.signing_key else {
+            return SubsystemCheck::skipped("signing", "No node key configured".to_string());
+        };
+
+        let message = b"blockchain-node self test";
+        let signature = signing_key.sign(message);
+        if let Err(e) = signing_key.verifying_key().verify(message, &signature) {
+            return SubsystemCheck::fail("signing", format!("Verification failed: {}", e));
+        }
+
+        let public_key = signing_key.verifying_key().to_bytes();
+        match self.consensus.validator_key(self.consensus.node_id()) {
+            Some(registered) if registered != public_key => SubsystemCheck::fail(
+                "signing",
+                format!("Node key {} is not the validator key {}", to_hex(&public_key), to_hex(&registered)),
+            ),
+            _ => SubsystemCheck::pass("signing", format!("Ed25519 round-trip verified with node key {}", to_hex(&public_key))),
+        }
+    }
+
+    /// Check that mempool bookkeeping is consistent
+    fn check_mempool(&self) -> SubsystemCheck {
+        let stats = self.mempool.get_stats();
+        let size = self.mempool.size();
+
+        if stats.total_transactions == size {
+            SubsystemCheck::pass("mempool", format!("{} pending transactions", size))
+        } else {
+            SubsystemCheck::fail(
+                "mempool",
+                format!("Stats report {} transactions but pool holds {}", stats.total_transactions, size),
+            )
+        }
+    }
+
+    /// Check that consensus has not fallen behind storage
+    fn check_consensus(&self) -> SubsystemCheck {
+        let stats = self.consensus.get_stats();
+
+        match self.storage.get_latest_height() {
+            Ok(latest_height) => {
+                let latest_height = latest_height.unwrap_or(0);
+                if stats.current_height <= latest_height {
+                    SubsystemCheck::pass(
+                        "consensus",
+                        format!("view {} round {} height {}", stats.current_view, stats.current_round, latest_height),
+                    )
+                } else {
+                    SubsystemCheck::fail(
+                        "consensus",
+                        format!("Consensus height {} ahead of storage {}", stats.current_height, latest_height),
+                    )
+                }
+            }
+            Err(e) => SubsystemCheck::fail("consensus", format!("Failed to read chain height: {}", e)),
+        }
+    }
+
+    /// Check the network manager's peer count agrees with its connected peers
+    async fn check_network(&self) -> SubsystemCheck {
+        let Some(network) = &self.network else {
+            return SubsystemCheck::skipped("network", "Network manager not attached to RPC handler".to_string());
+        };
+
+        let stats = network.get_stats().await;
+        let peers = network.get_connected_peers().await.len();
+        if stats.connected_peers == peers {
+            SubsystemCheck::pass(
+                "network",
+                format!(
+                    "{} connected peers, {} messages sent, {} received",
+                    peers, stats.total_messages_sent, stats.total_messages_received
+                ),
+            )
+        } else {
+            SubsystemCheck::fail(
+                "network",
+                format!("Stats report {} peers but {} are connected", stats.connected_peers, peers),
+            )
+        }
+    }
+
     /// Convert block to API info
     fn block_to_info(&self, block: &Block) -> BlockInfo {
-        BlockInfo {
-            height: block.header.height,
-            hash: format!("{:x?}", block.hash()),
-            previous_hash: format!("{:x?}", block.header.previous_hash),
-            timestamp: block.header.timestamp,
-            proposer: block.header.proposer.clone(),
-            transaction_count: block.transactions.len(),
-            size: bincode::serialize(block).unwrap_or_default().len(),
-        }
+        BlockInfo::from_block(block)
     }
 
     /// Convert transaction to API info
     fn transaction_to_info(&self, tx: &Transaction, status: &str) -> TransactionInfo {
         TransactionInfo {
             id: tx.id.to_string(),
-            from: format!("{:x?}", tx.from),
-            to: format!("{:x?}", tx.to),
+            from: to_hex(&tx.from),
+            to: to_hex(&tx.to),
             amount: tx.amount,
             fee: tx.fee,
             nonce: tx.nonce,
@@ -433,10 +1457,1021 @@ impl JsonRpcHandler {
             status: status.to_string(),
         }
     }
+}
+
+/// Encode bytes as a `0x`-prefixed lowercase hex string
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    format!("0x{}", hex::encode(bytes))
+}
+
+/// Decode a hex string, with or without a `0x` prefix
+pub(crate) fn from_hex(value: &str) -> Option<Vec<u8>> {
+    hex::decode(value.strip_prefix("0x").unwrap_or(value)).ok()
+}
+
+/// Parse a hex-encoded 32-byte hash
+fn parse_hash(hash: &str) -> Result<Hash, JsonRpcError> {
+    parse_fixed_hex("hash", hash)
+}
+
+/// Parse a hex-encoded 20-byte address
+fn parse_address(address: &str) -> Result<Address, JsonRpcError> {
+    parse_fixed_hex("address", address)
+}
+
+/// Parse a hex-encoded field of exactly `N` bytes
+fn parse_fixed_hex<const N: usize>(field: &str, value: &str) -> Result<[u8; N], JsonRpcError> {
+    from_hex(value)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| JsonRpcError {
+            code: -32602,
+            message: format!("Invalid params: {} must be {} hex-encoded bytes", field, N),
+            data: None,
+        })
+}
+
+/// Map a storage or consensus failure to a JSON-RPC error
+///
+/// Missing data is the caller's problem, while corrupt data and database
+/// failures get their own codes so operators can tell them from bugs.
+fn rpc_error(error: anyhow::Error) -> JsonRpcError {
+    let code = match error.downcast_ref::<StorageError>() {
+        Some(StorageError::NotFound(_)) => -32001,
+        Some(StorageError::HistoryUnavailable(_)) => -32000,
+        Some(StorageError::Corrupt { .. }) => -32007,
+        Some(StorageError::Database { .. }) => -32008,
+        Some(_) => -32603,
+        None if error.is::<rocksdb::Error>() => -32008,
+        None => match error.downcast_ref::<ConsensusError>() {
+            Some(ConsensusError::UnknownBlock(_)) => -32001,
+            _ => -32603,
+        },
+    };
+
+    let message = match code {
+        -32603 => format!("Internal error: {}", error),
+        _ => error.to_string(),
+    };
+    JsonRpcError { code, message, data: None }
+}
+
+/// Run a method handler, cancelling it if it exceeds its budget
+async fn run_with_timeout<F>(budget: Duration, handler: F) -> Result<serde_json::Value, JsonRpcError>
+where
+    F: std::future::Future<Output = Result<serde_json::Value, JsonRpcError>>,
+{
+    match tokio::time::timeout(budget, handler).await {
+        Ok(result) => result,
+        Err(_) => Err(JsonRpcError {
+            code: -32006,
+            message: format!("Request timed out after {}ms", budget.as_millis()),
+            data: None,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::{ConsensusConfig, ViewChangeTimeout};
+    use crate::mempool::MempoolConfig;
+    use tempfile::TempDir;
+
+    fn create_test_components(storage: Storage, mempool_config: MempoolConfig)
+        -> (Arc<Storage>, Arc<Mempool>, Arc<ConsensusEngine>)
+    {
+        let storage = Arc::new(storage);
+        let mempool = Arc::new(Mempool::new(mempool_config));
+        let consensus_config = ConsensusConfig {
+            node_id: "validator-1".to_string(),
+            is_validator: true,
+            validator_set: vec!["validator-1".to_string()],
+            block_time_ms: 1000,
+            view_timeout: ViewChangeTimeout::default(),
+            max_block_size: 1024 * 1024,
+            max_transactions_per_block: 100,
+            leader_shuffle_epoch_length: 0,
+            dynamic_block_size: None,
+            block_interval: None,
+            backup_proposer_timeout_ms: None,
+            empty_block_timeout_ms: None,
+            min_transactions_per_block: None,
+            pbft_log_retention: 100,
+            dev_mode: false,
+            vrf_leader_election: false,
+        };
+        let consensus = Arc::new(
+            ConsensusEngine::new(consensus_config, storage.clone(), mempool.clone()).unwrap()
+        );
+
+        (storage, mempool, consensus)
+    }
+
+    fn create_test_handler(storage: Storage) -> JsonRpcHandler {
+        let (storage, mempool, consensus) = create_test_components(storage, MempoolConfig::default());
+        JsonRpcHandler::new(storage, mempool, consensus, RpcTimeouts::default())
+    }
+
+    #[tokio::test]
+    async fn test_commit_certificate_verifies_offline() {
+        use crate::types::{BlockHeader, ValidatorSignature};
+        use ed25519_dalek::{Signer, Signature, SigningKey, Verifier, VerifyingKey};
+
+        let temp_dir = TempDir::new().unwrap();
+        let handler = create_test_handler(Storage::new(temp_dir.path()).unwrap());
+
+        let mut block = Block {
+            header: BlockHeader {
+                height: 1,
+                previous_hash: [0; 32],
+                merkle_root: [0; 32],
+                state_root: [0; 32],
+                timestamp: 1_000,
+                proposer: "validator-1".to_string(),
+                round: 0,
+                view: 0,
+                vrf_proof: None,
+            },
+            transactions: vec![],
+            signatures: vec![],
+        };
+
+        // Known validator set: 3 of 4 sign the commit vote
+        let keys: Vec<(String, SigningKey)> = (1..=4)
+            .map(|i| (format!("validator-{}", i), SigningKey::from_bytes(&[i as u8; 32])))
+            .collect();
+        let payload = block.commit_payload();
+        for (validator_id, key) in keys.iter().take(3) {
+            block.signatures.push(ValidatorSignature {
+                validator_id: validator_id.clone(),
+                signature: key.sign(&payload).to_bytes(),
+                public_key: key.verifying_key().to_bytes(),
+            });
+        }
+        handler.storage.store_block(&block).unwrap();
+
+        let result = handler
+            .dispatch("blockchain_getCommitCertificate", Some(serde_json::json!(1)))
+            .await
+            .unwrap();
+
+        // Verify against the known validator keys, not the keys echoed in the response
+        let signed_payload = from_hex(result["signed_payload"].as_str().unwrap()).unwrap();
+        assert_eq!(signed_payload, payload.to_vec());
+
+        let signatures = result["signatures"].as_array().unwrap();
+        let mut valid = 0;
+        for entry in signatures {
+            let validator_id = entry["validator_id"].as_str().unwrap();
+            let (_, key) = keys.iter().find(|(id, _)| id == validator_id).unwrap();
+            let verifying_key = VerifyingKey::from_bytes(&key.verifying_key().to_bytes()).unwrap();
+
+            let sig_bytes: [u8; 64] = from_hex(entry["signature"].as_str().unwrap()).unwrap().try_into().unwrap();
+            if verifying_key.verify(&signed_payload, &Signature::from_bytes(&sig_bytes)).is_ok() {
+                valid += 1;
+            }
+        }
+        assert!(valid >= (keys.len() * 2 / 3) + 1);
+
+        let missing = handler
+            .dispatch("blockchain_getCommitCertificate", Some(serde_json::json!(99)))
+            .await;
+        assert!(missing.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_transaction_receipt() {
+        use crate::types::BlockHeader;
+
+        let temp_dir = TempDir::new().unwrap();
+        let handler = create_test_handler(Storage::new(temp_dir.path()).unwrap());
+        let sender = [1u8; 20];
+        handler.storage.state().set_balance(&sender, 1_000).unwrap();
+
+        let transactions: Vec<Transaction> = (1..=2)
+            .map(|nonce| Transaction {
+                id: Uuid::new_v4(),
+                from: sender,
+                to: [2u8; 20],
+                amount: 100,
+                fee: nonce * 5,
+                nonce,
+                timestamp: 1_000,
+                signature: [0u8; 64],
+                public_key: [0u8; 32],
+                data: vec![],
+            })
+            .collect();
+        let block = Block {
+            header: BlockHeader {
+                height: 1,
+                previous_hash: [0; 32],
+                merkle_root: [0; 32],
+                state_root: [0; 32],
+                timestamp: 1_000,
+                proposer: "validator-1".to_string(),
+                round: 0,
+                view: 0,
+                vrf_proof: None,
+            },
+            transactions,
+            signatures: vec![],
+        };
+        handler.storage.commit_block(&block).unwrap();
+
+        let tx = &block.transactions[1];
+        let receipt = handler
+            .dispatch("blockchain_getTransactionReceipt", Some(serde_json::json!(tx.id.to_string())))
+            .await
+            .unwrap();
+        assert_eq!(receipt["tx_id"], tx.id.to_string());
+        assert_eq!(receipt["block_height"], 1);
+        assert_eq!(receipt["index"], 1);
+        assert_eq!(receipt["status"], "success");
+        assert_eq!(receipt["fee_paid"], 10);
+
+        let missing = handler
+            .dispatch("blockchain_getTransactionReceipt", Some(serde_json::json!(Uuid::new_v4().to_string())))
+            .await
+            .unwrap_err();
+        assert_eq!(missing.code, -32001);
+    }
+
+    #[tokio::test]
+    async fn test_transaction_proof_verifies_against_root() {
+        use crate::types::{address_from_public_key, verify_merkle_proof, BlockHeader, MerkleProof};
+
+        let temp_dir = TempDir::new().unwrap();
+        let handler = create_test_handler(Storage::new(temp_dir.path()).unwrap());
+
+        let signing_key = SigningKey::from_bytes(&[1u8; 32]);
+        let transactions: Vec<Transaction> = (0..3)
+            .map(|nonce| {
+                let mut tx = Transaction {
+                    id: Uuid::new_v4(),
+                    from: address_from_public_key(&signing_key.verifying_key().to_bytes()),
+                    to: [2u8; 20],
+                    amount: 100,
+                    fee: 1,
+                    nonce,
+                    timestamp: 1_000,
+                    signature: [0u8; 64],
+                    public_key: [0u8; 32],
+                    data: vec![],
+                };
+                tx.sign(&signing_key);
+                tx
+            })
+            .collect();
+
+        let mut block = Block {
+            header: BlockHeader {
+                height: 1,
+                previous_hash: [0; 32],
+                merkle_root: [0; 32],
+                state_root: [0; 32],
+                timestamp: 1_000,
+                proposer: "validator-1".to_string(),
+                round: 0,
+                view: 0,
+                vrf_proof: None,
+            },
+            transactions,
+            signatures: vec![],
+        };
+        block.header.merkle_root = block.calculate_merkle_root();
+        handler.storage.store_block(&block).unwrap();
+
+        let tx = &block.transactions[2];
+        let result = handler
+            .dispatch("blockchain_getTransactionProof", Some(serde_json::json!({"height": 1, "tx_id": tx.id})))
+            .await
+            .unwrap();
+
+        let proof = MerkleProof {
+            index: result["index"].as_u64().unwrap() as usize,
+            siblings: result["siblings"].as_array().unwrap()
+                .iter()
+                .map(|sibling| from_hex(sibling.as_str().unwrap()).unwrap().try_into().unwrap())
+                .collect(),
+        };
+        assert_eq!(result["merkle_root"], to_hex(&block.header.merkle_root));
+        assert!(verify_merkle_proof(&tx.hash(), &proof, &block.header.merkle_root));
+
+        let mut tampered = proof.clone();
+        tampered.siblings[1][31] ^= 1;
+        assert!(!verify_merkle_proof(&tx.hash(), &tampered, &block.header.merkle_root));
+
+        let missing = handler
+            .dispatch("blockchain_getTransactionProof", Some(serde_json::json!({"height": 1, "tx_id": Uuid::new_v4()})))
+            .await
+            .unwrap_err();
+        assert_eq!(missing.code, -32001);
+    }
+
+    #[tokio::test]
+    async fn test_self_test_healthy_node() {
+        let temp_dir = TempDir::new().unwrap();
+        let handler = create_test_handler(Storage::new(temp_dir.path()).unwrap());
+
+        let report = handler.self_test().await;
+        assert!(report.passed);
+        assert!(report.checks.iter().all(|check| check.status != "fail"));
+    }
+
+    #[tokio::test]
+    async fn test_self_test_signs_with_the_node_key() {
+        let temp_dir = TempDir::new().unwrap();
+        let (storage, mempool, consensus) =
+            create_test_components(Storage::new(temp_dir.path()).unwrap(), MempoolConfig::default());
+        let registered = SigningKey::from_bytes(&[1u8; 32]);
+        let consensus = Arc::new(
+            Arc::try_unwrap(consensus).ok().unwrap().with_validator_keys(HashMap::from([
+                ("validator-1".to_string(), registered.verifying_key().to_bytes()),
+            ])),
+        );
+        let handler = |signing_key: Option<SigningKey>| {
+            let handler = JsonRpcHandler::new(storage.clone(), mempool.clone(), consensus.clone(), RpcTimeouts::default());
+            match signing_key {
+                Some(signing_key) => handler.with_signing_key(signing_key),
+                None => handler,
+            }
+        };
+        let signing_status = |report: SelfTestReport| {
+            report.checks.into_iter().find(|check| check.subsystem == "signing").unwrap().status
+        };
+
+        assert_eq!(signing_status(handler(Some(registered)).self_test().await), "pass");
+        assert_eq!(signing_status(handler(None).self_test().await), "skipped");
+
+        // A key other than the one genesis registers for this validator is a misconfiguration
+        let report = handler(Some(SigningKey::from_bytes(&[2u8; 32]))).self_test().await;
+        assert!(!report.passed);
+        assert_eq!(signing_status(report), "fail");
+    }
+
+    #[tokio::test]
+    async fn test_self_test_reports_read_only_storage() {
+        let temp_dir = TempDir::new().unwrap();
+        drop(Storage::new(temp_dir.path()).unwrap());
+        let handler = create_test_handler(Storage::open_read_only(temp_dir.path()).unwrap())
+            .with_signing_key(SigningKey::from_bytes(&[1u8; 32]));
+
+        let report = handler.self_test().await;
+        assert!(!report.passed);
+
+        let storage_check = report.checks.iter().find(|check| check.subsystem == "storage").unwrap();
+        assert_eq!(storage_check.status, "fail");
+        let signing_check = report.checks.iter().find(|check| check.subsystem == "signing").unwrap();
+        assert_eq!(signing_check.status, "pass");
+    }
+
+    #[test]
+    fn test_timeouts_per_method() {
+        let mut timeouts = RpcTimeouts::default();
+        timeouts.method_overrides.insert(
+            "blockchain_getBlockByHeight".to_string(),
+            Duration::from_millis(250),
+        );
+
+        assert_eq!(timeouts.for_method("blockchain_sendTransaction"), timeouts.write_timeout);
+        assert_eq!(timeouts.for_method("blockchain_getLatestBlock"), timeouts.read_timeout);
+        assert_eq!(timeouts.for_method("blockchain_getBlockByHeight"), Duration::from_millis(250));
+    }
+
+    #[test]
+    fn test_rate_limit_bucket_refills() {
+        let limiter = RpcRateLimiter::new(RateLimitConfig {
+            read: Some(RateLimit { per_second: 10.0, burst: 3 }),
+            write: None,
+            per_ip: false,
+        });
+        let client: SocketAddr = "10.0.0.1:4000".parse().unwrap();
+        let now = Instant::now();
+
+        for _ in 0..3 {
+            assert!(limiter.check_at(client, "blockchain_getLatestBlock", now));
+        }
+        assert!(!limiter.check_at(client, "blockchain_getLatestBlock", now));
+        // Writes are unlimited and other connections have their own budget
+        assert!(limiter.check_at(client, "blockchain_sendTransaction", now));
+        assert!(limiter.check_at("10.0.0.1:4001".parse().unwrap(), "blockchain_getLatestBlock", now));
+
+        // One token comes back every 100ms
+        let later = now + Duration::from_millis(100);
+        assert!(limiter.check_at(client, "blockchain_getLatestBlock", later));
+        assert!(!limiter.check_at(client, "blockchain_getLatestBlock", later));
+    }
+
+    #[tokio::test]
+    async fn test_burst_past_rate_limit_is_rejected() {
+        let temp_dir = TempDir::new().unwrap();
+        let (storage, mempool, consensus) =
+            create_test_components(Storage::new(temp_dir.path()).unwrap(), MempoolConfig::default());
+        let handler = JsonRpcHandler::new(storage, mempool, consensus, RpcTimeouts::default())
+            .with_rate_limits(RateLimitConfig {
+                read: Some(RateLimit { per_second: 20.0, burst: 2 }),
+                write: None,
+                per_ip: true,
+            });
+
+        let request = || JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            method: "blockchain_getNodeStatus".to_string(),
+            params: None,
+            id: Some(serde_json::json!(1)),
+        };
+        let connection = |port| SocketAddr::from(([127, 0, 0, 1], port));
+
+        assert!(handler.handle_request(connection(5000), request()).await.error.is_none());
+        assert!(handler.handle_request(connection(5001), request()).await.error.is_none());
+        // Per-IP limiting shares the budget between the two connections
+        let limited = handler.handle_request(connection(5002), request()).await;
+        assert_eq!(limited.error.unwrap().code, -32005);
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert!(handler.handle_request(connection(5000), request()).await.error.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_slow_method_times_out() {
+        let slow_method = async {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            Ok(serde_json::Value::Null)
+        };
+
+        let started = std::time::Instant::now();
+        let result = run_with_timeout(Duration::from_millis(50), slow_method).await;
+
+        let error = result.unwrap_err();
+        assert_eq!(error.code, -32006);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn test_node_status_reports_syncing() {
+        use crate::sync::{SyncConfig, SyncManager};
+        use tokio::sync::mpsc;
+
+        let temp_dir = TempDir::new().unwrap();
+        let (storage, mempool, consensus) =
+            create_test_components(Storage::new(temp_dir.path()).unwrap(), MempoolConfig::default());
+        let (outbound, _outbound_receiver) = mpsc::unbounded_channel();
+        let sync = SyncManager::new(
+            SyncConfig { lag_threshold: 2, ..SyncConfig::default() },
+            "validator-1".to_string(),
+            storage.clone(),
+            Arc::new(Validator::new((*storage).clone())),
+            outbound,
+        );
+        let handler = JsonRpcHandler::new(storage, mempool, consensus, RpcTimeouts::default())
+            .with_sync(sync.clone());
+
+        // Two blocks behind is within the threshold
+        sync.observe_peer_height(&"peer".to_string(), 1).unwrap();
+        let status = handler.dispatch("blockchain_getNodeStatus", None).await.unwrap();
+        assert_eq!(status["is_syncing"], false);
+
+        sync.observe_peer_height(&"peer".to_string(), 2).unwrap();
+        let status = handler.dispatch("blockchain_getNodeStatus", None).await.unwrap();
+        assert_eq!(status["is_syncing"], true);
+    }
+
+    #[tokio::test]
+    async fn test_node_status_over_http() {
+        let temp_dir = TempDir::new().unwrap();
+        let (storage, mempool, consensus) =
+            create_test_components(Storage::new(temp_dir.path()).unwrap(), MempoolConfig::default());
+        let mut server = JsonRpcServer::new(0, storage, mempool, consensus, RpcTimeouts::default()).unwrap();
+        server.start().await.unwrap();
+
+        let url = format!("http://127.0.0.1:{}", server.local_addr().unwrap().port());
+        let client = reqwest::Client::new();
+
+        let response: serde_json::Value = client
+            .post(&url)
+            .json(&serde_json::json!({"jsonrpc": "2.0", "method": "blockchain_getNodeStatus", "id": 1}))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+
+        assert_eq!(response["jsonrpc"], "2.0");
+        assert_eq!(response["id"], 1);
+        assert!(response.get("error").is_none());
+        let status = &response["result"];
+        assert!(status["node_id"].is_string());
+        assert!(status["is_validator"].is_boolean());
+        assert_eq!(status["current_height"], 0);
+        assert!(status["current_view"].is_u64());
+        assert!(status["current_round"].is_u64());
+        assert!(status["connected_peers"].is_u64());
+        assert_eq!(status["mempool_size"], 0);
+        assert_eq!(status["is_syncing"], false);
+
+        // Batches answer every call, including the ones that fail
+        let batch: serde_json::Value = client
+            .post(&url)
+            .json(&serde_json::json!([
+                {"jsonrpc": "2.0", "method": "blockchain_getNodeStatus", "id": "a"},
+                {"jsonrpc": "2.0", "method": "blockchain_noSuchMethod", "id": "b"},
+                {"jsonrpc": "1.0", "method": "blockchain_getNodeStatus", "id": "c"},
+            ]))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+
+        let batch = batch.as_array().unwrap();
+        assert_eq!(batch.len(), 3);
+        assert_eq!(batch[0]["id"], "a");
+        assert!(batch[0]["result"].is_object());
+        assert_eq!(batch[1]["error"]["code"], -32601);
+        assert_eq!(batch[2]["id"], "c");
+        assert_eq!(batch[2]["error"]["code"], -32600);
+
+        for (body, code) in [("{not json", -32700), ("[]", -32600), ("42", -32600)] {
+            let response: serde_json::Value = client
+                .post(&url)
+                .header("content-type", "application/json")
+                .body(body)
+                .send()
+                .await
+                .unwrap()
+                .json()
+                .await
+                .unwrap();
+            assert_eq!(response["error"]["code"], code, "body {}", body);
+            assert!(response["id"].is_null());
+        }
+
+        server.shutdown().await.unwrap();
+        assert!(client.post(&url).body("{}").send().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_oversized_requests_are_refused() {
+        let temp_dir = TempDir::new().unwrap();
+        let (storage, mempool, consensus) =
+            create_test_components(Storage::new(temp_dir.path()).unwrap(), MempoolConfig::default());
+        let mut server = JsonRpcServer::new(0, storage, mempool, consensus, RpcTimeouts::default()).unwrap();
+        server.start().await.unwrap();
+
+        let url = format!("http://127.0.0.1:{}", server.local_addr().unwrap().port());
+        let client = reqwest::Client::new();
+
+        let response = client.post(&url).body(vec![b' '; MAX_BODY_BYTES + 1]).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::PAYLOAD_TOO_LARGE);
+
+        // A batch over the limit is refused as a whole, without running any call
+        let call = serde_json::json!({"jsonrpc": "2.0", "method": "blockchain_getNodeStatus", "id": 1});
+        let response: serde_json::Value = client
+            .post(&url)
+            .json(&vec![call.clone(); MAX_BATCH_LEN + 1])
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(response["error"]["code"], -32600);
+
+        let response: serde_json::Value = client
+            .post(&url)
+            .json(&vec![call; MAX_BATCH_LEN])
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(response.as_array().unwrap().len(), MAX_BATCH_LEN);
+
+        server.shutdown().await.unwrap();
+    }
+
+    fn create_transfer_block(height: BlockHeight, from: Address, to: Address, amount: u64) -> Block {
+        use crate::types::BlockHeader;
+
+        let tx = Transaction {
+            id: Uuid::new_v4(),
+            from,
+            to,
+            amount,
+            fee: 1,
+            nonce: height + 1,
+            timestamp: 1_000 + height,
+            signature: [0u8; 64],
+            public_key: [0u8; 32],
+            data: vec![],
+        };
+
+        Block {
+            header: BlockHeader {
+                height,
+                previous_hash: [0; 32],
+                merkle_root: [0; 32],
+                state_root: [0; 32],
+                timestamp: 1_000 + height,
+                proposer: "validator-1".to_string(),
+                round: 0,
+                view: 0,
+                vrf_proof: None,
+            },
+            transactions: vec![tx],
+            signatures: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_balance_with_min_confirmations() {
+        let temp_dir = TempDir::new().unwrap();
+        let handler = create_test_handler(Storage::new(temp_dir.path()).unwrap().with_archive(true));
+
+        let sender = [1u8; 20];
+        let recipient = [2u8; 20];
+        handler.storage.state().set_balance(&sender, 1_000).unwrap();
+        let first = create_transfer_block(0, sender, recipient, 100);
+        let mut second = create_transfer_block(1, sender, recipient, 50);
+        second.header.previous_hash = first.hash();
+        handler.storage.commit_block(&first).unwrap();
+        handler.storage.commit_block(&second).unwrap();
+
+        let address = to_hex(&recipient);
+        let balance = |min_confirmations: Option<u64>| {
+            let params = serde_json::json!({"address": address, "min_confirmations": min_confirmations});
+            handler.dispatch("blockchain_getBalance", Some(params))
+        };
+
+        // The latest block's transfer counts unless confirmations are required
+        assert_eq!(balance(None).await.unwrap()["balance"], 150);
+        assert_eq!(balance(Some(0)).await.unwrap()["balance"], 150);
+        assert_eq!(balance(Some(1)).await.unwrap()["balance"], 100);
+        assert_eq!(balance(Some(2)).await.unwrap_err().code, -32000);
+        let sender_now = handler
+            .dispatch("blockchain_getBalance", Some(serde_json::json!(to_hex(&sender))))
+            .await
+            .unwrap();
+        assert_eq!(sender_now["balance"], 1_000 - 101 - 51);
+        assert_eq!(sender_now["nonce"], 2);
+
+        // The sender is rolled back to before the unconfirmed debit
+        let params = serde_json::json!({"address": to_hex(&sender), "min_confirmations": 1});
+        let sender_confirmed = handler.dispatch("blockchain_getBalance", Some(params)).await.unwrap();
+        assert_eq!(sender_confirmed["balance"], 1_000 - 101);
+        assert_eq!(sender_confirmed["nonce"], 1);
+
+        let bad_address = handler.dispatch("blockchain_getBalance", Some(serde_json::json!("0x1234"))).await;
+        assert_eq!(bad_address.unwrap_err().code, -32602);
+    }
+
+    #[tokio::test]
+    async fn test_confirmed_balance_requires_archive() {
+        let temp_dir = TempDir::new().unwrap();
+        let handler = create_test_handler(Storage::new(temp_dir.path()).unwrap());
+
+        let sender = [1u8; 20];
+        handler.storage.state().set_balance(&sender, 1_000).unwrap();
+        let first = create_transfer_block(0, sender, [2u8; 20], 100);
+        let mut second = create_transfer_block(1, sender, [2u8; 20], 50);
+        second.header.previous_hash = first.hash();
+        handler.storage.commit_block(&first).unwrap();
+        handler.storage.commit_block(&second).unwrap();
+
+        let params = serde_json::json!({"address": to_hex(&[2u8; 20]), "min_confirmations": 1});
+        let error = handler.dispatch("blockchain_getBalance", Some(params)).await.unwrap_err();
+        assert_eq!(error.code, -32000);
+        assert!(error.message.contains("archive"));
+    }
+
+    #[test]
+    fn test_storage_errors_map_to_codes() {
+        let not_found = rpc_error(StorageError::NotFound("Block at height 9".to_string()).into());
+        assert_eq!(not_found.code, -32001);
+        assert_eq!(not_found.message, "Block at height 9 not found");
+
+        let corrupt = rpc_error(StorageError::Corrupt { what: "block", reason: "truncated".to_string() }.into());
+        assert_eq!(corrupt.code, -32007);
+
+        assert_eq!(rpc_error(anyhow!("unexpected")).code, -32603);
+    }
+
+    fn signed_transaction_params(fee: u64) -> (Uuid, serde_json::Value) {
+        use crate::types::address_from_public_key;
+
+        let key = SigningKey::from_bytes(&[5u8; 32]);
+        let mut tx = Transaction {
+            id: Uuid::new_v4(),
+            from: address_from_public_key(&key.verifying_key().to_bytes()),
+            to: [2u8; 20],
+            amount: 100,
+            fee,
+            nonce: 1,
+            timestamp: 1_000,
+            signature: [0u8; 64],
+            public_key: [0u8; 32],
+            data: vec![],
+        };
+        tx.sign(&key);
+
+        (tx.id, transaction_params(&tx))
+    }
+
+    /// `blockchain_sendTransaction` params submitting `tx`
+    fn transaction_params(tx: &Transaction) -> serde_json::Value {
+        serde_json::json!({
+            "id": tx.id.to_string(),
+            "from": to_hex(&tx.from),
+            "to": to_hex(&tx.to),
+            "amount": tx.amount,
+            "fee": tx.fee,
+            "nonce": tx.nonce,
+            "timestamp": tx.timestamp,
+            "signature": to_hex(&tx.signature),
+            "public_key": to_hex(&tx.public_key),
+            "data": to_hex(&tx.data),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_send_transaction() {
+        let temp_dir = TempDir::new().unwrap();
+        let handler = create_test_handler(Storage::new(temp_dir.path()).unwrap());
+        let (tx_id, params) = signed_transaction_params(10);
+
+        let result = handler.dispatch("blockchain_sendTransaction", Some(params.clone())).await.unwrap();
+        assert_eq!(result, tx_id.to_string());
+        assert!(handler.mempool.contains(&tx_id));
+
+        let duplicate = handler.dispatch("blockchain_sendTransaction", Some(params.clone())).await.unwrap_err();
+        assert_eq!(duplicate.code, -32000);
+        assert!(duplicate.message.contains("already known"));
+
+        // Tampering breaks the signature
+        let mut tampered = params;
+        tampered["id"] = serde_json::json!(Uuid::new_v4().to_string());
+        tampered["amount"] = serde_json::json!(1_000_000);
+        let invalid = handler.dispatch("blockchain_sendTransaction", Some(tampered)).await.unwrap_err();
+        assert_eq!(invalid.code, -32000);
+        assert!(invalid.message.contains("Invalid signature"));
+        assert_eq!(handler.mempool.size(), 1);
+
+        let malformed = handler
+            .dispatch("blockchain_sendTransaction", Some(serde_json::json!({"from": "0x01"})))
+            .await
+            .unwrap_err();
+        assert_eq!(malformed.code, -32602);
+    }
+
+    #[tokio::test]
+    async fn test_estimate_fee() {
+        let temp_dir = TempDir::new().unwrap();
+        let handler = create_test_handler(Storage::new(temp_dir.path()).unwrap());
+
+        // An empty pool needs only the minimum fee
+        let estimate = handler.dispatch("blockchain_estimateFee", Some(serde_json::json!(3))).await.unwrap();
+        assert_eq!(estimate["target_blocks"], 3);
+        assert_eq!(estimate["fee"], handler.mempool.min_fee());
+        assert_eq!(estimate["pending"], 0);
+
+        for params in [None, Some(serde_json::json!(0)), Some(serde_json::json!("soon"))] {
+            let error = handler.dispatch("blockchain_estimateFee", params).await.unwrap_err();
+            assert_eq!(error.code, -32602);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_send_transaction_below_min_fee() {
+        let temp_dir = TempDir::new().unwrap();
+        let mempool_config = MempoolConfig {
+            min_fee: 50,
+            ..MempoolConfig::default()
+        };
+        let (storage, mempool, consensus) =
+            create_test_components(Storage::new(temp_dir.path()).unwrap(), mempool_config);
+        let handler = JsonRpcHandler::new(storage, mempool, consensus, RpcTimeouts::default());
+
+        let (tx_id, params) = signed_transaction_params(10);
+        let error = handler.dispatch("blockchain_sendTransaction", Some(params)).await.unwrap_err();
+        assert_eq!(error.code, -32000);
+        assert!(error.message.contains("below the minimum of 50"));
+        assert!(!handler.mempool.contains(&tx_id));
+    }
+
+    #[tokio::test]
+    async fn test_send_transaction_checks_with_the_node_validator() {
+        use crate::types::address_from_public_key;
+
+        let temp_dir = TempDir::new().unwrap();
+        let (storage, mempool, consensus) =
+            create_test_components(Storage::new(temp_dir.path()).unwrap(), MempoolConfig::default());
+        let validator = Validator::new((*storage).clone()).with_max_data_bytes(4);
+        let handler = JsonRpcHandler::new(storage, mempool, consensus, RpcTimeouts::default())
+            .with_validator(Arc::new(validator));
+
+        let key = SigningKey::from_bytes(&[5u8; 32]);
+        let mut tx = Transaction {
+            id: Uuid::new_v4(),
+            from: address_from_public_key(&key.verifying_key().to_bytes()),
+            to: [2u8; 20],
+            amount: 0,
+            fee: 10,
+            nonce: 1,
+            timestamp: 1_000,
+            signature: [0u8; 64],
+            public_key: [0u8; 32],
+            data: vec![7; 8],
+        };
+        tx.sign(&key);
+
+        // The node's data limit applies, and the client is told which rule failed
+        let error = handler.dispatch("blockchain_sendTransaction", Some(transaction_params(&tx))).await.unwrap_err();
+        assert_eq!(error.code, -32000);
+        assert!(error.message.contains("over the limit of 4"));
+        assert!(!handler.mempool.contains(&tx.id));
+    }
+
+    #[tokio::test]
+    async fn test_get_balance_reads_account_state() {
+        use crate::storage::state_store::AccountState;
+
+        let temp_dir = TempDir::new().unwrap();
+        let handler = create_test_handler(Storage::new(temp_dir.path()).unwrap());
+
+        let address = [7u8; 20];
+        let account = AccountState {
+            balance: 12_345,
+            nonce: 9,
+            code_hash: Some([3u8; 32]),
+            storage_root: [0; 32],
+        };
+        handler.storage.state().set_account(&address, &account).unwrap();
+
+        let result = handler
+            .dispatch("blockchain_getBalance", Some(serde_json::json!(to_hex(&address))))
+            .await
+            .unwrap();
+        assert_eq!(result["balance"], 12_345);
+        assert_eq!(result["nonce"], 9);
+        assert_eq!(result["code_hash"], to_hex(&[3u8; 32]));
+
+        // Unknown accounts are empty rather than an error
+        let empty = handler
+            .dispatch("blockchain_getBalance", Some(serde_json::json!(to_hex(&[8u8; 20]))))
+            .await
+            .unwrap();
+        assert_eq!(empty["balance"], 0);
+        assert_eq!(empty["nonce"], 0);
+        assert!(empty["code_hash"].is_null());
+
+        for params in [None, Some(serde_json::json!("0x0102")), Some(serde_json::json!({"nonce": 1}))] {
+            let error = handler.dispatch("blockchain_getBalance", params).await.unwrap_err();
+            assert_eq!(error.code, -32602);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_block_lookup_by_hash_round_trips_hex() {
+        let temp_dir = TempDir::new().unwrap();
+        let handler = create_test_handler(Storage::new(temp_dir.path()).unwrap());
+
+        let block = create_transfer_block(0, [1u8; 20], [0xab; 20], 100);
+        handler.storage.state().set_balance(&[1u8; 20], 1_000).unwrap();
+        handler.storage.commit_block(&block).unwrap();
+
+        let hash = to_hex(&block.hash());
+        assert_eq!(hash.len(), 2 + 64);
+        assert_eq!(parse_hash(&hash).unwrap(), block.hash());
+        assert_eq!(parse_hash(&hash[2..]).unwrap(), block.hash());
+
+        let found = handler
+            .dispatch("blockchain_getBlockByHash", Some(serde_json::json!(hash)))
+            .await
+            .unwrap();
+        assert_eq!(found["hash"], hash);
+        assert_eq!(found["previous_hash"], format!("0x{}", "0".repeat(64)));
+
+        let by_height = handler
+            .dispatch("blockchain_getBlockByHeight", Some(serde_json::json!(0)))
+            .await
+            .unwrap();
+        assert_eq!(by_height["hash"], hash);
+
+        let tx_id = block.transactions[0].id.to_string();
+        let tx = handler
+            .dispatch("blockchain_getTransaction", Some(serde_json::json!(tx_id)))
+            .await
+            .unwrap();
+        assert_eq!(tx["from"], format!("0x{}", "01".repeat(20)));
+        assert_eq!(tx["to"], format!("0x{}", "ab".repeat(20)));
+
+        // Short, odd-length and non-hex hashes are rejected rather than looked up
+        for bad_hash in ["0x1234", &hash[..65], "zz".repeat(32).as_str()] {
+            let error = handler
+                .dispatch("blockchain_getBlockByHash", Some(serde_json::json!(bad_hash)))
+                .await
+                .unwrap_err();
+            assert_eq!(error.code, -32602);
+        }
+
+        let missing = handler
+            .dispatch("blockchain_getBlockByHash", Some(serde_json::json!(to_hex(&[9u8; 32]))))
+            .await
+            .unwrap_err();
+        assert_eq!(missing.code, -32001);
+    }
+
+    #[tokio::test]
+    async fn test_audit_log_pages_over_rpc() {
+        let temp_dir = TempDir::new().unwrap();
+        let handler = create_test_handler(Storage::new(temp_dir.path()).unwrap().with_audit_log(true));
+
+        let (sender, recipient) = ([1u8; 20], [2u8; 20]);
+        handler.storage.state().set_balance(&sender, 1_000).unwrap();
+        let block = create_transfer_block(0, sender, recipient, 100);
+        handler.storage.commit_block(&block).unwrap();
+
+        let first = handler
+            .dispatch("admin_getAuditLog", Some(serde_json::json!({"limit": 1})))
+            .await
+            .unwrap();
+        assert_eq!(first["records"].as_array().unwrap().len(), 1);
+        let record = &first["records"][0];
+        assert_eq!(record["tx_id"], block.transactions[0].id.to_string());
+        assert_eq!(record["address"], to_hex(&sender));
+        assert_eq!(record["old_balance"], 1_000);
+        assert_eq!(record["new_balance"], 1_000 - 101);
+        assert_eq!(record["old_nonce"], 0);
+        assert_eq!(record["new_nonce"], 1);
+        assert_eq!(first["next"], serde_json::json!({"height": 0, "index": 1}));
+
+        let params = serde_json::json!({"from_height": 0, "from_index": 1});
+        let rest = handler.dispatch("admin_getAuditLog", Some(params)).await.unwrap();
+        assert_eq!(rest["records"][0]["address"], to_hex(&recipient));
+        assert_eq!(rest["records"][0]["new_balance"], 100);
+        assert!(rest["next"].is_null());
+
+        let other_dir = TempDir::new().unwrap();
+        let disabled = create_test_handler(Storage::new(other_dir.path()).unwrap());
+        assert_eq!(disabled.dispatch("admin_getAuditLog", None).await.unwrap_err().code, -32000);
+    }
+
+    #[tokio::test]
+    async fn test_node_status_reports_connected_peers() {
+        use crate::network::{MessageRouter, NetworkConfig};
+        use libp2p::multiaddr::Protocol;
+
+        let create_network = |node_id: &str, bootstrap_peers| {
+            let config = NetworkConfig {
+                node_id: node_id.to_string(),
+                listen_addresses: vec!["/ip4/127.0.0.1/tcp/0".parse().unwrap()],
+                bootstrap_peers,
+                ..Default::default()
+            };
+            let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+            NetworkManager::new(config, MessageRouter::new(sender, Arc::new(Mempool::new(MempoolConfig::default()))))
+        };
+
+        let node_a = Arc::new(create_network("node-a", vec![]).await.unwrap());
+        node_a.start().await.unwrap();
+
+        let temp_dir = TempDir::new().unwrap();
+        let handler = create_test_handler(Storage::new(temp_dir.path()).unwrap()).with_network(node_a.clone());
+
+        let status = handler.dispatch("blockchain_getNodeStatus", None).await.unwrap();
+        assert_eq!(status["node_id"], "validator-1");
+        assert_eq!(status["is_validator"], true);
+        assert_eq!(status["connected_peers"], 0);
+
+        let mut listen_addr = None;
+        for _ in 0..100 {
+            listen_addr = node_a.listen_addresses().await.into_iter().next();
+            if listen_addr.is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let addr = listen_addr.unwrap().with(Protocol::P2p(node_a.local_peer_id()));
+
+        let node_b = create_network("node-b", vec![addr]).await.unwrap();
+        node_b.start().await.unwrap();
+
+        let mut connected_peers = 0;
+        for _ in 0..100 {
+            let status = handler.dispatch("blockchain_getNodeStatus", None).await.unwrap();
+            connected_peers = status["connected_peers"].as_u64().unwrap();
+            if connected_peers > 0 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        assert_eq!(connected_peers, 1);
 
-    /// Parse hash from hex string (simplified)
-    fn parse_hash(&self, hash_str: &str) -> Result<Hash, JsonRpcError> {
-        // Simplified hash parsing
-        Ok([0; 32]) // Placeholder
+        let peers = handler.dispatch("blockchain_getPeers", None).await.unwrap();
+        assert_eq!(peers.as_array().unwrap().len(), 1);
     }
 }
@@ -0,0 +1,123 @@
+use crate::types::Hash;
+
+/// Bloom filter over the most recently inserted hashes
+///
+/// Two generations of `capacity` entries each: once the current generation is
+/// full it becomes the previous one and the oldest is dropped, so a hash is
+/// remembered for at least `capacity` further insertions in bounded memory.
+#[derive(Debug, Clone)]
+pub struct RollingBloomFilter {
+    current: Generation,
+    previous: Generation,
+    capacity: usize,
+}
+
+#[derive(Debug, Clone)]
+struct Generation {
+    bits: Vec<u64>,
+    len: usize,
+}
+
+impl RollingBloomFilter {
+    /// Size the filter for `capacity` hashes per generation at `false_positive_rate`
+    pub fn new(capacity: usize, false_positive_rate: f64) -> Self {
+        let capacity = capacity.max(1);
+        // A lookup checks both generations, so each gets half the error budget
+        let rate = (false_positive_rate / 2.0).clamp(f64::MIN_POSITIVE, 0.5);
+        let ln2 = std::f64::consts::LN_2;
+        let num_bits = (-(capacity as f64) * rate.ln() / (ln2 * ln2)).ceil().max(64.0) as usize;
+        let words = num_bits.div_ceil(64);
+
+        Self {
+            current: Generation::new(words),
+            previous: Generation::new(words),
+            capacity,
+        }
+    }
+
+    /// Number of bit positions set per hash
+    fn num_hashes(&self) -> u64 {
+        let bits = (self.current.bits.len() * 64) as f64;
+        ((bits / self.capacity as f64) * std::f64::consts::LN_2).round().max(1.0) as u64
+    }
+
+    /// Remember a hash
+    pub fn insert(&mut self, hash: &Hash) {
+        if self.current.len >= self.capacity {
+            let words = self.current.bits.len();
+            self.previous = std::mem::replace(&mut self.current, Generation::new(words));
+        }
+
+        let num_hashes = self.num_hashes();
+        self.current.insert(hash, num_hashes);
+    }
+
+    /// Whether a hash may have been inserted; never false for a remembered hash
+    pub fn contains(&self, hash: &Hash) -> bool {
+        let num_hashes = self.num_hashes();
+        self.current.contains(hash, num_hashes) || self.previous.contains(hash, num_hashes)
+    }
+}
+
+impl Generation {
+    fn new(words: usize) -> Self {
+        Self { bits: vec![0; words], len: 0 }
+    }
+
+    /// Bit positions of a hash, by double hashing two words of the SHA-256 digest
+    fn positions(&self, hash: &Hash, num_hashes: u64) -> Vec<usize> {
+        let num_bits = (self.bits.len() * 64) as u64;
+        let first = u64::from_le_bytes(hash[0..8].try_into().unwrap());
+        let second = u64::from_le_bytes(hash[8..16].try_into().unwrap()) | 1;
+        (0..num_hashes)
+            .map(|i| (first.wrapping_add(i.wrapping_mul(second)) % num_bits) as usize)
+            .collect()
+    }
+
+    fn insert(&mut self, hash: &Hash, num_hashes: u64) {
+        for position in self.positions(hash, num_hashes) {
+            self.bits[position / 64] |= 1 << (position % 64);
+        }
+        self.len += 1;
+    }
+
+    fn contains(&self, hash: &Hash, num_hashes: u64) -> bool {
+        self.positions(hash, num_hashes)
+            .into_iter()
+            .all(|position| self.bits[position / 64] & (1 << (position % 64)) != 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha2::{Digest, Sha256};
+
+    fn hash(n: u64) -> Hash {
+        Sha256::digest(n.to_le_bytes()).into()
+    }
+
+    #[test]
+    fn test_rolling_filter_remembers_recent_hashes() {
+        let mut filter = RollingBloomFilter::new(100, 0.001);
+        for n in 0..100 {
+            filter.insert(&hash(n));
+        }
+        assert!((0..100).all(|n| filter.contains(&hash(n))));
+
+        // A full generation rolls over; the oldest hashes survive one more generation
+        for n in 100..200 {
+            filter.insert(&hash(n));
+        }
+        assert!((0..200).all(|n| filter.contains(&hash(n))));
+
+        for n in 200..300 {
+            filter.insert(&hash(n));
+        }
+        let forgotten = (0..100).filter(|n| !filter.contains(&hash(*n))).count();
+        assert!(forgotten > 90);
+
+        let false_positives = (1000..11000).filter(|n| filter.contains(&hash(*n))).count();
+        assert!(false_positives < 50);
+    }
+}
@@ -1,6 +1,13 @@
-use clap::{Parser, ValueEnum};
+use anyhow::Result;
+use clap::{ArgMatches, Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use serde::Deserialize;
+use std::ffi::OsString;
 use std::path::PathBuf;
 
+use crate::config::{self, NodeConfig};
+use crate::mempool::OrderingPolicy;
+use crate::storage::{Compression, StorageConfig};
+
 #[derive(Parser, Debug, Clone)]
 #[command(name = "blockchain-node")]
 #[command(about = "High-throughput, fault-tolerant blockchain node")]
@@ -34,6 +41,30 @@ pub struct Cli {
     #[arg(long, default_value = "./data")]
     pub db_path: PathBuf,
 
+    /// RocksDB memtable size per column family in MiB (doubled for blocks)
+    #[arg(long, default_value = "64")]
+    pub db_write_buffer_mb: usize,
+
+    /// Memtables a column family may fill before writes stall
+    #[arg(long, default_value = "4")]
+    pub db_max_write_buffers: i32,
+
+    /// RocksDB block cache shared by all column families, in MiB
+    #[arg(long, default_value = "256")]
+    pub db_block_cache_mb: usize,
+
+    /// Compression of stored blocks: none, snappy, lz4 or zstd
+    #[arg(long, default_value = "zstd")]
+    pub db_block_compression: Compression,
+
+    /// Compression of every other column family: none, snappy, lz4 or zstd
+    #[arg(long, default_value = "lz4")]
+    pub db_compression: Compression,
+
+    /// Bloom filter bits per key for point lookups (0 disables the filters)
+    #[arg(long, default_value = "10")]
+    pub db_bloom_bits: f64,
+
     /// JSON-RPC server port
     #[arg(long, default_value = "8545")]
     pub rpc_port: u16,
@@ -54,20 +85,341 @@ pub struct Cli {
     #[arg(long, default_value = "1000")]
     pub max_peers: usize,
 
+    /// Detect reachability with AutoNAT and dial or listen through relays
+    #[arg(long, default_value = "false")]
+    pub enable_relay: bool,
+
+    /// Relay multiaddr, ending in its /p2p peer id, to listen through when peers can't dial us
+    #[arg(long)]
+    pub relay_addr: Option<String>,
+
+    /// Maximum number of peers accepted from a single IP address
+    #[arg(long, default_value = "8")]
+    pub max_peers_per_ip: usize,
+
     /// Block time in milliseconds
     #[arg(long, default_value = "1000")]
     pub block_time_ms: u64,
 
+    /// Correct the proposal interval so the average block time tracks block_time_ms
+    #[arg(long, default_value = "false")]
+    pub adaptive_block_time: bool,
+
     /// Transaction pool size limit
     #[arg(long, default_value = "10000")]
     pub mempool_size: usize,
 
-    /// Enable development mode (faster consensus, less security)
+    /// Timeout for read-only RPC methods in milliseconds
+    #[arg(long, default_value = "5000")]
+    pub rpc_read_timeout_ms: u64,
+
+    /// Timeout for state-changing RPC methods in milliseconds
+    #[arg(long, default_value = "30000")]
+    pub rpc_write_timeout_ms: u64,
+
+    /// Read-only RPC calls allowed per second for each client (0 disables the limit)
+    #[arg(long, default_value = "100")]
+    pub rpc_read_rate_limit: f64,
+
+    /// Read-only RPC calls a client may burst above its rate
+    #[arg(long, default_value = "200")]
+    pub rpc_read_burst: u32,
+
+    /// State-changing RPC calls allowed per second for each client (0 disables the limit)
+    #[arg(long, default_value = "20")]
+    pub rpc_write_rate_limit: f64,
+
+    /// State-changing RPC calls a client may burst above its rate
+    #[arg(long, default_value = "40")]
+    pub rpc_write_burst: u32,
+
+    /// Apply RPC rate limits per IP address instead of per connection
+    #[arg(long, default_value = "false")]
+    pub rpc_rate_limit_per_ip: bool,
+
+    /// Views per leader shuffle epoch (0 keeps plain round-robin)
+    #[arg(long, default_value = "0")]
+    pub leader_shuffle_epoch: u64,
+
+    /// Let the next validator propose if the leader is silent this many milliseconds past its slot
+    #[arg(long)]
+    pub backup_proposer_timeout_ms: Option<u64>,
+
+    /// Propose an empty block when no block has been committed for this many milliseconds
+    #[arg(long)]
+    pub empty_block_timeout_ms: Option<u64>,
+
+    /// Refuse proposals with fewer transactions than this while the mempool holds at least as many
+    #[arg(long)]
+    pub min_block_transactions: Option<usize>,
+
+    /// PBFT instances kept in the consensus log behind the last stable checkpoint
+    #[arg(long, default_value = "100")]
+    pub pbft_log_retention: usize,
+
+    /// Milliseconds without progress before a view change
+    #[arg(long, default_value = "10000")]
+    pub view_timeout_base_ms: u64,
+
+    /// Factor the view timeout grows by after each failed view
+    #[arg(long, default_value = "1.5")]
+    pub view_timeout_multiplier: f64,
+
+    /// Longest the view timeout grows to, in milliseconds
+    #[arg(long, default_value = "60000")]
+    pub view_timeout_max_ms: u64,
+
+    /// Adjust the block size limit from recent block fullness
+    #[arg(long, default_value = "false")]
+    pub dynamic_block_size: bool,
+
+    /// Preceding blocks whose median timestamp a new block must exceed
+    #[arg(long, default_value = "11")]
+    pub median_time_past_window: usize,
+
+    /// Largest data payload a transaction may carry, in bytes
+    #[arg(long, default_value = "65536")]
+    pub max_data_bytes: usize,
+
+    /// Block transaction order: priority, fee-then-nonce, nonce-then-fee or fifo-within-account
+    #[arg(long, default_value = "priority")]
+    pub tx_ordering: OrderingPolicy,
+
+    /// Re-check the mempool batch against current state before proposing a block
+    #[arg(long, default_value = "true")]
+    pub prevalidate_batch: bool,
+
+    /// Senders allowed to transact (comma-separated hex addresses; empty is permissionless)
+    #[arg(long)]
+    pub allowed_senders: Option<String>,
+
+    /// Weight mempool priority by each sender's inclusion/rejection history
+    #[arg(long, default_value = "false")]
+    pub sender_reputation: bool,
+
+    /// Write pending transactions through to disk so a crash doesn't lose them
+    #[arg(long, default_value = "false")]
+    pub persist_mempool: bool,
+
+    /// Remember each pending transaction's size from admission instead of re-measuring it per block
+    #[arg(long, default_value = "true")]
+    pub cache_tx_sizes: bool,
+
+    /// Recently committed transactions remembered so re-submissions are rejected
+    #[arg(long, default_value = "100000")]
+    pub committed_filter_capacity: usize,
+
+    /// False-positive rate of the committed transaction filter
+    #[arg(long, default_value = "0.0001")]
+    pub committed_filter_fp_rate: f64,
+
+    /// Largest network message accepted from a peer, in bytes, before or after decoding
+    #[arg(long, default_value = "10485760")]
+    pub max_message_size: u64,
+
+    /// Peers kept in each gossip topic mesh
+    #[arg(long, default_value = "6")]
+    pub gossip_mesh_n: usize,
+
+    /// Fewest gossip mesh peers before more are grafted
+    #[arg(long, default_value = "5")]
+    pub gossip_mesh_n_low: usize,
+
+    /// Most gossip mesh peers before some are pruned
+    #[arg(long, default_value = "12")]
+    pub gossip_mesh_n_high: usize,
+
+    /// Fraction of non-mesh peers sent gossip each heartbeat
+    #[arg(long, default_value = "0.25")]
+    pub gossip_factor: f64,
+
+    /// Gossip heartbeats a message stays cached
+    #[arg(long, default_value = "5")]
+    pub gossip_history_length: usize,
+
+    /// Gossip heartbeats of cached messages advertised to peers
+    #[arg(long, default_value = "3")]
+    pub gossip_history_gossip: usize,
+
+    /// Gossiped transactions buffered for validation before new arrivals are dropped
+    #[arg(long, default_value = "10000")]
+    pub ingress_queue_size: usize,
+
+    /// Workers validating queued transactions into the mempool
+    #[arg(long, default_value = "4")]
+    pub ingress_workers: usize,
+
+    /// Milliseconds submitted transactions wait to be gossiped together; 0 gossips each alone
+    #[arg(long, default_value = "200")]
+    pub tx_batch_interval_ms: u64,
+
+    /// Most transactions gossiped in one batch; a full batch goes out immediately
+    #[arg(long, default_value = "256")]
+    pub tx_batch_size: usize,
+
+    /// Record every balance and nonce change in an append-only audit log
+    #[arg(long, default_value = "false")]
+    pub audit_log: bool,
+
+    /// Block responses slower than this many milliseconds count against the peer
+    #[arg(long, default_value = "2000")]
+    pub sync_slow_peer_threshold_ms: u64,
+
+    /// Consecutive slow block responses before sync deprioritizes a peer
+    #[arg(long, default_value = "3")]
+    pub sync_slow_peer_strikes: u32,
+
+    /// Blocks behind the highest peer before the node reports itself as syncing
+    #[arg(long, default_value = "2")]
+    pub sync_lag_threshold: u64,
+
+    /// Minimum milliseconds between fee replacements of the same sender and nonce
+    #[arg(long, default_value = "1000")]
+    pub replacement_cooldown_ms: u64,
+
+    /// Percentage fee increase required to replace a pending transaction with the same nonce
+    #[arg(long, default_value = "10")]
+    pub replacement_fee_bump_percent: u64,
+
+    /// Validator peer pins (comma-separated node_id=multiaddr with a /p2p peer id)
+    #[arg(long)]
+    pub pinned_peers: Option<String>,
+
+    /// Accept pinned validators under a new peer id if their address matches
+    #[arg(long, default_value = "false")]
+    pub allow_identity_rotation: bool,
+
+    /// URL to POST committed block info to
+    #[arg(long)]
+    pub webhook_url: Option<String>,
+
+    /// Hex address whose governance transactions add or remove validators (unset disables)
+    #[arg(long)]
+    pub governance_authority: Option<String>,
+
+    /// Ed25519 key file created by `keygen`; keeps the peer id and validator key stable
+    #[arg(long)]
+    pub key_file: Option<PathBuf>,
+
+    /// Hand off leadership and persist the mempool before shutting down
+    #[arg(long, default_value = "true")]
+    pub drain_on_shutdown: bool,
+
+    /// Development mode: a sole validator commits its own blocks without waiting for votes
     #[arg(long, default_value = "false")]
     pub dev_mode: bool,
+
+    /// Order leaders by the previous proposer's VRF output instead of round-robin
+    #[arg(long, default_value = "false")]
+    pub vrf_leader_election: bool,
+
+    /// Run a maintenance command instead of starting the node
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Export blocks and their transactions from the database
+    Export(ExportArgs),
+    /// Generate a node key file for --key-file
+    Keygen(KeygenArgs),
+    /// Write or load a state snapshot for fast bootstrap
+    Snapshot(SnapshotArgs),
+    /// Write a range of blocks to a file for replay
+    ExportBlocks(ExportBlocksArgs),
+    /// Validate and commit the blocks of an export-blocks file
+    ReplayBlocks(ReplayBlocksArgs),
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct ExportBlocksArgs {
+    /// First block height to write
+    #[arg(long)]
+    pub from: u64,
+
+    /// Last block height to write
+    #[arg(long)]
+    pub to: u64,
+
+    /// Dump file to write
+    #[arg(long)]
+    pub out: PathBuf,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct ReplayBlocksArgs {
+    /// Dump file to replay
+    #[arg(long = "in")]
+    pub input: PathBuf,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct SnapshotArgs {
+    #[command(subcommand)]
+    pub action: SnapshotAction,
 }
 
-#[derive(Debug, Clone, ValueEnum)]
+#[derive(Subcommand, Debug, Clone)]
+pub enum SnapshotAction {
+    /// Write the account state and header chain at a height to a file
+    Export {
+        /// Checkpoint height (defaults to the latest block)
+        #[arg(long)]
+        height: Option<u64>,
+
+        /// Snapshot file to write
+        #[arg(long)]
+        output: PathBuf,
+    },
+    /// Start an empty database at the checkpoint in a snapshot file
+    Import {
+        /// Snapshot file to read
+        #[arg(long)]
+        input: PathBuf,
+    },
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct KeygenArgs {
+    /// Where to write the key
+    #[arg(long, default_value = "node.key")]
+    pub output: PathBuf,
+
+    /// Replace an existing key file
+    #[arg(long, default_value = "false")]
+    pub force: bool,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct ExportArgs {
+    /// Output format
+    #[arg(long, default_value = "jsonl")]
+    pub format: ExportFormat,
+
+    /// First block height to export
+    #[arg(long, default_value = "0")]
+    pub from: u64,
+
+    /// Last block height to export (defaults to the latest block)
+    #[arg(long)]
+    pub to: Option<u64>,
+
+    /// Output file (defaults to stdout)
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum ExportFormat {
+    /// One JSON object per block, transactions nested
+    Jsonl,
+    /// One row per transaction with its block's fields
+    Csv,
+}
+
+#[derive(Debug, Clone, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum NodeMode {
     /// Full validator node participating in consensus
     Validator,
@@ -78,6 +430,35 @@ pub enum NodeMode {
 }
 
 impl Cli {
+    /// Parse the process arguments, filling unset flags from `--config-file`
+    pub fn load() -> Result<Self> {
+        Self::load_from(std::env::args_os())
+    }
+
+    /// Parse `args`, filling flags not given on the command line from `--config-file`
+    ///
+    /// Invalid arguments and `--help` exit the process as `Cli::parse` does.
+    pub fn load_from<I, T>(args: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let matches = Self::command()
+            .try_get_matches_from(args)
+            .unwrap_or_else(|e| e.exit());
+        Self::from_matches(&matches)
+    }
+
+    fn from_matches(matches: &ArgMatches) -> Result<Self> {
+        let mut cli = Self::from_arg_matches(matches)?;
+        if let Some(path) = cli.config_file.clone() {
+            NodeConfig::load(&path)?.merge_into(&mut cli, matches);
+        }
+
+        config::validate(&cli)?;
+        Ok(cli)
+    }
+
     /// Parse bootstrap peers from comma-separated string
     pub fn get_bootstrap_peers(&self) -> Vec<String> {
         self.bootstrap_peers
@@ -92,6 +473,46 @@ impl Cli {
             .unwrap_or_default()
     }
 
+    /// Parse allowlisted senders from comma-separated string
+    pub fn get_allowed_senders(&self) -> Vec<String> {
+        self.allowed_senders
+            .as_ref()
+            .map(|senders| {
+                senders
+                    .split(',')
+                    .map(|s| s.trim().to_string())
+                    .filter(|s| !s.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Parse validator peer pins from comma-separated string
+    pub fn get_pinned_peers(&self) -> Vec<String> {
+        self.pinned_peers
+            .as_ref()
+            .map(|pins| {
+                pins
+                    .split(',')
+                    .map(|s| s.trim().to_string())
+                    .filter(|s| !s.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// RocksDB tuning from the `db_*` flags
+    pub fn storage_config(&self) -> StorageConfig {
+        StorageConfig {
+            write_buffer_size: self.db_write_buffer_mb * 1024 * 1024,
+            max_write_buffer_number: self.db_max_write_buffers,
+            block_cache_size: self.db_block_cache_mb * 1024 * 1024,
+            block_compression: self.db_block_compression,
+            compression: self.db_compression,
+            bloom_filter_bits: self.db_bloom_bits,
+        }
+    }
+
     /// Check if node should participate in consensus
     pub fn is_validator(&self) -> bool {
         matches!(self.mode, NodeMode::Validator)
@@ -0,0 +1,74 @@
+use anyhow::{Result, anyhow};
+use bincode::Options;
+use serde::de::DeserializeOwned;
+use std::io::Read;
+
+/// Default cap on the decoded size of a message from an untrusted source
+pub const DEFAULT_MAX_DECODED_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Decode a bincode payload, refusing anything that would decode to more than `max_size` bytes
+///
+/// Length prefixes are checked against the remaining budget before anything is
+/// allocated, so a few bytes claiming a huge vector fail immediately instead of
+/// exhausting memory. The encoding matches `bincode::serialize`.
+pub fn decode_bounded<T: DeserializeOwned>(data: &[u8], max_size: u64) -> Result<T> {
+    bincode::options()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .with_limit(max_size)
+        .deserialize(data)
+        .map_err(|e| anyhow!("Failed to decode payload within {} bytes: {}", max_size, e))
+}
+
+/// Drain a decompressing reader, aborting once the output would exceed `max_size` bytes
+///
+/// At most `max_size + 1` bytes are ever read, however far the input claims to expand.
+pub fn read_bounded<R: Read>(reader: R, max_size: u64) -> Result<Vec<u8>> {
+    let mut output = Vec::new();
+    reader
+        .take(max_size.saturating_add(1))
+        .read_to_end(&mut output)
+        .map_err(|e| anyhow!("Failed to read payload: {}", e))?;
+
+    if output.len() as u64 > max_size {
+        return Err(anyhow!("Decompressed payload exceeds {} bytes", max_size));
+    }
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{MessagePayload, NetworkMessage};
+
+    #[test]
+    fn test_decode_rejects_oversized_length_claim() {
+        let message = NetworkMessage::new("node-1".to_string(), MessagePayload::Heartbeat);
+        let data = bincode::serialize(&message).unwrap();
+        let decoded: NetworkMessage = decode_bounded(&data, 1024).unwrap();
+        assert_eq!(decoded.message_id, message.message_id);
+
+        // Sixteen bytes claiming a vector of 2^60 elements
+        let mut bomb = Vec::new();
+        bomb.extend_from_slice(&(1u64 << 60).to_le_bytes());
+        bomb.extend_from_slice(&[0u8; 8]);
+        assert!(decode_bounded::<Vec<u64>>(&bomb, 1024 * 1024).is_err());
+
+        // A well-formed payload is still refused past the limit
+        let large = bincode::serialize(&vec![7u8; 4096]).unwrap();
+        assert!(decode_bounded::<Vec<u8>>(&large, 1024).is_err());
+        assert_eq!(decode_bounded::<Vec<u8>>(&large, 8192).unwrap().len(), 4096);
+    }
+
+    #[test]
+    fn test_read_stops_at_limit() {
+        // A reader standing in for a decompressor that never stops expanding
+        let expanding = std::io::repeat(0);
+        let error = read_bounded(expanding, 64 * 1024).unwrap_err();
+        assert!(error.to_string().contains("exceeds"));
+
+        let fits = read_bounded(&[1u8; 100][..], 100).unwrap();
+        assert_eq!(fits.len(), 100);
+    }
+}
@@ -0,0 +1,280 @@
+use anyhow::{Context, Result, anyhow};
+use clap::ArgMatches;
+use clap::parser::ValueSource;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+use crate::cli::{Cli, NodeMode};
+use crate::mempool::OrderingPolicy;
+use crate::storage::Compression;
+
+/// Node settings read from `--config-file`
+///
+/// Keys match the CLI flags with underscores (`block_time_ms` for `--block-time-ms`).
+/// Anything left out keeps the flag's default, and flags given on the command line
+/// override the file. Comma-separated flags are lists here.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NodeConfig {
+    pub mode: Option<NodeMode>,
+    pub node_id: Option<String>,
+    pub listen_addr: Option<String>,
+    pub bootstrap_peers: Option<Vec<String>>,
+    pub enable_relay: Option<bool>,
+    pub relay_addr: Option<String>,
+    pub genesis_file: Option<PathBuf>,
+    pub db_path: Option<PathBuf>,
+    pub db_write_buffer_mb: Option<usize>,
+    pub db_max_write_buffers: Option<i32>,
+    pub db_block_cache_mb: Option<usize>,
+    pub db_block_compression: Option<Compression>,
+    pub db_compression: Option<Compression>,
+    pub db_bloom_bits: Option<f64>,
+    pub rpc_port: Option<u16>,
+    pub metrics_port: Option<u16>,
+    pub enable_metrics: Option<bool>,
+    pub log_level: Option<String>,
+    pub max_peers: Option<usize>,
+    pub max_peers_per_ip: Option<usize>,
+    pub block_time_ms: Option<u64>,
+    pub adaptive_block_time: Option<bool>,
+    pub mempool_size: Option<usize>,
+    pub rpc_read_timeout_ms: Option<u64>,
+    pub rpc_write_timeout_ms: Option<u64>,
+    pub rpc_read_rate_limit: Option<f64>,
+    pub rpc_read_burst: Option<u32>,
+    pub rpc_write_rate_limit: Option<f64>,
+    pub rpc_write_burst: Option<u32>,
+    pub rpc_rate_limit_per_ip: Option<bool>,
+    pub leader_shuffle_epoch: Option<u64>,
+    pub backup_proposer_timeout_ms: Option<u64>,
+    pub empty_block_timeout_ms: Option<u64>,
+    pub min_block_transactions: Option<usize>,
+    pub pbft_log_retention: Option<usize>,
+    pub view_timeout_base_ms: Option<u64>,
+    pub view_timeout_multiplier: Option<f64>,
+    pub view_timeout_max_ms: Option<u64>,
+    pub dynamic_block_size: Option<bool>,
+    pub median_time_past_window: Option<usize>,
+    pub max_data_bytes: Option<usize>,
+    pub tx_ordering: Option<OrderingPolicy>,
+    pub prevalidate_batch: Option<bool>,
+    pub allowed_senders: Option<Vec<String>>,
+    pub sender_reputation: Option<bool>,
+    pub persist_mempool: Option<bool>,
+    pub cache_tx_sizes: Option<bool>,
+    pub committed_filter_capacity: Option<usize>,
+    pub committed_filter_fp_rate: Option<f64>,
+    pub max_message_size: Option<u64>,
+    pub gossip_mesh_n: Option<usize>,
+    pub gossip_mesh_n_low: Option<usize>,
+    pub gossip_mesh_n_high: Option<usize>,
+    pub gossip_factor: Option<f64>,
+    pub gossip_history_length: Option<usize>,
+    pub gossip_history_gossip: Option<usize>,
+    pub ingress_queue_size: Option<usize>,
+    pub ingress_workers: Option<usize>,
+    pub tx_batch_interval_ms: Option<u64>,
+    pub tx_batch_size: Option<usize>,
+    pub audit_log: Option<bool>,
+    pub sync_slow_peer_threshold_ms: Option<u64>,
+    pub sync_slow_peer_strikes: Option<u32>,
+    pub sync_lag_threshold: Option<u64>,
+    pub replacement_cooldown_ms: Option<u64>,
+    pub replacement_fee_bump_percent: Option<u64>,
+    pub pinned_peers: Option<Vec<String>>,
+    pub allow_identity_rotation: Option<bool>,
+    pub webhook_url: Option<String>,
+    pub governance_authority: Option<String>,
+    pub key_file: Option<PathBuf>,
+    pub drain_on_shutdown: Option<bool>,
+    pub dev_mode: Option<bool>,
+    pub vrf_leader_election: Option<bool>,
+}
+
+/// Copy each file value into the CLI unless that flag was given on the command line
+///
+/// `values` are plain CLI fields, `options` are `Option` fields, and `lists` are
+/// comma-separated `Option<String>` fields.
+macro_rules! merge_fields {
+    ($file:expr, $cli:expr, $matches:expr,
+     values: [$($value:ident),* $(,)?],
+     options: [$($option:ident),* $(,)?],
+     lists: [$($list:ident),* $(,)?]) => {
+        $(if let Some(value) = $file.$value {
+            if !from_command_line($matches, stringify!($value)) {
+                $cli.$value = value;
+            }
+        })*
+        $(if let Some(value) = $file.$option {
+            if !from_command_line($matches, stringify!($option)) {
+                $cli.$option = Some(value);
+            }
+        })*
+        $(if let Some(values) = $file.$list {
+            if !from_command_line($matches, stringify!($list)) {
+                $cli.$list = Some(values.join(","));
+            }
+        })*
+    };
+}
+
+impl NodeConfig {
+    /// Read a config file; `.json` files are parsed as JSON, anything else as TOML
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+
+        let is_json = path.extension().is_some_and(|ext| ext == "json");
+        let config = if is_json {
+            serde_json::from_str(&contents).map_err(anyhow::Error::from)
+        } else {
+            toml::from_str(&contents).map_err(anyhow::Error::from)
+        };
+        config.with_context(|| format!("Malformed config file {}", path.display()))
+    }
+
+    /// Fill `cli` from this file, keeping every flag `matches` saw on the command line
+    pub fn merge_into(self, cli: &mut Cli, matches: &ArgMatches) {
+        merge_fields!(self, cli, matches,
+            values: [
+                mode, node_id, listen_addr, genesis_file, db_path, db_write_buffer_mb, db_max_write_buffers,
+                db_block_cache_mb, db_block_compression, db_compression, db_bloom_bits, rpc_port, metrics_port,
+                enable_metrics, log_level, enable_relay, max_peers, max_peers_per_ip, block_time_ms,
+                adaptive_block_time, mempool_size, rpc_read_timeout_ms, rpc_write_timeout_ms,
+                leader_shuffle_epoch, dynamic_block_size, median_time_past_window, max_data_bytes, tx_ordering, prevalidate_batch, sender_reputation,
+                persist_mempool, cache_tx_sizes, max_message_size, ingress_queue_size, ingress_workers, audit_log,
+                tx_batch_interval_ms, tx_batch_size,
+                sync_slow_peer_threshold_ms, sync_slow_peer_strikes, replacement_cooldown_ms,
+                replacement_fee_bump_percent, allow_identity_rotation, drain_on_shutdown, dev_mode,
+                vrf_leader_election,
+                pbft_log_retention, view_timeout_base_ms, view_timeout_multiplier, view_timeout_max_ms,
+                sync_lag_threshold, gossip_mesh_n, gossip_mesh_n_low,
+                gossip_mesh_n_high, gossip_factor, gossip_history_length, gossip_history_gossip,
+                committed_filter_capacity, committed_filter_fp_rate, rpc_read_rate_limit, rpc_read_burst,
+                rpc_write_rate_limit, rpc_write_burst, rpc_rate_limit_per_ip,
+            ],
+            options: [
+                backup_proposer_timeout_ms, empty_block_timeout_ms, min_block_transactions, webhook_url,
+                governance_authority, key_file, relay_addr,
+            ],
+            lists: [bootstrap_peers, allowed_senders, pinned_peers]
+        );
+    }
+}
+
+/// Check the effective settings have every value the node can't start without
+pub fn validate(cli: &Cli) -> Result<()> {
+    let required = [
+        ("node_id", cli.node_id.trim().is_empty()),
+        ("listen_addr", cli.listen_addr.trim().is_empty()),
+        ("genesis_file", cli.genesis_file.as_os_str().is_empty()),
+        ("db_path", cli.db_path.as_os_str().is_empty()),
+    ];
+    if let Some((field, _)) = required.iter().find(|(_, missing)| *missing) {
+        return Err(anyhow!("{} is required", field));
+    }
+
+    let zero = [
+        ("block_time_ms", cli.block_time_ms == 0),
+        ("mempool_size", cli.mempool_size == 0),
+        ("max_peers", cli.max_peers == 0),
+        ("max_message_size", cli.max_message_size == 0),
+        ("committed_filter_capacity", cli.committed_filter_capacity == 0),
+        ("median_time_past_window", cli.median_time_past_window == 0),
+        ("db_write_buffer_mb", cli.db_write_buffer_mb == 0),
+    ];
+    if let Some((field, _)) = zero.iter().find(|(_, is_zero)| *is_zero) {
+        return Err(anyhow!("{} must be greater than zero", field));
+    }
+
+    cli.storage_config().validate()?;
+
+    let fp_rate = cli.committed_filter_fp_rate;
+    if !(fp_rate > 0.0 && fp_rate < 1.0) {
+        return Err(anyhow!("committed_filter_fp_rate must be between 0 and 1"));
+    }
+
+    Ok(())
+}
+
+fn from_command_line(matches: &ArgMatches, id: &str) -> bool {
+    matches.value_source(id) == Some(ValueSource::CommandLine)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write_config(dir: &TempDir, name: &str, contents: &str) -> String {
+        let path = dir.path().join(name);
+        std::fs::write(&path, contents).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_config_file_values_and_cli_overrides() {
+        let dir = TempDir::new().unwrap();
+        let path = write_config(&dir, "node.toml", r#"
+            node_id = "validator-2"
+            block_time_ms = 250
+            mempool_size = 500
+            bootstrap_peers = ["/ip4/10.0.0.1/tcp/8000", "/ip4/10.0.0.2/tcp/8000"]
+            empty_block_timeout_ms = 5000
+        "#);
+
+        let cli = Cli::load_from(["blockchain-node", "--config-file", path.as_str()]).unwrap();
+        assert_eq!(cli.block_time_ms, 250);
+        assert_eq!(cli.node_id, "validator-2");
+        assert_eq!(cli.mempool_size, 500);
+        assert_eq!(cli.empty_block_timeout_ms, Some(5000));
+        assert_eq!(cli.get_bootstrap_peers().len(), 2);
+        // Not in the file, so the flag default stays
+        assert_eq!(cli.rpc_port, 8545);
+
+        let cli = Cli::load_from([
+            "blockchain-node", "--config-file", path.as_str(), "--block-time-ms", "750",
+        ]).unwrap();
+        assert_eq!(cli.block_time_ms, 750);
+        assert_eq!(cli.mempool_size, 500);
+
+        // Passing the default value explicitly still beats the file
+        let cli = Cli::load_from([
+            "blockchain-node", "--config-file", path.as_str(), "--block-time-ms", "1000",
+        ]).unwrap();
+        assert_eq!(cli.block_time_ms, 1000);
+    }
+
+    #[test]
+    fn test_json_config_file() {
+        let dir = TempDir::new().unwrap();
+        let path = write_config(&dir, "node.json", r#"{ "block_time_ms": 300, "mode": "observer" }"#);
+
+        let cli = Cli::load_from(["blockchain-node", "--config-file", path.as_str()]).unwrap();
+        assert_eq!(cli.block_time_ms, 300);
+        assert!(!cli.is_validator());
+    }
+
+    #[test]
+    fn test_invalid_config_is_rejected() {
+        let dir = TempDir::new().unwrap();
+
+        let typo = write_config(&dir, "typo.toml", "block_time = 250");
+        let err = Cli::load_from(["blockchain-node", "--config-file", typo.as_str()]).unwrap_err();
+        assert!(format!("{:#}", err).contains("Malformed config file"));
+
+        let empty_id = write_config(&dir, "empty.toml", "node_id = \"\"");
+        let err = Cli::load_from(["blockchain-node", "--config-file", empty_id.as_str()]).unwrap_err();
+        assert!(err.to_string().contains("node_id is required"));
+
+        let zero = write_config(&dir, "zero.toml", "block_time_ms = 0");
+        let err = Cli::load_from(["blockchain-node", "--config-file", zero.as_str()]).unwrap_err();
+        assert!(err.to_string().contains("block_time_ms must be greater than zero"));
+
+        let missing = dir.path().join("absent.toml");
+        let err = Cli::load_from(["blockchain-node", "--config-file", missing.to_str().unwrap()]).unwrap_err();
+        assert!(format!("{:#}", err).contains("Failed to read config file"));
+    }
+}
@@ -0,0 +1,124 @@
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// Block interval controller configuration
+#[derive(Debug, Clone)]
+pub struct BlockIntervalConfig {
+    pub target_ms: u64,
+    pub min_ms: u64,
+    pub max_ms: u64,
+    pub kp: f64,
+    pub ki: f64,
+    pub kd: f64,
+}
+
+impl BlockIntervalConfig {
+    /// Controller targeting the given block time with default gains and bounds
+    pub fn with_target(target_ms: u64) -> Self {
+        Self {
+            target_ms,
+            min_ms: target_ms / 10,
+            max_ms: target_ms * 2,
+            kp: 0.5,
+            ki: 0.1,
+            kd: 0.05,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct ControllerState {
+    integral: f64,
+    last_error: Option<f64>,
+    interval_ms: f64,
+}
+
+/// PID controller adjusting the proposal interval so the average block time stays on target
+#[derive(Debug, Clone)]
+pub struct BlockIntervalController {
+    config: BlockIntervalConfig,
+    state: Arc<RwLock<ControllerState>>,
+}
+
+impl BlockIntervalController {
+    pub fn new(config: BlockIntervalConfig) -> Self {
+        let interval_ms = config.target_ms as f64;
+        Self {
+            config,
+            state: Arc::new(RwLock::new(ControllerState {
+                integral: 0.0,
+                last_error: None,
+                interval_ms,
+            })),
+        }
+    }
+
+    /// Feed a measured block interval and get the corrected proposal interval
+    pub fn record_interval(&self, measured: Duration) -> Duration {
+        let mut state = self.state.write().unwrap();
+
+        // Positive error means blocks arrive too fast, so the interval grows
+        let error = self.config.target_ms as f64 - measured.as_millis() as f64;
+
+        // Bound the integral so a long stall can't wind the controller up indefinitely
+        let integral_limit = (self.config.max_ms - self.config.min_ms) as f64 / self.config.ki.max(f64::EPSILON);
+        state.integral = (state.integral + error).clamp(-integral_limit, integral_limit);
+
+        let derivative = state.last_error.map(|last| error - last).unwrap_or(0.0);
+        state.last_error = Some(error);
+
+        let output = self.config.target_ms as f64
+            + self.config.kp * error
+            + self.config.ki * state.integral
+            + self.config.kd * derivative;
+        state.interval_ms = output.clamp(self.config.min_ms as f64, self.config.max_ms as f64);
+
+        Duration::from_millis(state.interval_ms as u64)
+    }
+
+    /// Get the current proposal interval
+    pub fn current_interval(&self) -> Duration {
+        let state = self.state.read().unwrap();
+        Duration::from_millis(state.interval_ms as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slow_rounds_shorten_interval() {
+        let controller = BlockIntervalController::new(BlockIntervalConfig::with_target(1000));
+
+        // Every round spends 500ms in consensus on top of the proposal interval
+        let mut measured = Duration::ZERO;
+        for _ in 0..100 {
+            measured = controller.current_interval() + Duration::from_millis(500);
+            controller.record_interval(measured);
+        }
+
+        let interval = controller.current_interval();
+        assert!(interval < Duration::from_millis(1000));
+        assert!(interval >= Duration::from_millis(100));
+
+        // Long-run block time converges on the target
+        let measured_ms = measured.as_millis() as i64;
+        assert!((measured_ms - 1000).abs() <= 10);
+    }
+
+    #[test]
+    fn test_interval_stays_within_bounds() {
+        let controller = BlockIntervalController::new(BlockIntervalConfig::with_target(1000));
+
+        for _ in 0..50 {
+            controller.record_interval(Duration::from_millis(10_000));
+        }
+        assert_eq!(controller.current_interval(), Duration::from_millis(100));
+
+        for _ in 0..200 {
+            controller.record_interval(Duration::ZERO);
+        }
+        assert_eq!(controller.current_interval(), Duration::from_millis(2000));
+    }
+}
@@ -0,0 +1,85 @@
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+use crate::types::{Address, Block, NodeId, PublicKey, Transaction};
+
+/// Prefix marking a transaction's `data` as a validator set change
+pub const GOVERNANCE_PREFIX: &[u8] = b"gov:";
+
+/// A validator set change carried in a transaction's `data`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum GovernanceAction {
+    /// Add a validator, with the key its votes are checked against
+    AddValidator { node_id: NodeId, public_key: PublicKey },
+    RemoveValidator(NodeId),
+}
+
+/// Validators in effect and their public keys, in the order they joined
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ValidatorSet {
+    pub validators: Vec<(NodeId, PublicKey)>,
+}
+
+impl ValidatorSet {
+    pub fn new(validators: Vec<(NodeId, PublicKey)>) -> Self {
+        Self { validators }
+    }
+
+    /// Node ids in join order, as leader election rotates through them
+    pub fn node_ids(&self) -> Vec<NodeId> {
+        self.validators.iter().map(|(node_id, _)| node_id.clone()).collect()
+    }
+
+    /// Public key of every validator, by node id
+    pub fn keys(&self) -> HashMap<NodeId, PublicKey> {
+        self.validators.iter().cloned().collect()
+    }
+
+    pub fn contains(&self, node_id: &NodeId) -> bool {
+        self.validators.iter().any(|(validator, _)| validator == node_id)
+    }
+}
+
+impl GovernanceAction {
+    /// Encode as transaction data
+    pub fn encode(&self) -> Vec<u8> {
+        let mut data = GOVERNANCE_PREFIX.to_vec();
+        data.extend(bincode::serialize(self).expect("Failed to serialize governance action"));
+        data
+    }
+
+    /// Decode a transaction's governance action, if its data carries one
+    pub fn from_transaction(tx: &Transaction) -> Option<Self> {
+        let payload = tx.data.strip_prefix(GOVERNANCE_PREFIX)?;
+        bincode::deserialize(payload).ok()
+    }
+}
+
+/// Apply a block's governance actions from `authority`, in transaction order
+///
+/// Returns the new validator set, or `None` if the block leaves it unchanged. Adding a
+/// current validator, removing an unknown one, or removing the last one is ignored.
+pub fn apply_block(validator_set: &ValidatorSet, block: &Block, authority: &Address) -> Option<ValidatorSet> {
+    let mut updated = validator_set.clone();
+
+    for tx in block.transactions.iter().filter(|tx| tx.from == *authority) {
+        match GovernanceAction::from_transaction(tx) {
+            Some(GovernanceAction::AddValidator { node_id, public_key }) => {
+                if !updated.contains(&node_id) {
+                    updated.validators.push((node_id, public_key));
+                }
+            }
+            Some(GovernanceAction::RemoveValidator(node_id)) => {
+                if updated.validators.len() > 1 {
+                    updated.validators.retain(|(validator, _)| *validator != node_id);
+                }
+            }
+            None => {}
+        }
+    }
+
+    if updated == *validator_set {
+        None
+    } else {
+        Some(updated)
+    }
+}
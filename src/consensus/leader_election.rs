@@ -1,30 +1,175 @@
 use std::collections::HashMap;
-use crate::types::NodeId;
+use std::sync::{Arc, RwLock};
+use sha2::{Digest, Sha256};
+use crate::types::{Block, Hash, NodeId, PublicKey};
+use super::vrf::VrfProof;
 
 /// Leader election mechanism for BFT consensus
 #[derive(Clone)]
 pub struct LeaderElection {
-    validator_set: Vec<NodeId>,
+    // Shared between clones so a governance change reaches every component at once
+    validator_set: Arc<RwLock<Vec<NodeId>>>,
     leader_history: HashMap<u64, NodeId>,
+    // Views per shuffle epoch (0 disables shuffling)
+    epoch_length: u64,
+    // Seeds agreed on-chain (e.g. a prior block hash), shared between clones
+    epoch_seeds: Arc<RwLock<HashMap<u64, Hash>>>,
+    // Keys proposers' VRF proofs are checked against, when VRF election is on
+    vrf_keys: Option<Arc<RwLock<HashMap<NodeId, PublicKey>>>>,
+    // VRF output of the head's proposer that orders the next height's views, shared between clones
+    vrf_seed: Arc<RwLock<Option<Hash>>>,
 }
 
 impl LeaderElection {
     /// Create a new leader election instance
     pub fn new(validator_set: Vec<NodeId>) -> Self {
+        Self::with_epoch_shuffle(validator_set, 0)
+    }
+
+    /// Create a leader election instance that reshuffles the order every `epoch_length` views
+    pub fn with_epoch_shuffle(validator_set: Vec<NodeId>, epoch_length: u64) -> Self {
         Self {
-            validator_set,
+            validator_set: Arc::new(RwLock::new(validator_set)),
             leader_history: HashMap::new(),
+            epoch_length,
+            epoch_seeds: Arc::new(RwLock::new(HashMap::new())),
+            vrf_keys: None,
+            vrf_seed: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    /// Order each height's views by the VRF output its parent's proposer attached
+    ///
+    /// Every block carries its proposer's proof for its slot, checked against these
+    /// keys. Nobody but that proposer can predict the next height's leaders before
+    /// the block is out, and as the proof is unique it can't pick them either.
+    pub fn with_vrf(mut self, validator_keys: HashMap<NodeId, PublicKey>) -> Self {
+        self.vrf_keys = Some(Arc::new(RwLock::new(validator_keys)));
+        self
+    }
+
+    /// Whether leaders are elected by VRF
+    pub fn is_vrf(&self) -> bool {
+        self.vrf_keys.is_some()
+    }
+
+    /// Replace the keys VRF proofs are checked against, as when governance changes the set
+    pub fn update_vrf_keys(&self, validator_keys: HashMap<NodeId, PublicKey>) {
+        if let Some(vrf_keys) = &self.vrf_keys {
+            *vrf_keys.write().unwrap() = validator_keys;
         }
     }
 
-    /// Get the leader for a specific view using round-robin selection
+    /// Seed the next height's leader order from a new head
+    ///
+    /// Blocks without a proof, such as genesis, seed it with their hash instead.
+    pub fn set_vrf_head(&self, head: &Block) {
+        let seed = head.header.vrf_proof.as_ref()
+            .map_or_else(|| head.hash(), |proof| proof.output);
+        *self.vrf_seed.write().unwrap() = Some(seed);
+    }
+
+    /// Check a proposer's VRF proof for the slot after `prev_hash` against its known key
+    pub fn verify_leader_proof(&self, prev_hash: Hash, view: u64, leader: &NodeId, proof: &VrfProof) -> bool {
+        if proof.validator_id != *leader {
+            return false;
+        }
+
+        let Some(vrf_keys) = &self.vrf_keys else {
+            return false;
+        };
+        match vrf_keys.read().unwrap().get(leader) {
+            Some(public_key) => proof.verify(public_key, &prev_hash, view),
+            None => false,
+        }
+    }
+
+    /// Get the leader for a specific view
     pub fn get_leader(&self, view: u64) -> NodeId {
-        if self.validator_set.is_empty() {
+        let order = self.view_order(view);
+        if order.is_empty() {
             return "unknown".to_string();
         }
 
-        let leader_index = (view as usize) % self.validator_set.len();
-        self.validator_set[leader_index].clone()
+        order[(view as usize) % order.len()].clone()
+    }
+
+    /// Get the validator ordering a view's leader is picked from: the validator set for
+    /// plain round-robin, the view's epoch shuffle, or a shuffle seeded by the head's VRF output
+    fn view_order(&self, view: u64) -> Vec<NodeId> {
+        let vrf_seed = *self.vrf_seed.read().unwrap();
+        if let (true, Some(seed)) = (self.is_vrf(), vrf_seed) {
+            let mut hasher = Sha256::new();
+            hasher.update(seed);
+            hasher.update(view.to_le_bytes());
+            return self.shuffled(hasher.finalize().into());
+        }
+
+        if self.epoch_length == 0 {
+            return self.get_validator_set();
+        }
+
+        self.epoch_order(self.epoch_for_view(view))
+    }
+
+    /// Get the epoch a view belongs to
+    pub fn epoch_for_view(&self, view: u64) -> u64 {
+        if self.epoch_length == 0 {
+            return 0;
+        }
+        view / self.epoch_length
+    }
+
+    /// Set the seed for an epoch (normally the hash of the last block before it)
+    pub fn set_epoch_seed(&self, epoch: u64, seed: Hash) {
+        let mut epoch_seeds = self.epoch_seeds.write().unwrap();
+        epoch_seeds.insert(epoch, seed);
+    }
+
+    /// Get the deterministic validator ordering for an epoch
+    pub fn epoch_order(&self, epoch: u64) -> Vec<NodeId> {
+        let seed = {
+            let epoch_seeds = self.epoch_seeds.read().unwrap();
+            epoch_seeds.get(&epoch).copied()
+        }
+        .unwrap_or_else(|| {
+            // Fall back to the epoch number when no block hash seed is known
+            let mut hasher = Sha256::new();
+            hasher.update(epoch.to_le_bytes());
+            hasher.finalize().into()
+        });
+
+        self.shuffled(seed)
+    }
+
+    /// Fisher-Yates shuffle of the validator set driven by a hash stream, so every node
+    /// derives the same order from the same seed
+    fn shuffled(&self, seed: Hash) -> Vec<NodeId> {
+        let mut order = self.get_validator_set();
+        for i in (1..order.len()).rev() {
+            let mut hasher = Sha256::new();
+            hasher.update(seed);
+            hasher.update((i as u64).to_le_bytes());
+            let digest: Hash = hasher.finalize().into();
+
+            let mut value = [0u8; 8];
+            value.copy_from_slice(&digest[..8]);
+            let j = (u64::from_le_bytes(value) % (i as u64 + 1)) as usize;
+            order.swap(i, j);
+        }
+
+        order
+    }
+
+    /// Get the backup proposer for a view: the validator after the leader in the same ordering
+    pub fn get_backup_leader(&self, view: u64) -> Option<NodeId> {
+        // Stay within the view's epoch so the backup never coincides with the leader
+        let order = self.view_order(view);
+        if order.len() < 2 {
+            return None;
+        }
+
+        Some(order[(view as usize + 1) % order.len()].clone())
     }
 
     /// Check if a node is the leader for a specific view
@@ -50,42 +195,43 @@ impl LeaderElection {
         schedule
     }
 
-    /// Update validator set (for dynamic validator changes)
-    pub fn update_validator_set(&mut self, new_validator_set: Vec<NodeId>) {
-        self.validator_set = new_validator_set;
-        // Clear history as validator set changed
-        self.leader_history.clear();
+    /// Update validator set (for dynamic validator changes), for this instance and all its clones
+    pub fn update_validator_set(&self, new_validator_set: Vec<NodeId>) {
+        let mut validator_set = self.validator_set.write().unwrap();
+        *validator_set = new_validator_set;
     }
 
     /// Get current validator set
-    pub fn get_validator_set(&self) -> &[NodeId] {
-        &self.validator_set
+    pub fn get_validator_set(&self) -> Vec<NodeId> {
+        self.validator_set.read().unwrap().clone()
     }
 
     /// Check if a node is a validator
     pub fn is_validator(&self, node_id: &NodeId) -> bool {
-        self.validator_set.contains(node_id)
+        self.validator_set.read().unwrap().contains(node_id)
     }
 
     /// Get validator count
     pub fn validator_count(&self) -> usize {
-        self.validator_set.len()
+        self.validator_set.read().unwrap().len()
     }
 
     /// Calculate Byzantine fault tolerance threshold
     pub fn byzantine_threshold(&self) -> usize {
-        if self.validator_set.is_empty() {
+        let count = self.validator_count();
+        if count == 0 {
             return 0;
         }
-        (self.validator_set.len() * 2 / 3) + 1
+        (count * 2 / 3) + 1
     }
 
     /// Get maximum number of Byzantine faults tolerated
     pub fn max_byzantine_faults(&self) -> usize {
-        if self.validator_set.len() < 4 {
+        let count = self.validator_count();
+        if count < 4 {
             return 0;
         }
-        (self.validator_set.len() - 1) / 3
+        (count - 1) / 3
     }
 
     /// Record leader for a view (for history tracking)
@@ -100,17 +246,17 @@ impl LeaderElection {
 
     /// Check if the validator set satisfies BFT requirements
     pub fn is_bft_capable(&self) -> bool {
-        self.validator_set.len() >= 4 // Need at least 4 nodes for BFT (3f+1 where f=1)
+        self.validator_count() >= 4 // Need at least 4 nodes for BFT (3f+1 where f=1)
     }
 
     /// Get validator index
     pub fn get_validator_index(&self, node_id: &NodeId) -> Option<usize> {
-        self.validator_set.iter().position(|v| v == node_id)
+        self.validator_set.read().unwrap().iter().position(|v| v == node_id)
     }
 
     /// Get validator by index
-    pub fn get_validator_by_index(&self, index: usize) -> Option<&NodeId> {
-        self.validator_set.get(index)
+    pub fn get_validator_by_index(&self, index: usize) -> Option<NodeId> {
+        self.validator_set.read().unwrap().get(index).cloned()
     }
 }
 
@@ -182,4 +328,123 @@ mod tests {
         assert_eq!(schedule[1], (1, validators[1].clone()));
         assert_eq!(schedule[4], (4, validators[0].clone())); // Wraps around
     }
+
+    fn create_large_validator_set() -> Vec<NodeId> {
+        (1..=16).map(|i| format!("validator-{}", i)).collect()
+    }
+
+    #[test]
+    fn test_epoch_shuffle_is_deterministic() {
+        let validators = create_large_validator_set();
+        let node_a = LeaderElection::with_epoch_shuffle(validators.clone(), 10);
+        let node_b = LeaderElection::with_epoch_shuffle(validators.clone(), 10);
+
+        // Same seed on both nodes
+        node_a.set_epoch_seed(1, [7; 32]);
+        node_b.set_epoch_seed(1, [7; 32]);
+
+        for view in 0..40 {
+            assert_eq!(node_a.get_leader(view), node_b.get_leader(view));
+        }
+
+        // Every validator still leads exactly once per rotation
+        let mut order = node_a.epoch_order(1);
+        order.sort();
+        let mut expected = validators;
+        expected.sort();
+        assert_eq!(order, expected);
+    }
+
+    fn create_vrf_validators() -> Vec<(NodeId, ed25519_dalek::SigningKey)> {
+        (1..=4)
+            .map(|i| (format!("validator-{}", i), ed25519_dalek::SigningKey::from_bytes(&[i as u8; 32])))
+            .collect()
+    }
+
+    fn create_vrf_node(validators: &[(NodeId, ed25519_dalek::SigningKey)]) -> LeaderElection {
+        let keys = validators.iter()
+            .map(|(id, key)| (id.clone(), key.verifying_key().to_bytes()))
+            .collect();
+        LeaderElection::new(validators.iter().map(|(id, _)| id.clone()).collect()).with_vrf(keys)
+    }
+
+    fn create_vrf_head(validators: &[(NodeId, ed25519_dalek::SigningKey)], proposer: usize, prev_hash: Hash) -> Block {
+        let (id, key) = &validators[proposer];
+        Block {
+            header: crate::types::BlockHeader {
+                height: 1,
+                previous_hash: prev_hash,
+                merkle_root: [0; 32],
+                state_root: [0; 32],
+                timestamp: 0,
+                proposer: id.clone(),
+                round: 0,
+                view: 0,
+                vrf_proof: Some(VrfProof::prove(id.clone(), key, &prev_hash, 0)),
+            },
+            transactions: vec![],
+            signatures: vec![],
+        }
+    }
+
+    #[test]
+    fn test_vrf_leader_agreed_and_verified() {
+        let validators = create_vrf_validators();
+        let nodes: Vec<LeaderElection> = validators.iter().map(|_| create_vrf_node(&validators)).collect();
+        let prev_hash = [7; 32];
+
+        // Until a head is known the order is plain round-robin
+        assert_eq!(nodes[0].get_leader(1), validators[1].0);
+
+        // Every node derives the same schedule from the head's proof
+        let head = create_vrf_head(&validators, 1, prev_hash);
+        for node in &nodes {
+            node.set_vrf_head(&head);
+        }
+        let schedule = nodes[0].get_leader_schedule(0, 8);
+        for node in &nodes[1..] {
+            assert_eq!(node.get_leader_schedule(0, 8), schedule);
+        }
+
+        // Another head's proof orders the views differently
+        let other_head = create_vrf_head(&validators, 2, prev_hash);
+        let other_node = create_vrf_node(&validators);
+        other_node.set_vrf_head(&other_head);
+        assert_ne!(other_node.get_leader_schedule(0, 8), schedule);
+
+        // The head's proof verifies for its proposer and slot only
+        let proof = head.header.vrf_proof.clone().unwrap();
+        let proposer = &validators[1].0;
+        assert!(nodes[1].verify_leader_proof(prev_hash, 0, proposer, &proof));
+        assert!(!nodes[1].verify_leader_proof(prev_hash, 1, proposer, &proof));
+        assert!(!nodes[1].verify_leader_proof([8; 32], 0, proposer, &proof));
+        let other = &validators[2].0;
+        let mut forged = proof.clone();
+        forged.validator_id = other.clone();
+        assert!(!nodes[1].verify_leader_proof(prev_hash, 0, other, &forged));
+
+        // A chosen output without a matching proof is rejected
+        let mut tampered = proof.clone();
+        tampered.output = [0; 32];
+        assert!(!nodes[1].verify_leader_proof(prev_hash, 0, proposer, &tampered));
+
+        // Without VRF election the head is ignored
+        let plain = LeaderElection::new(validators.iter().map(|(id, _)| id.clone()).collect());
+        plain.set_vrf_head(&head);
+        assert_eq!(plain.get_leader(1), validators[1].0);
+        assert!(!plain.verify_leader_proof(prev_hash, 0, proposer, &proof));
+    }
+
+    #[test]
+    fn test_epoch_shuffle_differs_across_epochs() {
+        let validators = create_large_validator_set();
+        let leader_election = LeaderElection::with_epoch_shuffle(validators, 10);
+
+        let epoch_0 = leader_election.epoch_order(0);
+        let epoch_1 = leader_election.epoch_order(1);
+        assert_ne!(epoch_0, epoch_1);
+
+        assert_eq!(leader_election.epoch_for_view(9), 0);
+        assert_eq!(leader_election.epoch_for_view(10), 1);
+    }
 }
@@ -2,23 +2,62 @@ use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, RwLock};
 use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
-use anyhow::{Result, anyhow};
+use anyhow::Result;
+use ed25519_dalek::SigningKey;
+use serde::{Serialize, Deserialize};
 use uuid::Uuid;
 
+use crate::crypto::{default_scheme, SignatureScheme};
 use crate::types::{
-    Block, ConsensusMessage, VoteType, Hash, NodeId, BlockHeight,
-    ValidatorSignature, NetworkMessage, MessagePayload
+    Block, ConsensusMessage, VoteType, Hash, NodeId, BlockHeight, PublicKey,
+    ValidatorSignature, NetworkMessage, MessagePayload, verify_vote
 };
-use crate::storage::Storage;
+use crate::storage::{ChainUpdate, Storage};
 use crate::mempool::Mempool;
+use crate::metrics::NodeMetrics;
+use crate::validation::{DynamicBlockSizeConfig, ValidationError, Validator};
+use crate::webhook::WebhookNotifier;
+use crate::shutdown::Shutdown;
 
 pub mod pbft;
 pub mod leader_election;
 pub mod view_change;
+pub mod reorg;
+pub mod block_interval;
+pub mod observer;
+pub mod vrf;
+pub mod governance;
 
 pub use pbft::PbftEngine;
 pub use leader_election::LeaderElection;
-pub use view_change::ViewChangeManager;
+pub use view_change::{ViewChangeManager, ViewChangeTimeout};
+pub use reorg::{ReorgTracker, ReorgStats};
+pub use block_interval::{BlockIntervalController, BlockIntervalConfig};
+pub use observer::{ConsensusObserver, NoopObserver};
+use vrf::VrfProof;
+
+/// Consensus errors
+#[derive(Debug, thiserror::Error)]
+pub enum ConsensusError {
+    #[error("Consensus already started")]
+    AlreadyStarted,
+    #[error("Block {0} not found in pending blocks")]
+    UnknownBlock(String),
+    #[error("Fee total of block at height {0} overflows")]
+    FeeOverflow(BlockHeight),
+    #[error("Invalid proposer for view {0}")]
+    InvalidProposer(u64),
+    #[error("View {new_view} is not greater than current view {current_view}")]
+    StaleView { new_view: u64, current_view: u64 },
+    #[error("Insufficient view change messages: have {have}, need {need}")]
+    InsufficientViewChanges { have: usize, need: usize },
+    #[error("Invalid NEW-VIEW message: {0}")]
+    InvalidNewView(&'static str),
+    #[error("Failed to send consensus message: {0}")]
+    ChannelClosed(String),
+    #[error("Invalid consensus configuration: {0}")]
+    InvalidConfig(String),
+}
 
 /// Consensus engine configuration
 #[derive(Debug, Clone)]
@@ -27,9 +66,41 @@ pub struct ConsensusConfig {
     pub is_validator: bool,
     pub validator_set: Vec<NodeId>,
     pub block_time_ms: u64,
-    pub view_timeout_ms: u64,
+    /// Base view timeout and its backoff after each failed view
+    pub view_timeout: ViewChangeTimeout,
     pub max_block_size: usize,
     pub max_transactions_per_block: usize,
+    pub leader_shuffle_epoch_length: u64,
+    pub dynamic_block_size: Option<DynamicBlockSizeConfig>,
+    pub block_interval: Option<BlockIntervalConfig>,
+    /// Silence from the leader, past its proposal time, before the backup proposes (None disables)
+    pub backup_proposer_timeout_ms: Option<u64>,
+    /// Time since the last block after which the leader proposes even with an empty mempool (None disables)
+    pub empty_block_timeout_ms: Option<u64>,
+    /// Fewest transactions a proposal may carry while our mempool holds at least as many (None disables)
+    pub min_transactions_per_block: Option<usize>,
+    /// PBFT instances kept in the log behind the stable checkpoint before they are discarded
+    pub pbft_log_retention: usize,
+    /// Commit our own proposals immediately when we are the only validator
+    pub dev_mode: bool,
+    /// Order each height's leaders by the VRF output the previous block's proposer attached
+    pub vrf_leader_election: bool,
+}
+
+/// Metadata key the engine's persisted progress is stored under
+const CONSENSUS_STATE_KEY: &str = "consensus_state";
+
+/// Consensus progress persisted so a restarted validator never votes twice for a slot
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct PersistedConsensusState {
+    pub height: BlockHeight,
+    pub view: u64,
+    pub round: u64,
+    pub prepared_slot: Option<(u64, u64, Hash)>,
+    /// Validator set in effect, including governance changes
+    pub validator_set: Vec<NodeId>,
+    pub prepared_certificates: Vec<(u64, u64, Hash)>,
+    pub committed_certificates: Vec<(u64, u64, Hash)>,
 }
 
 /// Consensus state
@@ -51,10 +122,48 @@ pub struct ConsensusStats {
     pub total_blocks_proposed: u64,
     pub total_blocks_committed: u64,
     pub total_view_changes: u64,
+    pub total_fees_collected: u64,
     pub avg_consensus_time_ms: f64,
     pub current_leader: Option<NodeId>,
 }
 
+/// Times each height from the first proposal seen for it until it commits
+#[derive(Debug, Default)]
+struct RoundTimer {
+    // Height being timed and when consensus on it started
+    current: Option<(BlockHeight, Instant)>,
+    // Heights timed so far, weighting the running average
+    timed: u64,
+}
+
+impl RoundTimer {
+    /// Start timing `height`, unless an earlier proposal already started it
+    fn start(&mut self, height: BlockHeight) {
+        if !matches!(self.current, Some((timed_height, _)) if timed_height == height) {
+            self.current = Some((height, Instant::now()));
+        }
+    }
+
+    /// Time the current height from now, as a view change abandons the attempt so far
+    fn restart(&mut self) {
+        if let Some((_, started)) = &mut self.current {
+            *started = Instant::now();
+        }
+    }
+
+    /// Stop timing `height`, returning how long it took to commit
+    fn finish(&mut self, height: BlockHeight) -> Option<Duration> {
+        match self.current {
+            Some((timed_height, started)) if timed_height == height => {
+                self.current = None;
+                self.timed += 1;
+                Some(started.elapsed())
+            }
+            _ => None,
+        }
+    }
+}
+
 /// Main consensus engine implementing Byzantine Fault Tolerant consensus
 pub struct ConsensusEngine {
     config: ConsensusConfig,
@@ -67,6 +176,7 @@ pub struct ConsensusEngine {
     pbft_engine: PbftEngine,
     leader_election: LeaderElection,
     view_change_manager: ViewChangeManager,
+    reorg_tracker: ReorgTracker,
     
     // Storage and mempool
     storage: Arc<Storage>,
@@ -78,14 +188,44 @@ pub struct ConsensusEngine {
     
     // Consensus data
     pending_blocks: Arc<RwLock<HashMap<Hash, Block>>>,
-    votes: Arc<RwLock<HashMap<(u64, u64, Hash), HashMap<NodeId, ConsensusMessage>>>>,
+    // Time from first proposal to commit of the height in progress
+    round_timer: Arc<RwLock<RoundTimer>>,
+    // (view, round, block) this node prepared; at most one block per slot
+    prepared_slot: Arc<RwLock<Option<(u64, u64, Hash)>>>,
+    
+    // Validators' keys votes are checked against, and the scheme they sign under;
+    // refreshed from storage as governance changes the set
+    validator_keys: Arc<RwLock<HashMap<NodeId, PublicKey>>>,
+    signature_scheme: Arc<dyn SignatureScheme>,
+    
+    // Key this validator proves its VRF output for a slot with
+    vrf_key: Option<SigningKey>,
+    
+    // External commit notification
+    webhook: Option<WebhookNotifier>,
+    
+    // Instrumentation hooks for state transitions
+    observer: Arc<dyn ConsensusObserver>,
+    metrics: Option<Arc<NodeMetrics>>,
+    
+    // Checks proposals with the same rules as mempool admission and sync
+    validator: Arc<Validator>,
+    // Re-checks mempool batches against current state before proposing
+    batch_validator: Option<Arc<Validator>>,
+    
+    // Set when the node is draining ahead of shutdown
+    draining: Arc<RwLock<bool>>,
     
     // Statistics
     stats: Arc<RwLock<ConsensusStats>>,
     
     // Timing
     last_block_time: Arc<RwLock<Instant>>,
+    interval_controller: Option<BlockIntervalController>,
     view_timeout: Arc<RwLock<Option<tokio::time::Instant>>>,
+
+    // Stops the consensus and message loops
+    shutdown: Shutdown,
 }
 
 impl ConsensusEngine {
@@ -95,25 +235,52 @@ impl ConsensusEngine {
         storage: Arc<Storage>,
         mempool: Arc<Mempool>,
     ) -> Result<Self> {
+        config.view_timeout.validate()?;
         let (message_sender, message_receiver) = mpsc::unbounded_channel();
         
-        let pbft_engine = PbftEngine::new(config.clone())?;
-        let leader_election = LeaderElection::new(config.validator_set.clone());
-        let view_change_manager = ViewChangeManager::new(config.clone());
+        let mut leader_election = LeaderElection::with_epoch_shuffle(
+            config.validator_set.clone(),
+            config.leader_shuffle_epoch_length,
+        );
+        if config.vrf_leader_election {
+            // Keys arrive with the validator keys or the stored validator set
+            leader_election = leader_election.with_vrf(HashMap::new());
+        }
+        let pbft_engine = PbftEngine::new(config.clone(), leader_election.clone())?;
+        let view_change_manager = ViewChangeManager::new(config.clone(), leader_election.clone());
         
-        // Initialize current state from storage
-        let current_height = storage.get_latest_height()?.unwrap_or(0);
+        // Initialize current state from storage, resuming where a previous run left off
+        let persisted: PersistedConsensusState = storage.get_metadata(CONSENSUS_STATE_KEY)?.unwrap_or_default();
+        if !persisted.validator_set.is_empty() {
+            leader_election.update_validator_set(persisted.validator_set.clone());
+        }
+        let current_height = storage.get_latest_height()?.unwrap_or(0).max(persisted.height);
+        pbft_engine.restore(
+            persisted.view,
+            persisted.round,
+            persisted.prepared_certificates,
+            persisted.committed_certificates,
+        );
+        let interval_controller = config.block_interval.clone().map(BlockIntervalController::new);
+        let validator = Arc::new(Validator::new((*storage).clone()));
+        let stats = ConsensusStats {
+            current_height,
+            current_view: persisted.view,
+            current_round: persisted.round,
+            ..ConsensusStats::default()
+        };
         
-        Ok(Self {
+        let engine = Self {
             config,
             state: Arc::new(RwLock::new(ConsensusState::Idle)),
-            current_view: Arc::new(RwLock::new(0)),
-            current_round: Arc::new(RwLock::new(0)),
+            current_view: Arc::new(RwLock::new(persisted.view)),
+            current_round: Arc::new(RwLock::new(persisted.round)),
             current_height: Arc::new(RwLock::new(current_height)),
             
             pbft_engine,
             leader_election,
             view_change_manager,
+            reorg_tracker: ReorgTracker::new(),
             
             storage,
             mempool,
@@ -122,17 +289,102 @@ impl ConsensusEngine {
             message_receiver: Arc::new(RwLock::new(Some(message_receiver))),
             
             pending_blocks: Arc::new(RwLock::new(HashMap::new())),
-            votes: Arc::new(RwLock::new(HashMap::new())),
+            round_timer: Arc::new(RwLock::new(RoundTimer::default())),
+            prepared_slot: Arc::new(RwLock::new(persisted.prepared_slot)),
+            
+            validator_keys: Arc::new(RwLock::new(HashMap::new())),
+            signature_scheme: default_scheme(),
+            
+            vrf_key: None,
+            
+            webhook: None,
             
-            stats: Arc::new(RwLock::new(ConsensusStats::default())),
+            observer: Arc::new(NoopObserver),
+            metrics: None,
+            
+            validator,
+            batch_validator: None,
+            
+            draining: Arc::new(RwLock::new(false)),
+            
+            stats: Arc::new(RwLock::new(stats)),
             
             last_block_time: Arc::new(RwLock::new(Instant::now())),
+            interval_controller,
             view_timeout: Arc::new(RwLock::new(None)),
-        })
+
+            shutdown: Shutdown::never(),
+        };
+
+        // Governance changes committed before a restart are in storage
+        engine.refresh_validator_set()?;
+        engine.refresh_vrf_seed()?;
+        Ok(engine)
+    }
+
+    /// Return from `start` once `shutdown` is signalled
+    pub fn with_shutdown(mut self, shutdown: Shutdown) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    /// Notify an external webhook on each committed block
+    pub fn with_webhook(mut self, webhook: WebhookNotifier) -> Self {
+        self.webhook = Some(webhook);
+        self
+    }
+
+    /// Invoke the observer at each consensus state transition
+    pub fn with_observer(mut self, observer: Arc<dyn ConsensusObserver>) -> Self {
+        self.observer = observer;
+        self
+    }
+
+    /// Report committed blocks, consensus latency, view changes and votes
+    pub fn with_metrics(mut self, metrics: Arc<NodeMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Sign this node's votes, and prove its VRF output under VRF leader election, with its validator key
+    pub fn with_signing_key(mut self, signing_key: SigningKey) -> Self {
+        self.vrf_key = Some(signing_key.clone());
+        self.pbft_engine = self.pbft_engine.with_signing_key(signing_key);
+        self
+    }
+
+    /// Sign and check votes and proposed transactions under `scheme` instead of Ed25519
+    pub fn with_signature_scheme(mut self, scheme: Arc<dyn SignatureScheme>) -> Self {
+        self.pbft_engine = self.pbft_engine.with_signature_scheme(scheme.clone());
+        self.signature_scheme = scheme;
+        self
+    }
+
+    /// Public keys votes are checked against; a validator missing here has its votes dropped
+    ///
+    /// Replaced by the validator set stored at the head, if there is one, once the
+    /// engine starts or commits a block.
+    pub fn with_validator_keys(mut self, validator_keys: HashMap<NodeId, PublicKey>) -> Self {
+        self.leader_election.update_vrf_keys(validator_keys.clone());
+        self.validator_keys = Arc::new(RwLock::new(validator_keys));
+        self
+    }
+
+    /// Check proposals with the node's validator, sharing its allowlist and limits
+    pub fn with_validator(mut self, validator: Arc<Validator>) -> Self {
+        self.validator = validator;
+        self
+    }
+
+    /// Drop batch transactions that are no longer valid against current state before proposing
+    pub fn with_batch_prevalidation(mut self, validator: Arc<Validator>) -> Self {
+        self.batch_validator = Some(validator);
+        self
     }
 
     /// Start the consensus engine
     pub async fn start(&self) -> Result<()> {
+        self.refresh_validator_set()?;
         if !self.config.is_validator {
             return self.start_observer_mode().await;
         }
@@ -140,47 +392,85 @@ impl ConsensusEngine {
         // Start message processing
         let message_receiver = {
             let mut receiver_guard = self.message_receiver.write().unwrap();
-            receiver_guard.take().ok_or_else(|| anyhow!("Consensus already started"))?
+            receiver_guard.take().ok_or(ConsensusError::AlreadyStarted)?
         };
 
         // Spawn message processing task
         let engine = self.clone();
-        tokio::spawn(async move {
+        let messages = tokio::spawn(async move {
             engine.process_messages(message_receiver).await;
         });
 
         // Start consensus rounds
-        self.start_consensus_loop().await
+        self.start_consensus_loop().await?;
+
+        // The loop only returns cleanly on shutdown, which stops message processing too
+        let _ = messages.await;
+        Ok(())
     }
 
     /// Start consensus loop for validators
     async fn start_consensus_loop(&self) -> Result<()> {
-        let mut interval = tokio::time::interval(Duration::from_millis(self.config.block_time_ms));
-        
+        let mut shutdown = self.shutdown.clone();
         loop {
-            interval.tick().await;
-            
-            // Check if we should propose a new block
-            if self.should_propose_block().await? {
-                if let Err(e) = self.propose_block().await {
-                    tracing::error!("Failed to propose block: {}", e);
-                }
+            tokio::select! {
+                _ = shutdown.wait() => break,
+                _ = tokio::time::sleep(self.proposal_interval()) => {}
             }
-            
-            // Check for view timeout
-            if self.should_trigger_view_change().await {
-                if let Err(e) = self.trigger_view_change().await {
-                    tracing::error!("Failed to trigger view change: {}", e);
-                }
+            self.run_consensus_tick().await?;
+        }
+
+        tracing::info!("Consensus loop stopped");
+        Ok(())
+    }
+
+    /// Run one iteration of the validator consensus loop
+    async fn run_consensus_tick(&self) -> Result<()> {
+        // Blocks applied by sync may have changed the validator set or the head
+        self.refresh_validator_set()?;
+        self.refresh_vrf_seed()?;
+        self.record_leader();
+
+        // Check if we should propose a new block
+        let should_propose = if self.should_propose_block().await? {
+            true
+        } else if self.should_propose_as_backup()? {
+            tracing::info!("Leader silent past the backup timeout, proposing as backup");
+            true
+        } else {
+            false
+        };
+
+        if should_propose {
+            if let Err(e) = self.propose_block().await {
+                tracing::error!("Failed to propose block: {}", e);
+            }
+        }
+
+        // Pending work with no timer armed means a round is open and waiting on the leader
+        let timer_armed = self.view_timeout.read().unwrap().is_some();
+        if !timer_armed && self.mempool.size() > 0 {
+            self.arm_view_timeout();
+        }
+        
+        // Check for view timeout
+        if self.should_trigger_view_change().await {
+            if let Err(e) = self.trigger_view_change().await {
+                tracing::error!("Failed to trigger view change: {}", e);
             }
         }
+
+        // Discard PBFT instances that fell out of the retention window
+        self.pbft_engine.cleanup_old_logs(self.config.pbft_log_retention);
+
+        Ok(())
     }
 
     /// Start observer mode (non-validator)
     async fn start_observer_mode(&self) -> Result<()> {
         let message_receiver = {
             let mut receiver_guard = self.message_receiver.write().unwrap();
-            receiver_guard.take().ok_or_else(|| anyhow!("Consensus already started"))?
+            receiver_guard.take().ok_or(ConsensusError::AlreadyStarted)?
         };
 
         // Only process messages, don't participate in consensus
@@ -190,7 +480,16 @@ impl ConsensusEngine {
 
     /// Process incoming consensus messages
     async fn process_messages(&self, mut receiver: mpsc::UnboundedReceiver<NetworkMessage>) {
-        while let Some(message) = receiver.recv().await {
+        let mut shutdown = self.shutdown.clone();
+        loop {
+            let message = tokio::select! {
+                _ = shutdown.wait() => break,
+                message = receiver.recv() => match message {
+                    Some(message) => message,
+                    None => break,
+                },
+            };
+
             if let MessagePayload::Consensus(consensus_msg) = message.payload {
                 if let Err(e) = self.handle_consensus_message(consensus_msg).await {
                     tracing::error!("Failed to handle consensus message: {}", e);
@@ -227,21 +526,67 @@ impl ConsensusEngine {
             return Ok(()); // Ignore outdated proposals
         }
 
+        // Never vote again at a height this node has already committed
+        let current_height = *self.current_height.read().unwrap();
+        if block.header.height <= current_height {
+            tracing::warn!("Ignoring proposal for already committed height {}", block.header.height);
+            return Ok(());
+        }
+
+        if !self.pbft_engine.is_eligible_proposer(&block.header.proposer, view) {
+            tracing::warn!("Ignoring proposal from {}, not a proposer for view {}", block.header.proposer, view);
+            return Ok(());
+        }
+
+        // Its VRF output orders the next height's leaders, so it must be the proposer's own for this slot
+        if self.leader_election.is_vrf() {
+            let proven = block.header.vrf_proof.as_ref().is_some_and(|proof| {
+                self.leader_election.verify_leader_proof(block.header.previous_hash, view, &block.header.proposer, proof)
+            });
+            if !proven {
+                tracing::warn!("Ignoring proposal from {} without a valid VRF proof for view {}", block.header.proposer, view);
+                return Ok(());
+            }
+        }
+
         // Validate the proposed block
         if !self.validate_proposed_block(&block).await? {
             tracing::warn!("Received invalid block proposal");
             return Ok(());
         }
 
-        // Store the block
+        // Prepare at most one block per slot, so the leader's and the backup's
+        // proposals can never both gather a quorum
         let block_hash = block.hash();
+        {
+            let mut prepared_slot = self.prepared_slot.write().unwrap();
+            match *prepared_slot {
+                Some((prepared_view, prepared_round, prepared_hash))
+                    if prepared_view == view && prepared_round == round && prepared_hash != block_hash =>
+                {
+                    tracing::info!("Already prepared a block for view {} round {}, ignoring competing proposal", view, round);
+                    return Ok(());
+                }
+                _ => *prepared_slot = Some((view, round, block_hash)),
+            }
+        }
+        self.persist_state()?;
+
+        self.observer.on_propose(&block, view, round);
+
+        // Store the block
         {
             let mut pending_blocks = self.pending_blocks.write().unwrap();
-            pending_blocks.insert(block_hash, block);
+            pending_blocks.insert(block_hash, block.clone());
+            self.round_timer.write().unwrap().start(block.header.height);
         }
 
-        // Send prepare vote
-        self.send_vote(block_hash, VoteType::Prepare, round, view).await?;
+        // The round must now complete before the timeout
+        self.arm_view_timeout();
+
+        // Log the pre-prepare and send our prepare vote
+        let responses = self.pbft_engine.process_message(ConsensusMessage::Propose { block, round, view })?;
+        self.broadcast_all(responses).await?;
         
         // Update state
         {
@@ -271,76 +616,52 @@ impl ConsensusEngine {
         }
 
         // Verify validator is in validator set
-        if !self.config.validator_set.contains(&validator_id) {
+        if !self.leader_election.is_validator(&validator_id) {
             return Ok(()); // Ignore votes from non-validators
         }
 
-        // Store the vote
-        let vote_key = (view, round, block_hash);
-        let vote_message = ConsensusMessage::Vote {
+        // Every vote must be signed by its validator; without a known key it can't count
+        let valid = self.validator_keys.read().unwrap().get(&validator_id)
+            .map(|key| verify_vote(self.signature_scheme.as_ref(), key, &block_hash, view, round, &vote_type, &signature))
+            .unwrap_or(false);
+        if !valid {
+            tracing::warn!("Dropping {:?} vote from {} with an invalid signature", vote_type, validator_id);
+            return Ok(());
+        }
+
+        self.observer.on_vote(&block_hash, &vote_type, &validator_id, view, round);
+        if let Some(metrics) = &self.metrics {
+            metrics.record_vote(&validator_id, &vote_type, round);
+        }
+
+        // PBFT counts the vote and tells us what to send next
+        let responses = self.pbft_engine.process_message(ConsensusMessage::Vote {
             block_hash,
-            vote_type: vote_type.clone(),
+            vote_type,
             round,
             view,
-            validator_id: validator_id.clone(),
+            validator_id,
             signature,
-        };
-
-        {
-            let mut votes = self.votes.write().unwrap();
-            votes.entry(vote_key)
-                .or_insert_with(HashMap::new)
-                .insert(validator_id, vote_message);
-        }
-
-        // Check if we have enough votes to proceed
-        self.check_vote_threshold(block_hash, vote_type, round, view).await?;
+        })?;
+        self.broadcast_all(responses).await?;
 
-        Ok(())
+        self.advance_phase(block_hash, round, view).await
     }
 
-    /// Check if we have enough votes to proceed to next phase
-    async fn check_vote_threshold(
-        &self,
-        block_hash: Hash,
-        vote_type: VoteType,
-        round: u64,
-        view: u64,
-    ) -> Result<()> {
-        let vote_key = (view, round, block_hash);
-        let required_votes = (self.config.validator_set.len() * 2 / 3) + 1;
-        
-        let vote_count = {
-            let votes = self.votes.read().unwrap();
-            votes.get(&vote_key)
-                .map(|vote_map| {
-                    vote_map.values()
-                        .filter(|msg| {
-                            if let ConsensusMessage::Vote { vote_type: msg_vote_type, .. } = msg {
-                                msg_vote_type == &vote_type
-                            } else {
-                                false
-                            }
-                        })
-                        .count()
-                })
-                .unwrap_or(0)
-        };
-
-        if vote_count >= required_votes {
-            match vote_type {
-                VoteType::Prepare => {
-                    // Move to commit phase
-                    self.send_vote(block_hash, VoteType::Commit, round, view).await?;
-                    
-                    let mut state = self.state.write().unwrap();
-                    *state = ConsensusState::Committing;
-                }
-                VoteType::Commit => {
-                    // Commit the block
-                    self.commit_block(block_hash).await?;
-                }
+    /// Follow the PBFT certificates for a block: commit it once, or move to the commit phase
+    async fn advance_phase(&self, block_hash: Hash, round: u64, view: u64) -> Result<()> {
+        if self.pbft_engine.is_committed(view, round, block_hash) {
+            // Later commit votes arrive after the block has left the pending set
+            let pending = self.pending_blocks.read().unwrap().contains_key(&block_hash);
+            if pending {
+                self.commit_block(block_hash).await?;
             }
+        } else if self.pbft_engine.is_prepared(view, round, block_hash) {
+            {
+                let mut state = self.state.write().unwrap();
+                *state = ConsensusState::Committing;
+            }
+            self.persist_state()?;
         }
 
         Ok(())
@@ -348,24 +669,75 @@ impl ConsensusEngine {
 
     /// Commit a block to the blockchain
     async fn commit_block(&self, block_hash: Hash) -> Result<()> {
-        let block = {
+        let started = Instant::now();
+        let mut block = {
             let pending_blocks = self.pending_blocks.read().unwrap();
             pending_blocks.get(&block_hash).cloned()
-                .ok_or_else(|| anyhow!("Block not found in pending blocks"))?
+                .ok_or_else(|| ConsensusError::UnknownBlock(hex::encode(block_hash)))?
         };
 
-        // Store the block
-        self.storage.store_block(&block)?;
+        // The commit votes become the block's finality certificate
+        block.signatures = self.collect_commit_signatures(&block, block_hash);
+
+        // Reject an overflowing fee total the same way on every node, before any state changes
+        let total_fees = block.total_fees()
+            .ok_or(ConsensusError::FeeOverflow(block.header.height))?;
+
+        // The round made progress; drop its timeout and any backoff
+        self.clear_view_timeout();
+        self.view_change_manager.reset_timeout();
+
+        // Store the block and apply its transfers, switching branches if it completes a longer one
+        let update = self.storage.commit_block(&block)?;
+        self.reorg_tracker.observe(&update);
+
+        // A side block moves neither the head nor consensus; its transactions may still be needed
+        if update == ChainUpdate::SideBranch {
+            tracing::warn!(
+                "Block {} at height {} competes with the canonical chain; kept on a side branch",
+                hex::encode(block_hash), block.header.height
+            );
+            self.pending_blocks.write().unwrap().remove(&block_hash);
+            *self.state.write().unwrap() = ConsensusState::Idle;
+            return Ok(());
+        }
 
-        // Update current height
+        self.observer.on_commit(&block);
+        self.record_consensus_time(block.header.height);
+        self.record_commit_metrics(&block, started);
+
+        // The head is this block, at the end of whichever branch it completed
+        let head_height = self.storage.get_latest_height()?.unwrap_or(block.header.height);
         {
             let mut current_height = self.current_height.write().unwrap();
-            *current_height = block.header.height;
+            *current_height = head_height;
+        }
+
+        // Validator set changes take effect from the next height, on every node alike
+        self.refresh_validator_set()?;
+        self.leader_election.set_vrf_head(&block);
+
+        // Feed the measured block interval to the controller
+        {
+            let mut last_block_time = self.last_block_time.write().unwrap();
+            let measured = last_block_time.elapsed();
+            *last_block_time = Instant::now();
+
+            if let Some(controller) = &self.interval_controller {
+                controller.record_interval(measured);
+            }
         }
 
         // Remove committed transactions from mempool
         for tx in &block.transactions {
-            let _ = self.mempool.remove_transaction(&tx.id);
+            self.mempool.mark_committed(tx);
+            let _ = self.mempool.remove_committed(&tx.id);
+        }
+
+        // Transactions only the rolled-back blocks carried are pending again
+        if let ChainUpdate::Reorged { orphaned, .. } = &update {
+            let readmitted = self.mempool.readmit(orphaned.clone())?;
+            tracing::info!("Returned {} of {} rolled-back transactions to the mempool", readmitted, orphaned.len());
         }
 
         // Clean up
@@ -377,9 +749,12 @@ impl ConsensusEngine {
         // Update statistics
         {
             let mut stats = self.stats.write().unwrap();
-            stats.current_height = block.header.height;
+            stats.current_height = head_height;
             stats.total_blocks_committed += 1;
+            stats.total_fees_collected = stats.total_fees_collected.saturating_add(total_fees);
         }
+        // Governance may have changed who leads the view
+        self.record_leader();
 
         // Reset state for next round
         {
@@ -392,13 +767,122 @@ impl ConsensusEngine {
             *current_round += 1;
         }
 
+        // Instances up to this commit are final; the consensus loop prunes the log behind them
+        self.pbft_engine.checkpoint();
+        self.persist_state()?;
+
+        // Best-effort; never waits on the endpoint
+        if let Some(webhook) = &self.webhook {
+            webhook.notify(&block);
+        }
+
         tracing::info!("Block committed at height {}", block.header.height);
 
         Ok(())
     }
 
+    /// Count a committed block and time how long storing it took
+    fn record_commit_metrics(&self, block: &Block, started: Instant) {
+        let Some(metrics) = &self.metrics else {
+            return;
+        };
+
+        metrics.blocks_processed.inc();
+        metrics.block_height.set(block.header.height as i64);
+        metrics.transactions_processed.inc_by(block.transactions.len() as u64);
+        metrics.consensus_rounds.inc();
+        metrics.block_processing_time.observe(started.elapsed().as_secs_f64());
+        if let Ok(size) = bincode::serialized_size(block) {
+            metrics.block_size.observe(size as f64);
+        }
+    }
+
+    /// Fold the time a height took from first proposal to commit into the stats
+    fn record_consensus_time(&self, height: BlockHeight) {
+        let (elapsed, timed) = {
+            let mut round_timer = self.round_timer.write().unwrap();
+            match round_timer.finish(height) {
+                Some(elapsed) => (elapsed, round_timer.timed),
+                None => return,
+            }
+        };
+
+        {
+            let mut stats = self.stats.write().unwrap();
+            let elapsed_ms = elapsed.as_secs_f64() * 1000.0;
+            stats.avg_consensus_time_ms += (elapsed_ms - stats.avg_consensus_time_ms) / timed as f64;
+        }
+        if let Some(metrics) = &self.metrics {
+            metrics.consensus_latency.observe(elapsed.as_secs_f64());
+        }
+    }
+
+    /// Signed commit votes for a block from validators with known keys, ordered by validator
+    fn collect_commit_signatures(&self, block: &Block, block_hash: Hash) -> Vec<ValidatorSignature> {
+        let commit_votes = self.pbft_engine.get_commit_votes(block.header.view, block.header.round, block_hash);
+        let validator_keys = self.validator_keys.read().unwrap();
+
+        let mut signatures: Vec<ValidatorSignature> = commit_votes.iter()
+            .filter_map(|msg| match msg {
+                ConsensusMessage::Vote { vote_type: VoteType::Commit, validator_id, signature, .. } => {
+                    validator_keys.get(validator_id).map(|public_key| ValidatorSignature {
+                        validator_id: validator_id.clone(),
+                        signature: *signature,
+                        public_key: *public_key,
+                    })
+                }
+                _ => None,
+            })
+            .collect();
+        signatures.sort_by(|a, b| a.validator_id.cmp(&b.validator_id));
+        signatures
+    }
+
+    /// Stop proposing and hand leadership to the next validator
+    pub async fn drain(&self) -> Result<()> {
+        {
+            let mut draining = self.draining.write().unwrap();
+            *draining = true;
+        }
+
+        // Let an in-flight round finish before rotating away
+        let deadline = Instant::now() + Duration::from_millis(self.config.view_timeout.base_timeout_ms);
+        loop {
+            let idle = *self.state.read().unwrap() == ConsensusState::Idle;
+            if idle || Instant::now() >= deadline {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+
+        let current_view = *self.current_view.read().unwrap();
+        if self.config.is_validator && self.leader_election.is_leader(&self.config.node_id, current_view) {
+            tracing::info!("Draining leader rotating away from view {}", current_view);
+            self.trigger_view_change().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Check if the engine is draining
+    pub fn is_draining(&self) -> bool {
+        *self.draining.read().unwrap()
+    }
+
+    /// Get the delay between proposals, corrected by the interval controller when enabled
+    fn proposal_interval(&self) -> Duration {
+        match &self.interval_controller {
+            Some(controller) => controller.current_interval(),
+            None => Duration::from_millis(self.config.block_time_ms),
+        }
+    }
+
     /// Check if this node should propose a block
     async fn should_propose_block(&self) -> Result<bool> {
+        if self.is_draining() {
+            return Ok(false);
+        }
+
         let current_view = *self.current_view.read().unwrap();
         let current_round = *self.current_round.read().unwrap();
         
@@ -417,54 +901,108 @@ impl ConsensusEngine {
         // Check if enough time has passed since last block
         let last_block_time = *self.last_block_time.read().unwrap();
         let elapsed = last_block_time.elapsed();
-        if elapsed < Duration::from_millis(self.config.block_time_ms) {
+        if elapsed < self.proposal_interval() {
             return Ok(false);
         }
 
         Ok(true)
     }
 
+    /// Check if the leader has stayed silent long enough for this node to propose as its backup
+    fn should_propose_as_backup(&self) -> Result<bool> {
+        let backup_timeout = match self.config.backup_proposer_timeout_ms {
+            Some(timeout_ms) => Duration::from_millis(timeout_ms),
+            None => return Ok(false),
+        };
+
+        if self.is_draining() {
+            return Ok(false);
+        }
+
+        let current_view = *self.current_view.read().unwrap();
+        if self.leader_election.get_backup_leader(current_view).as_ref() != Some(&self.config.node_id) {
+            return Ok(false);
+        }
+
+        // Any proposal for this slot, from the leader or from us, moves us out of Idle
+        if *self.state.read().unwrap() != ConsensusState::Idle {
+            return Ok(false);
+        }
+
+        // The leader is due one proposal interval after the last block
+        let last_block_time = *self.last_block_time.read().unwrap();
+        Ok(last_block_time.elapsed() >= self.proposal_interval() + backup_timeout)
+    }
+
     /// Propose a new block
     async fn propose_block(&self) -> Result<()> {
         let current_view = *self.current_view.read().unwrap();
         let current_round = *self.current_round.read().unwrap();
         let current_height = *self.current_height.read().unwrap();
 
+        // Respect the dynamic size limit for this height when enabled
+        let max_block_size = self.block_size_limit(current_height + 1)?;
+
         // Get transactions from mempool
         let transactions = self.mempool.get_next_batch(
             self.config.max_transactions_per_block,
-            self.config.max_block_size,
+            max_block_size,
         )?;
 
-        if transactions.is_empty() {
+        // Under no load an empty block keeps heights and timestamps advancing
+        let empty_block_due = match self.config.empty_block_timeout_ms {
+            Some(timeout_ms) => self.last_block_time.read().unwrap().elapsed() >= Duration::from_millis(timeout_ms),
+            None => false,
+        };
+
+        if transactions.is_empty() && !empty_block_due {
             return Ok(()); // No transactions to include
         }
 
         // Create new block
         let block = self.create_block(transactions, current_height + 1, current_view, current_round).await?;
 
-        // Broadcast proposal
+        // Everything selected may have been invalidated since
+        if block.transactions.is_empty() && !empty_block_due {
+            return Ok(());
+        }
+
+        // Broadcast proposal, logging it as our pre-prepare
         let proposal = ConsensusMessage::Propose {
             block: block.clone(),
             round: current_round,
             view: current_view,
         };
 
+        self.pbft_engine.process_message(proposal.clone())?;
         self.broadcast_consensus_message(proposal).await?;
+        self.observer.on_propose(&block, current_view, current_round);
 
-        // Store our own proposal
+        // Store our own proposal, claiming the slot against any competing one
         let block_hash = block.hash();
+        *self.prepared_slot.write().unwrap() = Some((current_view, current_round, block_hash));
+        self.persist_state()?;
         {
             let mut pending_blocks = self.pending_blocks.write().unwrap();
+            self.round_timer.write().unwrap().start(block.header.height);
             pending_blocks.insert(block_hash, block);
         }
 
+        // A lone dev-mode validator is its own quorum, so there are no votes to wait for
+        if self.commits_alone() {
+            self.stats.write().unwrap().total_blocks_proposed += 1;
+            tracing::debug!("Dev mode: committing our proposal at height {} directly", current_height + 1);
+            return self.commit_block(block_hash).await;
+        }
+
         // Update state
         {
             let mut state = self.state.write().unwrap();
             *state = ConsensusState::Proposing;
         }
 
+        self.arm_view_timeout();
+
         // Update statistics
         {
             let mut stats = self.stats.write().unwrap();
@@ -484,13 +1022,23 @@ impl ConsensusEngine {
         view: u64,
         round: u64,
     ) -> Result<Block> {
+        let transactions = self.prevalidate_batch(transactions)?;
+
         // Get previous block hash
         let previous_hash = if height == 0 {
             [0; 32] // Genesis block
         } else {
-            let prev_block = self.storage.blocks().get_block(height - 1)?
-                .ok_or_else(|| anyhow!("Previous block not found"))?;
-            prev_block.hash()
+            self.storage.blocks().require_block(height - 1)?.hash()
+        };
+
+        // Under VRF election the proof seeds the next height's leader order
+        let vrf_proof = if self.leader_election.is_vrf() {
+            let vrf_key = self.vrf_key.as_ref().ok_or_else(|| {
+                ConsensusError::InvalidConfig("VRF leader election needs the validator's signing key".to_string())
+            })?;
+            Some(VrfProof::prove(self.config.node_id.clone(), vrf_key, &previous_hash, view))
+        } else {
+            None
         };
 
         // Create block header
@@ -499,7 +1047,7 @@ impl ConsensusEngine {
                 height,
                 previous_hash,
                 merkle_root: [0; 32], // Will be calculated
-                state_root: [0; 32], // Simplified
+                state_root: [0; 32], // Will be calculated
                 timestamp: std::time::SystemTime::now()
                     .duration_since(std::time::UNIX_EPOCH)
                     .unwrap()
@@ -507,35 +1055,70 @@ impl ConsensusEngine {
                 proposer: self.config.node_id.clone(),
                 round,
                 view,
+                vrf_proof,
             },
             transactions,
             signatures: vec![],
         };
 
-        // Calculate merkle root
+        // Calculate merkle and state roots
         block.header.merkle_root = block.calculate_merkle_root();
+        block.header.state_root = self.storage.state().preview_state_root(&block)?;
 
         Ok(block)
     }
 
-    /// Send a vote message
-    async fn send_vote(
-        &self,
-        block_hash: Hash,
-        vote_type: VoteType,
-        round: u64,
-        view: u64,
-    ) -> Result<()> {
-        let vote = ConsensusMessage::Vote {
-            block_hash,
-            vote_type,
-            round,
-            view,
-            validator_id: self.config.node_id.clone(),
-            signature: [0; 64], // Simplified signature
+    /// Drop transactions invalidated since the batch was selected, e.g. by a just-committed block
+    fn prevalidate_batch(&self, transactions: Vec<crate::types::Transaction>) -> Result<Vec<crate::types::Transaction>> {
+        let validator = match &self.batch_validator {
+            Some(validator) => validator,
+            None => return Ok(transactions),
         };
 
-        self.broadcast_consensus_message(vote).await
+        let mut valid = Vec::with_capacity(transactions.len());
+        // Last nonce and running spend of each sender already accepted into the batch
+        let mut accepted: HashMap<crate::types::Address, (u64, u64)> = HashMap::new();
+
+        for tx in transactions {
+            let previous = accepted.get(&tx.from).copied();
+            let result = match previous {
+                Some((previous_nonce, spent)) => {
+                    validator.validate_transaction_in_sequence(&tx, previous_nonce, spent)
+                }
+                None => validator.validate_transaction_against_state(&tx),
+            };
+            let spent = result.and_then(|()| {
+                previous.map_or(0, |(_, spent)| spent)
+                    .checked_add(tx.amount)
+                    .and_then(|spent| spent.checked_add(tx.fee))
+                    .ok_or_else(|| ValidationError::InsufficientBalance.into())
+            });
+
+            match spent {
+                Ok(spent) => {
+                    accepted.insert(tx.from, (tx.nonce, spent));
+                    valid.push(tx);
+                }
+                Err(e) => {
+                    tracing::debug!("Dropping transaction {} from proposal: {}", tx.id, e);
+
+                    // A consumed nonce can never become valid again
+                    if tx.nonce <= self.storage.state().get_nonce(&tx.from)? {
+                        self.mempool.remove_transaction(&tx.id)?;
+                    }
+                }
+            }
+        }
+
+        Ok(valid)
+    }
+
+    /// Broadcast the messages PBFT produced in response to one we processed
+    async fn broadcast_all(&self, messages: Vec<ConsensusMessage>) -> Result<()> {
+        for message in messages {
+            self.broadcast_consensus_message(message).await?;
+        }
+        Ok(())
     }
 
     /// Broadcast consensus message to all validators
@@ -547,16 +1130,70 @@ impl ConsensusEngine {
 
         // Send through network layer (simplified)
         self.message_sender.send(network_message)
-            .map_err(|e| anyhow!("Failed to send message: {}", e))?;
+            .map_err(|e| ConsensusError::ChannelClosed(e.to_string()))?;
 
         Ok(())
     }
 
+    /// Check whether we may commit proposals without votes: dev mode with no other validators
+    fn commits_alone(&self) -> bool {
+        self.config.dev_mode && self.leader_election.get_validator_set() == [self.config.node_id.clone()]
+    }
+
+    /// Size budget for a block's transactions, following the dynamic limit when enabled
+    fn block_size_limit(&self, height: BlockHeight) -> Result<usize> {
+        match &self.config.dynamic_block_size {
+            Some(dynamic) => dynamic.limit_at_height(&self.storage, height),
+            None => Ok(self.config.max_block_size),
+        }
+    }
+
     /// Validate a proposed block
     async fn validate_proposed_block(&self, block: &Block) -> Result<bool> {
-        // Use the validation module
-        // This is a simplified validation
-        Ok(block.verify())
+        if !block.verify_with(self.signature_scheme.as_ref()) {
+            return Ok(false);
+        }
+
+        // Hold the leader to the same limits we fill our own proposals up to
+        let transaction_count = block.transactions.len();
+        if transaction_count > self.config.max_transactions_per_block {
+            tracing::warn!(
+                "Proposal carries {} transactions, over the limit of {}",
+                transaction_count,
+                self.config.max_transactions_per_block
+            );
+            return Ok(false);
+        }
+
+        let size: u64 = block.transactions.iter()
+            .map(|tx| bincode::serialized_size(tx).unwrap_or(u64::MAX))
+            .fold(0, u64::saturating_add);
+        let max_size = self.block_size_limit(block.header.height)?;
+        if size > max_size as u64 {
+            tracing::warn!("Proposal carries {} bytes of transactions, over the limit of {}", size, max_size);
+            return Ok(false);
+        }
+
+        // A near-empty block while transactions are waiting is spam
+        if let Some(min) = self.config.min_transactions_per_block {
+            if transaction_count < min && self.mempool.size() >= min {
+                tracing::warn!(
+                    "Proposal carries {} transactions, under the minimum of {} with {} pending",
+                    transaction_count,
+                    min,
+                    self.mempool.size()
+                );
+                return Ok(false);
+            }
+        }
+
+        // The same transaction, header and chain rules synced blocks must pass
+        if let Err(e) = self.validator.validate_block(block) {
+            tracing::warn!("Proposal at height {} failed validation: {}", block.header.height, e);
+            return Ok(false);
+        }
+
+        Ok(true)
     }
 
     /// Handle view change message
@@ -587,6 +1224,19 @@ impl ConsensusEngine {
         false
     }
 
+    /// Start the view timeout for the current round
+    fn arm_view_timeout(&self) {
+        let deadline = tokio::time::Instant::now() + self.view_change_manager.current_timeout();
+        let mut view_timeout = self.view_timeout.write().unwrap();
+        *view_timeout = Some(deadline);
+    }
+
+    /// Cancel the view timeout
+    fn clear_view_timeout(&self) {
+        let mut view_timeout = self.view_timeout.write().unwrap();
+        *view_timeout = None;
+    }
+
     /// Trigger view change
     async fn trigger_view_change(&self) -> Result<()> {
         let current_view = *self.current_view.read().unwrap();
@@ -599,22 +1249,126 @@ impl ConsensusEngine {
             let mut view = self.current_view.write().unwrap();
             *view = new_view;
         }
+        self.pbft_engine.set_view(new_view);
+        self.round_timer.write().unwrap().restart();
+        self.observer.on_view_change(current_view, new_view);
+        if let Some(metrics) = &self.metrics {
+            metrics.view_changes.inc();
+        }
+
+        // Back off so repeated failures give the new leader longer to make progress
+        self.view_change_manager.update_timeout();
+        self.arm_view_timeout();
+
+        {
+            let mut stats = self.stats.write().unwrap();
+            stats.current_view = new_view;
+            stats.total_view_changes += 1;
+        }
+        self.record_leader();
 
         // Update state
         {
             let mut state = self.state.write().unwrap();
             *state = ConsensusState::ViewChanging;
         }
+        self.persist_state()?;
+
+        Ok(())
+    }
+
+    /// Take the validator set and its vote keys from storage, where committed governance actions land
+    ///
+    /// Without a stored set the configured validators and keys stay in effect.
+    fn refresh_validator_set(&self) -> Result<()> {
+        let Some(validator_set) = self.storage.validator_set()? else {
+            return Ok(());
+        };
+
+        let node_ids = validator_set.node_ids();
+        if node_ids != self.leader_election.get_validator_set() {
+            tracing::info!("Validator set is now {:?}", node_ids);
+            self.leader_election.update_validator_set(node_ids);
+        }
+        self.leader_election.update_vrf_keys(validator_set.keys());
+        *self.validator_keys.write().unwrap() = validator_set.keys();
+        Ok(())
+    }
+
+    /// Seed VRF leader election from the stored head, which sync may have moved
+    fn refresh_vrf_seed(&self) -> Result<()> {
+        if !self.leader_election.is_vrf() {
+            return Ok(());
+        }
 
+        let Some(head_hash) = self.storage.get_head_hash()? else {
+            return Ok(());
+        };
+        if let Some(head) = self.storage.blocks().find_block(&head_hash)? {
+            self.leader_election.set_vrf_head(&head);
+        }
         Ok(())
     }
 
+    /// Report the current view's leader in the stats
+    fn record_leader(&self) {
+        let current_view = *self.current_view.read().unwrap();
+        let leader = self.leader_election.get_leader(current_view);
+        self.stats.write().unwrap().current_leader = Some(leader);
+    }
+
+    /// Save the committed height, view, round and PBFT certificates so a restart resumes here
+    fn persist_state(&self) -> Result<()> {
+        let (prepared_certificates, committed_certificates) = self.pbft_engine.get_certificates();
+        let persisted = PersistedConsensusState {
+            height: *self.current_height.read().unwrap(),
+            view: *self.current_view.read().unwrap(),
+            round: *self.current_round.read().unwrap(),
+            prepared_slot: *self.prepared_slot.read().unwrap(),
+            validator_set: self.leader_election.get_validator_set(),
+            prepared_certificates,
+            committed_certificates,
+        };
+
+        self.storage.put_metadata(CONSENSUS_STATE_KEY, &persisted)
+    }
+
+    /// Get this node's id
+    pub fn node_id(&self) -> &NodeId {
+        &self.config.node_id
+    }
+
+    /// Check if this node participates in consensus as a validator
+    pub fn is_validator(&self) -> bool {
+        self.config.is_validator
+    }
+
+    /// Get the public key votes from a validator are checked against, if known
+    pub fn validator_key(&self, node_id: &NodeId) -> Option<PublicKey> {
+        self.validator_keys.read().unwrap().get(node_id).copied()
+    }
+
     /// Get current consensus statistics
     pub fn get_stats(&self) -> ConsensusStats {
         let stats = self.stats.read().unwrap();
         stats.clone()
     }
 
+    /// Get the current consensus state
+    pub fn get_state(&self) -> ConsensusState {
+        self.state.read().unwrap().clone()
+    }
+
+    /// Most transactions a proposed block may carry
+    pub fn max_transactions_per_block(&self) -> usize {
+        self.config.max_transactions_per_block
+    }
+
+    /// Get observed reorg statistics
+    pub fn get_reorg_stats(&self) -> ReorgStats {
+        self.reorg_tracker.stats()
+    }
+
     /// Get message sender for network layer
     pub fn get_message_sender(&self) -> mpsc::UnboundedSender<NetworkMessage> {
         self.message_sender.clone()
@@ -633,15 +1387,1100 @@ impl Clone for ConsensusEngine {
             pbft_engine: self.pbft_engine.clone(),
             leader_election: self.leader_election.clone(),
             view_change_manager: self.view_change_manager.clone(),
+            reorg_tracker: self.reorg_tracker.clone(),
             storage: self.storage.clone(),
             mempool: self.mempool.clone(),
             message_sender: self.message_sender.clone(),
             message_receiver: self.message_receiver.clone(),
             pending_blocks: self.pending_blocks.clone(),
-            votes: self.votes.clone(),
+            round_timer: self.round_timer.clone(),
+            prepared_slot: self.prepared_slot.clone(),
+            validator_keys: self.validator_keys.clone(),
+            signature_scheme: self.signature_scheme.clone(),
+            vrf_key: self.vrf_key.clone(),
+            webhook: self.webhook.clone(),
+            observer: self.observer.clone(),
+            metrics: self.metrics.clone(),
+            validator: self.validator.clone(),
+            batch_validator: self.batch_validator.clone(),
+            draining: self.draining.clone(),
             stats: self.stats.clone(),
             last_block_time: self.last_block_time.clone(),
+            interval_controller: self.interval_controller.clone(),
             view_timeout: self.view_timeout.clone(),
+            shutdown: self.shutdown.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::Ed25519Scheme;
+    use crate::mempool::MempoolConfig;
+    use crate::types::{BlockHeader, Transaction, address_from_public_key, sign_vote};
+    use crate::validation::SenderAllowlist;
+    use tempfile::TempDir;
+
+    fn create_test_config() -> ConsensusConfig {
+        ConsensusConfig {
+            node_id: "validator-1".to_string(),
+            is_validator: true,
+            validator_set: vec![
+                "validator-1".to_string(),
+                "validator-2".to_string(),
+                "validator-3".to_string(),
+                "validator-4".to_string(),
+            ],
+            block_time_ms: 1000,
+            view_timeout: ViewChangeTimeout { base_timeout_ms: 1000, ..ViewChangeTimeout::default() },
+            max_block_size: 1024 * 1024,
+            max_transactions_per_block: 100,
+            leader_shuffle_epoch_length: 0,
+            dynamic_block_size: None,
+            block_interval: None,
+            backup_proposer_timeout_ms: None,
+            empty_block_timeout_ms: None,
+            min_transactions_per_block: None,
+            pbft_log_retention: 100,
+            dev_mode: false,
+            vrf_leader_election: false,
+        }
+    }
+
+    fn create_test_engine(temp_dir: &TempDir) -> ConsensusEngine {
+        let storage = Arc::new(Storage::new(temp_dir.path()).unwrap());
+        let mempool = Arc::new(Mempool::new(MempoolConfig::default()));
+
+        new_engine(create_test_config(), storage, mempool)
+    }
+
+    /// Engine that checks votes against the test validators' keys
+    fn new_engine(config: ConsensusConfig, storage: Arc<Storage>, mempool: Arc<Mempool>) -> ConsensusEngine {
+        ConsensusEngine::new(config, storage, mempool)
+            .with_validator_keys(validator_keys())
+    }
+
+    /// Signing key of a test validator, `validator-<n>`
+    fn validator_key(validator_id: &str) -> SigningKey {
+        let index: u8 = validator_id.trim_start_matches("validator-").parse().unwrap();
+        SigningKey::from_bytes(&[100 + index; 32])
+    }
+
+    fn validator_keys() -> HashMap<NodeId, PublicKey> {
+        (1..=4)
+            .map(|i| format!("validator-{}", i))
+            .map(|id| {
+                let public_key = validator_key(&id).verifying_key().to_bytes();
+                (id, public_key)
+            })
+            .collect()
+    }
+
+    /// Deliver a vote signed by `validator_id`'s key
+    async fn vote(engine: &ConsensusEngine, block_hash: Hash, vote_type: VoteType, round: u64, view: u64, validator_id: &str) {
+        let signature = sign_vote(&Ed25519Scheme, &validator_key(validator_id).to_bytes(), &block_hash, view, round, &vote_type);
+        engine.handle_vote_message(block_hash, vote_type, round, view, validator_id.to_string(), signature)
+            .await
+            .unwrap();
+    }
+
+    fn now_ms() -> u64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64
+    }
+
+    fn genesis_block() -> Block {
+        Block {
+            header: BlockHeader {
+                height: 0,
+                previous_hash: [0; 32],
+                merkle_root: [0; 32],
+                state_root: [0; 32],
+                timestamp: 0,
+                proposer: "genesis".to_string(),
+                round: 0,
+                view: 0,
+                vrf_proof: None,
+            },
+            transactions: vec![],
+            signatures: vec![],
+        }
+    }
+
+    /// Store an empty genesis block for proposals to build on
+    fn store_genesis(storage: &Storage) -> Block {
+        let genesis = genesis_block();
+        storage.store_block(&genesis).unwrap();
+        genesis
+    }
+
+    /// Block at the height after `parent`, built on it by `proposer`
+    fn block_on(parent: &Block, proposer: &str, transactions: Vec<Transaction>) -> Block {
+        let mut block = Block {
+            header: BlockHeader {
+                height: parent.header.height + 1,
+                previous_hash: parent.hash(),
+                merkle_root: [0; 32],
+                state_root: [0; 32],
+                timestamp: now_ms(),
+                proposer: proposer.to_string(),
+                round: 0,
+                view: 0,
+                vrf_proof: None,
+            },
+            transactions,
+            signatures: vec![],
+        };
+        block.header.merkle_root = block.calculate_merkle_root();
+        block
+    }
+
+    /// Votes this node sent since the last call
+    fn sent_votes(outbound: &mut mpsc::UnboundedReceiver<NetworkMessage>) -> Vec<VoteType> {
+        let mut votes = Vec::new();
+        while let Ok(message) = outbound.try_recv() {
+            if let MessagePayload::Consensus(ConsensusMessage::Vote { vote_type, .. }) = message.payload {
+                votes.push(vote_type);
+            }
+        }
+        votes
+    }
+
+    /// Transfer of 1000 from the key's address to `[2; 20]`, signed by the key
+    fn signed_transfer(signing_key: &SigningKey, nonce: u64) -> Transaction {
+        let mut tx = Transaction {
+            id: Uuid::new_v4(),
+            from: address_from_public_key(&signing_key.verifying_key().to_bytes()),
+            to: [2u8; 20],
+            amount: 1000,
+            fee: 10,
+            nonce,
+            timestamp: now_ms(),
+            signature: [0u8; 64],
+            public_key: [0u8; 32],
+            data: vec![],
+        };
+        tx.sign(signing_key);
+        tx
+    }
+
+    #[tokio::test]
+    async fn test_view_changes_when_leader_never_proposes() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Arc::new(Storage::new(temp_dir.path()).unwrap());
+        let mempool = Arc::new(Mempool::new(MempoolConfig::default()));
+        let config = ConsensusConfig {
+            node_id: "validator-2".to_string(),
+            view_timeout: ViewChangeTimeout { base_timeout_ms: 100, ..ViewChangeTimeout::default() },
+            ..create_test_config()
+        };
+        let engine = new_engine(config, storage, mempool.clone());
+
+        // Work is pending but validator-1, the leader of view 0, is silent
+        let tx = signed_transfer(&SigningKey::from_bytes(&[1u8; 32]), 1);
+        assert!(mempool.add_transaction(tx).unwrap());
+
+        engine.run_consensus_tick().await.unwrap();
+        assert_eq!(*engine.current_view.read().unwrap(), 0);
+
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        engine.run_consensus_tick().await.unwrap();
+        assert_eq!(*engine.current_view.read().unwrap(), 1);
+        assert_eq!(engine.get_stats().total_view_changes, 1);
+
+        // The next timeout backs off exponentially
+        assert_eq!(engine.view_change_manager.current_timeout(), Duration::from_millis(150));
+    }
+
+    #[test]
+    fn test_view_timeout_backoff_follows_config() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Arc::new(Storage::new(temp_dir.path()).unwrap());
+        let mempool = Arc::new(Mempool::new(MempoolConfig::default()));
+        let view_timeout = ViewChangeTimeout { base_timeout_ms: 200, timeout_multiplier: 2.0, max_timeout_ms: 1000 };
+        let config = ConsensusConfig { view_timeout, ..create_test_config() };
+        let engine = new_engine(config, storage.clone(), mempool.clone());
+
+        let mut sequence = vec![engine.view_change_manager.current_timeout().as_millis()];
+        for _ in 0..4 {
+            engine.view_change_manager.update_timeout();
+            sequence.push(engine.view_change_manager.current_timeout().as_millis());
+        }
+        assert_eq!(sequence, vec![200, 400, 800, 1000, 1000]);
+
+        engine.view_change_manager.reset_timeout();
+        assert_eq!(engine.view_change_manager.current_timeout(), Duration::from_millis(200));
+
+        let invalid = [
+            ViewChangeTimeout { base_timeout_ms: 2000, timeout_multiplier: 2.0, max_timeout_ms: 1000 },
+            ViewChangeTimeout { base_timeout_ms: 200, timeout_multiplier: 0.5, max_timeout_ms: 1000 },
+        ];
+        for view_timeout in invalid {
+            let config = ConsensusConfig { view_timeout, ..create_test_config() };
+            let error = ConsensusEngine::new(config, storage.clone(), mempool.clone()).err().unwrap();
+            assert!(matches!(error.downcast_ref::<ConsensusError>(), Some(ConsensusError::InvalidConfig(_))));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stats_report_current_leader() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = create_test_engine(&temp_dir);
+        assert_eq!(engine.get_stats().current_leader, None);
+
+        engine.run_consensus_tick().await.unwrap();
+        assert_eq!(engine.get_stats().current_leader, Some(engine.leader_election.get_leader(0)));
+
+        // A view change hands leadership to the next validator
+        engine.trigger_view_change().await.unwrap();
+        let leader = engine.get_stats().current_leader.unwrap();
+        assert_eq!(leader, engine.leader_election.get_leader(1));
+        assert_ne!(leader, engine.leader_election.get_leader(0));
+    }
+
+    #[tokio::test]
+    async fn test_missing_blocks_are_typed_errors() {
+        use crate::storage::StorageError;
+
+        let temp_dir = TempDir::new().unwrap();
+        let engine = create_test_engine(&temp_dir);
+
+        let error = engine.commit_block([7; 32]).await.unwrap_err();
+        assert!(matches!(error.downcast_ref::<ConsensusError>(), Some(ConsensusError::UnknownBlock(_))));
+
+        // The parent of height 5 was never stored
+        let error = engine.create_block(vec![], 5, 0, 0).await.unwrap_err();
+        assert!(matches!(error.downcast_ref::<StorageError>(), Some(StorageError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_draining_leader_rotates_away() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = create_test_engine(&temp_dir);
+
+        let node_id = "validator-1".to_string();
+        assert!(engine.leader_election.is_leader(&node_id, 0));
+        engine.drain().await.unwrap();
+
+        assert!(engine.is_draining());
+        let current_view = *engine.current_view.read().unwrap();
+        assert_eq!(current_view, 1);
+        assert!(!engine.leader_election.is_leader(&node_id, current_view));
+        assert!(!engine.should_propose_block().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_invalidated_transaction_excluded_from_proposal() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Arc::new(Storage::new(temp_dir.path()).unwrap());
+        let mempool = Arc::new(Mempool::new(MempoolConfig::default()));
+        let validator = Arc::new(Validator::new((*storage).clone()));
+        let engine = new_engine(create_test_config(), storage.clone(), mempool.clone())
+            .with_batch_prevalidation(validator);
+
+        let create_transaction = |seed: u8| {
+            let tx = signed_transfer(&SigningKey::from_bytes(&[seed; 32]), 1);
+            storage.state().set_balance(&tx.from, 1_000_000).unwrap();
+            tx
+        };
+
+        let stale = create_transaction(1);
+        let fresh = create_transaction(2);
+        assert!(mempool.add_transaction(stale.clone()).unwrap());
+        assert!(mempool.add_transaction(fresh.clone()).unwrap());
+
+        let batch = mempool.get_next_batch(10, 1024 * 1024).unwrap();
+        assert_eq!(batch.len(), 2);
+
+        // A block committed after selection consumes the stale transaction's nonce
+        storage.state().increment_nonce(&stale.from).unwrap();
+
+        let block = engine.create_block(batch, 0, 0, 0).await.unwrap();
+        assert_eq!(block.transactions.len(), 1);
+        assert_eq!(block.transactions[0].id, fresh.id);
+        assert_eq!(block.header.merkle_root, block.calculate_merkle_root());
+
+        // The stale transaction can never be included, so it leaves the mempool
+        assert!(!mempool.contains(&stale.id));
+        assert!(mempool.contains(&fresh.id));
+    }
+
+    #[tokio::test]
+    async fn test_proposal_limits() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Arc::new(Storage::new(temp_dir.path()).unwrap());
+        let genesis = store_genesis(&storage);
+        let mempool = Arc::new(Mempool::new(MempoolConfig::default()));
+
+        let create_transaction = |seed: u8| {
+            let tx = signed_transfer(&SigningKey::from_bytes(&[seed; 32]), 1);
+            storage.state().set_balance(&tx.from, 1_000_000).unwrap();
+            tx
+        };
+        let create_block = |seeds: &[u8]| {
+            block_on(&genesis, "validator-1", seeds.iter().map(|seed| create_transaction(*seed)).collect())
+        };
+        let tx_size = bincode::serialized_size(&create_transaction(1)).unwrap() as usize;
+
+        let config = ConsensusConfig {
+            max_transactions_per_block: 2,
+            max_block_size: tx_size * 2,
+            min_transactions_per_block: Some(2),
+            ..create_test_config()
+        };
+        let engine = new_engine(config.clone(), storage.clone(), mempool.clone());
+
+        // Within every limit
+        assert!(engine.validate_proposed_block(&create_block(&[1, 2])).await.unwrap());
+        assert!(engine.validate_proposed_block(&create_block(&[1])).await.unwrap());
+
+        // Too many transactions, or too many bytes of them
+        assert!(!engine.validate_proposed_block(&create_block(&[1, 2, 3])).await.unwrap());
+        let small = ConsensusConfig { max_block_size: tx_size * 2 - 1, ..config };
+        let small_engine = new_engine(small, storage.clone(), mempool.clone());
+        assert!(!small_engine.validate_proposed_block(&create_block(&[1, 2])).await.unwrap());
+
+        // Underfilled blocks are only refused while enough transactions are pending
+        assert!(mempool.add_transaction(create_transaction(3)).unwrap());
+        assert!(mempool.add_transaction(create_transaction(4)).unwrap());
+        assert!(!engine.validate_proposed_block(&create_block(&[1])).await.unwrap());
+        assert!(engine.validate_proposed_block(&create_block(&[1, 2])).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_overflowing_fee_total_is_rejected() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = create_test_engine(&temp_dir);
+
+        let create_transaction = |seed: u8, fee: u64| Transaction {
+            fee,
+            ..signed_transfer(&SigningKey::from_bytes(&[seed; 32]), 1)
+        };
+        let create_block = |transactions: Vec<Transaction>| Block { transactions, ..genesis_block() };
+
+        // Each fee fits in a u64 but the sum does not, in any order
+        let half = u64::MAX / 2 + 1;
+        let overflowing = vec![create_transaction(1, half), create_transaction(2, 1), create_transaction(3, half)];
+        let mut reversed = overflowing.clone();
+        reversed.reverse();
+        assert_eq!(create_block(overflowing.clone()).total_fees(), None);
+        assert_eq!(create_block(reversed).total_fees(), None);
+
+        let block = create_block(overflowing);
+        let block_hash = block.hash();
+        engine.pending_blocks.write().unwrap().insert(block_hash, block);
+
+        assert!(engine.commit_block(block_hash).await.is_err());
+        assert_eq!(engine.storage.get_latest_height().unwrap(), None);
+        assert_eq!(engine.get_stats().total_blocks_committed, 0);
+        assert_eq!(engine.get_stats().total_fees_collected, 0);
+
+        // The largest total that fits is summed exactly
+        let fits = create_block(vec![create_transaction(1, u64::MAX - 1), create_transaction(2, 1)]);
+        assert_eq!(fits.total_fees(), Some(u64::MAX));
+    }
+
+    #[derive(Default)]
+    struct RecordingObserver {
+        events: std::sync::Mutex<Vec<String>>,
+    }
+
+    impl RecordingObserver {
+        fn record(&self, event: String) {
+            self.events.lock().unwrap().push(event);
+        }
+    }
+
+    impl ConsensusObserver for RecordingObserver {
+        fn on_propose(&self, block: &Block, view: u64, round: u64) {
+            self.record(format!("propose {} view {} round {}", block.header.height, view, round));
+        }
+
+        fn on_vote(&self, _block_hash: &Hash, vote_type: &VoteType, validator_id: &NodeId, _view: u64, _round: u64) {
+            self.record(format!("vote {:?} {}", vote_type, validator_id));
+        }
+
+        fn on_commit(&self, block: &Block) {
+            self.record(format!("commit {}", block.header.height));
+        }
+
+        fn on_view_change(&self, old_view: u64, new_view: u64) {
+            self.record(format!("view change {} -> {}", old_view, new_view));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_observer_records_committed_block() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Arc::new(Storage::new(temp_dir.path()).unwrap());
+        store_genesis(&storage);
+
+        let mempool = Arc::new(Mempool::new(MempoolConfig::default()));
+        let observer = Arc::new(RecordingObserver::default());
+        let engine = new_engine(create_test_config(), storage.clone(), mempool.clone())
+            .with_observer(observer.clone());
+
+        let tx = signed_transfer(&SigningKey::from_bytes(&[1u8; 32]), 1);
+        storage.state().set_balance(&tx.from, 1_000_000).unwrap();
+        assert!(mempool.add_transaction(tx).unwrap());
+
+        engine.propose_block().await.unwrap();
+        let block_hash = *engine.pending_blocks.read().unwrap().keys().next().unwrap();
+
+        // Three of four validators form a quorum for each phase
+        let quorum = ["validator-1", "validator-2", "validator-3"];
+        for vote_type in [VoteType::Prepare, VoteType::Commit] {
+            for validator_id in quorum {
+                vote(&engine, block_hash, vote_type.clone(), 0, 0, validator_id).await;
+            }
+        }
+        assert_eq!(storage.get_latest_height().unwrap(), Some(1));
+
+        engine.trigger_view_change().await.unwrap();
+
+        let events = observer.events.lock().unwrap().clone();
+        assert_eq!(events, vec![
+            "propose 1 view 0 round 0",
+            "vote Prepare validator-1",
+            "vote Prepare validator-2",
+            "vote Prepare validator-3",
+            "vote Commit validator-1",
+            "vote Commit validator-2",
+            "vote Commit validator-3",
+            "commit 1",
+            "view change 0 -> 1",
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_commit_updates_metrics() {
+        let metrics = Arc::new(NodeMetrics::new().unwrap());
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Arc::new(Storage::new(temp_dir.path()).unwrap().with_metrics(metrics.clone()));
+        store_genesis(&storage);
+
+        let mempool = Arc::new(Mempool::new(MempoolConfig::default()).with_metrics(metrics.clone()));
+        let engine = new_engine(create_test_config(), storage.clone(), mempool.clone())
+            .with_metrics(metrics.clone());
+
+        let tx = signed_transfer(&SigningKey::from_bytes(&[1u8; 32]), 1);
+        storage.state().set_balance(&tx.from, 1_000_000).unwrap();
+        assert!(mempool.add_transaction(tx).unwrap());
+        assert_eq!(metrics.transactions_in_mempool.get(), 1);
+
+        engine.propose_block().await.unwrap();
+        let block_hash = *engine.pending_blocks.read().unwrap().keys().next().unwrap();
+        for vote_type in [VoteType::Prepare, VoteType::Commit] {
+            for validator_id in ["validator-1", "validator-2", "validator-3"] {
+                vote(&engine, block_hash, vote_type.clone(), 0, 0, validator_id).await;
+            }
+        }
+
+        assert_eq!(metrics.blocks_processed.get(), 1);
+        assert_eq!(metrics.block_height.get(), 1);
+        assert_eq!(metrics.transactions_processed.get(), 1);
+        assert_eq!(metrics.transactions_in_mempool.get(), 0);
+        assert_eq!(metrics.consensus_latency.get_sample_count(), 1);
+        assert_eq!(metrics.validator_votes.get(), 6);
+        // The genesis write and the commit
+        assert_eq!(metrics.storage_operations.get(), 2);
+        assert!(engine.round_timer.read().unwrap().current.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_consensus_time_is_averaged_over_rounds() {
+        let metrics = Arc::new(NodeMetrics::new().unwrap());
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Arc::new(Storage::new(temp_dir.path()).unwrap());
+        store_genesis(&storage);
+
+        let mempool = Arc::new(Mempool::new(MempoolConfig::default()));
+        let engine = new_engine(create_test_config(), storage.clone(), mempool.clone())
+            .with_metrics(metrics.clone());
+
+        let signing_key = SigningKey::from_bytes(&[1u8; 32]);
+        let sender = address_from_public_key(&signing_key.verifying_key().to_bytes());
+        storage.state().set_balance(&sender, 1_000_000).unwrap();
+
+        // Votes arrive 30ms after the first proposal and 90ms after the second
+        for (round, delay_ms) in [(0u64, 30u64), (1, 90)] {
+            assert!(mempool.add_transaction(signed_transfer(&signing_key, round + 1)).unwrap());
+
+            engine.propose_block().await.unwrap();
+            let block_hash = *engine.pending_blocks.read().unwrap().keys().next().unwrap();
+            tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+            for vote_type in [VoteType::Prepare, VoteType::Commit] {
+                for validator_id in ["validator-1", "validator-2", "validator-3"] {
+                    vote(&engine, block_hash, vote_type.clone(), round, 0, validator_id).await;
+                }
+            }
+            assert_eq!(storage.get_latest_height().unwrap(), Some(round + 1));
+        }
+
+        // The mean of two rounds taking at least 30ms and 90ms
+        let average = engine.get_stats().avg_consensus_time_ms;
+        assert!((60.0..5_000.0).contains(&average), "implausible average {}ms", average);
+        assert_eq!(metrics.consensus_latency.get_sample_count(), 2);
+        assert!(metrics.consensus_latency.get_sample_sum() >= 0.12);
+    }
+
+    #[test]
+    fn test_round_timer_restarts_on_view_change() {
+        let mut timer = RoundTimer::default();
+        timer.start(5);
+        std::thread::sleep(Duration::from_millis(50));
+
+        // A second proposal for the height keeps the original start
+        timer.start(5);
+        let mut kept = RoundTimer { current: timer.current, timed: 0 };
+        assert!(kept.finish(5).unwrap() >= Duration::from_millis(50));
+
+        // A view change times the height from scratch
+        timer.restart();
+        assert!(timer.finish(4).is_none());
+        assert!(timer.finish(5).unwrap() < Duration::from_millis(50));
+        assert!(timer.finish(5).is_none());
+        assert_eq!(timer.timed, 1);
+    }
+
+    #[tokio::test]
+    async fn test_backup_proposer_commits_when_primary_silent() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Arc::new(Storage::new(temp_dir.path()).unwrap());
+        store_genesis(&storage);
+
+        // validator-2 backs up validator-1, the leader of view 0
+        let mempool = Arc::new(Mempool::new(MempoolConfig::default()));
+        let config = ConsensusConfig {
+            node_id: "validator-2".to_string(),
+            block_time_ms: 50,
+            backup_proposer_timeout_ms: Some(100),
+            ..create_test_config()
+        };
+        let engine = new_engine(config, storage.clone(), mempool.clone());
+        let mut outbound = engine.message_receiver.write().unwrap().take().unwrap();
+        assert_eq!(engine.leader_election.get_backup_leader(0), Some("validator-2".to_string()));
+
+        let tx = signed_transfer(&SigningKey::from_bytes(&[1u8; 32]), 1);
+        storage.state().set_balance(&tx.from, 1_000_000).unwrap();
+        assert!(mempool.add_transaction(tx).unwrap());
+
+        // The leader is still within its slot
+        engine.run_consensus_tick().await.unwrap();
+        assert!(engine.pending_blocks.read().unwrap().is_empty());
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        engine.run_consensus_tick().await.unwrap();
+        let block = match outbound.try_recv().unwrap().payload {
+            MessagePayload::Consensus(ConsensusMessage::Propose { block, .. }) => block,
+            other => panic!("expected a proposal, got {:?}", other),
+        };
+        assert_eq!(block.header.proposer, "validator-2");
+        let block_hash = block.hash();
+
+        // The proposal is logged as the slot's pre-prepare; a proposer sends no prepare of its own
+        engine.handle_propose_message(block.clone(), 0, 0).await.unwrap();
+        assert!(outbound.try_recv().is_err());
+        assert!(engine.pending_blocks.read().unwrap().contains_key(&block_hash));
+
+        // A late proposal from the leader for the same slot gets no vote, nor does a non-proposer's
+        for proposer in ["validator-1", "validator-4"] {
+            let mut competing = block.clone();
+            competing.header.proposer = proposer.to_string();
+            engine.handle_propose_message(competing.clone(), 0, 0).await.unwrap();
+            assert!(outbound.try_recv().is_err());
+            assert!(!engine.pending_blocks.read().unwrap().contains_key(&competing.hash()));
+        }
+
+        let quorum = ["validator-1", "validator-2", "validator-3"];
+        for vote_type in [VoteType::Prepare, VoteType::Commit] {
+            for validator_id in quorum {
+                vote(&engine, block_hash, vote_type.clone(), 0, 0, validator_id).await;
+            }
+        }
+
+        let committed = storage.blocks().get_block(1).unwrap().unwrap();
+        assert_eq!(committed.header.proposer, "validator-2");
+        assert_eq!(*engine.current_view.read().unwrap(), 0);
+        assert_eq!(engine.get_stats().total_view_changes, 0);
+    }
+
+    #[tokio::test]
+    async fn test_commit_collects_verified_vote_signatures() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Arc::new(Storage::new(temp_dir.path()).unwrap());
+        store_genesis(&storage);
+
+        let mempool = Arc::new(Mempool::new(MempoolConfig::default()));
+        let engine = new_engine(create_test_config(), storage.clone(), mempool.clone())
+            .with_signing_key(validator_key("validator-1"));
+
+        let tx = signed_transfer(&SigningKey::from_bytes(&[1u8; 32]), 1);
+        storage.state().set_balance(&tx.from, 1_000_000).unwrap();
+        assert!(mempool.add_transaction(tx).unwrap());
+
+        engine.propose_block().await.unwrap();
+        let block_hash = *engine.pending_blocks.read().unwrap().keys().next().unwrap();
+
+        // Unsigned votes and votes signed by the wrong key don't count
+        let outsider_key = validator_key("validator-4");
+        for validator_id in ["validator-1", "validator-2", "validator-3"] {
+            for signature in [[0; 64], sign_vote(&Ed25519Scheme, &outsider_key.to_bytes(), &block_hash, 0, 0, &VoteType::Prepare)] {
+                engine.handle_vote_message(block_hash, VoteType::Prepare, 0, 0, validator_id.to_string(), signature)
+                    .await
+                    .unwrap();
+            }
+        }
+        assert_eq!(engine.get_state(), ConsensusState::Proposing);
+
+        // Nor does any vote on an engine that knows no validator keys
+        let observer = Arc::new(RecordingObserver::default());
+        let keyless = ConsensusEngine::new(create_test_config(), storage.clone(), mempool.clone())
+            .unwrap()
+            .with_observer(observer.clone());
+        for validator_id in ["validator-1", "validator-2", "validator-3"] {
+            vote(&keyless, block_hash, VoteType::Prepare, 0, 0, validator_id).await;
+        }
+        assert!(observer.events.lock().unwrap().is_empty());
+
+        for vote_type in [VoteType::Prepare, VoteType::Commit] {
+            for validator_id in ["validator-1", "validator-2", "validator-3"] {
+                vote(&engine, block_hash, vote_type.clone(), 0, 0, validator_id).await;
+            }
+        }
+
+        // The stored block carries the commit votes and keeps the hash voted on
+        let committed = storage.blocks().get_block(1).unwrap().unwrap();
+        assert_eq!(committed.hash(), block_hash);
+        assert_eq!(committed.signatures.len(), 3);
+
+        let other_dir = TempDir::new().unwrap();
+        let validator = Validator::new(Storage::new(other_dir.path()).unwrap());
+        assert!(validator.validate_consensus_signatures(&committed, &validator_keys()).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_pbft_cycle_commits_once() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Arc::new(Storage::new(temp_dir.path()).unwrap());
+        let genesis = store_genesis(&storage);
+
+        // validator-2 follows validator-1, the leader of view 0
+        let mempool = Arc::new(Mempool::new(MempoolConfig::default()));
+        let config = ConsensusConfig {
+            node_id: "validator-2".to_string(),
+            ..create_test_config()
+        };
+        let engine = new_engine(config, storage.clone(), mempool);
+        let mut outbound = engine.message_receiver.write().unwrap().take().unwrap();
+
+        let tx = signed_transfer(&SigningKey::from_bytes(&[1u8; 32]), 1);
+        storage.state().set_balance(&tx.from, 1_000_000).unwrap();
+
+        let block = block_on(&genesis, "validator-1", vec![tx]);
+        let block_hash = block.hash();
+
+        engine.handle_propose_message(block, 0, 0).await.unwrap();
+        assert_eq!(sent_votes(&mut outbound), vec![VoteType::Prepare]);
+        assert_eq!(engine.get_state(), ConsensusState::Preparing);
+
+        // The prepare quorum sends one commit, however many prepares follow
+        for validator_id in ["validator-1", "validator-3", "validator-4", "validator-2"] {
+            vote(&engine, block_hash, VoteType::Prepare, 0, 0, validator_id).await;
+        }
+        assert_eq!(sent_votes(&mut outbound), vec![VoteType::Commit]);
+        assert_eq!(engine.get_state(), ConsensusState::Committing);
+
+        for validator_id in ["validator-1", "validator-3", "validator-4"] {
+            vote(&engine, block_hash, VoteType::Commit, 0, 0, validator_id).await;
+        }
+        assert!(engine.pbft_engine.is_committed(0, 0, block_hash));
+
+        // A late commit vote finds the block already committed
+        engine.advance_phase(block_hash, 0, 0).await.unwrap();
+
+        assert_eq!(storage.get_latest_height().unwrap(), Some(1));
+        assert_eq!(engine.get_stats().total_blocks_committed, 1);
+        assert_eq!(engine.pbft_engine.get_current_sequence(), 1);
+        assert_eq!(*engine.current_round.read().unwrap(), 1);
+        assert_eq!(engine.get_state(), ConsensusState::Idle);
+    }
+
+    #[tokio::test]
+    async fn test_proposal_from_unlisted_sender_gets_no_prepare_vote() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Arc::new(Storage::new(temp_dir.path()).unwrap());
+        let genesis = store_genesis(&storage);
+
+        let listed = signed_transfer(&SigningKey::from_bytes(&[1u8; 32]), 1);
+        let unlisted = signed_transfer(&SigningKey::from_bytes(&[2u8; 32]), 1);
+        for tx in [&listed, &unlisted] {
+            storage.state().set_balance(&tx.from, 1_000_000).unwrap();
+        }
+
+        // validator-2 follows validator-1 and shares the node's allowlist
+        let validator = Validator::new((*storage).clone())
+            .with_sender_allowlist(SenderAllowlist::new(vec![listed.from]));
+        let config = ConsensusConfig {
+            node_id: "validator-2".to_string(),
+            ..create_test_config()
+        };
+        let engine = new_engine(config, storage.clone(), Arc::new(Mempool::new(MempoolConfig::default())))
+            .with_validator(Arc::new(validator));
+        let mut outbound = engine.message_receiver.write().unwrap().take().unwrap();
+
+        let rejected = block_on(&genesis, "validator-1", vec![listed.clone(), unlisted]);
+        engine.handle_propose_message(rejected.clone(), 0, 0).await.unwrap();
+        assert!(sent_votes(&mut outbound).is_empty());
+        assert!(!engine.pending_blocks.read().unwrap().contains_key(&rejected.hash()));
+
+        // The slot is still open to a proposal of listed senders only
+        engine.handle_propose_message(block_on(&genesis, "validator-1", vec![listed]), 0, 0).await.unwrap();
+        assert_eq!(sent_votes(&mut outbound), vec![VoteType::Prepare]);
+    }
+
+    #[tokio::test]
+    async fn test_proposal_breaking_data_rules_gets_no_prepare_vote() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Arc::new(Storage::new(temp_dir.path()).unwrap());
+        let genesis = store_genesis(&storage);
+
+        let key = SigningKey::from_bytes(&[3u8; 32]);
+        let data_carrier = |amount: u64, data_len: usize| {
+            let mut tx = Transaction { amount, data: vec![7; data_len], ..signed_transfer(&key, 1) };
+            tx.sign(&key);
+            tx
+        };
+        storage.state().set_balance(&data_carrier(0, 0).from, 1_000_000).unwrap();
+
+        let validator = Validator::new((*storage).clone()).with_max_data_bytes(16);
+        let config = ConsensusConfig {
+            node_id: "validator-2".to_string(),
+            ..create_test_config()
+        };
+        let engine = new_engine(config, storage.clone(), Arc::new(Mempool::new(MempoolConfig::default())))
+            .with_validator(Arc::new(validator));
+        let mut outbound = engine.message_receiver.write().unwrap().take().unwrap();
+
+        // Oversized data, and data that also moves value, are refused as sync would refuse them
+        for tx in [data_carrier(0, 17), data_carrier(5, 16)] {
+            engine.handle_propose_message(block_on(&genesis, "validator-1", vec![tx]), 0, 0).await.unwrap();
+            assert!(sent_votes(&mut outbound).is_empty());
+        }
+
+        engine.handle_propose_message(block_on(&genesis, "validator-1", vec![data_carrier(0, 16)]), 0, 0).await.unwrap();
+        assert_eq!(sent_votes(&mut outbound), vec![VoteType::Prepare]);
+    }
+
+    #[tokio::test]
+    async fn test_vrf_proposal_needs_the_leaders_proof_for_its_slot() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Arc::new(Storage::new(temp_dir.path()).unwrap());
+        let genesis = store_genesis(&storage);
+        let config = ConsensusConfig {
+            vrf_leader_election: true,
+            ..create_test_config()
+        };
+        let engine_for = |node_id: &str| {
+            let config = ConsensusConfig { node_id: node_id.to_string(), ..config.clone() };
+            new_engine(config, storage.clone(), Arc::new(Mempool::new(MempoolConfig::default())))
+        };
+
+        // Every node orders the views from the stored head, so they agree on the leader
+        let leader = engine_for("validator-1").leader_election.get_leader(0);
+        let voter_id = config.validator_set.iter().find(|id| **id != leader).unwrap().clone();
+        let voter = engine_for(&voter_id);
+        assert_eq!(voter.leader_election.get_leader(0), leader);
+
+        let leader_key = validator_key(&leader);
+        let proposal = engine_for(&leader)
+            .with_signing_key(leader_key.clone())
+            .create_block(vec![], 1, 0, 0)
+            .await
+            .unwrap();
+        let proof = proposal.header.vrf_proof.clone().unwrap();
+        assert!(proof.verify(&leader_key.verifying_key().to_bytes(), &genesis.hash(), 0));
+
+        // A proposal without the proof, or with the leader's proof for another view, gets no vote
+        let mut outbound = voter.message_receiver.write().unwrap().take().unwrap();
+        let other_view = VrfProof::prove(leader.clone(), &leader_key, &genesis.hash(), 1);
+        for vrf_proof in [None, Some(other_view)] {
+            let mut block = proposal.clone();
+            block.header.vrf_proof = vrf_proof;
+            voter.handle_propose_message(block, 0, 0).await.unwrap();
+            assert!(sent_votes(&mut outbound).is_empty());
+        }
+
+        voter.handle_propose_message(proposal, 0, 0).await.unwrap();
+        assert_eq!(sent_votes(&mut outbound), vec![VoteType::Prepare]);
+    }
+
+    #[tokio::test]
+    async fn test_empty_block_proposed_and_committed_after_timeout() {
+        use crate::validation::Validator;
+
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Arc::new(Storage::new(temp_dir.path()).unwrap());
+        store_genesis(&storage);
+
+        // Without the timeout an idle leader proposes nothing
+        let mempool = Arc::new(Mempool::new(MempoolConfig::default()));
+        let idle = new_engine(create_test_config(), storage.clone(), mempool.clone());
+        idle.propose_block().await.unwrap();
+        assert!(idle.pending_blocks.read().unwrap().is_empty());
+
+        let config = ConsensusConfig {
+            empty_block_timeout_ms: Some(0),
+            ..create_test_config()
+        };
+        let engine = new_engine(config, storage.clone(), mempool);
+        engine.propose_block().await.unwrap();
+
+        let block = engine.pending_blocks.read().unwrap().values().next().cloned().unwrap();
+        assert_eq!(block.header.height, 1);
+        assert!(block.transactions.is_empty());
+        assert!(block.verify());
+        Validator::new((*storage).clone()).validate_block(&block).unwrap();
+
+        let block_hash = block.hash();
+        for validator_id in ["validator-2", "validator-3", "validator-4"] {
+            vote(&engine, block_hash, VoteType::Prepare, 0, 0, validator_id).await;
+        }
+        for validator_id in ["validator-1", "validator-2", "validator-3"] {
+            vote(&engine, block_hash, VoteType::Commit, 0, 0, validator_id).await;
+        }
+
+        assert_eq!(storage.get_latest_height().unwrap(), Some(1));
+        assert_eq!(storage.blocks().get_block(1).unwrap().unwrap().hash(), block_hash);
+    }
+
+    #[tokio::test]
+    async fn test_dev_mode_commits_without_peers() {
+        use crate::shutdown::Shutdown;
+
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Arc::new(Storage::new(temp_dir.path()).unwrap());
+        store_genesis(&storage);
+
+        let signing_key = SigningKey::from_bytes(&[1u8; 32]);
+        let sender = address_from_public_key(&signing_key.verifying_key().to_bytes());
+        storage.state().set_balance(&sender, 1_000_000).unwrap();
+
+        let mempool = Arc::new(Mempool::new(MempoolConfig::default()));
+        let tx = signed_transfer(&signing_key, 1);
+        assert!(mempool.add_transaction(tx.clone()).unwrap());
+
+        let config = ConsensusConfig {
+            validator_set: vec!["validator-1".to_string()],
+            block_time_ms: 20,
+            dev_mode: true,
+            ..create_test_config()
+        };
+        let (shutdown_sender, shutdown) = Shutdown::new();
+        let engine = Arc::new(
+            new_engine(config, storage.clone(), mempool.clone()).with_shutdown(shutdown)
+        );
+        let running = engine.clone();
+        let task = tokio::spawn(async move { running.start().await });
+
+        let mut committed = None;
+        for _ in 0..100 {
+            committed = storage.blocks().get_block(1).unwrap();
+            if committed.is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        shutdown_sender.send(true).unwrap();
+        task.await.unwrap().unwrap();
+
+        // Committed through the normal path: stored, applied and removed from the mempool
+        let block = committed.expect("dev mode never committed a block");
+        assert_eq!(block.transactions.len(), 1);
+        assert_eq!(block.transactions[0].id, tx.id);
+        assert_eq!(storage.state().get_nonce(&sender).unwrap(), 1);
+        assert_eq!(storage.state().get_balance(&[2u8; 20]).unwrap(), 1000);
+        assert!(storage.receipts().get_receipt(&tx.id).unwrap().is_some());
+        assert_eq!(mempool.size(), 0);
+        assert_eq!(engine.get_stats().total_blocks_committed, 1);
+        assert!(engine.pending_blocks.read().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_restart_resumes_consensus_state() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Arc::new(Storage::new(temp_dir.path()).unwrap());
+        store_genesis(&storage);
+
+        let signing_key = SigningKey::from_bytes(&[1u8; 32]);
+        let sender = address_from_public_key(&signing_key.verifying_key().to_bytes());
+        storage.state().set_balance(&sender, 1_000_000).unwrap();
+
+        // Commit two blocks, then time out of view 0
+        let mut committed = Vec::new();
+        {
+            let mempool = Arc::new(Mempool::new(MempoolConfig::default()));
+            let engine = new_engine(create_test_config(), storage.clone(), mempool.clone());
+
+            for nonce in 1..=2 {
+                assert!(mempool.add_transaction(signed_transfer(&signing_key, nonce)).unwrap());
+
+                engine.propose_block().await.unwrap();
+                let round = *engine.current_round.read().unwrap();
+                let block_hash = *engine.pending_blocks.read().unwrap().keys().next().unwrap();
+                for vote_type in [VoteType::Prepare, VoteType::Commit] {
+                    for validator_id in ["validator-1", "validator-2", "validator-3"] {
+                        vote(&engine, block_hash, vote_type.clone(), round, 0, validator_id).await;
+                    }
+                }
+                committed.push((round, block_hash));
+            }
+
+            engine.trigger_view_change().await.unwrap();
+        }
+
+        let mempool = Arc::new(Mempool::new(MempoolConfig::default()));
+        let engine = new_engine(create_test_config(), storage.clone(), mempool);
+        assert_eq!(*engine.current_height.read().unwrap(), 2);
+        assert_eq!(*engine.current_view.read().unwrap(), 1);
+        assert_eq!(*engine.current_round.read().unwrap(), 2);
+        assert_eq!(engine.get_stats().current_view, 1);
+        assert_eq!(engine.pbft_engine.get_current_view(), 1);
+        assert_eq!(engine.pbft_engine.get_current_sequence(), 2);
+        for (round, block_hash) in committed {
+            assert!(engine.pbft_engine.is_committed(0, round, block_hash));
+        }
+
+        // A replayed proposal for a committed height gets no vote in the restored slot
+        let mut stale = storage.blocks().get_block(2).unwrap().unwrap();
+        stale.header.proposer = engine.leader_election.get_leader(1);
+        engine.handle_propose_message(stale, 2, 1).await.unwrap();
+        assert!(engine.pending_blocks.read().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_side_branch_commit_leaves_consensus_alone_until_it_reorgs() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Arc::new(Storage::new(temp_dir.path()).unwrap());
+        let genesis = store_genesis(&storage);
+
+        let signing_key = SigningKey::from_bytes(&[1u8; 32]);
+        let sender = address_from_public_key(&signing_key.verifying_key().to_bytes());
+        storage.state().set_balance(&sender, 1_000_000).unwrap();
+        let transfer = signed_transfer(&signing_key, 1);
+        let a1 = block_on(&genesis, "validator-1", vec![]);
+        let a2 = block_on(&a1, "validator-1", vec![transfer.clone()]);
+        storage.commit_block(&a1).unwrap();
+        storage.commit_block(&a2).unwrap();
+
+        let mempool = Arc::new(Mempool::new(MempoolConfig::default()));
+        let engine = new_engine(create_test_config(), storage.clone(), mempool.clone());
+        async fn commit(engine: &ConsensusEngine, block: Block) {
+            let block_hash = block.hash();
+            engine.pending_blocks.write().unwrap().insert(block_hash, block);
+            engine.commit_block(block_hash).await.unwrap();
+        }
+        let round = *engine.current_round.read().unwrap();
+
+        // A competing block no higher than the head is only kept by hash
+        let b1 = block_on(&genesis, "validator-2", vec![]);
+        commit(&engine, b1.clone()).await;
+        assert_eq!(*engine.current_height.read().unwrap(), 2);
+        assert_eq!(*engine.current_round.read().unwrap(), round);
+        assert_eq!(engine.get_stats().total_blocks_committed, 0);
+        assert!(engine.pending_blocks.read().unwrap().is_empty());
+        assert_eq!(storage.get_head_hash().unwrap(), Some(a2.hash()));
+
+        // Completing a longer branch moves the head there and returns a2's transfer to the pool
+        let b2 = block_on(&b1, "validator-2", vec![]);
+        storage.commit_block(&b2).unwrap();
+        let b3 = block_on(&b2, "validator-2", vec![]);
+        commit(&engine, b3.clone()).await;
+        assert_eq!(*engine.current_height.read().unwrap(), 3);
+        assert_eq!(*engine.current_round.read().unwrap(), round + 1);
+        assert_eq!(engine.get_stats().current_height, 3);
+        assert_eq!(engine.get_stats().total_blocks_committed, 1);
+        assert_eq!(storage.get_head_hash().unwrap(), Some(b3.hash()));
+        assert!(mempool.contains(&transfer.id));
+    }
+
+    #[tokio::test]
+    async fn test_governance_adds_validator_at_block_boundary() {
+        use governance::{GovernanceAction, ValidatorSet};
+
+        let authority_key = SigningKey::from_bytes(&[9u8; 32]);
+        let authority = address_from_public_key(&authority_key.verifying_key().to_bytes());
+
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Arc::new(Storage::new(temp_dir.path()).unwrap().with_governance_authority(authority));
+        store_genesis(&storage);
+        storage.state().set_balance(&authority, 1_000_000).unwrap();
+        let validators = create_test_config().validator_set
+            .into_iter()
+            .map(|id| {
+                let public_key = validator_key(&id).verifying_key().to_bytes();
+                (id, public_key)
+            })
+            .collect();
+        storage.put_validator_set(&ValidatorSet::new(validators)).unwrap();
+
+        let mempool = Arc::new(Mempool::new(MempoolConfig::default()));
+        let engine = new_engine(create_test_config(), storage.clone(), mempool.clone());
+
+        let mut tx = Transaction {
+            amount: 1,
+            data: GovernanceAction::AddValidator {
+                node_id: "validator-5".to_string(),
+                public_key: validator_key("validator-5").verifying_key().to_bytes(),
+            }
+            .encode(),
+            ..signed_transfer(&authority_key, 1)
+        };
+        tx.sign(&authority_key);
+        assert!(mempool.add_transaction(tx).unwrap());
+
+        engine.propose_block().await.unwrap();
+        let block_hash = *engine.pending_blocks.read().unwrap().keys().next().unwrap();
+
+        // validator-5 can't vote before the block that adds it commits
+        vote(&engine, block_hash, VoteType::Prepare, 0, 0, "validator-5").await;
+        assert!(!engine.pbft_engine.is_prepared(0, 0, block_hash));
+
+        for vote_type in [VoteType::Prepare, VoteType::Commit] {
+            for validator_id in ["validator-1", "validator-2", "validator-3"] {
+                vote(&engine, block_hash, vote_type.clone(), 0, 0, validator_id).await;
+            }
+        }
+        assert_eq!(storage.get_latest_height().unwrap(), Some(1));
+
+        // Rotation and the quorum follow the new set, in every component sharing it
+        assert_eq!(engine.leader_election.validator_count(), 5);
+        assert_eq!(engine.leader_election.byzantine_threshold(), 4);
+        let leaders: Vec<NodeId> = engine.leader_election.get_leader_schedule(1, 5)
+            .into_iter()
+            .map(|(_, leader)| leader)
+            .collect();
+        assert!(leaders.contains(&"validator-5".to_string()));
+        assert!(engine.pbft_engine.is_eligible_proposer(&"validator-5".to_string(), 4));
+
+        // validator-5's key now counts: three of five validators no longer prepare a block without it
+        assert!(mempool.add_transaction(signed_transfer(&authority_key, 2)).unwrap());
+        engine.propose_block().await.unwrap();
+        let round = *engine.current_round.read().unwrap();
+        let block_hash = *engine.pending_blocks.read().unwrap().keys().next().unwrap();
+        for validator_id in ["validator-1", "validator-2", "validator-3"] {
+            vote(&engine, block_hash, VoteType::Prepare, round, 0, validator_id).await;
         }
+        assert!(!engine.pbft_engine.is_prepared(0, round, block_hash));
+        vote(&engine, block_hash, VoteType::Prepare, round, 0, "validator-5").await;
+        assert!(engine.pbft_engine.is_prepared(0, round, block_hash));
+
+        // The change survives a restart
+        let restarted = ConsensusEngine::new(create_test_config(), storage.clone(), Arc::new(Mempool::new(MempoolConfig::default())))
+            .unwrap();
+        assert_eq!(restarted.leader_election.get_validator_set(), engine.leader_election.get_validator_set());
     }
 }
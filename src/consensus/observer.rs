@@ -0,0 +1,25 @@
+use crate::types::{Block, Hash, NodeId, VoteType};
+
+/// Hooks into consensus state transitions for instrumentation or fault injection
+///
+/// Callbacks run inline on the consensus path, so implementations should return quickly.
+/// Every method defaults to a no-op.
+pub trait ConsensusObserver: Send + Sync {
+    /// A block proposal was made locally or accepted from the leader
+    fn on_propose(&self, _block: &Block, _view: u64, _round: u64) {}
+
+    /// A vote from a validator was accepted for the current round
+    fn on_vote(&self, _block_hash: &Hash, _vote_type: &VoteType, _validator_id: &NodeId, _view: u64, _round: u64) {}
+
+    /// A block was committed to storage
+    fn on_commit(&self, _block: &Block) {}
+
+    /// The engine moved to a new view
+    fn on_view_change(&self, _old_view: u64, _new_view: u64) {}
+}
+
+/// Observer that ignores every callback
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopObserver;
+
+impl ConsensusObserver for NoopObserver {}
@@ -1,8 +1,10 @@
 use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, RwLock};
-use anyhow::{Result, anyhow};
-use crate::types::{ConsensusMessage, VoteType, Hash, NodeId, Block};
-use super::ConsensusConfig;
+use anyhow::Result;
+use ed25519_dalek::SigningKey;
+use crate::crypto::{default_scheme, SecretKey, SignatureScheme};
+use crate::types::{ConsensusMessage, VoteType, Hash, NodeId, Block, Signature, sign_vote};
+use super::{ConsensusConfig, ConsensusError, LeaderElection};
 
 /// PBFT (Practical Byzantine Fault Tolerance) consensus phases
 #[derive(Debug, Clone, PartialEq)]
@@ -23,10 +25,21 @@ pub struct PbftLogEntry {
     pub messages: HashMap<NodeId, ConsensusMessage>,
 }
 
+/// Two conflicting votes from one validator in the same phase of a consensus instance
+#[derive(Debug, Clone)]
+pub struct EquivocationEvidence {
+    pub validator_id: NodeId,
+    pub view: u64,
+    pub sequence: u64,
+    pub first: ConsensusMessage,
+    pub second: ConsensusMessage,
+}
+
 /// PBFT consensus engine implementing the three-phase protocol
 #[derive(Clone)]
 pub struct PbftEngine {
     config: ConsensusConfig,
+    leader_election: LeaderElection,
     
     // PBFT state
     current_view: Arc<RwLock<u64>>,
@@ -39,24 +52,61 @@ pub struct PbftEngine {
     prepared_certificates: Arc<RwLock<HashSet<(u64, u64, Hash)>>>,
     committed_certificates: Arc<RwLock<HashSet<(u64, u64, Hash)>>>,
     
+    // Highest sequence known committed; log garbage collection is measured from it
+    stable_checkpoint: Arc<RwLock<Option<u64>>>,
+    
     // View change state
     view_change_votes: Arc<RwLock<HashMap<u64, HashSet<NodeId>>>>,
+    
+    // First vote seen from each validator per (view, sequence, phase), and any conflicting ones
+    recorded_votes: Arc<RwLock<HashMap<(u64, u64, VoteType), HashMap<NodeId, ConsensusMessage>>>>,
+    equivocation_evidence: Arc<RwLock<Vec<EquivocationEvidence>>>,
+    
+    // Key this validator signs its votes with, and the scheme it signs under
+    signing_key: Option<SecretKey>,
+    signature_scheme: Arc<dyn SignatureScheme>,
 }
 
 impl PbftEngine {
     /// Create a new PBFT engine
-    pub fn new(config: ConsensusConfig) -> Result<Self> {
+    pub fn new(config: ConsensusConfig, leader_election: LeaderElection) -> Result<Self> {
         Ok(Self {
             config,
+            leader_election,
             current_view: Arc::new(RwLock::new(0)),
             current_sequence: Arc::new(RwLock::new(0)),
             message_log: Arc::new(RwLock::new(HashMap::new())),
             prepared_certificates: Arc::new(RwLock::new(HashSet::new())),
             committed_certificates: Arc::new(RwLock::new(HashSet::new())),
+            stable_checkpoint: Arc::new(RwLock::new(None)),
             view_change_votes: Arc::new(RwLock::new(HashMap::new())),
+            recorded_votes: Arc::new(RwLock::new(HashMap::new())),
+            equivocation_evidence: Arc::new(RwLock::new(Vec::new())),
+            signing_key: None,
+            signature_scheme: default_scheme(),
         })
     }
 
+    /// Sign outgoing votes with this validator's key
+    pub fn with_signing_key(mut self, signing_key: SigningKey) -> Self {
+        self.signing_key = Some(signing_key.to_bytes());
+        self
+    }
+
+    /// Sign votes under `scheme` instead of Ed25519
+    pub fn with_signature_scheme(mut self, scheme: Arc<dyn SignatureScheme>) -> Self {
+        self.signature_scheme = scheme;
+        self
+    }
+
+    /// Signature over a vote, or all zeros when no key is configured
+    fn sign(&self, block_hash: &Hash, view: u64, sequence: u64, vote_type: &VoteType) -> Signature {
+        match &self.signing_key {
+            Some(key) => sign_vote(self.signature_scheme.as_ref(), key, block_hash, view, sequence, vote_type),
+            None => [0; 64],
+        }
+    }
+
     /// Process a consensus message according to PBFT protocol
     pub fn process_message(&self, message: ConsensusMessage) -> Result<Vec<ConsensusMessage>> {
         match message {
@@ -91,9 +141,9 @@ impl PbftEngine {
             return Ok(vec![]); // Ignore messages from different views
         }
 
-        // Verify the proposer is the primary for this view
-        if !self.is_primary(&block.header.proposer, view) {
-            return Err(anyhow!("Invalid proposer for view {}", view));
+        // Verify the proposer is the primary (or its enabled backup) for this view
+        if !self.is_eligible_proposer(&block.header.proposer, view) {
+            return Err(ConsensusError::InvalidProposer(view).into());
         }
 
         let block_hash = block.hash();
@@ -125,7 +175,7 @@ impl PbftEngine {
                 round: sequence,
                 view,
                 validator_id: self.config.node_id.clone(),
-                signature: [0; 64], // Simplified signature
+                signature: self.sign(&block_hash, view, sequence, &VoteType::Prepare),
             };
 
             Ok(vec![prepare_message])
@@ -150,28 +200,31 @@ impl PbftEngine {
         }
 
         // Verify validator is in validator set
-        if !self.config.validator_set.contains(&validator_id) {
+        if !self.leader_election.is_validator(&validator_id) {
             return Ok(vec![]);
         }
 
         let log_key = (view, sequence);
+        let vote = ConsensusMessage::Vote {
+            block_hash,
+            vote_type: VoteType::Prepare,
+            round: sequence,
+            view,
+            validator_id: validator_id.clone(),
+            signature,
+        };
+
+        // A second, conflicting vote is evidence, not a vote
+        if !self.record_vote(view, sequence, &validator_id, &vote) {
+            return Ok(vec![]);
+        }
 
         // Store the PREPARE message
         {
             let mut message_log = self.message_log.write().unwrap();
             if let Some(entry) = message_log.get_mut(&log_key) {
                 if entry.block_hash == block_hash {
-                    entry.messages.insert(
-                        validator_id.clone(),
-                        ConsensusMessage::Vote {
-                            block_hash,
-                            vote_type: VoteType::Prepare,
-                            round: sequence,
-                            view,
-                            validator_id: validator_id.clone(),
-                            signature,
-                        },
-                    );
+                    entry.messages.insert(validator_id.clone(), vote);
                     entry.phase = PbftPhase::Prepare;
                 }
             }
@@ -182,10 +235,13 @@ impl PbftEngine {
         let required_count = self.byzantine_threshold();
 
         if prepare_count >= required_count {
-            // Mark as prepared
-            {
+            // Mark as prepared; only the first quorum sends our COMMIT
+            let newly_prepared = {
                 let mut prepared = self.prepared_certificates.write().unwrap();
-                prepared.insert((view, sequence, block_hash));
+                prepared.insert((view, sequence, block_hash))
+            };
+            if !newly_prepared {
+                return Ok(vec![]);
             }
 
             // Send COMMIT message if we're a validator
@@ -196,7 +252,7 @@ impl PbftEngine {
                     round: sequence,
                     view,
                     validator_id: self.config.node_id.clone(),
-                    signature: [0; 64], // Simplified signature
+                    signature: self.sign(&block_hash, view, sequence, &VoteType::Commit),
                 };
 
                 return Ok(vec![commit_message]);
@@ -222,28 +278,31 @@ impl PbftEngine {
         }
 
         // Verify validator is in validator set
-        if !self.config.validator_set.contains(&validator_id) {
+        if !self.leader_election.is_validator(&validator_id) {
             return Ok(vec![]);
         }
 
         let log_key = (view, sequence);
+        let vote = ConsensusMessage::Vote {
+            block_hash,
+            vote_type: VoteType::Commit,
+            round: sequence,
+            view,
+            validator_id: validator_id.clone(),
+            signature,
+        };
+
+        // A second, conflicting vote is evidence, not a vote
+        if !self.record_vote(view, sequence, &validator_id, &vote) {
+            return Ok(vec![]);
+        }
 
         // Store the COMMIT message
         {
             let mut message_log = self.message_log.write().unwrap();
             if let Some(entry) = message_log.get_mut(&log_key) {
                 if entry.block_hash == block_hash {
-                    entry.messages.insert(
-                        validator_id.clone(),
-                        ConsensusMessage::Vote {
-                            block_hash,
-                            vote_type: VoteType::Commit,
-                            round: sequence,
-                            view,
-                            validator_id: validator_id.clone(),
-                            signature,
-                        },
-                    );
+                    entry.messages.insert(validator_id.clone(), vote);
                     entry.phase = PbftPhase::Commit;
                 }
             }
@@ -254,10 +313,13 @@ impl PbftEngine {
         let required_count = self.byzantine_threshold();
 
         if commit_count >= required_count {
-            // Mark as committed
-            {
+            // Mark as committed; later COMMITs for the instance change nothing
+            let newly_committed = {
                 let mut committed = self.committed_certificates.write().unwrap();
-                committed.insert((view, sequence, block_hash));
+                committed.insert((view, sequence, block_hash))
+            };
+            if !newly_committed {
+                return Ok(vec![]);
             }
 
             // Update phase to committed
@@ -288,7 +350,7 @@ impl PbftEngine {
         signature: crate::types::Signature,
     ) -> Result<Vec<ConsensusMessage>> {
         // Verify validator is in validator set
-        if !self.config.validator_set.contains(&validator_id) {
+        if !self.leader_election.is_validator(&validator_id) {
             return Ok(vec![]);
         }
 
@@ -352,19 +414,71 @@ impl PbftEngine {
         Ok(vec![])
     }
 
+    /// Record a validator's vote, or evidence if it conflicts with the vote it already cast
+    /// in this phase; returns whether the vote may be counted
+    fn record_vote(&self, view: u64, sequence: u64, validator_id: &NodeId, vote: &ConsensusMessage) -> bool {
+        let (block_hash, vote_type) = match vote {
+            ConsensusMessage::Vote { block_hash, vote_type, .. } => (*block_hash, vote_type.clone()),
+            _ => return false,
+        };
+
+        let mut recorded_votes = self.recorded_votes.write().unwrap();
+        let slot_votes = recorded_votes.entry((view, sequence, vote_type)).or_default();
+
+        let first = match slot_votes.get(validator_id) {
+            None => {
+                slot_votes.insert(validator_id.clone(), vote.clone());
+                return true;
+            }
+            Some(first) => first,
+        };
+
+        match first {
+            ConsensusMessage::Vote { block_hash: first_hash, .. } if *first_hash == block_hash => true,
+            _ => {
+                tracing::warn!(
+                    "Validator {} equivocated in view {} sequence {}",
+                    validator_id, view, sequence
+                );
+                self.equivocation_evidence.write().unwrap().push(EquivocationEvidence {
+                    validator_id: validator_id.clone(),
+                    view,
+                    sequence,
+                    first: first.clone(),
+                    second: vote.clone(),
+                });
+                false
+            }
+        }
+    }
+
+    /// Conflicting votes observed so far, for reporting or slashing
+    pub fn get_equivocation_evidence(&self) -> Vec<EquivocationEvidence> {
+        self.equivocation_evidence.read().unwrap().clone()
+    }
+
     /// Check if a node is the primary for a given view
     fn is_primary(&self, node_id: &NodeId, view: u64) -> bool {
-        if self.config.validator_set.is_empty() {
+        if self.leader_election.validator_count() == 0 {
             return false;
         }
         
-        let primary_index = (view as usize) % self.config.validator_set.len();
-        &self.config.validator_set[primary_index] == node_id
+        self.leader_election.is_leader(node_id, view)
+    }
+
+    /// Check if a node may propose in a view: its primary, or the backup when enabled
+    pub fn is_eligible_proposer(&self, proposer: &NodeId, view: u64) -> bool {
+        if self.is_primary(proposer, view) {
+            return true;
+        }
+
+        self.config.backup_proposer_timeout_ms.is_some()
+            && self.leader_election.get_backup_leader(view).as_ref() == Some(proposer)
     }
 
     /// Get the Byzantine fault threshold (2f+1)
     fn byzantine_threshold(&self) -> usize {
-        (self.config.validator_set.len() * 2 / 3) + 1
+        self.leader_election.byzantine_threshold()
     }
 
     /// Count PREPARE messages for a specific consensus instance
@@ -403,6 +517,19 @@ impl PbftEngine {
         0
     }
 
+    /// COMMIT votes logged for a consensus instance
+    pub fn get_commit_votes(&self, view: u64, sequence: u64, block_hash: Hash) -> Vec<ConsensusMessage> {
+        let message_log = self.message_log.read().unwrap();
+
+        match message_log.get(&(view, sequence)) {
+            Some(entry) if entry.block_hash == block_hash => entry.messages.values()
+                .filter(|msg| matches!(msg, ConsensusMessage::Vote { vote_type: VoteType::Commit, .. }))
+                .cloned()
+                .collect(),
+            _ => vec![],
+        }
+    }
+
     /// Check if a block is prepared
     pub fn is_prepared(&self, view: u64, sequence: u64, block_hash: Hash) -> bool {
         let prepared = self.prepared_certificates.read().unwrap();
@@ -420,20 +547,68 @@ impl PbftEngine {
         *self.current_view.read().unwrap()
     }
 
+    /// Move to a view decided outside the PBFT message flow, e.g. by a local view change
+    pub fn set_view(&self, view: u64) {
+        let mut current_view = self.current_view.write().unwrap();
+        *current_view = view;
+    }
+
     /// Get current sequence number
     pub fn get_current_sequence(&self) -> u64 {
         *self.current_sequence.read().unwrap()
     }
 
+    /// Prepared and committed certificates, for persisting across restarts
+    pub fn get_certificates(&self) -> (Vec<(u64, u64, Hash)>, Vec<(u64, u64, Hash)>) {
+        let prepared = self.prepared_certificates.read().unwrap().iter().copied().collect();
+        let committed = self.committed_certificates.read().unwrap().iter().copied().collect();
+        (prepared, committed)
+    }
+
+    /// Resume at a persisted view and sequence with the certificates reached before a restart
+    pub fn restore(
+        &self,
+        view: u64,
+        sequence: u64,
+        prepared: Vec<(u64, u64, Hash)>,
+        committed: Vec<(u64, u64, Hash)>,
+    ) {
+        *self.current_view.write().unwrap() = view;
+        *self.current_sequence.write().unwrap() = sequence;
+        self.prepared_certificates.write().unwrap().extend(prepared);
+        self.committed_certificates.write().unwrap().extend(committed);
+    }
+
+    /// Mark every instance committed so far as stable, returning the checkpoint sequence
+    pub fn checkpoint(&self) -> Option<u64> {
+        // The sequence only advances past instances that committed
+        let committed = self.get_current_sequence().checked_sub(1)?;
+
+        let mut stable_checkpoint = self.stable_checkpoint.write().unwrap();
+        if stable_checkpoint.is_none_or(|checkpoint| committed > checkpoint) {
+            *stable_checkpoint = Some(committed);
+        }
+        *stable_checkpoint
+    }
+
+    /// Get the last stable checkpoint, if one has been taken
+    pub fn get_stable_checkpoint(&self) -> Option<u64> {
+        *self.stable_checkpoint.read().unwrap()
+    }
+
     /// Clean up old message logs (garbage collection)
+    ///
+    /// Keeps the `keep_last_n` instances up to and including the stable checkpoint, and
+    /// everything after it; nothing is discarded before the first checkpoint.
     pub fn cleanup_old_logs(&self, keep_last_n: usize) {
-        let current_sequence = *self.current_sequence.read().unwrap();
-        
-        if current_sequence <= keep_last_n as u64 {
+        let Some(checkpoint) = self.get_stable_checkpoint() else {
             return;
-        }
+        };
 
-        let cutoff_sequence = current_sequence - keep_last_n as u64;
+        let cutoff_sequence = (checkpoint + 1).saturating_sub(keep_last_n as u64);
+        if cutoff_sequence == 0 {
+            return;
+        }
         
         {
             let mut message_log = self.message_log.write().unwrap();
@@ -449,5 +624,159 @@ impl PbftEngine {
             let mut committed = self.committed_certificates.write().unwrap();
             committed.retain(|(_, sequence, _)| *sequence >= cutoff_sequence);
         }
+
+        {
+            let mut recorded_votes = self.recorded_votes.write().unwrap();
+            recorded_votes.retain(|(_, sequence, _), _| *sequence >= cutoff_sequence);
+        }
+
+        tracing::debug!("Discarded PBFT instances below sequence {}", cutoff_sequence);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::BlockHeader;
+    use crate::consensus::view_change::ViewChangeTimeout;
+
+    fn create_test_engine() -> PbftEngine {
+        let validator_set: Vec<NodeId> = (1..=4).map(|i| format!("validator-{}", i)).collect();
+        let config = ConsensusConfig {
+            node_id: "validator-2".to_string(),
+            is_validator: true,
+            validator_set: validator_set.clone(),
+            block_time_ms: 1000,
+            view_timeout: ViewChangeTimeout { base_timeout_ms: 1000, ..ViewChangeTimeout::default() },
+            max_block_size: 1024 * 1024,
+            max_transactions_per_block: 100,
+            leader_shuffle_epoch_length: 0,
+            dynamic_block_size: None,
+            block_interval: None,
+            backup_proposer_timeout_ms: None,
+            empty_block_timeout_ms: None,
+            min_transactions_per_block: None,
+            pbft_log_retention: 100,
+            dev_mode: false,
+            vrf_leader_election: false,
+        };
+
+        PbftEngine::new(config, LeaderElection::new(validator_set)).unwrap()
+    }
+
+    fn create_test_block(proposer: &str, timestamp: u64) -> Block {
+        Block {
+            header: BlockHeader {
+                height: 1,
+                previous_hash: [0; 32],
+                merkle_root: [0; 32],
+                state_root: [0; 32],
+                timestamp,
+                proposer: proposer.to_string(),
+                round: 0,
+                view: 0,
+                vrf_proof: None,
+            },
+            transactions: vec![],
+            signatures: vec![],
+        }
+    }
+
+    fn prepare(block_hash: Hash, validator_id: &str) -> ConsensusMessage {
+        ConsensusMessage::Vote {
+            block_hash,
+            vote_type: VoteType::Prepare,
+            round: 0,
+            view: 0,
+            validator_id: validator_id.to_string(),
+            signature: [0; 64],
+        }
+    }
+
+    fn vote(block_hash: Hash, vote_type: VoteType, sequence: u64, validator_id: &str) -> ConsensusMessage {
+        ConsensusMessage::Vote {
+            block_hash,
+            vote_type,
+            round: sequence,
+            view: 0,
+            validator_id: validator_id.to_string(),
+            signature: [0; 64],
+        }
+    }
+
+    fn log_sizes(engine: &PbftEngine) -> (usize, usize, usize, usize) {
+        (
+            engine.message_log.read().unwrap().len(),
+            engine.prepared_certificates.read().unwrap().len(),
+            engine.committed_certificates.read().unwrap().len(),
+            engine.recorded_votes.read().unwrap().len(),
+        )
+    }
+
+    #[test]
+    fn test_checkpoint_discards_old_instances() {
+        let engine = create_test_engine();
+        let leader = engine.leader_election.get_leader(0);
+
+        // Nothing is committed yet, so there is nothing to checkpoint or discard
+        assert_eq!(engine.checkpoint(), None);
+        engine.cleanup_old_logs(0);
+
+        for sequence in 0..20 {
+            let block = create_test_block(&leader, 1_000 + sequence);
+            let block_hash = block.hash();
+            engine.process_message(ConsensusMessage::Propose { block, round: sequence, view: 0 }).unwrap();
+            for validator in ["validator-1", "validator-3", "validator-4"] {
+                engine.process_message(vote(block_hash, VoteType::Prepare, sequence, validator)).unwrap();
+            }
+            for validator in ["validator-1", "validator-3", "validator-4"] {
+                engine.process_message(vote(block_hash, VoteType::Commit, sequence, validator)).unwrap();
+            }
+            assert!(engine.is_committed(0, sequence, block_hash));
+        }
+        assert_eq!(log_sizes(&engine), (20, 20, 20, 40));
+
+        // Before a checkpoint, cleanup keeps everything
+        engine.cleanup_old_logs(5);
+        assert_eq!(log_sizes(&engine), (20, 20, 20, 40));
+
+        assert_eq!(engine.checkpoint(), Some(19));
+        engine.cleanup_old_logs(5);
+        assert_eq!(log_sizes(&engine), (5, 5, 5, 10));
+        assert!(engine.message_log.read().unwrap().keys().all(|(_, sequence)| *sequence >= 15));
+
+        // The checkpoint never moves backwards
+        engine.restore(0, 3, vec![], vec![]);
+        assert_eq!(engine.checkpoint(), Some(19));
+    }
+
+    #[test]
+    fn test_conflicting_prepares_are_evidence_not_votes() {
+        let engine = create_test_engine();
+        let leader = engine.leader_election.get_leader(0);
+
+        let block = create_test_block(&leader, 1_000);
+        let block_hash = block.hash();
+        let conflicting_hash = create_test_block(&leader, 2_000).hash();
+        engine.process_message(ConsensusMessage::Propose { block, round: 0, view: 0 }).unwrap();
+
+        // validator-3 votes for the proposed block, then for a conflicting one
+        engine.process_message(prepare(block_hash, "validator-3")).unwrap();
+        engine.process_message(prepare(conflicting_hash, "validator-3")).unwrap();
+        assert_eq!(engine.count_prepare_messages(0, 0, block_hash), 1);
+
+        let evidence = engine.get_equivocation_evidence();
+        assert_eq!(evidence.len(), 1);
+        assert_eq!(evidence[0].validator_id, "validator-3");
+        assert!(matches!(evidence[0].first, ConsensusMessage::Vote { block_hash: hash, .. } if hash == block_hash));
+        assert!(matches!(evidence[0].second, ConsensusMessage::Vote { block_hash: hash, .. } if hash == conflicting_hash));
+
+        // Switching back still counts once, and repeating a vote is not equivocation
+        engine.process_message(prepare(block_hash, "validator-3")).unwrap();
+        engine.process_message(prepare(block_hash, "validator-1")).unwrap();
+        engine.process_message(prepare(block_hash, "validator-1")).unwrap();
+        assert_eq!(engine.count_prepare_messages(0, 0, block_hash), 2);
+        assert_eq!(engine.get_equivocation_evidence().len(), 1);
+        assert!(!engine.is_prepared(0, 0, block_hash));
     }
 }
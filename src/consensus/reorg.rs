@@ -0,0 +1,96 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, RwLock};
+use serde::Serialize;
+
+use crate::storage::ChainUpdate;
+
+/// Number of recent reorg depths retained for reporting
+const RECENT_REORG_WINDOW: usize = 32;
+
+/// Observed chain reorganization statistics
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReorgStats {
+    pub total_reorgs: u64,
+    pub max_depth: u64,
+    pub recent_depths: Vec<u64>,
+}
+
+/// Tracks the depth of reorgs observed when committing competing blocks
+#[derive(Debug, Clone, Default)]
+pub struct ReorgTracker {
+    total_reorgs: Arc<RwLock<u64>>,
+    max_depth: Arc<RwLock<u64>>,
+    recent_depths: Arc<RwLock<VecDeque<u64>>>,
+}
+
+impl ReorgTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the reorg, if any, made by committing a block
+    pub fn observe(&self, update: &ChainUpdate) -> Option<u64> {
+        match update {
+            ChainUpdate::Reorged { depth, .. } if *depth > 0 => {
+                self.record(*depth);
+                tracing::warn!("Reorg onto a longer branch rolled back {} blocks", depth);
+                Some(*depth)
+            }
+            _ => None,
+        }
+    }
+
+    /// Record a reorg of the given depth
+    pub fn record(&self, depth: u64) {
+        *self.total_reorgs.write().unwrap() += 1;
+
+        let mut max_depth = self.max_depth.write().unwrap();
+        *max_depth = (*max_depth).max(depth);
+
+        let mut recent = self.recent_depths.write().unwrap();
+        if recent.len() == RECENT_REORG_WINDOW {
+            recent.pop_front();
+        }
+        recent.push_back(depth);
+    }
+
+    /// Get a snapshot of the observed reorg statistics
+    pub fn stats(&self) -> ReorgStats {
+        ReorgStats {
+            total_reorgs: *self.total_reorgs.read().unwrap(),
+            max_depth: *self.max_depth.read().unwrap(),
+            recent_depths: self.recent_depths.read().unwrap().iter().copied().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reorg_depth_recorded() {
+        let tracker = ReorgTracker::new();
+
+        // Extending the head or keeping a side block is not a reorg
+        assert_eq!(tracker.observe(&ChainUpdate::Extended { state_root: [1; 32] }), None);
+        assert_eq!(tracker.observe(&ChainUpdate::SideBranch), None);
+
+        // A longer branch forking below height 2 of a 3-block chain rolls back heights 2 and 3
+        let reorg = ChainUpdate::Reorged { state_root: [2; 32], depth: 2, orphaned: vec![] };
+        assert_eq!(tracker.observe(&reorg), Some(2));
+
+        let stats = tracker.stats();
+        assert_eq!(stats.total_reorgs, 1);
+        assert_eq!(stats.max_depth, 2);
+        assert_eq!(stats.recent_depths, vec![2]);
+    }
+
+    #[test]
+    fn test_no_reorgs_reports_zero() {
+        let stats = ReorgTracker::new().stats();
+        assert_eq!(stats.total_reorgs, 0);
+        assert_eq!(stats.max_depth, 0);
+        assert!(stats.recent_depths.is_empty());
+    }
+}
@@ -1,11 +1,11 @@
 use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, RwLock};
 use std::time::{Duration, Instant};
-use anyhow::{Result, anyhow};
+use anyhow::Result;
 use tokio::sync::mpsc;
 
 use crate::types::{ConsensusMessage, NodeId, Hash};
-use super::ConsensusConfig;
+use super::{ConsensusConfig, ConsensusError, LeaderElection};
 
 /// View change state
 #[derive(Debug, Clone, PartialEq)]
@@ -16,6 +16,9 @@ pub enum ViewChangeState {
 }
 
 /// View change timeout configuration
+///
+/// A view times out after `base_timeout_ms`; each failed view multiplies the
+/// timeout by `timeout_multiplier`, up to `max_timeout_ms`.
 #[derive(Debug, Clone)]
 pub struct ViewChangeTimeout {
     pub base_timeout_ms: u64,
@@ -33,10 +36,32 @@ impl Default for ViewChangeTimeout {
     }
 }
 
+impl ViewChangeTimeout {
+    /// Check the backoff never shrinks and the base fits under the cap
+    pub fn validate(&self) -> Result<()> {
+        if self.base_timeout_ms == 0 {
+            return Err(ConsensusError::InvalidConfig("view timeout base must be greater than zero".to_string()).into());
+        }
+        if self.base_timeout_ms > self.max_timeout_ms {
+            return Err(ConsensusError::InvalidConfig(format!(
+                "view timeout base {}ms exceeds the maximum {}ms", self.base_timeout_ms, self.max_timeout_ms
+            )).into());
+        }
+        // Also rejects NaN
+        if !(self.timeout_multiplier >= 1.0 && self.timeout_multiplier.is_finite()) {
+            return Err(ConsensusError::InvalidConfig(format!(
+                "view timeout multiplier {} must be at least 1.0", self.timeout_multiplier
+            )).into());
+        }
+        Ok(())
+    }
+}
+
 /// View change manager for handling view changes in BFT consensus
 #[derive(Clone)]
 pub struct ViewChangeManager {
     config: ConsensusConfig,
+    leader_election: LeaderElection,
     timeout_config: ViewChangeTimeout,
     
     // View change state
@@ -57,19 +82,22 @@ pub struct ViewChangeManager {
 
 impl ViewChangeManager {
     /// Create a new view change manager
-    pub fn new(config: ConsensusConfig) -> Self {
+    pub fn new(config: ConsensusConfig, leader_election: LeaderElection) -> Self {
+        let timeout_config = config.view_timeout.clone();
+
         Self {
             config,
-            timeout_config: ViewChangeTimeout::default(),
+            leader_election,
             current_view: Arc::new(RwLock::new(0)),
             state: Arc::new(RwLock::new(ViewChangeState::Normal)),
             view_change_messages: Arc::new(RwLock::new(HashMap::new())),
             new_view_messages: Arc::new(RwLock::new(HashMap::new())),
             view_start_time: Arc::new(RwLock::new(Instant::now())),
             timeout_duration: Arc::new(RwLock::new(Duration::from_millis(
-                ViewChangeTimeout::default().base_timeout_ms
+                timeout_config.base_timeout_ms
             ))),
             message_sender: Arc::new(RwLock::new(None)),
+            timeout_config,
         }
     }
 
@@ -120,7 +148,7 @@ impl ViewChangeManager {
         let current_view = *self.current_view.read().unwrap();
         
         if new_view <= current_view {
-            return Err(anyhow!("New view must be greater than current view"));
+            return Err(ConsensusError::StaleView { new_view, current_view }.into());
         }
 
         // Update state
@@ -159,7 +187,7 @@ impl ViewChangeManager {
         signature: crate::types::Signature,
     ) -> Result<()> {
         // Verify validator is in validator set
-        if !self.config.validator_set.contains(&validator_id) {
+        if !self.leader_election.is_validator(&validator_id) {
             return Ok(()); // Ignore invalid validators
         }
 
@@ -225,20 +253,23 @@ impl ViewChangeManager {
 
         // Verify the NEW-VIEW message contains enough VIEW-CHANGE messages
         if view_change_messages.len() < self.byzantine_threshold() {
-            return Err(anyhow!("Insufficient view change messages in NEW-VIEW"));
+            return Err(ConsensusError::InsufficientViewChanges {
+                have: view_change_messages.len(),
+                need: self.byzantine_threshold(),
+            }.into());
         }
 
         // Verify all view change messages are valid
         for msg in &view_change_messages {
             if let ConsensusMessage::ViewChange { new_view, validator_id, .. } = msg {
                 if *new_view != view {
-                    return Err(anyhow!("Invalid view in view change message"));
+                    return Err(ConsensusError::InvalidNewView("view change for a different view").into());
                 }
-                if !self.config.validator_set.contains(validator_id) {
-                    return Err(anyhow!("Invalid validator in view change message"));
+                if !self.leader_election.is_validator(validator_id) {
+                    return Err(ConsensusError::InvalidNewView("view change from a non-validator").into());
                 }
             } else {
-                return Err(anyhow!("Invalid message type in NEW-VIEW"));
+                return Err(ConsensusError::InvalidNewView("not a view change message").into());
             }
         }
 
@@ -272,7 +303,10 @@ impl ViewChangeManager {
         };
 
         if view_change_messages.len() < self.byzantine_threshold() {
-            return Err(anyhow!("Insufficient view change messages to send NEW-VIEW"));
+            return Err(ConsensusError::InsufficientViewChanges {
+                have: view_change_messages.len(),
+                need: self.byzantine_threshold(),
+            }.into());
         }
 
         let new_view_message = ConsensusMessage::NewView {
@@ -305,17 +339,16 @@ impl ViewChangeManager {
 
     /// Check if this node is the new primary for the given view
     fn is_new_primary(&self, view: u64) -> bool {
-        if self.config.validator_set.is_empty() {
+        if self.leader_election.validator_count() == 0 {
             return false;
         }
         
-        let primary_index = (view as usize) % self.config.validator_set.len();
-        &self.config.validator_set[primary_index] == &self.config.node_id
+        self.leader_election.is_leader(&self.config.node_id, view)
     }
 
     /// Get Byzantine fault threshold
     fn byzantine_threshold(&self) -> usize {
-        (self.config.validator_set.len() * 2 / 3) + 1
+        self.leader_election.byzantine_threshold()
     }
 
     /// Broadcast a consensus message
@@ -323,7 +356,7 @@ impl ViewChangeManager {
         let message_sender = self.message_sender.read().unwrap();
         if let Some(sender) = message_sender.as_ref() {
             sender.send(message)
-                .map_err(|e| anyhow!("Failed to broadcast message: {}", e))?;
+                .map_err(|e| ConsensusError::ChannelClosed(e.to_string()))?;
         }
         Ok(())
     }
@@ -339,6 +372,17 @@ impl ViewChangeManager {
         *timeout_duration = new_duration.min(max_duration);
     }
 
+    /// Reset the timeout to its base value once the view makes progress
+    pub fn reset_timeout(&self) {
+        let mut timeout_duration = self.timeout_duration.write().unwrap();
+        *timeout_duration = Duration::from_millis(self.timeout_config.base_timeout_ms);
+    }
+
+    /// Get the current view timeout duration
+    pub fn current_timeout(&self) -> Duration {
+        *self.timeout_duration.read().unwrap()
+    }
+
     /// Get current view
     pub fn get_current_view(&self) -> u64 {
         *self.current_view.read().unwrap()
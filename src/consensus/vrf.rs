@@ -0,0 +1,174 @@
+use curve25519_dalek::edwards::{CompressedEdwardsY, EdwardsPoint};
+use curve25519_dalek::scalar::{clamp_integer, Scalar};
+use ed25519_dalek::SigningKey;
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256, Sha512};
+use crate::types::{Hash, NodeId, PublicKey};
+
+/// ECVRF-EDWARDS25519-SHA512-TAI (RFC 9381) suite identifier
+const SUITE: u8 = 0x03;
+
+/// A validator's verifiable random output for one (previous block, view) slot
+///
+/// An ECVRF proof (RFC 9381, edwards25519 with SHA-512) under the validator's Ed25519
+/// key. Each key has exactly one valid proof per slot, so the output can't be ground
+/// by re-signing, and nobody without the key can predict it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct VrfProof {
+    pub validator_id: NodeId,
+    /// First 32 bytes of the ECVRF output
+    pub output: Hash,
+    pub gamma: [u8; 32],
+    pub challenge: [u8; 16],
+    pub response: [u8; 32],
+}
+
+impl VrfProof {
+    /// Compute this validator's output and proof for a slot
+    pub fn prove(validator_id: NodeId, signing_key: &SigningKey, prev_hash: &Hash, view: u64) -> Self {
+        // The Ed25519 secret scalar and nonce prefix, so the VRF key is the validator's key
+        let expanded = Sha512::digest(signing_key.to_bytes());
+        let secret = Scalar::from_bytes_mod_order(clamp_integer(expanded[..32].try_into().unwrap()));
+        let public_key = signing_key.verifying_key().to_bytes();
+        let public_point = EdwardsPoint::mul_base(&secret);
+
+        let alpha = vrf_input(prev_hash, view);
+        let h = encode_to_curve(&public_key, &alpha)
+            .expect("a point is found within 256 tries except with negligible probability");
+        let gamma = secret * h;
+
+        let mut nonce_hasher = Sha512::new();
+        nonce_hasher.update(&expanded[32..]);
+        nonce_hasher.update(h.compress().as_bytes());
+        let mut wide = [0u8; 64];
+        wide.copy_from_slice(&nonce_hasher.finalize());
+        let nonce = Scalar::from_bytes_mod_order_wide(&wide);
+
+        let challenge = challenge(&[
+            public_point,
+            h,
+            gamma,
+            EdwardsPoint::mul_base(&nonce),
+            nonce * h,
+        ]);
+        let response = nonce + challenge_scalar(&challenge) * secret;
+
+        Self {
+            validator_id,
+            output: proof_to_output(&gamma),
+            gamma: gamma.compress().to_bytes(),
+            challenge,
+            response: response.to_bytes(),
+        }
+    }
+
+    /// Check the proof was made by `public_key` for this slot and yields the claimed output
+    pub fn verify(&self, public_key: &PublicKey, prev_hash: &Hash, view: u64) -> bool {
+        let Some(public_point) = CompressedEdwardsY(*public_key).decompress() else {
+            return false;
+        };
+        let Some(gamma) = CompressedEdwardsY(self.gamma).decompress() else {
+            return false;
+        };
+        let Some(response) = Option::<Scalar>::from(Scalar::from_canonical_bytes(self.response)) else {
+            return false;
+        };
+        // Small-order keys would let one proof verify for many outputs
+        if public_point.is_small_order() {
+            return false;
+        }
+        let Some(h) = encode_to_curve(public_key, &vrf_input(prev_hash, view)) else {
+            return false;
+        };
+
+        let challenge_scalar = challenge_scalar(&self.challenge);
+        let u = EdwardsPoint::vartime_double_scalar_mul_basepoint(&-challenge_scalar, &public_point, &response);
+        let v = response * h - challenge_scalar * gamma;
+
+        challenge(&[public_point, h, gamma, u, v]) == self.challenge
+            && proof_to_output(&gamma) == self.output
+    }
+}
+
+/// Domain-separated input for a slot, so proofs can't double as vote signatures
+fn vrf_input(prev_hash: &Hash, view: u64) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update(b"leader-vrf");
+    hasher.update(prev_hash);
+    hasher.update(view.to_le_bytes());
+    hasher.finalize().into()
+}
+
+/// Hash the key and input to a curve point by try-and-increment
+fn encode_to_curve(public_key: &PublicKey, alpha: &[u8]) -> Option<EdwardsPoint> {
+    (0..=u8::MAX).find_map(|counter| {
+        let mut hasher = Sha512::new();
+        hasher.update([SUITE, 0x01]);
+        hasher.update(public_key);
+        hasher.update(alpha);
+        hasher.update([counter, 0x00]);
+        let digest = hasher.finalize();
+
+        CompressedEdwardsY(digest[..32].try_into().unwrap())
+            .decompress()
+            .map(|point| point.mul_by_cofactor())
+    })
+}
+
+/// The challenge over the proof's points, truncated to 16 bytes
+fn challenge(points: &[EdwardsPoint]) -> [u8; 16] {
+    let mut hasher = Sha512::new();
+    hasher.update([SUITE, 0x02]);
+    for point in points {
+        hasher.update(point.compress().as_bytes());
+    }
+    hasher.update([0x00]);
+
+    hasher.finalize()[..16].try_into().unwrap()
+}
+
+fn challenge_scalar(challenge: &[u8; 16]) -> Scalar {
+    let mut bytes = [0u8; 32];
+    bytes[..16].copy_from_slice(challenge);
+    Scalar::from_bytes_mod_order(bytes)
+}
+
+fn proof_to_output(gamma: &EdwardsPoint) -> Hash {
+    let mut hasher = Sha512::new();
+    hasher.update([SUITE, 0x03]);
+    hasher.update(gamma.mul_by_cofactor().compress().as_bytes());
+    hasher.update([0x00]);
+
+    hasher.finalize()[..32].try_into().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_proof_is_unique_and_bound_to_key_and_slot() {
+        let key = SigningKey::from_bytes(&[1u8; 32]);
+        let public_key = key.verifying_key().to_bytes();
+        let prev_hash = [7; 32];
+
+        let proof = VrfProof::prove("validator-1".to_string(), &key, &prev_hash, 3);
+        assert!(proof.verify(&public_key, &prev_hash, 3));
+        assert_eq!(VrfProof::prove("validator-1".to_string(), &key, &prev_hash, 3), proof);
+
+        // Another slot, another key, or any altered part fails
+        assert!(!proof.verify(&public_key, &prev_hash, 4));
+        assert!(!proof.verify(&public_key, &[8; 32], 3));
+        assert!(!proof.verify(&SigningKey::from_bytes(&[2u8; 32]).verifying_key().to_bytes(), &prev_hash, 3));
+        let other_slot = VrfProof::prove("validator-1".to_string(), &key, &prev_hash, 4);
+        assert_ne!(other_slot.output, proof.output);
+        for tampered in [
+            VrfProof { output: other_slot.output, ..proof.clone() },
+            VrfProof { gamma: other_slot.gamma, ..proof.clone() },
+            VrfProof { challenge: other_slot.challenge, ..proof.clone() },
+            VrfProof { response: other_slot.response, ..proof.clone() },
+        ] {
+            assert!(!tampered.verify(&public_key, &prev_hash, 3));
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use ed25519_dalek::{Signer, SigningKey, Verifier, VerifyingKey, Signature as Ed25519Signature};
+use std::fmt;
+use std::sync::Arc;
+
+use crate::types::{Hash, PublicKey, Signature};
+
+/// Secret key bytes handed to a signature scheme
+pub type SecretKey = [u8; 32];
+
+/// Signature algorithm transactions and consensus votes are signed with
+///
+/// Keys and signatures keep the fixed sizes of `PublicKey` and `Signature`, so
+/// a scheme can be swapped without changing how transactions, votes or blocks
+/// are serialized. Addresses are derived from the public key bytes whatever
+/// the scheme.
+pub trait SignatureScheme: Send + Sync + fmt::Debug {
+    /// Sign a 32-byte payload with `secret_key`
+    fn sign(&self, secret_key: &SecretKey, payload: &Hash) -> Signature;
+
+    /// Check `signature` over `payload` was made with the key behind `public_key`
+    fn verify(&self, public_key: &PublicKey, payload: &Hash, signature: &Signature) -> bool;
+
+    /// Public key matching `secret_key`
+    fn public_key_from(&self, secret_key: &SecretKey) -> PublicKey;
+}
+
+/// Ed25519, the scheme the chain uses unless a node is built with another
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Ed25519Scheme;
+
+impl SignatureScheme for Ed25519Scheme {
+    fn sign(&self, secret_key: &SecretKey, payload: &Hash) -> Signature {
+        SigningKey::from_bytes(secret_key).sign(payload).to_bytes()
+    }
+
+    fn verify(&self, public_key: &PublicKey, payload: &Hash, signature: &Signature) -> bool {
+        match VerifyingKey::from_bytes(public_key) {
+            Ok(key) => key.verify(payload, &Ed25519Signature::from_bytes(signature)).is_ok(),
+            Err(_) => false,
+        }
+    }
+
+    fn public_key_from(&self, secret_key: &SecretKey) -> PublicKey {
+        SigningKey::from_bytes(secret_key).verifying_key().to_bytes()
+    }
+}
+
+/// Scheme components use until given another with `with_signature_scheme`
+pub fn default_scheme() -> Arc<dyn SignatureScheme> {
+    Arc::new(Ed25519Scheme)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha2::{Digest, Sha256};
+    use tempfile::TempDir;
+    use uuid::Uuid;
+    use crate::mempool::{Mempool, MempoolConfig};
+    use crate::storage::Storage;
+    use crate::types::{address_from_public_key, Transaction};
+    use crate::validation::Validator;
+
+    /// Ed25519 over a domain-tagged payload, so its signatures never verify as plain Ed25519
+    #[derive(Debug)]
+    struct TaggedScheme;
+
+    impl TaggedScheme {
+        fn tagged(payload: &Hash) -> Hash {
+            Sha256::digest([b"tagged".as_slice(), payload].concat()).into()
+        }
+    }
+
+    impl SignatureScheme for TaggedScheme {
+        fn sign(&self, secret_key: &SecretKey, payload: &Hash) -> Signature {
+            Ed25519Scheme.sign(secret_key, &Self::tagged(payload))
+        }
+
+        fn verify(&self, public_key: &PublicKey, payload: &Hash, signature: &Signature) -> bool {
+            Ed25519Scheme.verify(public_key, &Self::tagged(payload), signature)
+        }
+
+        fn public_key_from(&self, secret_key: &SecretKey) -> PublicKey {
+            Ed25519Scheme.public_key_from(secret_key)
+        }
+    }
+
+    #[test]
+    fn test_ed25519_round_trip() {
+        let secret_key = [7u8; 32];
+        let public_key = Ed25519Scheme.public_key_from(&secret_key);
+        assert_eq!(public_key, SigningKey::from_bytes(&secret_key).verifying_key().to_bytes());
+
+        let payload = [3u8; 32];
+        let signature = Ed25519Scheme.sign(&secret_key, &payload);
+        assert!(Ed25519Scheme.verify(&public_key, &payload, &signature));
+
+        // Another payload, key or signature fails
+        assert!(!Ed25519Scheme.verify(&public_key, &[4u8; 32], &signature));
+        assert!(!Ed25519Scheme.verify(&Ed25519Scheme.public_key_from(&[8u8; 32]), &payload, &signature));
+        let mut tampered = signature;
+        tampered[0] ^= 1;
+        assert!(!Ed25519Scheme.verify(&public_key, &payload, &tampered));
+    }
+
+    #[test]
+    fn test_schemes_do_not_accept_each_others_signatures() {
+        let secret_key = [7u8; 32];
+        let public_key = TaggedScheme.public_key_from(&secret_key);
+        let payload = [3u8; 32];
+
+        let signature = TaggedScheme.sign(&secret_key, &payload);
+        assert!(TaggedScheme.verify(&public_key, &payload, &signature));
+        assert!(!Ed25519Scheme.verify(&public_key, &payload, &signature));
+        assert!(!TaggedScheme.verify(&public_key, &payload, &Ed25519Scheme.sign(&secret_key, &payload)));
+    }
+
+    fn create_transaction(scheme: &dyn SignatureScheme, secret_key: &SecretKey) -> Transaction {
+        let mut tx = Transaction {
+            id: Uuid::new_v4(),
+            from: address_from_public_key(&scheme.public_key_from(secret_key)),
+            to: [2u8; 20],
+            amount: 1000,
+            fee: 10,
+            nonce: 1,
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_millis() as u64,
+            signature: [0u8; 64],
+            public_key: [0u8; 32],
+            data: vec![],
+        };
+        tx.sign_with(scheme, secret_key);
+        tx
+    }
+
+    #[test]
+    fn test_components_check_signatures_under_their_scheme() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::new(temp_dir.path()).unwrap();
+        let tx = create_transaction(&TaggedScheme, &[7u8; 32]);
+
+        // Built with the default scheme, the transaction's signature is invalid
+        assert!(!tx.verify());
+        assert!(Validator::new(storage.clone()).validate_transaction(&tx).is_err());
+        assert!(!Mempool::new(MempoolConfig::default()).add_transaction(tx.clone()).unwrap());
+
+        // Swapping the scheme is one builder call at construction
+        let scheme: Arc<dyn SignatureScheme> = Arc::new(TaggedScheme);
+        assert!(tx.verify_with(scheme.as_ref()));
+        let validator = Validator::new(storage).with_signature_scheme(scheme.clone());
+        validator.validate_transaction(&tx).unwrap();
+        let mempool = Mempool::new(MempoolConfig::default()).with_signature_scheme(scheme);
+        assert!(mempool.add_transaction(tx).unwrap());
+
+        // Default-signed transactions are now the ones refused
+        let ed25519_tx = create_transaction(&Ed25519Scheme, &[7u8; 32]);
+        assert!(ed25519_tx.verify());
+        assert!(validator.validate_transaction(&ed25519_tx).is_err());
+    }
+}
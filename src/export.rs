@@ -0,0 +1,245 @@
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::api::to_hex;
+use crate::cli::{ExportArgs, ExportFormat};
+use crate::storage::Storage;
+use crate::types::{Block, BlockHeight, Transaction};
+
+const CSV_HEADER: &str = "block_height,block_hash,block_timestamp,proposer,tx_id,tx_hash,from,to,amount,fee,nonce,tx_timestamp";
+
+/// Exported block with its transactions
+#[derive(Debug, Serialize)]
+struct BlockRecord {
+    height: BlockHeight,
+    hash: String,
+    previous_hash: String,
+    merkle_root: String,
+    state_root: String,
+    timestamp: u64,
+    proposer: String,
+    round: u64,
+    view: u64,
+    transactions: Vec<TransactionRecord>,
+}
+
+/// Exported transaction
+#[derive(Debug, Serialize)]
+struct TransactionRecord {
+    id: String,
+    hash: String,
+    from: String,
+    to: String,
+    amount: u64,
+    fee: u64,
+    nonce: u64,
+    timestamp: u64,
+    data: String,
+}
+
+impl From<&Block> for BlockRecord {
+    fn from(block: &Block) -> Self {
+        Self {
+            height: block.header.height,
+            hash: to_hex(&block.hash()),
+            previous_hash: to_hex(&block.header.previous_hash),
+            merkle_root: to_hex(&block.header.merkle_root),
+            state_root: to_hex(&block.header.state_root),
+            timestamp: block.header.timestamp,
+            proposer: block.header.proposer.clone(),
+            round: block.header.round,
+            view: block.header.view,
+            transactions: block.transactions.iter().map(TransactionRecord::from).collect(),
+        }
+    }
+}
+
+impl From<&Transaction> for TransactionRecord {
+    fn from(tx: &Transaction) -> Self {
+        Self {
+            id: tx.id.to_string(),
+            hash: to_hex(&tx.hash()),
+            from: to_hex(&tx.from),
+            to: to_hex(&tx.to),
+            amount: tx.amount,
+            fee: tx.fee,
+            nonce: tx.nonce,
+            timestamp: tx.timestamp,
+            data: to_hex(&tx.data),
+        }
+    }
+}
+
+/// Run the `export` subcommand against the database at `db_path`
+pub fn run(db_path: &Path, args: &ExportArgs) -> Result<()> {
+    let storage = Storage::open_read_only(db_path)?;
+
+    let to = match args.to {
+        Some(to) => to,
+        None => storage.get_latest_height()?
+            .ok_or_else(|| anyhow!("Database contains no blocks"))?,
+    };
+
+    let writer: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(File::create(path)
+            .map_err(|e| anyhow!("Failed to create {}: {}", path.display(), e))?),
+        None => Box::new(io::stdout().lock()),
+    };
+
+    export_blocks(&storage, args.format, args.from, to, BufWriter::new(writer))?;
+    Ok(())
+}
+
+/// Stream blocks `from..=to` to the writer, returning the number of records written
+pub fn export_blocks<W: Write>(
+    storage: &Storage,
+    format: ExportFormat,
+    from: BlockHeight,
+    to: BlockHeight,
+    mut writer: W,
+) -> Result<u64> {
+    if from > to {
+        return Err(anyhow!("Export range is empty: --from {} is after --to {}", from, to));
+    }
+
+    if format == ExportFormat::Csv {
+        writeln!(writer, "{}", CSV_HEADER)?;
+    }
+
+    let mut records = 0;
+    for block in storage.blocks().iter_range(from, to)? {
+        let record = BlockRecord::from(&block?);
+        records += match format {
+            ExportFormat::Jsonl => write_jsonl(&mut writer, &record)?,
+            ExportFormat::Csv => write_csv(&mut writer, &record)?,
+        };
+    }
+
+    writer.flush()?;
+    Ok(records)
+}
+
+fn write_jsonl<W: Write>(writer: &mut W, record: &BlockRecord) -> Result<u64> {
+    serde_json::to_writer(&mut *writer, record)?;
+    writeln!(writer)?;
+    Ok(1)
+}
+
+/// One row per transaction; a block without transactions still gets a row
+fn write_csv<W: Write>(writer: &mut W, record: &BlockRecord) -> Result<u64> {
+    let block_fields = format!(
+        "{},{},{},{}",
+        record.height,
+        record.hash,
+        record.timestamp,
+        csv_field(&record.proposer),
+    );
+
+    if record.transactions.is_empty() {
+        writeln!(writer, "{},,,,,,,,", block_fields)?;
+        return Ok(1);
+    }
+
+    for tx in &record.transactions {
+        writeln!(
+            writer,
+            "{},{},{},{},{},{},{},{},{}",
+            block_fields, tx.id, tx.hash, tx.from, tx.to, tx.amount, tx.fee, tx.nonce, tx.timestamp,
+        )?;
+    }
+    Ok(record.transactions.len() as u64)
+}
+
+/// Quote a free-form CSV field if it contains separators or quotes
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    use uuid::Uuid;
+    use crate::types::BlockHeader;
+
+    fn create_test_block(height: BlockHeight) -> Block {
+        let tx = Transaction {
+            id: Uuid::new_v4(),
+            from: [1u8; 20],
+            to: [2u8; 20],
+            amount: 100 + height,
+            fee: 1,
+            nonce: height + 1,
+            timestamp: 1234567890 + height,
+            signature: [0u8; 64],
+            public_key: [0u8; 32],
+            data: vec![],
+        };
+
+        Block {
+            header: BlockHeader {
+                height,
+                previous_hash: [0; 32],
+                merkle_root: [0; 32],
+                state_root: [0; 32],
+                timestamp: 1234567890 + height,
+                proposer: "validator-1".to_string(),
+                round: 0,
+                view: 0,
+                vrf_proof: None,
+            },
+            transactions: vec![tx],
+            signatures: vec![],
+        }
+    }
+
+    #[test]
+    fn test_export_range_to_jsonl() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::new(temp_dir.path()).unwrap();
+        for height in 0..10 {
+            storage.store_block(&create_test_block(height)).unwrap();
+        }
+
+        let mut output = Vec::new();
+        let records = export_blocks(&storage, ExportFormat::Jsonl, 3, 6, &mut output).unwrap();
+        assert_eq!(records, 4);
+
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 4);
+
+        let expected = storage.blocks().get_block(3).unwrap().unwrap();
+        let first: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(first["height"], 3);
+        assert_eq!(first["hash"], to_hex(&expected.hash()));
+        assert_eq!(first["transactions"][0]["id"], expected.transactions[0].id.to_string());
+        assert_eq!(first["transactions"][0]["amount"], 103);
+    }
+
+    #[test]
+    fn test_export_csv_quotes_fields() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::new(temp_dir.path()).unwrap();
+        let mut block = create_test_block(0);
+        block.header.proposer = "validator, \"one\"".to_string();
+        storage.store_block(&block).unwrap();
+
+        let mut output = Vec::new();
+        export_blocks(&storage, ExportFormat::Csv, 0, 0, &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], CSV_HEADER);
+        assert!(lines[1].starts_with("0,"));
+        assert!(lines[1].contains(",\"validator, \"\"one\"\"\","));
+    }
+}
@@ -0,0 +1,287 @@
+use anyhow::{Context, Result, anyhow};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+
+use crate::api::to_hex;
+use crate::consensus::governance::ValidatorSet;
+use crate::storage::Storage;
+use crate::storage::state_store::{state_root, AccountState};
+use crate::types::{Address, Block, BlockHeader, Hash, NodeId, PublicKey};
+
+/// Initial chain configuration shared by every node of a network
+///
+/// Fields of the genesis file this node doesn't use (consensus parameters,
+/// application state) are ignored.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Genesis {
+    pub chain_id: String,
+    pub genesis_time: DateTime<Utc>,
+    pub validators: Vec<GenesisValidator>,
+    #[serde(default)]
+    pub initial_accounts: Vec<GenesisAccount>,
+}
+
+/// A member of the initial validator set
+#[derive(Debug, Clone, Deserialize)]
+pub struct GenesisValidator {
+    pub node_id: NodeId,
+    /// Hex-encoded Ed25519 public key
+    pub public_key: String,
+}
+
+/// An account funded at genesis
+#[derive(Debug, Clone, Deserialize)]
+pub struct GenesisAccount {
+    /// Hex-encoded 20-byte address
+    pub address: String,
+    pub balance: u64,
+    #[serde(default)]
+    pub nonce: u64,
+}
+
+impl Genesis {
+    /// Read and check a genesis file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read genesis file {}", path.display()))?;
+        let genesis: Genesis = serde_json::from_str(&contents)
+            .with_context(|| format!("Malformed genesis file {}", path.display()))?;
+
+        genesis.validate()
+            .with_context(|| format!("Invalid genesis file {}", path.display()))?;
+        Ok(genesis)
+    }
+
+    /// Check the chain id, validator set and account entries are usable
+    pub fn validate(&self) -> Result<()> {
+        if self.chain_id.is_empty() {
+            return Err(anyhow!("chain_id must not be empty"));
+        }
+
+        if self.genesis_time.timestamp_millis() < 0 {
+            return Err(anyhow!("genesis_time must not be before the Unix epoch"));
+        }
+
+        if self.validators.is_empty() {
+            return Err(anyhow!("at least one validator is required"));
+        }
+
+        let mut node_ids = HashSet::new();
+        for validator in &self.validators {
+            if !node_ids.insert(&validator.node_id) {
+                return Err(anyhow!("validator {} is listed twice", validator.node_id));
+            }
+            validator.public_key()?;
+        }
+
+        let mut addresses = HashSet::new();
+        for account in &self.initial_accounts {
+            if !addresses.insert(account.address()?) {
+                return Err(anyhow!("account {} is listed twice", account.address));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Node ids of the initial validator set, in file order
+    pub fn validator_set(&self) -> Vec<NodeId> {
+        self.validators.iter().map(|validator| validator.node_id.clone()).collect()
+    }
+
+    /// Public key of every initial validator, by node id
+    pub fn validator_keys(&self) -> Result<HashMap<NodeId, PublicKey>> {
+        self.validators
+            .iter()
+            .map(|validator| Ok((validator.node_id.clone(), validator.public_key()?)))
+            .collect()
+    }
+
+    /// The initial validator set with its keys, in file order
+    pub fn validators(&self) -> Result<ValidatorSet> {
+        self.validators
+            .iter()
+            .map(|validator| Ok((validator.node_id.clone(), validator.public_key()?)))
+            .collect::<Result<_>>()
+            .map(ValidatorSet::new)
+    }
+
+    /// The block at height 0, committing to the initial accounts in its state root
+    pub fn block(&self) -> Result<Block> {
+        let mut accounts = BTreeMap::new();
+        for account in &self.initial_accounts {
+            accounts.insert(account.address()?, account.state());
+        }
+
+        let mut block = Block {
+            header: BlockHeader {
+                height: 0,
+                previous_hash: [0; 32],
+                merkle_root: [0; 32],
+                state_root: state_root(&accounts)?,
+                timestamp: self.genesis_time.timestamp_millis() as u64,
+                proposer: "genesis".to_string(),
+                round: 0,
+                view: 0,
+                vrf_proof: None,
+            },
+            transactions: vec![],
+            signatures: vec![],
+        };
+        block.header.merkle_root = block.calculate_merkle_root();
+        Ok(block)
+    }
+
+    /// Write the genesis block and initial balances to a fresh database
+    ///
+    /// A database already initialized from this genesis is left untouched, so
+    /// restarts don't reset balances or governance changes to the validator set;
+    /// one initialized from another genesis is an error.
+    pub fn apply(&self, storage: &Storage) -> Result<Hash> {
+        let block = self.block()?;
+        let hash = block.hash();
+
+        match storage.get_genesis_hash()? {
+            Some(existing) if existing != hash => {
+                return Err(anyhow!(
+                    "Database was initialized from genesis {} but the genesis file describes {}",
+                    to_hex(&existing),
+                    to_hex(&hash),
+                ));
+            }
+            Some(_) => {}
+            None => {
+                for account in &self.initial_accounts {
+                    storage.state().set_account(&account.address()?, &account.state())?;
+                }
+
+                storage.store_block(&block)?;
+                storage.set_genesis_hash(&hash)?;
+            }
+        }
+
+        // Databases initialized before the validator set was stored start from the genesis set
+        if storage.validator_set()?.is_none() {
+            storage.put_validator_set(&self.validators()?)?;
+        }
+        Ok(hash)
+    }
+}
+
+impl GenesisValidator {
+    /// Decode the validator's public key
+    pub fn public_key(&self) -> Result<PublicKey> {
+        decode_hex(&self.public_key)
+            .with_context(|| format!("validator {} has an invalid public key", self.node_id))
+    }
+}
+
+impl GenesisAccount {
+    /// Decode the account address
+    pub fn address(&self) -> Result<Address> {
+        decode_hex(&self.address)
+            .with_context(|| format!("invalid account address {}", self.address))
+    }
+
+    /// The account's state at height 0
+    pub fn state(&self) -> AccountState {
+        AccountState {
+            balance: self.balance,
+            nonce: self.nonce,
+            ..AccountState::default()
+        }
+    }
+}
+
+/// Decode `0x`-prefixed or bare hex into a fixed-size array
+fn decode_hex<const N: usize>(value: &str) -> Result<[u8; N]> {
+    let bytes = hex::decode(value.trim_start_matches("0x"))
+        .map_err(|e| anyhow!("not valid hex: {}", e))?;
+    let len = bytes.len();
+    bytes.try_into().map_err(|_| anyhow!("expected {} bytes, got {}", N, len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const SAMPLE_GENESIS: &str = r#"{
+        "chain_id": "blockchain-test",
+        "genesis_time": "2024-01-01T00:00:00Z",
+        "consensus_params": { "block_time_ms": 1000 },
+        "validators": [
+            { "node_id": "validator-1", "public_key": "0x0101010101010101010101010101010101010101010101010101010101010101", "voting_power": 100 },
+            { "node_id": "validator-2", "public_key": "0x0202020202020202020202020202020202020202020202020202020202020202", "voting_power": 100 }
+        ],
+        "initial_accounts": [
+            { "address": "0x1111111111111111111111111111111111111111", "balance": 5000, "nonce": 0 },
+            { "address": "0x2222222222222222222222222222222222222222", "balance": 7000, "nonce": 3 }
+        ],
+        "app_state": { "native_token": { "total_supply": 21000000000000000000000000 } }
+    }"#;
+
+    fn write_genesis(dir: &TempDir, contents: &str) -> std::path::PathBuf {
+        let path = dir.path().join("genesis.json");
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_genesis_seeds_state_and_validators() {
+        let dir = TempDir::new().unwrap();
+        let genesis = Genesis::load(write_genesis(&dir, SAMPLE_GENESIS)).unwrap();
+        assert_eq!(genesis.chain_id, "blockchain-test");
+        assert_eq!(genesis.validator_set(), vec!["validator-1".to_string(), "validator-2".to_string()]);
+
+        let storage = Storage::new(dir.path().join("db")).unwrap();
+        let hash = genesis.apply(&storage).unwrap();
+
+        assert_eq!(storage.get_genesis_hash().unwrap(), Some(hash));
+        assert_eq!(storage.get_latest_height().unwrap(), Some(0));
+        let block = storage.blocks().get_block(0).unwrap().unwrap();
+        assert_eq!(block.hash(), hash);
+        assert_eq!(block.header.timestamp, 1_704_067_200_000);
+        assert!(block.verify());
+        assert_eq!(block.header.state_root, storage.state().calculate_state_root().unwrap());
+
+        assert_eq!(storage.state().get_balance(&[0x11; 20]).unwrap(), 5000);
+        assert_eq!(storage.state().get_balance(&[0x22; 20]).unwrap(), 7000);
+        assert_eq!(storage.state().get_nonce(&[0x22; 20]).unwrap(), 3);
+        let validators = storage.validator_set().unwrap().unwrap();
+        assert_eq!(validators.node_ids(), genesis.validator_set());
+        assert_eq!(validators.keys()["validator-2"], [0x02; 32]);
+
+        // Restarting doesn't reset balances spent or validators changed since genesis
+        storage.state().set_balance(&[0x11; 20], 1).unwrap();
+        let governed = ValidatorSet::new(vec![("validator-1".to_string(), [0x01; 32])]);
+        storage.put_validator_set(&governed).unwrap();
+        assert_eq!(genesis.apply(&storage).unwrap(), hash);
+        assert_eq!(storage.state().get_balance(&[0x11; 20]).unwrap(), 1);
+        assert_eq!(storage.validator_set().unwrap(), Some(governed));
+
+        // A different genesis can't be applied to the same database
+        let other = Genesis {
+            genesis_time: DateTime::from_timestamp(1, 0).unwrap(),
+            ..genesis.clone()
+        };
+        assert!(other.apply(&storage).is_err());
+    }
+
+    #[test]
+    fn test_missing_or_malformed_genesis_is_an_error() {
+        let dir = TempDir::new().unwrap();
+        let missing = Genesis::load(dir.path().join("absent.json")).unwrap_err();
+        assert!(format!("{:#}", missing).contains("Failed to read genesis file"));
+
+        let malformed = Genesis::load(write_genesis(&dir, "{ not json")).unwrap_err();
+        assert!(format!("{:#}", malformed).contains("Malformed genesis file"));
+
+        let bad_key = SAMPLE_GENESIS.replace("0x0202020202020202020202020202020202020202020202020202020202020202", "0x02");
+        let invalid = Genesis::load(write_genesis(&dir, &bad_key)).unwrap_err();
+        assert!(format!("{:#}", invalid).contains("validator-2 has an invalid public key"));
+    }
+}
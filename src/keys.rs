@@ -0,0 +1,143 @@
+use anyhow::{Context, Result, anyhow};
+use ed25519_dalek::SigningKey;
+use libp2p::{identity, PeerId};
+use serde::{Serialize, Deserialize};
+use std::fs;
+use std::path::Path;
+
+use crate::api::to_hex;
+use crate::cli::KeygenArgs;
+use crate::types::PublicKey;
+
+/// On-disk form of a node key
+#[derive(Debug, Serialize, Deserialize)]
+struct KeyFile {
+    /// Hex Ed25519 secret key
+    secret_key: String,
+    /// Hex public key, for adding the node to a genesis validator set
+    public_key: String,
+}
+
+/// A node's Ed25519 key, used both as its libp2p identity and to sign as a validator
+#[derive(Clone)]
+pub struct NodeKey {
+    signing_key: SigningKey,
+}
+
+impl NodeKey {
+    /// Generate a new random key
+    pub fn generate() -> Self {
+        Self {
+            signing_key: SigningKey::generate(&mut rand::rngs::OsRng),
+        }
+    }
+
+    /// Read a key written by `save`
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read key file {}", path.display()))?;
+        let file: KeyFile = serde_json::from_str(&contents)
+            .with_context(|| format!("Malformed key file {}", path.display()))?;
+
+        let secret: [u8; 32] = hex::decode(file.secret_key.trim_start_matches("0x"))
+            .map_err(|e| anyhow!("Invalid secret key in {}: {}", path.display(), e))?
+            .try_into()
+            .map_err(|_| anyhow!("Secret key in {} is not 32 bytes", path.display()))?;
+        let key = Self { signing_key: SigningKey::from_bytes(&secret) };
+
+        // A hand-edited public key would mislead whoever copies it into genesis
+        if file.public_key.trim_start_matches("0x") != hex::encode(key.public_key()) {
+            return Err(anyhow!("Public key in {} doesn't match its secret key", path.display()));
+        }
+
+        Ok(key)
+    }
+
+    /// Write the key to `path`, readable only by the owner where supported
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let file = KeyFile {
+            secret_key: to_hex(&self.signing_key.to_bytes()),
+            public_key: to_hex(&self.public_key()),
+        };
+        let contents = serde_json::to_string_pretty(&file)?;
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, contents)
+            .with_context(|| format!("Failed to write key file {}", path.display()))?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+        }
+
+        Ok(())
+    }
+
+    /// Key for signing votes and gossip
+    pub fn signing_key(&self) -> SigningKey {
+        self.signing_key.clone()
+    }
+
+    /// Public key validators are listed under in genesis
+    pub fn public_key(&self) -> PublicKey {
+        self.signing_key.verifying_key().to_bytes()
+    }
+
+    /// The same key as a libp2p identity
+    pub fn identity(&self) -> identity::Keypair {
+        identity::Keypair::ed25519_from_bytes(self.signing_key.to_bytes())
+            .expect("a 32-byte Ed25519 secret is always valid")
+    }
+
+    /// Peer id derived from the key
+    pub fn peer_id(&self) -> PeerId {
+        PeerId::from(self.identity().public())
+    }
+}
+
+/// Run the `keygen` subcommand
+pub fn run(args: &KeygenArgs) -> Result<()> {
+    if args.output.exists() && !args.force {
+        return Err(anyhow!(
+            "{} already exists; pass --force to replace it",
+            args.output.display()
+        ));
+    }
+
+    let key = NodeKey::generate();
+    key.save(&args.output)?;
+
+    println!("Wrote node key to {}", args.output.display());
+    println!("Peer id:    {}", key.peer_id());
+    println!("Public key: {}", to_hex(&key.public_key()));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_saved_key_loads_back() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("node.key");
+
+        let key = NodeKey::generate();
+        key.save(&path).unwrap();
+        let loaded = NodeKey::load(&path).unwrap();
+
+        assert_eq!(loaded.public_key(), key.public_key());
+        assert_eq!(loaded.peer_id(), key.peer_id());
+
+        // keygen won't overwrite an existing key unless forced
+        let args = KeygenArgs { output: path.clone(), force: false };
+        assert!(run(&args).is_err());
+        assert_eq!(NodeKey::load(&path).unwrap().peer_id(), key.peer_id());
+        run(&KeygenArgs { force: true, ..args }).unwrap();
+        assert_ne!(NodeKey::load(&path).unwrap().peer_id(), key.peer_id());
+    }
+}
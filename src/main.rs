@@ -1,7 +1,7 @@
-use clap::Parser;
 use tracing::{info, error};
 
 mod cli;
+mod config;
 mod consensus;
 mod network;
 mod storage;
@@ -11,8 +11,20 @@ mod types;
 mod mempool;
 mod validation;
 mod node;
+mod webhook;
+mod sync;
+mod export;
+mod codec;
+mod genesis;
+mod keys;
+mod shutdown;
+mod bloom;
+mod replay;
+mod crypto;
+#[cfg(test)]
+mod test_fixtures;
 
-use cli::Cli;
+use cli::{Cli, Command};
 use node::BlockchainNode;
 
 #[tokio::main]
@@ -23,12 +35,23 @@ async fn main() -> anyhow::Result<()> {
         .init();
 
     // Parse command line arguments
-    let cli = Cli::parse();
+    let cli = Cli::load()?;
+
+    match &cli.command {
+        Some(Command::Export(args)) => return export::run(&cli.db_path, args),
+        Some(Command::Keygen(args)) => return keys::run(args),
+        Some(Command::Snapshot(args)) => return storage::snapshot::run(&cli.db_path, args),
+        Some(Command::ExportBlocks(args)) => return replay::run_export(&cli.db_path, args),
+        Some(Command::ReplayBlocks(args)) => return replay::run_replay(&cli, args),
+        None => {}
+    }
     
     info!("Starting blockchain node in {:?} mode", cli.mode);
     info!("Node ID: {}", cli.node_id);
     info!("Listen address: {}", cli.listen_addr);
 
+    let drain_on_shutdown = cli.drain_on_shutdown;
+
     // Create and start the blockchain node
     let mut node = BlockchainNode::new(cli).await?;
     
@@ -42,6 +65,9 @@ async fn main() -> anyhow::Result<()> {
     tokio::signal::ctrl_c().await?;
     info!("Shutting down blockchain node...");
     
+    if drain_on_shutdown {
+        node.drain().await?;
+    }
     node.shutdown().await?;
     info!("Node shutdown complete");
 
@@ -1,9 +1,24 @@
-use std::collections::{HashMap, BinaryHeap, HashSet};
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap, BinaryHeap, HashSet, VecDeque};
 use std::cmp::Ordering;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
+#[cfg(test)]
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use crate::bloom::RollingBloomFilter;
+use crate::crypto::{default_scheme, SignatureScheme};
+use crate::metrics::NodeMetrics;
 use crate::types::{Transaction, Address, Hash};
+use crate::validation::SenderAllowlist;
+use crate::storage::Storage;
+use crate::storage::mempool_store::MempoolStore;
+use crate::storage::state_store::StateStore;
+
+/// Stale heap entries tolerated beyond twice the live count before the heap is rebuilt
+const HEAP_COMPACTION_SLACK: usize = 1024;
 
 /// Transaction wrapper for priority queue ordering
 #[derive(Debug, Clone)]
@@ -11,6 +26,7 @@ struct PriorityTransaction {
     transaction: Transaction,
     priority_score: u64,
     insertion_time: u64,
+    size: usize,
 }
 
 impl PartialEq for PriorityTransaction {
@@ -37,6 +53,56 @@ impl Ord for PriorityTransaction {
     }
 }
 
+/// Order in which `get_next_batch` takes pending transactions
+///
+/// Every policy takes a sender's transactions in nonce order, starting at the
+/// nonce it needs next and stopping at a gap. `Priority` breaks ties by arrival
+/// and weighs sender reputation, both local to the node; `FeeThenNonce` and
+/// `NonceThenFee` depend only on the pending transactions, so leaders holding
+/// the same pool build the same block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OrderingPolicy {
+    /// Highest priority score first, earliest arrival on ties
+    #[default]
+    Priority,
+    /// Highest fee first; ties go to the lower nonce, then the lower sender address
+    FeeThenNonce,
+    /// Lowest nonce first; ties go to the higher fee, then the lower sender address
+    NonceThenFee,
+    /// Senders in order of their earliest pending arrival, each sender's run taken whole
+    FifoWithinAccount,
+}
+
+impl OrderingPolicy {
+    /// Order two candidates from different senders, best first
+    fn compare(self, a: &Transaction, b: &Transaction) -> Ordering {
+        let by_fee = b.fee.cmp(&a.fee);
+        let by_nonce = a.nonce.cmp(&b.nonce);
+        let ordering = match self {
+            OrderingPolicy::NonceThenFee => by_nonce.then(by_fee),
+            _ => by_fee.then(by_nonce),
+        };
+        ordering.then_with(|| a.from.cmp(&b.from))
+    }
+}
+
+impl std::str::FromStr for OrderingPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "priority" => Ok(OrderingPolicy::Priority),
+            "fee-then-nonce" => Ok(OrderingPolicy::FeeThenNonce),
+            "nonce-then-fee" => Ok(OrderingPolicy::NonceThenFee),
+            "fifo-within-account" => Ok(OrderingPolicy::FifoWithinAccount),
+            other => Err(anyhow!(
+                "unknown ordering {:?}; expected priority, fee-then-nonce, nonce-then-fee or fifo-within-account", other
+            )),
+        }
+    }
+}
+
 /// Transaction pool configuration
 #[derive(Debug, Clone)]
 pub struct MempoolConfig {
@@ -46,6 +112,62 @@ pub struct MempoolConfig {
     pub max_tx_size: usize,
     pub cleanup_interval_secs: u64,
     pub max_age_secs: u64,
+    pub sender_allowlist: SenderAllowlist,
+    pub reputation: Option<ReputationConfig>,
+    /// Minimum time between replacements of the same (sender, nonce)
+    pub replacement_cooldown_ms: u64,
+    /// Fee increase over the pending transaction required to replace it
+    pub replacement_fee_bump_percent: u64,
+    /// Mirror every admission and removal to storage, so a crash doesn't lose the pool
+    pub persist: bool,
+    /// Recently committed transactions remembered to turn away re-submissions
+    pub committed_filter_capacity: usize,
+    /// Chance a new transaction is mistaken for a committed one
+    pub committed_filter_fp_rate: f64,
+    /// How block batches are ordered
+    pub ordering: OrderingPolicy,
+    /// Keep each transaction's serialized size from admission instead of re-measuring it for every batch
+    pub cache_tx_sizes: bool,
+}
+
+/// Sender reputation weighting for transaction priority
+#[derive(Debug, Clone)]
+pub struct ReputationConfig {
+    /// Largest priority bump or penalty, as a percentage of the fee
+    pub max_adjustment_percent: u64,
+    /// Outcomes needed before a sender's history is fully weighted
+    pub min_history: u64,
+}
+
+impl Default for ReputationConfig {
+    fn default() -> Self {
+        Self {
+            max_adjustment_percent: 10,
+            min_history: 10,
+        }
+    }
+}
+
+/// Historical inclusion and rejection counts for a sender
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SenderReputation {
+    pub included: u64,
+    pub rejected: u64,
+}
+
+impl SenderReputation {
+    /// Priority adjustment in percent, bounded by the configured maximum
+    pub fn adjustment_percent(&self, config: &ReputationConfig) -> i64 {
+        let outcomes = self.included + self.rejected;
+        if outcomes == 0 {
+            return 0;
+        }
+
+        // Scale by confidence so a single outcome can't swing priority fully
+        let weight = outcomes.max(config.min_history) as i64;
+        let balance = self.included as i64 - self.rejected as i64;
+        balance * config.max_adjustment_percent as i64 / weight
+    }
 }
 
 impl Default for MempoolConfig {
@@ -57,6 +179,15 @@ impl Default for MempoolConfig {
             max_tx_size: 1024 * 1024, // 1MB
             cleanup_interval_secs: 60,
             max_age_secs: 3600, // 1 hour
+            sender_allowlist: SenderAllowlist::default(),
+            reputation: None,
+            replacement_cooldown_ms: 1000,
+            replacement_fee_bump_percent: 10,
+            persist: false,
+            committed_filter_capacity: 100_000,
+            committed_filter_fp_rate: 0.0001,
+            ordering: OrderingPolicy::Priority,
+            cache_tx_sizes: true,
         }
     }
 }
@@ -72,42 +203,122 @@ pub struct Mempool {
     by_sender: Arc<RwLock<HashMap<Address, Vec<Uuid>>>>,
     // Track transaction hashes to prevent duplicates
     tx_hashes: Arc<RwLock<HashSet<Hash>>>,
+    // Hashes of recently committed transactions, so gossip can't re-add them
+    committed: Arc<RwLock<RollingBloomFilter>>,
+    // Hashes a reorg rolled back, which the committed filter can't forget
+    reverted: Arc<RwLock<HashSet<Hash>>>,
+    // Serialized sizes measured once at admission
+    tx_sizes: Arc<RwLock<HashMap<Uuid, usize>>>,
+    // Number of serializations performed to measure transaction size
+    #[cfg(test)]
+    size_measurements: Arc<AtomicU64>,
+    // Inclusion and rejection history by sender
+    reputation: Arc<RwLock<HashMap<Address, SenderReputation>>>,
+    // Time of the last replacement for each (sender, nonce)
+    last_replacement: Arc<RwLock<HashMap<(Address, u64), Instant>>>,
+    // Held from the duplicate check to insertion so concurrent submissions of
+    // one nonce are resolved one at a time
+    admission: Arc<Mutex<()>>,
+    // Scheme transaction signatures are checked under
+    signature_scheme: Arc<dyn SignatureScheme>,
+    // Committed account nonces used to order batches
+    state: Option<StateStore>,
+    // On-disk copy of the pool, written through when `persist` is set
+    store: Option<MempoolStore>,
     // Statistics
     stats: Arc<RwLock<MempoolStats>>,
+    // Pool size and admitted fees, when metrics are enabled
+    metrics: Option<Arc<NodeMetrics>>,
     // Insertion counter for FIFO ordering
     insertion_counter: Arc<RwLock<u64>>,
 }
 
 /// Mempool statistics
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct MempoolStats {
     pub total_transactions: usize,
     pub total_added: u64,
     pub total_removed: u64,
     pub total_rejected: u64,
+    pub total_expired: u64,
     pub avg_fee: u64,
+    /// Pending transaction count by fee bucket; see `fee_bucket`
     pub pending_by_fee: HashMap<u64, usize>,
 }
 
+/// Histogram bucket of a fee: the largest power of two not above it, or 0 for no fee
+///
+/// Power-of-two buckets keep the histogram to at most 65 entries whatever the
+/// fee range, so estimates are cheap to compute.
+pub fn fee_bucket(fee: u64) -> u64 {
+    match fee {
+        0 => 0,
+        fee => 1 << (63 - fee.leading_zeros()),
+    }
+}
+
 impl Mempool {
     /// Create a new mempool
     pub fn new(config: MempoolConfig) -> Self {
+        let committed = RollingBloomFilter::new(config.committed_filter_capacity, config.committed_filter_fp_rate);
         Self {
             config,
             priority_queue: Arc::new(RwLock::new(BinaryHeap::new())),
             transactions: Arc::new(RwLock::new(HashMap::new())),
             by_sender: Arc::new(RwLock::new(HashMap::new())),
             tx_hashes: Arc::new(RwLock::new(HashSet::new())),
+            committed: Arc::new(RwLock::new(committed)),
+            reverted: Arc::new(RwLock::new(HashSet::new())),
+            tx_sizes: Arc::new(RwLock::new(HashMap::new())),
+            #[cfg(test)]
+            size_measurements: Arc::new(AtomicU64::new(0)),
+            reputation: Arc::new(RwLock::new(HashMap::new())),
+            last_replacement: Arc::new(RwLock::new(HashMap::new())),
+            admission: Arc::new(Mutex::new(())),
+            signature_scheme: default_scheme(),
+            state: None,
+            store: None,
             stats: Arc::new(RwLock::new(MempoolStats::default())),
+            metrics: None,
             insertion_counter: Arc::new(RwLock::new(0)),
         }
     }
 
+    /// Check transaction signatures under `scheme` instead of Ed25519
+    pub fn with_signature_scheme(mut self, scheme: Arc<dyn SignatureScheme>) -> Self {
+        self.signature_scheme = scheme;
+        self
+    }
+
+    /// Start each sender's batch at its committed nonce instead of its lowest pending one
+    pub fn with_state(mut self, state: StateStore) -> Self {
+        self.state = Some(state);
+        self
+    }
+
+    /// Write admissions and removals through to storage if `persist` is configured
+    pub fn with_storage(mut self, storage: &Storage) -> Self {
+        if self.config.persist {
+            self.store = Some(storage.mempool().clone());
+        }
+        self
+    }
+
+    /// Report the pool size and admitted fees
+    pub fn with_metrics(mut self, metrics: Arc<NodeMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
     /// Add a transaction to the mempool
     pub fn add_transaction(&self, tx: Transaction) -> Result<bool> {
+        // Measure once; batch assembly reuses the cached size if caching is on
+        let tx_size = self.measure_size(&tx);
+
         // Basic validation
-        if !self.validate_transaction(&tx)? {
+        if !self.validate_transaction(&tx, tx_size)? {
             self.increment_rejected();
+            self.record_rejection(&tx.from);
             return Ok(false);
         }
 
@@ -115,6 +326,8 @@ impl Mempool {
         let tx_id = tx.id;
         let sender = tx.from;
 
+        let _admission = self.admission.lock().unwrap();
+
         // Check for duplicates
         {
             let hashes = self.tx_hashes.read().unwrap();
@@ -123,6 +336,23 @@ impl Mempool {
             }
         }
 
+        // Already confirmed in a block
+        if self.committed.read().unwrap().contains(&tx_hash) && !self.reverted.read().unwrap().contains(&tx_hash) {
+            self.increment_rejected();
+            return Ok(false);
+        }
+
+        // Only one transaction per sender and nonce is pending: a high enough fee
+        // evicts the pending one, otherwise the earlier submission stays
+        if let Some(existing) = self.get_by_nonce(&sender, tx.nonce) {
+            if !self.can_replace(&existing, &tx) {
+                self.increment_rejected();
+                return Ok(false);
+            }
+            self.remove_transaction(&existing.id)?;
+            self.record_replacement(&tx);
+        }
+
         // Check mempool capacity
         if self.is_full() && !self.should_replace(&tx)? {
             self.increment_rejected();
@@ -144,16 +374,22 @@ impl Mempool {
             let mut priority_queue = self.priority_queue.write().unwrap();
             let mut by_sender = self.by_sender.write().unwrap();
             let mut tx_hashes = self.tx_hashes.write().unwrap();
+            let mut tx_sizes = self.tx_sizes.write().unwrap();
 
             // Add to main storage
             transactions.insert(tx_id, tx.clone());
             tx_hashes.insert(tx_hash);
+            if self.config.cache_tx_sizes {
+                tx_sizes.insert(tx_id, tx_size);
+            }
 
             // Add to priority queue
+            Self::compact_priority_queue(&transactions, &mut priority_queue);
             priority_queue.push(PriorityTransaction {
                 transaction: tx.clone(),
                 priority_score,
                 insertion_time,
+                size: tx_size,
             });
 
             // Track by sender
@@ -163,6 +399,16 @@ impl Mempool {
             let mut stats = self.stats.write().unwrap();
             stats.total_transactions = transactions.len();
             stats.total_added += 1;
+            *stats.pending_by_fee.entry(fee_bucket(tx.fee)).or_insert(0) += 1;
+
+            if let Some(metrics) = &self.metrics {
+                metrics.transactions_in_mempool.set(transactions.len() as i64);
+                metrics.transaction_fees.observe(tx.fee as f64);
+            }
+        }
+
+        if let Some(store) = &self.store {
+            store.put_transaction(&tx)?;
         }
 
         // Clean up if necessary
@@ -175,13 +421,26 @@ impl Mempool {
 
     /// Remove a transaction from the mempool
     pub fn remove_transaction(&self, tx_id: &Uuid) -> Result<Option<Transaction>> {
+        let removed = self.remove_from_pool(tx_id);
+
+        if let (Some(store), Some(_)) = (&self.store, &removed) {
+            store.delete_transaction(tx_id)?;
+        }
+
+        Ok(removed)
+    }
+
+    /// Drop a transaction from the in-memory structures
+    fn remove_from_pool(&self, tx_id: &Uuid) -> Option<Transaction> {
         let mut transactions = self.transactions.write().unwrap();
         let mut by_sender = self.by_sender.write().unwrap();
         let mut tx_hashes = self.tx_hashes.write().unwrap();
+        let mut tx_sizes = self.tx_sizes.write().unwrap();
 
         if let Some(tx) = transactions.remove(tx_id) {
             // Remove from hash set
             tx_hashes.remove(&tx.hash());
+            tx_sizes.remove(tx_id);
 
             // Remove from sender tracking
             if let Some(sender_txs) = by_sender.get_mut(&tx.from) {
@@ -195,53 +454,128 @@ impl Mempool {
             let mut stats = self.stats.write().unwrap();
             stats.total_transactions = transactions.len();
             stats.total_removed += 1;
+            if let Some(metrics) = &self.metrics {
+                metrics.transactions_in_mempool.set(transactions.len() as i64);
+            }
+            if let Entry::Occupied(mut bucket) = stats.pending_by_fee.entry(fee_bucket(tx.fee)) {
+                *bucket.get_mut() -= 1;
+                if *bucket.get() == 0 {
+                    bucket.remove();
+                }
+            }
 
             // Note: We don't remove from priority queue immediately for performance
-            // The queue will be cleaned up during next iteration
+            // The stale entry is skipped by batch assembly and dropped on compaction
 
-            Ok(Some(tx))
+            Some(tx)
         } else {
-            Ok(None)
+            None
+        }
+    }
+
+    /// Remember a transaction included in a committed block so it can't be re-added
+    pub fn mark_committed(&self, tx: &Transaction) {
+        let tx_hash = tx.hash();
+        self.reverted.write().unwrap().remove(&tx_hash);
+        self.committed.write().unwrap().insert(&tx_hash);
+    }
+
+    /// Put back transactions a reorg rolled back, returning how many were admitted again
+    ///
+    /// They are no longer committed, so neither these nor later copies from gossip are
+    /// turned away as committed; other admission checks still apply.
+    pub fn readmit(&self, transactions: Vec<Transaction>) -> Result<usize> {
+        let mut admitted = 0;
+        for tx in transactions {
+            self.reverted.write().unwrap().insert(tx.hash());
+            if self.add_transaction(tx)? {
+                admitted += 1;
+            }
+        }
+        Ok(admitted)
+    }
+
+    /// Remove a transaction that was included in a committed block
+    pub fn remove_committed(&self, tx_id: &Uuid) -> Result<Option<Transaction>> {
+        let removed = self.remove_transaction(tx_id)?;
+        if let Some(tx) = &removed {
+            self.record_inclusion(&tx.from);
         }
+        Ok(removed)
+    }
+
+    /// Get the recorded reputation of a sender
+    pub fn get_sender_reputation(&self, sender: &Address) -> SenderReputation {
+        let reputation = self.reputation.read().unwrap();
+        reputation.get(sender).copied().unwrap_or_default()
     }
 
     /// Get next batch of transactions for block creation
     pub fn get_next_batch(&self, max_count: usize, max_size: usize) -> Result<Vec<Transaction>> {
+        match self.config.ordering {
+            OrderingPolicy::Priority => self.next_batch_by_priority(max_count, max_size),
+            policy => self.next_batch_by_policy(policy, max_count, max_size),
+        }
+    }
+
+    /// Take transactions off the priority queue, holding back any with an earlier nonce pending
+    fn next_batch_by_priority(&self, max_count: usize, max_size: usize) -> Result<Vec<Transaction>> {
         let mut batch = Vec::new();
         let mut total_size = 0;
-        let mut processed_senders = HashSet::new();
 
         let transactions = self.transactions.read().unwrap();
         let mut priority_queue = self.priority_queue.write().unwrap();
         let mut temp_queue = BinaryHeap::new();
 
+        // Next nonce each sender needs, and transactions waiting on an earlier nonce
+        let mut next_nonces = self.batch_start_nonces(&transactions)?;
+        let mut waiting: HashMap<Address, BTreeMap<u64, PriorityTransaction>> = HashMap::new();
+
         // Extract transactions from priority queue
-        while let Some(priority_tx) = priority_queue.pop() {
+        'select: while let Some(priority_tx) = priority_queue.pop() {
             // Check if transaction still exists (might have been removed)
             if !transactions.contains_key(&priority_tx.transaction.id) {
                 continue; // Skip removed transactions
             }
 
-            let tx = &priority_tx.transaction;
-            let tx_size = bincode::serialize(tx).unwrap_or_default().len();
-
-            // Check batch limits
-            if batch.len() >= max_count || total_size + tx_size > max_size {
-                temp_queue.push(priority_tx);
-                break;
-            }
+            let sender = priority_tx.transaction.from;
+            let next_nonce = next_nonces.get(&sender).copied().unwrap_or(0);
 
-            // Check nonce ordering for sender
-            if self.is_valid_nonce_order(&tx, &processed_senders)? {
-                batch.push(tx.clone());
-                total_size += tx_size;
-                processed_senders.insert(tx.from);
+            match priority_tx.transaction.nonce.cmp(&next_nonce) {
+                // Already consumed; left for cleanup
+                Ordering::Less => temp_queue.push(priority_tx),
+                // Held back until the nonces before it are selected
+                Ordering::Greater => {
+                    waiting.entry(sender).or_default().insert(priority_tx.transaction.nonce, priority_tx);
+                }
+                Ordering::Equal => {
+                    // Take this transaction and any queued successors in nonce order
+                    let mut next = Some(priority_tx);
+                    while let Some(priority_tx) = next.take() {
+                        // Check batch limits
+                        let size = self.batch_size(&priority_tx.transaction, Some(priority_tx.size));
+                        if batch.len() >= max_count || total_size + size > max_size {
+                            temp_queue.push(priority_tx);
+                            break 'select;
+                        }
+
+                        batch.push(priority_tx.transaction.clone());
+                        total_size += size;
+
+                        let nonce = priority_tx.transaction.nonce + 1;
+                        next_nonces.insert(sender, nonce);
+                        temp_queue.push(priority_tx);
+
+                        next = waiting.get_mut(&sender).and_then(|queued| queued.remove(&nonce));
+                    }
+                }
             }
-
-            temp_queue.push(priority_tx);
         }
 
         // Restore remaining transactions to priority queue
+        for priority_tx in waiting.into_values().flat_map(BTreeMap::into_values) {
+            temp_queue.push(priority_tx);
+        }
         while let Some(priority_tx) = temp_queue.pop() {
             priority_queue.push(priority_tx);
         }
@@ -249,6 +583,71 @@ impl Mempool {
         Ok(batch)
     }
 
+    /// Build a batch from each sender's run of consecutive nonces in `policy` order
+    fn next_batch_by_policy(&self, policy: OrderingPolicy, max_count: usize, max_size: usize) -> Result<Vec<Transaction>> {
+        let transactions = self.transactions.read().unwrap();
+        let next_nonces = self.batch_start_nonces(&transactions)?;
+
+        let mut pending: HashMap<Address, BTreeMap<u64, &Transaction>> = HashMap::new();
+        for tx in transactions.values() {
+            pending.entry(tx.from).or_default().insert(tx.nonce, tx);
+        }
+        let mut runs: Vec<VecDeque<&Transaction>> = pending.into_iter()
+            .map(|(sender, by_nonce)| {
+                let mut nonce = next_nonces.get(&sender).copied().unwrap_or(0);
+                let mut run = VecDeque::new();
+                while let Some(tx) = by_nonce.get(&nonce) {
+                    run.push_back(*tx);
+                    nonce += 1;
+                }
+                run
+            })
+            .filter(|run| !run.is_empty())
+            .collect();
+
+        let ordered: Vec<&Transaction> = match policy {
+            OrderingPolicy::FifoWithinAccount => {
+                let arrivals: HashMap<Uuid, u64> = self.priority_queue.read().unwrap()
+                    .iter()
+                    .map(|priority_tx| (priority_tx.transaction.id, priority_tx.insertion_time))
+                    .collect();
+                let first_arrival = |run: &VecDeque<&Transaction>| {
+                    run.iter().filter_map(|tx| arrivals.get(&tx.id)).min().copied().unwrap_or(u64::MAX)
+                };
+                runs.sort_by_cached_key(|run| (first_arrival(run), run[0].from));
+                runs.into_iter().flatten().collect()
+            }
+            _ => {
+                // Repeatedly take the best transaction a sender can include next
+                let mut ordered = Vec::new();
+                while ordered.len() < max_count {
+                    let Some(best) = (0..runs.len()).min_by(|&a, &b| policy.compare(runs[a][0], runs[b][0])) else {
+                        break;
+                    };
+                    ordered.push(runs[best].pop_front().unwrap());
+                    if runs[best].is_empty() {
+                        runs.swap_remove(best);
+                    }
+                }
+                ordered
+            }
+        };
+
+        let tx_sizes = self.tx_sizes.read().unwrap();
+        let mut batch = Vec::new();
+        let mut total_size = 0;
+        for tx in ordered {
+            let size = self.batch_size(tx, tx_sizes.get(&tx.id).copied());
+            if batch.len() >= max_count || total_size + size > max_size {
+                break;
+            }
+            batch.push(tx.clone());
+            total_size += size;
+        }
+
+        Ok(batch)
+    }
+
     /// Get transaction by ID
     pub fn get_transaction(&self, tx_id: &Uuid) -> Option<Transaction> {
         let transactions = self.transactions.read().unwrap();
@@ -269,6 +668,26 @@ impl Mempool {
         }
     }
 
+    /// Get the pending transaction of a sender at a nonce
+    ///
+    /// Same-nonce submissions are resolved on admission, so this is the one that won.
+    pub fn get_by_nonce(&self, sender: &Address, nonce: u64) -> Option<Transaction> {
+        let transactions = self.transactions.read().unwrap();
+        let by_sender = self.by_sender.read().unwrap();
+
+        by_sender.get(sender)?
+            .iter()
+            .filter_map(|id| transactions.get(id))
+            .find(|pending| pending.nonce == nonce)
+            .cloned()
+    }
+
+    /// Get the cached serialized size of a pending transaction
+    pub fn get_transaction_size(&self, tx_id: &Uuid) -> Option<usize> {
+        let tx_sizes = self.tx_sizes.read().unwrap();
+        tx_sizes.get(tx_id).copied()
+    }
+
     /// Check if mempool contains transaction
     pub fn contains(&self, tx_id: &Uuid) -> bool {
         let transactions = self.transactions.read().unwrap();
@@ -286,39 +705,208 @@ impl Mempool {
         self.size() >= self.config.max_size
     }
 
+    /// Lowest fee admitted to the pool
+    pub fn min_fee(&self) -> u64 {
+        self.config.min_fee
+    }
+
+    /// How often `prune_expired` should run
+    pub fn cleanup_interval(&self) -> Duration {
+        Duration::from_secs(self.config.cleanup_interval_secs)
+    }
+
+    /// Evict transactions whose timestamp is more than `max_age_secs` old
+    ///
+    /// Returns the number of transactions evicted.
+    pub fn prune_expired(&self) -> Result<usize> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+        let max_age_ms = self.config.max_age_secs.saturating_mul(1000);
+
+        let expired: Vec<Uuid> = {
+            let transactions = self.transactions.read().unwrap();
+            transactions
+                .values()
+                .filter(|tx| now.saturating_sub(tx.timestamp) > max_age_ms)
+                .map(|tx| tx.id)
+                .collect()
+        };
+
+        let mut pruned = 0;
+        for tx_id in expired {
+            if self.remove_transaction(&tx_id)?.is_some() {
+                pruned += 1;
+            }
+        }
+
+        if pruned > 0 {
+            let mut stats = self.stats.write().unwrap();
+            stats.total_expired += pruned as u64;
+        }
+
+        Ok(pruned)
+    }
+
     /// Get mempool statistics
     pub fn get_stats(&self) -> MempoolStats {
         let stats = self.stats.read().unwrap();
         stats.clone()
     }
 
+    /// Suggest a fee for inclusion within `target_blocks` blocks of `block_capacity` transactions
+    ///
+    /// Walks the fee histogram from the top: a transaction is expected in time if
+    /// fewer pending transactions than fit in the target blocks outbid it. The
+    /// estimate is the lower edge of the cheapest bucket that clears that bar, and
+    /// never below the minimum fee.
+    pub fn estimate_fee(&self, target_blocks: u64, block_capacity: usize) -> u64 {
+        let space = target_blocks.saturating_mul(block_capacity as u64);
+        let stats = self.stats.read().unwrap();
+
+        let mut buckets: Vec<(u64, usize)> = stats.pending_by_fee.iter().map(|(fee, count)| (*fee, *count)).collect();
+        buckets.sort_unstable_by(|a, b| b.0.cmp(&a.0));
+
+        let mut ahead = 0u64;
+        for (bucket, count) in buckets {
+            ahead += count as u64;
+            if ahead >= space {
+                // Outbid this whole bucket by landing in the next one up
+                return bucket.saturating_mul(2).max(1).max(self.config.min_fee);
+            }
+        }
+
+        self.config.min_fee
+    }
+
+    /// Get all pending transactions
+    pub fn get_all_transactions(&self) -> Vec<Transaction> {
+        let transactions = self.transactions.read().unwrap();
+        transactions.values().cloned().collect()
+    }
+
+    /// Persist pending transactions so they survive a restart
+    pub fn persist(&self, storage: &Storage) -> Result<usize> {
+        let pending = self.get_all_transactions();
+        storage.mempool().replace_all(&pending)?;
+        Ok(pending.len())
+    }
+
+    /// Re-admit transactions persisted by a previous run
+    ///
+    /// Transactions that expired or were committed in the meantime are dropped.
+    pub fn restore(&self, storage: &Storage) -> Result<usize> {
+        let mut pending = storage.mempool().get_transactions()?;
+        // Re-admitting writes survivors back through when persistence is on
+        storage.mempool().replace_all(&[])?;
+
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+        let max_age_ms = self.config.max_age_secs.saturating_mul(1000);
+
+        // Each sender's nonces in order, so none waits behind a later one
+        pending.sort_by_key(|tx| (tx.from, tx.nonce));
+
+        let mut restored = 0;
+        for tx in pending {
+            if now.saturating_sub(tx.timestamp) > max_age_ms
+                || storage.transactions().has_transaction(&tx.id)?
+            {
+                continue;
+            }
+            if self.add_transaction(tx)? {
+                restored += 1;
+            }
+        }
+
+        Ok(restored)
+    }
+
     /// Clear all transactions
     pub fn clear(&self) -> Result<()> {
         let mut transactions = self.transactions.write().unwrap();
         let mut priority_queue = self.priority_queue.write().unwrap();
         let mut by_sender = self.by_sender.write().unwrap();
         let mut tx_hashes = self.tx_hashes.write().unwrap();
+        let mut tx_sizes = self.tx_sizes.write().unwrap();
 
         transactions.clear();
         priority_queue.clear();
         by_sender.clear();
         tx_hashes.clear();
+        tx_sizes.clear();
+        self.last_replacement.write().unwrap().clear();
+
+        if let Some(store) = &self.store {
+            store.replace_all(&[])?;
+        }
 
         let mut stats = self.stats.write().unwrap();
         stats.total_transactions = 0;
+        stats.pending_by_fee.clear();
+        if let Some(metrics) = &self.metrics {
+            metrics.transactions_in_mempool.set(0);
+        }
 
         Ok(())
     }
 
+    /// Rebuild the heap from live transactions once stale entries dominate it
+    ///
+    /// Removals leave their heap entries behind, so without this the heap grows
+    /// with every transaction ever admitted. Rebuilding only when the heap is more
+    /// than twice the live size keeps the cost amortized constant per insertion.
+    fn compact_priority_queue(
+        transactions: &HashMap<Uuid, Transaction>,
+        priority_queue: &mut BinaryHeap<PriorityTransaction>,
+    ) {
+        if priority_queue.len() <= transactions.len() * 2 + HEAP_COMPACTION_SLACK {
+            return;
+        }
+
+        // A transaction removed and re-admitted has two entries; keep one
+        let mut seen = HashSet::with_capacity(transactions.len());
+        let entries = std::mem::take(priority_queue).into_vec();
+        *priority_queue = entries
+            .into_iter()
+            .filter(|entry| {
+                transactions.contains_key(&entry.transaction.id) && seen.insert(entry.transaction.id)
+            })
+            .collect();
+    }
+
+    /// Measure the serialized size of a transaction
+    fn measure_size(&self, tx: &Transaction) -> usize {
+        #[cfg(test)]
+        self.size_measurements.fetch_add(1, AtomicOrdering::Relaxed);
+        bincode::serialized_size(tx).unwrap_or(0) as usize
+    }
+
+    /// Size a pending transaction counts against a batch's limit: the size cached at
+    /// admission when caching is on, otherwise measured again
+    fn batch_size(&self, tx: &Transaction, cached: Option<usize>) -> usize {
+        match cached {
+            Some(size) if self.config.cache_tx_sizes => size,
+            _ => self.measure_size(tx),
+        }
+    }
+
     /// Validate transaction before adding to mempool
-    fn validate_transaction(&self, tx: &Transaction) -> Result<bool> {
+    fn validate_transaction(&self, tx: &Transaction, tx_size: usize) -> Result<bool> {
         // Check minimum fee
         if tx.fee < self.config.min_fee {
             return Ok(false);
         }
 
+        // Permissioned chains only admit allowlisted senders
+        if self.config.sender_allowlist.check(tx).is_err() {
+            return Ok(false);
+        }
+
         // Check transaction size
-        let tx_size = bincode::serialize(tx).unwrap_or_default().len();
         if tx_size > self.config.max_tx_size {
             return Ok(false);
         }
@@ -332,7 +920,7 @@ impl Mempool {
         }
 
         // Basic transaction validation
-        if !tx.verify() {
+        if !tx.verify_with(self.signature_scheme.as_ref()) {
             return Ok(false);
         }
 
@@ -341,8 +929,65 @@ impl Mempool {
 
     /// Calculate transaction priority score
     fn calculate_priority(&self, tx: &Transaction) -> u64 {
-        // Simple fee-based priority (can be enhanced with other factors)
-        tx.fee
+        let config = match &self.config.reputation {
+            Some(config) => config,
+            None => return tx.fee,
+        };
+
+        // Scores are in hundredths of the fee so small fees still get a bounded bump
+        let adjustment = self.get_sender_reputation(&tx.from).adjustment_percent(config);
+        let multiplier = (100 + adjustment).max(0) as u64;
+        tx.fee.saturating_mul(multiplier)
+    }
+
+    /// Record that a sender's transaction made it into a block
+    fn record_inclusion(&self, sender: &Address) {
+        if self.config.reputation.is_some() {
+            let mut reputation = self.reputation.write().unwrap();
+            reputation.entry(*sender).or_default().included += 1;
+        }
+    }
+
+    /// Record that a sender's transaction was rejected or evicted
+    fn record_rejection(&self, sender: &Address) {
+        if self.config.reputation.is_some() {
+            let mut reputation = self.reputation.write().unwrap();
+            reputation.entry(*sender).or_default().rejected += 1;
+        }
+    }
+
+    /// Check whether a replacement pays enough more and is outside the cooldown
+    fn can_replace(&self, existing: &Transaction, tx: &Transaction) -> bool {
+        if tx.fee < self.min_replacement_fee(existing) {
+            return false;
+        }
+
+        // Rate-limit fee bidding so a sender can't flood propagation with replacements
+        let cooldown = Duration::from_millis(self.config.replacement_cooldown_ms);
+        let last_replacement = self.last_replacement.read().unwrap();
+        match last_replacement.get(&(tx.from, tx.nonce)) {
+            Some(replaced_at) => replaced_at.elapsed() >= cooldown,
+            None => true,
+        }
+    }
+
+    /// Lowest fee that may replace a pending transaction
+    ///
+    /// The bump is rounded up, and a replacement must always pay strictly more.
+    fn min_replacement_fee(&self, existing: &Transaction) -> u64 {
+        let bump_percent = self.config.replacement_fee_bump_percent as u128;
+        let bump = (existing.fee as u128 * bump_percent).div_ceil(100).max(1);
+        (existing.fee as u128 + bump).min(u64::MAX as u128) as u64
+    }
+
+    /// Start the cooldown for a (sender, nonce) after a replacement
+    fn record_replacement(&self, tx: &Transaction) {
+        let cooldown = Duration::from_millis(self.config.replacement_cooldown_ms);
+        let mut last_replacement = self.last_replacement.write().unwrap();
+
+        // Expired entries no longer limit anything
+        last_replacement.retain(|_, replaced_at| replaced_at.elapsed() < cooldown);
+        last_replacement.insert((tx.from, tx.nonce), Instant::now());
     }
 
     /// Check if transaction should replace existing ones
@@ -362,37 +1007,49 @@ impl Mempool {
         }
 
         let to_remove = current_size - target_size;
-        let mut removed_count = 0;
 
-        // Remove lowest priority transactions
-        let mut priority_queue = self.priority_queue.write().unwrap();
-        let mut temp_queue = BinaryHeap::new();
+        // The heap pops the highest priority first; sorted ascending, the lowest come first
+        let evicted: Vec<Transaction> = {
+            let transactions = self.transactions.read().unwrap();
+            let mut priority_queue = self.priority_queue.write().unwrap();
+            let mut entries = std::mem::take(&mut *priority_queue).into_sorted_vec().into_iter();
+
+            // Stale entries for transactions already gone are dropped along the way
+            let evicted = entries.by_ref()
+                .filter(|priority_tx| transactions.contains_key(&priority_tx.transaction.id))
+                .take(to_remove)
+                .map(|priority_tx| priority_tx.transaction)
+                .collect();
+            priority_queue.extend(entries);
+            evicted
+        };
 
-        while let Some(priority_tx) = priority_queue.pop() {
-            if removed_count < to_remove {
-                // Remove this transaction
-                self.remove_transaction(&priority_tx.transaction.id)?;
-                removed_count += 1;
-            } else {
-                temp_queue.push(priority_tx);
+        for tx in evicted {
+            if self.remove_transaction(&tx.id)?.is_some() {
+                self.record_rejection(&tx.from);
             }
         }
 
-        // Restore remaining transactions
-        while let Some(priority_tx) = temp_queue.pop() {
-            priority_queue.push(priority_tx);
-        }
-
         Ok(())
     }
 
-    /// Check if transaction nonce is valid for batch ordering
-    fn is_valid_nonce_order(&self, tx: &Transaction, processed_senders: &HashSet<Address>) -> Result<bool> {
-        // Simplified nonce validation - in production, this would be more sophisticated
-        if processed_senders.contains(&tx.from) {
-            return Ok(false); // Only one tx per sender per batch for simplicity
+    /// Nonce each pending sender's batch must start from
+    fn batch_start_nonces(&self, transactions: &HashMap<Uuid, Transaction>) -> Result<HashMap<Address, u64>> {
+        let mut next_nonces = HashMap::new();
+
+        for tx in transactions.values() {
+            if let Some(state) = &self.state {
+                if let Entry::Vacant(entry) = next_nonces.entry(tx.from) {
+                    entry.insert(state.get_nonce(&tx.from)? + 1);
+                }
+            } else {
+                // Without committed state, the lowest pending nonce is assumed to be next
+                let next = next_nonces.entry(tx.from).or_insert(tx.nonce);
+                *next = (*next).min(tx.nonce);
+            }
         }
-        Ok(true)
+
+        Ok(next_nonces)
     }
 
     /// Increment rejected transaction counter
@@ -406,29 +1063,46 @@ impl Mempool {
 mod tests {
     use super::*;
     use uuid::Uuid;
+    use ed25519_dalek::SigningKey;
+    use crate::types::address_from_public_key;
+
+    fn test_key(sender: u8) -> SigningKey {
+        SigningKey::from_bytes(&[sender; 32])
+    }
+
+    fn test_address(sender: u8) -> Address {
+        address_from_public_key(&test_key(sender).verifying_key().to_bytes())
+    }
+
+    fn create_test_transaction(sender: u8, fee: u64) -> Transaction {
+        create_transaction_with_nonce(sender, 1, fee)
+    }
 
-    fn create_test_transaction(from: Address, fee: u64) -> Transaction {
-        Transaction {
+    fn create_transaction_with_nonce(sender: u8, nonce: u64, fee: u64) -> Transaction {
+        let mut tx = Transaction {
             id: Uuid::new_v4(),
-            from,
+            from: test_address(sender),
             to: [2u8; 20],
             amount: 1000,
             fee,
-            nonce: 1,
+            nonce,
             timestamp: std::time::SystemTime::now()
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap()
                 .as_millis() as u64,
             signature: [0u8; 64],
+            public_key: [0u8; 32],
             data: vec![],
-        }
+        };
+        tx.sign(&test_key(sender));
+        tx
     }
 
     #[test]
     fn test_mempool_basic_operations() {
         let mempool = Mempool::new(MempoolConfig::default());
         
-        let tx = create_test_transaction([1u8; 20], 10);
+        let tx = create_test_transaction(1, 10);
         let tx_id = tx.id;
         
         // Add transaction
@@ -447,14 +1121,112 @@ mod tests {
         assert_eq!(mempool.size(), 0);
     }
 
+    #[test]
+    fn test_committed_transaction_is_rejected() {
+        let mempool = Mempool::new(MempoolConfig::default());
+        let tx = create_test_transaction(1, 10);
+        assert!(mempool.add_transaction(tx.clone()).unwrap());
+
+        mempool.mark_committed(&tx);
+        mempool.remove_committed(&tx.id).unwrap();
+
+        // Gossip delivering the transaction again doesn't put it back in the pool
+        assert!(!mempool.add_transaction(tx.clone()).unwrap());
+        assert!(!mempool.contains(&tx.id));
+        assert_eq!(mempool.get_stats().total_rejected, 1);
+
+        // Transactions committed from another proposer's block are recognized too
+        let other = create_test_transaction(2, 10);
+        mempool.mark_committed(&other);
+        assert!(!mempool.add_transaction(other).unwrap());
+    }
+
+    #[test]
+    fn test_rolled_back_transaction_is_readmitted() {
+        let mempool = Mempool::new(MempoolConfig::default());
+        let tx = create_test_transaction(1, 10);
+        assert!(mempool.add_transaction(tx.clone()).unwrap());
+        mempool.mark_committed(&tx);
+        mempool.remove_committed(&tx.id).unwrap();
+
+        // A reorg drops the block that committed it
+        assert_eq!(mempool.readmit(vec![tx.clone()]).unwrap(), 1);
+        assert!(mempool.contains(&tx.id));
+
+        // Committed again, it is turned away once more
+        mempool.mark_committed(&tx);
+        mempool.remove_committed(&tx.id).unwrap();
+        assert!(!mempool.add_transaction(tx.clone()).unwrap());
+    }
+
+    #[test]
+    fn test_fee_estimate_rises_for_sooner_inclusion() {
+        let mempool = Mempool::new(MempoolConfig::default());
+        let add = |sender: u8, nonces: std::ops::RangeInclusive<u64>, fee: u64| {
+            for nonce in nonces {
+                assert!(mempool.add_transaction(create_transaction_with_nonce(sender, nonce, fee)).unwrap());
+            }
+        };
+
+        // A few high bidders over a long tail of cheap transactions
+        add(1, 1..=10, 1000);
+        add(2, 1..=50, 100);
+        add(3, 1..=100, 10);
+        add(4, 1..=100, 10);
+        assert_eq!(mempool.get_stats().pending_by_fee, HashMap::from([(512, 10), (64, 50), (8, 200)]));
+
+        // Blocks of 20 transactions
+        let estimates: Vec<u64> = [1, 2, 5, 20].iter().map(|&target| mempool.estimate_fee(target, 20)).collect();
+        assert_eq!(estimates, vec![128, 128, 16, 1]);
+
+        // The histogram follows removals
+        let top = mempool.get_transactions_by_sender(&test_address(1))[0].id;
+        mempool.remove_transaction(&top).unwrap();
+        assert_eq!(mempool.get_stats().pending_by_fee[&512], 9);
+        mempool.clear().unwrap();
+        assert!(mempool.get_stats().pending_by_fee.is_empty());
+        assert_eq!(mempool.estimate_fee(1, 20), mempool.min_fee());
+    }
+
+    #[test]
+    fn test_prune_expired_transactions() {
+        let mempool = Mempool::new(MempoolConfig::default());
+
+        // Two hours old, past the one hour default
+        let mut stale = create_transaction_with_nonce(1, 1, 10);
+        stale.timestamp -= 2 * 3600 * 1000;
+        stale.sign(&test_key(1));
+        let fresh = create_test_transaction(2, 10);
+
+        assert!(mempool.add_transaction(stale.clone()).unwrap());
+        assert!(mempool.add_transaction(fresh.clone()).unwrap());
+
+        assert_eq!(mempool.prune_expired().unwrap(), 1);
+        assert!(!mempool.contains(&stale.id));
+        assert!(mempool.contains(&fresh.id));
+        assert!(mempool.get_transactions_by_sender(&test_address(1)).is_empty());
+        assert!(!mempool.by_sender.read().unwrap().contains_key(&test_address(1)));
+        assert!(!mempool.tx_hashes.read().unwrap().contains(&stale.hash()));
+
+        let stats = mempool.get_stats();
+        assert_eq!(stats.total_transactions, 1);
+        assert_eq!(stats.total_expired, 1);
+        assert_eq!(stats.total_removed, 1);
+
+        // Nothing else is old enough
+        assert_eq!(mempool.prune_expired().unwrap(), 0);
+        // The evicted transaction may be resubmitted
+        assert!(mempool.add_transaction(stale).unwrap());
+    }
+
     #[test]
     fn test_mempool_priority_ordering() {
         let mempool = Mempool::new(MempoolConfig::default());
         
         // Add transactions with different fees
-        let tx1 = create_test_transaction([1u8; 20], 5);
-        let tx2 = create_test_transaction([2u8; 20], 15);
-        let tx3 = create_test_transaction([3u8; 20], 10);
+        let tx1 = create_test_transaction(1, 5);
+        let tx2 = create_test_transaction(2, 15);
+        let tx3 = create_test_transaction(3, 10);
         
         mempool.add_transaction(tx1).unwrap();
         mempool.add_transaction(tx2.clone()).unwrap();
@@ -465,4 +1237,401 @@ mod tests {
         assert_eq!(batch.len(), 3);
         assert_eq!(batch[0].id, tx2.id); // Highest fee should be first
     }
+
+    #[test]
+    fn test_mempool_sender_allowlist() {
+        let config = MempoolConfig {
+            sender_allowlist: SenderAllowlist::new(vec![test_address(1)]),
+            ..Default::default()
+        };
+        let mempool = Mempool::new(config);
+
+        assert!(mempool.add_transaction(create_test_transaction(1, 10)).unwrap());
+        assert!(!mempool.add_transaction(create_test_transaction(2, 10)).unwrap());
+        assert_eq!(mempool.size(), 1);
+        assert_eq!(mempool.get_stats().total_rejected, 1);
+    }
+
+    #[test]
+    fn test_batch_assembly_reuses_cached_size() {
+        let mempool = Mempool::new(MempoolConfig::default());
+
+        for i in 0..10u8 {
+            let tx = create_test_transaction(i + 1, 10 + i as u64);
+            let tx_id = tx.id;
+            let expected = bincode::serialize(&tx).unwrap().len();
+            assert!(mempool.add_transaction(tx).unwrap());
+            assert_eq!(mempool.get_transaction_size(&tx_id), Some(expected));
+        }
+
+        // One measurement per admitted transaction
+        assert_eq!(mempool.size_measurements.load(AtomicOrdering::Relaxed), 10);
+
+        // Repeated batch assembly never re-serializes
+        for _ in 0..5 {
+            let batch = mempool.get_next_batch(10, 1024 * 1024).unwrap();
+            assert_eq!(batch.len(), 10);
+        }
+        assert_eq!(mempool.size_measurements.load(AtomicOrdering::Relaxed), 10);
+    }
+
+    #[test]
+    fn test_batch_assembly_measures_again_without_size_cache() {
+        let config = MempoolConfig {
+            cache_tx_sizes: false,
+            ordering: OrderingPolicy::FeeThenNonce,
+            ..MempoolConfig::default()
+        };
+        let mempool = Mempool::new(config.clone());
+        let priority = Mempool::new(MempoolConfig { ordering: OrderingPolicy::Priority, ..config });
+
+        for pool in [&mempool, &priority] {
+            let txs: Vec<Transaction> = (1..=4u8).map(|sender| create_test_transaction(sender, 10)).collect();
+            let tx_size = bincode::serialize(&txs[0]).unwrap().len();
+            for tx in &txs {
+                assert!(pool.add_transaction(tx.clone()).unwrap());
+            }
+            assert_eq!(pool.get_transaction_size(&txs[0].id), None);
+            assert_eq!(pool.size_measurements.load(AtomicOrdering::Relaxed), 4);
+
+            // Each assembly measures every transaction it considers, and still honours the byte limit
+            let batch = pool.get_next_batch(10, tx_size * 2).unwrap();
+            assert_eq!(batch.len(), 2);
+            assert_eq!(pool.size_measurements.load(AtomicOrdering::Relaxed), 7);
+        }
+    }
+
+    #[test]
+    fn test_full_pool_evicts_lowest_priority() {
+        let config = MempoolConfig {
+            max_size: 10,
+            reputation: Some(ReputationConfig::default()),
+            ..Default::default()
+        };
+        let mempool = Mempool::new(config);
+
+        // Filling the pool trims it to 90%, dropping only the cheapest transaction
+        let txs: Vec<Transaction> = (1..=10).map(|sender| create_test_transaction(sender, sender as u64 * 10)).collect();
+        for tx in &txs {
+            assert!(mempool.add_transaction(tx.clone()).unwrap());
+        }
+
+        assert_eq!(mempool.size(), 9);
+        assert!(!mempool.contains(&txs[0].id));
+        assert!(txs[1..].iter().all(|tx| mempool.contains(&tx.id)));
+
+        // Only the evicted sender is penalized
+        assert_eq!(mempool.get_sender_reputation(&test_address(1)).rejected, 1);
+        assert_eq!(mempool.get_sender_reputation(&test_address(10)).rejected, 0);
+    }
+
+    #[test]
+    fn test_sender_reputation_priority() {
+        let config = MempoolConfig {
+            reputation: Some(ReputationConfig::default()),
+            ..Default::default()
+        };
+        let mempool = Mempool::new(config);
+
+        let spammer = 1;
+        let clean = 2;
+
+        // Zero-fee submissions are rejected and count against the sender
+        for _ in 0..20 {
+            assert!(!mempool.add_transaction(create_test_transaction(spammer, 0)).unwrap());
+        }
+        assert_eq!(mempool.get_sender_reputation(&test_address(spammer)).rejected, 20);
+
+        // Spammer submits first, so FIFO alone would favour it
+        let spam_tx = create_test_transaction(spammer, 100);
+        let clean_tx = create_test_transaction(clean, 100);
+        assert!(mempool.add_transaction(spam_tx.clone()).unwrap());
+        assert!(mempool.add_transaction(clean_tx.clone()).unwrap());
+
+        let batch = mempool.get_next_batch(10, 1024 * 1024).unwrap();
+        assert_eq!(batch[0].id, clean_tx.id);
+        assert_eq!(batch[1].id, spam_tx.id);
+
+        // The penalty is bounded, so a higher fee still wins
+        let rich_spam_tx = create_test_transaction(spammer, 120);
+        mempool.remove_transaction(&spam_tx.id).unwrap();
+        assert!(mempool.add_transaction(rich_spam_tx.clone()).unwrap());
+
+        let batch = mempool.get_next_batch(10, 1024 * 1024).unwrap();
+        assert_eq!(batch[0].id, rich_spam_tx.id);
+    }
+
+    #[test]
+    fn test_mempool_persist_and_restore() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let storage = Storage::new(temp_dir.path()).unwrap();
+
+        let mempool = Mempool::new(MempoolConfig::default());
+        let tx = create_test_transaction(1, 10);
+        mempool.add_transaction(tx.clone()).unwrap();
+        assert_eq!(mempool.persist(&storage).unwrap(), 1);
+
+        let restarted = Mempool::new(MempoolConfig::default());
+        assert_eq!(restarted.restore(&storage).unwrap(), 1);
+        assert!(restarted.contains(&tx.id));
+
+        // The snapshot is consumed on restore
+        assert_eq!(Mempool::new(MempoolConfig::default()).restore(&storage).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_persistent_mempool_survives_restart() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let storage = Storage::new(temp_dir.path()).unwrap();
+        let config = MempoolConfig { persist: true, ..Default::default() };
+
+        let mempool = Mempool::new(config.clone()).with_storage(&storage);
+        let kept = create_test_transaction(1, 10);
+        let removed = create_test_transaction(2, 10);
+        let committed = create_test_transaction(3, 10);
+        let mut expired = create_test_transaction(4, 10);
+        expired.timestamp -= (config.max_age_secs + 1) * 1000;
+        expired.sign(&test_key(4));
+        for tx in [&kept, &removed, &committed, &expired] {
+            assert!(mempool.add_transaction(tx.clone()).unwrap());
+        }
+        mempool.remove_transaction(&removed.id).unwrap();
+        storage.transactions().put_transaction(&committed).unwrap();
+
+        // No explicit persist: every admission was already written through
+        drop(mempool);
+        let restarted = Mempool::new(config).with_storage(&storage);
+        assert_eq!(restarted.restore(&storage).unwrap(), 1);
+        assert!(restarted.contains(&kept.id));
+        assert_eq!(restarted.size(), 1);
+
+        // Survivors stay on disk for the next restart
+        let ids: Vec<Uuid> = storage.mempool().get_transactions().unwrap().iter().map(|tx| tx.id).collect();
+        assert_eq!(ids, vec![kept.id]);
+    }
+
+    #[test]
+    fn test_replacement_cooldown() {
+        let config = MempoolConfig {
+            replacement_cooldown_ms: 200,
+            ..Default::default()
+        };
+        let mempool = Mempool::new(config);
+
+        let original = create_transaction_with_nonce(1, 1, 10);
+        let first = create_transaction_with_nonce(1, 1, 20);
+        assert!(mempool.add_transaction(original.clone()).unwrap());
+        assert!(mempool.add_transaction(first.clone()).unwrap());
+        assert!(!mempool.contains(&original.id));
+
+        // A bigger bump inside the cooldown is still rejected
+        let second = create_transaction_with_nonce(1, 1, 40);
+        assert!(!mempool.add_transaction(second).unwrap());
+        assert!(mempool.contains(&first.id));
+
+        // Other nonces from the same sender are unaffected
+        assert!(mempool.add_transaction(create_transaction_with_nonce(1, 2, 10)).unwrap());
+
+        std::thread::sleep(Duration::from_millis(250));
+
+        let third = create_transaction_with_nonce(1, 1, 40);
+        assert!(mempool.add_transaction(third.clone()).unwrap());
+        assert!(!mempool.contains(&first.id));
+        assert!(mempool.contains(&third.id));
+        assert_eq!(mempool.size(), 2);
+    }
+
+    #[test]
+    fn test_replace_by_fee() {
+        let mempool = Mempool::new(MempoolConfig::default());
+
+        let stuck = create_transaction_with_nonce(1, 1, 100);
+        assert!(mempool.add_transaction(stuck.clone()).unwrap());
+
+        // The default 10% bump requires a fee of at least 110
+        let replacement = create_transaction_with_nonce(1, 1, 110);
+        assert!(mempool.add_transaction(replacement.clone()).unwrap());
+
+        assert!(!mempool.contains(&stuck.id));
+        assert!(mempool.contains(&replacement.id));
+        assert_eq!(mempool.size(), 1);
+        assert_eq!(mempool.get_transactions_by_sender(&test_address(1)).len(), 1);
+
+        // The old hash is purged, so the original could be admitted again
+        let tx_hashes = mempool.tx_hashes.read().unwrap();
+        assert!(!tx_hashes.contains(&stuck.hash()));
+        assert!(tx_hashes.contains(&replacement.hash()));
+    }
+
+    #[test]
+    fn test_same_nonce_keeps_only_higher_fee() {
+        let mempool = Mempool::new(MempoolConfig::default());
+
+        let higher = create_transaction_with_nonce(1, 1, 200);
+        let lower = create_transaction_with_nonce(1, 1, 150);
+        assert!(mempool.add_transaction(higher.clone()).unwrap());
+        assert!(!mempool.add_transaction(lower.clone()).unwrap());
+
+        assert_eq!(mempool.size(), 1);
+        assert!(!mempool.contains(&lower.id));
+        assert_eq!(mempool.get_by_nonce(&test_address(1), 1).unwrap().id, higher.id);
+        assert!(mempool.get_by_nonce(&test_address(1), 2).is_none());
+    }
+
+    #[test]
+    fn test_concurrent_same_nonce_submissions_leave_one_winner() {
+        let config = MempoolConfig {
+            replacement_cooldown_ms: 0,
+            replacement_fee_bump_percent: 0,
+            ..Default::default()
+        };
+        let mempool = Arc::new(Mempool::new(config));
+
+        let handles: Vec<_> = (10..18)
+            .map(|fee| {
+                let mempool = mempool.clone();
+                let tx = create_transaction_with_nonce(1, 1, fee);
+                std::thread::spawn(move || mempool.add_transaction(tx).unwrap())
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        // Whatever the arrival order, each higher fee evicts the one before it
+        assert_eq!(mempool.size(), 1);
+        assert_eq!(mempool.get_transactions_by_sender(&test_address(1)).len(), 1);
+        assert_eq!(mempool.get_by_nonce(&test_address(1), 1).unwrap().fee, 17);
+        assert_eq!(mempool.get_next_batch(10, usize::MAX).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_replacement_with_insufficient_bump_is_rejected() {
+        let config = MempoolConfig {
+            replacement_fee_bump_percent: 25,
+            ..Default::default()
+        };
+        let mempool = Mempool::new(config);
+
+        let pending = create_transaction_with_nonce(1, 1, 100);
+        assert!(mempool.add_transaction(pending.clone()).unwrap());
+
+        for fee in [90, 100, 124] {
+            let underpriced = create_transaction_with_nonce(1, 1, fee);
+            assert!(!mempool.add_transaction(underpriced.clone()).unwrap());
+            assert!(!mempool.contains(&underpriced.id));
+        }
+        assert!(mempool.contains(&pending.id));
+        assert_eq!(mempool.get_stats().total_rejected, 3);
+
+        let replacement = create_transaction_with_nonce(1, 1, 125);
+        assert!(mempool.add_transaction(replacement.clone()).unwrap());
+        assert!(!mempool.contains(&pending.id));
+    }
+
+    #[test]
+    fn test_priority_queue_stays_bounded_under_churn() {
+        let mempool = Mempool::new(MempoolConfig::default());
+        let txs: Vec<Transaction> = (1..=100u8)
+            .map(|sender| create_test_transaction(sender, 10))
+            .collect();
+
+        // 100k admissions and commits
+        for _ in 0..1000 {
+            for tx in &txs {
+                assert!(mempool.add_transaction(tx.clone()).unwrap());
+            }
+            let heap_len = mempool.priority_queue.read().unwrap().len();
+            assert!(heap_len <= mempool.size() * 2 + HEAP_COMPACTION_SLACK + 1);
+
+            for tx in &txs {
+                mempool.remove_committed(&tx.id).unwrap();
+            }
+        }
+
+        assert_eq!(mempool.size(), 0);
+        assert!(mempool.priority_queue.read().unwrap().len() <= txs.len() * 2 + HEAP_COMPACTION_SLACK + 1);
+    }
+
+    #[test]
+    fn test_batch_includes_consecutive_nonces_in_order() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let storage = Storage::new(temp_dir.path()).unwrap();
+        let mempool = Mempool::new(MempoolConfig::default()).with_state(storage.state().clone());
+
+        // Later nonces pay more, so priority order is the reverse of nonce order
+        let txs: Vec<Transaction> = (1..=3)
+            .map(|nonce| create_transaction_with_nonce(1, nonce, 10 * nonce))
+            .collect();
+        for tx in &txs {
+            assert!(mempool.add_transaction(tx.clone()).unwrap());
+        }
+
+        let batch = mempool.get_next_batch(10, 1024 * 1024).unwrap();
+        let nonces: Vec<u64> = batch.iter().map(|tx| tx.nonce).collect();
+        assert_eq!(nonces, vec![1, 2, 3]);
+
+        // Selection leaves everything queued until commit
+        assert_eq!(mempool.get_next_batch(10, 1024 * 1024).unwrap().len(), 3);
+
+        // Once nonce 1 is committed the batch starts at nonce 2
+        storage.state().increment_nonce(&test_address(1)).unwrap();
+        let batch = mempool.get_next_batch(10, 1024 * 1024).unwrap();
+        let nonces: Vec<u64> = batch.iter().map(|tx| tx.nonce).collect();
+        assert_eq!(nonces, vec![2, 3]);
+    }
+
+    #[test]
+    fn test_batch_stops_at_nonce_gap() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let storage = Storage::new(temp_dir.path()).unwrap();
+        let mempool = Mempool::new(MempoolConfig::default()).with_state(storage.state().clone());
+
+        let first = create_transaction_with_nonce(1, 1, 10);
+        let future = create_transaction_with_nonce(1, 3, 50);
+        assert!(mempool.add_transaction(first.clone()).unwrap());
+        assert!(mempool.add_transaction(future.clone()).unwrap());
+
+        let batch = mempool.get_next_batch(10, 1024 * 1024).unwrap();
+        assert_eq!(batch.len(), 1);
+        assert_eq!(batch[0].id, first.id);
+
+        // The future nonce stays queued and becomes eligible once the gap is filled
+        assert!(mempool.contains(&future.id));
+        assert!(mempool.add_transaction(create_transaction_with_nonce(1, 2, 10)).unwrap());
+        let nonces: Vec<u64> = mempool.get_next_batch(10, 1024 * 1024).unwrap()
+            .iter()
+            .map(|tx| tx.nonce)
+            .collect();
+        assert_eq!(nonces, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_ordering_policies_on_fixed_pool() {
+        // (sender, nonce, fee) in arrival order; sender 3's nonce 4 is past a gap
+        let pool = [(3, 1, 20), (1, 1, 10), (2, 1, 30), (1, 2, 50), (3, 2, 40), (3, 4, 100)];
+        let expected = [
+            (OrderingPolicy::FeeThenNonce, vec![(2, 1), (3, 1), (3, 2), (1, 1), (1, 2)]),
+            (OrderingPolicy::NonceThenFee, vec![(2, 1), (3, 1), (1, 1), (1, 2), (3, 2)]),
+            (OrderingPolicy::FifoWithinAccount, vec![(3, 1), (3, 2), (1, 1), (1, 2), (2, 1)]),
+        ];
+
+        let senders: HashMap<Address, u8> = (1..=3).map(|sender| (test_address(sender), sender)).collect();
+        for (ordering, expected) in expected {
+            let mempool = Mempool::new(MempoolConfig { ordering, ..Default::default() });
+            for (sender, nonce, fee) in pool {
+                assert!(mempool.add_transaction(create_transaction_with_nonce(sender, nonce, fee)).unwrap());
+            }
+
+            let order: Vec<(u8, u64)> = mempool.get_next_batch(10, 1024 * 1024).unwrap()
+                .iter()
+                .map(|tx| (senders[&tx.from], tx.nonce))
+                .collect();
+            assert_eq!(order, expected, "{:?}", ordering);
+
+            // Assembling a batch doesn't disturb the pool, so the order is reproducible
+            assert_eq!(mempool.get_next_batch(10, 1024 * 1024).unwrap().len(), 5);
+            assert_eq!(mempool.get_next_batch(2, 1024 * 1024).unwrap().len(), 2);
+        }
+    }
 }
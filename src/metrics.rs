@@ -1,12 +1,22 @@
-use std::sync::Arc;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use anyhow::{Result, anyhow};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{header, Body, Method, Request, Response, Server, StatusCode};
 use prometheus::{
-    Counter, Gauge, Histogram, IntCounter, IntGauge, 
-    Registry, Encoder, TextEncoder
+    Counter, Gauge, Histogram, IntCounter, IntCounterVec, IntGauge, IntGaugeVec,
+    Opts, Registry, Encoder, TextEncoder
 };
-use tokio::sync::RwLock;
+use sysinfo::{Pid, ProcessRefreshKind, System};
+use tokio::sync::{Notify, RwLock};
+use tokio::task::JoinHandle;
 use std::collections::HashMap;
 
+use crate::storage::Storage;
+use crate::types::{NodeId, VoteType};
+
 /// Blockchain node metrics collector
 pub struct NodeMetrics {
     // Block metrics
@@ -26,6 +36,9 @@ pub struct NodeMetrics {
     pub view_changes: IntCounter,
     pub consensus_latency: Histogram,
     pub validator_votes: IntCounter,
+    pub validator_prepares: IntCounterVec,
+    pub validator_commits: IntCounterVec,
+    pub validator_last_round: IntGaugeVec,
     
     // Network metrics
     pub connected_peers: IntGauge,
@@ -34,18 +47,29 @@ pub struct NodeMetrics {
     pub bytes_sent: IntCounter,
     pub bytes_received: IntCounter,
     pub network_latency: Histogram,
+    pub ingress_dropped: IntCounter,
     
     // Storage metrics
     pub storage_size: IntGauge,
     pub storage_operations: IntCounter,
     pub storage_latency: Histogram,
     
+    // RocksDB internal metrics
+    pub rocksdb_pending_compaction_bytes: IntGauge,
+    pub rocksdb_memtable_bytes: IntGauge,
+    pub rocksdb_estimated_keys: IntGauge,
+    pub rocksdb_block_cache_usage_bytes: IntGauge,
+    pub rocksdb_block_cache_hit_ratio: Gauge,
+    
     // System metrics
     pub cpu_usage: Gauge,
     pub memory_usage: IntGauge,
     pub disk_usage: IntGauge,
     
     registry: Registry,
+    // Kept between samples; CPU usage is measured from one refresh to the next
+    system: Mutex<System>,
+    pid: Option<Pid>,
 }
 
 impl NodeMetrics {
@@ -138,6 +162,25 @@ impl NodeMetrics {
         )?;
         registry.register(Box::new(validator_votes.clone()))?;
         
+        // Per-validator series show which validators have stopped voting
+        let validator_prepares = IntCounterVec::new(
+            Opts::new("blockchain_validator_prepares_total", "Prepare votes accepted per validator"),
+            &["validator"]
+        )?;
+        registry.register(Box::new(validator_prepares.clone()))?;
+        
+        let validator_commits = IntCounterVec::new(
+            Opts::new("blockchain_validator_commits_total", "Commit votes accepted per validator"),
+            &["validator"]
+        )?;
+        registry.register(Box::new(validator_commits.clone()))?;
+        
+        let validator_last_round = IntGaugeVec::new(
+            Opts::new("blockchain_validator_last_round", "Last round each validator voted in"),
+            &["validator"]
+        )?;
+        registry.register(Box::new(validator_last_round.clone()))?;
+        
         // Network metrics
         let connected_peers = IntGauge::new(
             "blockchain_connected_peers",
@@ -177,6 +220,12 @@ impl NodeMetrics {
         )?;
         registry.register(Box::new(network_latency.clone()))?;
         
+        let ingress_dropped = IntCounter::new(
+            "blockchain_ingress_dropped_total",
+            "Transactions dropped because the ingress queue was full"
+        )?;
+        registry.register(Box::new(ingress_dropped.clone()))?;
+        
         // Storage metrics
         let storage_size = IntGauge::new(
             "blockchain_storage_size_bytes",
@@ -198,6 +247,37 @@ impl NodeMetrics {
         )?;
         registry.register(Box::new(storage_latency.clone()))?;
         
+        // RocksDB internal metrics
+        let rocksdb_pending_compaction_bytes = IntGauge::new(
+            "blockchain_rocksdb_pending_compaction_bytes",
+            "Estimated bytes RocksDB still has to compact"
+        )?;
+        registry.register(Box::new(rocksdb_pending_compaction_bytes.clone()))?;
+        
+        let rocksdb_memtable_bytes = IntGauge::new(
+            "blockchain_rocksdb_memtable_bytes",
+            "Size of all RocksDB memtables in bytes"
+        )?;
+        registry.register(Box::new(rocksdb_memtable_bytes.clone()))?;
+        
+        let rocksdb_estimated_keys = IntGauge::new(
+            "blockchain_rocksdb_estimated_keys",
+            "Estimated number of keys in RocksDB"
+        )?;
+        registry.register(Box::new(rocksdb_estimated_keys.clone()))?;
+        
+        let rocksdb_block_cache_usage_bytes = IntGauge::new(
+            "blockchain_rocksdb_block_cache_usage_bytes",
+            "Memory used by the RocksDB block cache in bytes"
+        )?;
+        registry.register(Box::new(rocksdb_block_cache_usage_bytes.clone()))?;
+        
+        let rocksdb_block_cache_hit_ratio = Gauge::new(
+            "blockchain_rocksdb_block_cache_hit_ratio",
+            "Fraction of RocksDB block cache lookups that hit"
+        )?;
+        registry.register(Box::new(rocksdb_block_cache_hit_ratio.clone()))?;
+        
         // System metrics
         let cpu_usage = Gauge::new(
             "blockchain_cpu_usage_percent",
@@ -230,19 +310,30 @@ impl NodeMetrics {
             view_changes,
             consensus_latency,
             validator_votes,
+            validator_prepares,
+            validator_commits,
+            validator_last_round,
             connected_peers,
             messages_sent,
             messages_received,
             bytes_sent,
             bytes_received,
             network_latency,
+            ingress_dropped,
             storage_size,
             storage_operations,
             storage_latency,
+            rocksdb_pending_compaction_bytes,
+            rocksdb_memtable_bytes,
+            rocksdb_estimated_keys,
+            rocksdb_block_cache_usage_bytes,
+            rocksdb_block_cache_hit_ratio,
             cpu_usage,
             memory_usage,
             disk_usage,
             registry,
+            system: Mutex::new(System::new()),
+            pid: sysinfo::get_current_pid().ok(),
         })
     }
     
@@ -255,25 +346,87 @@ impl NodeMetrics {
         Ok(String::from_utf8(buffer)?)
     }
     
-    /// Update system metrics
-    pub fn update_system_metrics(&self) -> Result<()> {
-        // This is simplified - in practice, you'd use system monitoring libraries
-        // like sysinfo or procfs to get actual system metrics
+    /// Count an accepted vote against the validator that cast it
+    pub fn record_vote(&self, validator_id: &NodeId, vote_type: &VoteType, round: u64) {
+        self.validator_votes.inc();
+        let per_type = match vote_type {
+            VoteType::Prepare => &self.validator_prepares,
+            VoteType::Commit => &self.validator_commits,
+        };
+        per_type.with_label_values(&[validator_id]).inc();
+        self.validator_last_round.with_label_values(&[validator_id]).set(round as i64);
+    }
+    
+    /// Update process CPU and memory usage, and the data directory's size when given
+    ///
+    /// CPU usage is averaged since the previous call, so the first sample reads zero.
+    pub fn update_system_metrics(&self, data_dir: Option<&Path>) -> Result<()> {
+        let pid = self.pid.ok_or_else(|| anyhow!("Current process id unavailable"))?;
+        
+        {
+            let mut system = self.system.lock().unwrap();
+            system.refresh_process_specifics(pid, ProcessRefreshKind::new().with_cpu().with_memory());
+            let process = system.process(pid)
+                .ok_or_else(|| anyhow!("Process {} not found", pid))?;
+            
+            self.cpu_usage.set(process.cpu_usage() as f64);
+            self.memory_usage.set(process.memory() as i64);
+        }
+        
+        if let Some(data_dir) = data_dir {
+            self.disk_usage.set(directory_size(data_dir)? as i64);
+        }
+        
+        Ok(())
+    }
+    
+    /// Update RocksDB metrics from the database's internal properties
+    pub fn update_storage_metrics(&self, storage: &Storage) -> Result<()> {
+        let stats = storage.get_rocksdb_stats()?;
         
-        // Placeholder values
-        self.cpu_usage.set(25.0);
-        self.memory_usage.set(1024 * 1024 * 512); // 512MB
-        self.disk_usage.set(1024 * 1024 * 1024 * 10); // 10GB
+        self.rocksdb_pending_compaction_bytes.set(stats.pending_compaction_bytes as i64);
+        self.rocksdb_memtable_bytes.set(stats.memtable_bytes as i64);
+        self.rocksdb_estimated_keys.set(stats.estimated_keys as i64);
+        self.rocksdb_block_cache_usage_bytes.set(stats.block_cache_usage_bytes as i64);
+        self.rocksdb_block_cache_hit_ratio.set(stats.block_cache_hit_ratio());
+        self.storage_size.set(storage.estimate_db_size()? as i64);
         
         Ok(())
     }
 }
 
+/// Total size of the files under a directory
+fn directory_size(path: &Path) -> Result<u64> {
+    let mut total = 0;
+    
+    for entry in std::fs::read_dir(path)? {
+        let entry = entry?;
+        // RocksDB deletes files during compaction; skip any that vanish mid-walk
+        let metadata = match entry.path().symlink_metadata() {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        };
+        
+        if metadata.is_dir() {
+            total += directory_size(&entry.path())?;
+        } else {
+            total += metadata.len();
+        }
+    }
+    
+    Ok(total)
+}
+
 /// Metrics server for exposing Prometheus metrics
 pub struct MetricsServer {
     port: u16,
     metrics: Arc<NodeMetrics>,
+    data_dir: Option<PathBuf>,
     is_running: Arc<RwLock<bool>>,
+    shutdown_notify: Arc<Notify>,
+    local_addr: Option<SocketAddr>,
+    server_task: Option<JoinHandle<()>>,
 }
 
 impl MetricsServer {
@@ -284,49 +437,66 @@ impl MetricsServer {
         Ok(Self {
             port,
             metrics,
+            data_dir: None,
             is_running: Arc::new(RwLock::new(false)),
+            shutdown_notify: Arc::new(Notify::new()),
+            local_addr: None,
+            server_task: None,
         })
     }
     
+    /// Report the disk usage of the node's data directory
+    pub fn with_data_dir(mut self, data_dir: PathBuf) -> Self {
+        self.data_dir = Some(data_dir);
+        self
+    }
+    
     /// Start the metrics server
     pub async fn start(&mut self) -> Result<()> {
+        let metrics = self.metrics.clone();
+        let make_service = make_service_fn(move |_conn| {
+            let metrics = metrics.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| serve_metrics(metrics.clone(), request)))
+            }
+        });
+
+        // Bind before spawning so a port in use is reported to the caller
+        let addr = SocketAddr::from(([0, 0, 0, 0], self.port));
+        let server = Server::try_bind(&addr)
+            .map_err(|e| anyhow!("Failed to bind metrics server to {}: {}", addr, e))?
+            .serve(make_service);
+        let local_addr = server.local_addr();
+        self.local_addr = Some(local_addr);
+
         {
             let mut is_running = self.is_running.write().await;
             *is_running = true;
         }
-        
-        let metrics = self.metrics.clone();
+
+        // Stop accepting connections once the running flag is cleared
         let is_running = self.is_running.clone();
-        let port = self.port;
-        
-        tokio::spawn(async move {
-            let app = warp::path("metrics")
-                .map(move || {
-                    match metrics.export() {
-                        Ok(metrics_text) => {
-                            warp::reply::with_header(
-                                metrics_text,
-                                "content-type",
-                                "text/plain; version=0.0.4; charset=utf-8"
-                            )
-                        }
-                        Err(_) => {
-                            warp::reply::with_header(
-                                "Error exporting metrics".to_string(),
-                                "content-type",
-                                "text/plain"
-                            )
-                        }
-                    }
-                });
-            
-            // This is simplified - in practice, you'd use a proper HTTP server
-            // warp::serve(app).run(([0, 0, 0, 0], port)).await;
+        let shutdown_notify = self.shutdown_notify.clone();
+        let server = server.with_graceful_shutdown(async move {
+            loop {
+                shutdown_notify.notified().await;
+                if !*is_running.read().await {
+                    break;
+                }
+            }
         });
+
+        tracing::info!("Metrics server listening on {}", local_addr);
+        self.server_task = Some(tokio::spawn(async move {
+            if let Err(e) = server.await {
+                tracing::error!("Metrics server error: {}", e);
+            }
+        }));
         
         // Start periodic system metrics update
         let metrics = self.metrics.clone();
         let is_running = self.is_running.clone();
+        let data_dir = self.data_dir.clone();
         
         tokio::spawn(async move {
             let mut interval = tokio::time::interval(std::time::Duration::from_secs(10));
@@ -341,7 +511,7 @@ impl MetricsServer {
                     }
                 }
                 
-                if let Err(e) = metrics.update_system_metrics() {
+                if let Err(e) = metrics.update_system_metrics(data_dir.as_deref()) {
                     tracing::warn!("Failed to update system metrics: {}", e);
                 }
             }
@@ -350,10 +520,22 @@ impl MetricsServer {
         Ok(())
     }
     
+    /// Address the server is bound to, once started
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.local_addr
+    }
+    
     /// Shutdown the metrics server
     pub async fn shutdown(&mut self) -> Result<()> {
-        let mut is_running = self.is_running.write().await;
-        *is_running = false;
+        {
+            let mut is_running = self.is_running.write().await;
+            *is_running = false;
+        }
+        self.shutdown_notify.notify_one();
+
+        if let Some(task) = self.server_task.take() {
+            task.await.map_err(|e| anyhow!("Metrics server task failed: {}", e))?;
+        }
         Ok(())
     }
     
@@ -362,3 +544,142 @@ impl MetricsServer {
         &self.metrics
     }
 }
+
+/// Serve the Prometheus text exposition at `GET /metrics`
+async fn serve_metrics(metrics: Arc<NodeMetrics>, request: Request<Body>) -> Result<Response<Body>, Infallible> {
+    if request.uri().path() != "/metrics" {
+        return Ok(Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::empty())
+            .unwrap());
+    }
+
+    if request.method() != Method::GET {
+        return Ok(Response::builder()
+            .status(StatusCode::METHOD_NOT_ALLOWED)
+            .header(header::ALLOW, "GET")
+            .body(Body::empty())
+            .unwrap());
+    }
+
+    let response = match metrics.export() {
+        Ok(metrics_text) => Response::builder()
+            .header(header::CONTENT_TYPE, "text/plain; version=0.0.4; charset=utf-8")
+            .body(Body::from(metrics_text)),
+        Err(e) => Response::builder()
+            .status(StatusCode::INTERNAL_SERVER_ERROR)
+            .header(header::CONTENT_TYPE, "text/plain")
+            .body(Body::from(format!("Error exporting metrics: {}", e))),
+    };
+
+    Ok(response.unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    use crate::types::{Block, BlockHeader};
+
+    #[test]
+    fn test_rocksdb_metrics_populated_after_writes() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::new(temp_dir.path()).unwrap();
+        let metrics = NodeMetrics::new().unwrap();
+
+        for height in 1..=20 {
+            let block = Block {
+                header: BlockHeader {
+                    height,
+                    previous_hash: [0; 32],
+                    merkle_root: [0; 32],
+                    state_root: [0; 32],
+                    timestamp: 1234567890 + height,
+                    proposer: "test-node".to_string(),
+                    round: 0,
+                    view: 0,
+                    vrf_proof: None,
+                },
+                transactions: vec![],
+                signatures: vec![],
+            };
+            storage.store_block(&block).unwrap();
+        }
+
+        metrics.update_storage_metrics(&storage).unwrap();
+
+        assert!(metrics.rocksdb_memtable_bytes.get() > 0);
+        assert!(metrics.rocksdb_estimated_keys.get() > 0);
+        assert!(metrics.export().unwrap().contains("blockchain_rocksdb_memtable_bytes"));
+    }
+
+    #[tokio::test]
+    async fn test_metrics_served_over_http() {
+        let mut server = MetricsServer::new(0).unwrap();
+        server.metrics().block_height.set(42);
+        server.start().await.unwrap();
+
+        let addr = server.local_addr().unwrap();
+        let client = reqwest::Client::new();
+        let response = client
+            .get(format!("http://127.0.0.1:{}/metrics", addr.port()))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let body = response.text().await.unwrap();
+        assert!(body.contains("blockchain_block_height 42"));
+
+        let missing = client.get(format!("http://127.0.0.1:{}/", addr.port())).send().await.unwrap();
+        assert_eq!(missing.status(), reqwest::StatusCode::NOT_FOUND);
+
+        // A second server on the same port fails to start instead of panicking in its task
+        let mut conflicting = MetricsServer::new(addr.port()).unwrap();
+        assert!(conflicting.start().await.is_err());
+
+        server.shutdown().await.unwrap();
+        assert!(client.get(format!("http://127.0.0.1:{}/metrics", addr.port())).send().await.is_err());
+    }
+
+    #[test]
+    fn test_votes_recorded_per_validator() {
+        let metrics = NodeMetrics::new().unwrap();
+        let (alice, bob) = ("validator-a".to_string(), "validator-b".to_string());
+
+        metrics.record_vote(&alice, &VoteType::Prepare, 3);
+        metrics.record_vote(&alice, &VoteType::Commit, 3);
+        metrics.record_vote(&bob, &VoteType::Prepare, 3);
+        metrics.record_vote(&alice, &VoteType::Prepare, 4);
+
+        assert_eq!(metrics.validator_votes.get(), 4);
+        assert_eq!(metrics.validator_prepares.with_label_values(&["validator-a"]).get(), 2);
+        assert_eq!(metrics.validator_prepares.with_label_values(&["validator-b"]).get(), 1);
+        assert_eq!(metrics.validator_commits.with_label_values(&["validator-a"]).get(), 1);
+        assert_eq!(metrics.validator_commits.with_label_values(&["validator-b"]).get(), 0);
+        assert_eq!(metrics.validator_last_round.with_label_values(&["validator-a"]).get(), 4);
+        assert_eq!(metrics.validator_last_round.with_label_values(&["validator-b"]).get(), 3);
+
+        let exported = metrics.export().unwrap();
+        assert!(exported.contains(r#"blockchain_validator_prepares_total{validator="validator-a"} 2"#));
+        assert!(exported.contains(r#"blockchain_validator_last_round{validator="validator-b"} 3"#));
+    }
+
+    #[test]
+    fn test_system_metrics_sampled() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::create_dir(temp_dir.path().join("nested")).unwrap();
+        std::fs::write(temp_dir.path().join("a.sst"), vec![0u8; 1000]).unwrap();
+        std::fs::write(temp_dir.path().join("nested").join("b.sst"), vec![0u8; 500]).unwrap();
+
+        let metrics = NodeMetrics::new().unwrap();
+        metrics.update_system_metrics(Some(temp_dir.path())).unwrap();
+
+        assert!(metrics.memory_usage.get() > 0);
+        assert!(metrics.cpu_usage.get() >= 0.0);
+        assert_eq!(metrics.disk_usage.get(), 1500);
+
+        // Without a data directory the disk gauge is left alone
+        metrics.update_system_metrics(None).unwrap();
+        assert_eq!(metrics.disk_usage.get(), 1500);
+    }
+}
@@ -0,0 +1,224 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+use crate::shutdown::Shutdown;
+use crate::types::{Hash, MessagePayload, NetworkMessage, NodeId, Transaction};
+
+/// Transaction gossip batching configuration
+#[derive(Debug, Clone)]
+pub struct BatchConfig {
+    /// How long a transaction waits for others to share its message; zero gossips each alone
+    pub interval: Duration,
+    /// Transactions per batch; a full batch is sent without waiting for the interval
+    pub max_batch_size: usize,
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_millis(200),
+            max_batch_size: 256,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct PendingBatch {
+    transactions: Vec<Transaction>,
+    hashes: HashSet<Hash>,
+}
+
+/// Aggregates locally admitted transactions into `TransactionBatch` gossip messages
+///
+/// Messages are handed to `outbound` for the network to publish, so the batcher
+/// can be created before the network starts.
+#[derive(Clone)]
+pub struct TransactionBatcher {
+    config: BatchConfig,
+    node_id: NodeId,
+    outbound: mpsc::UnboundedSender<NetworkMessage>,
+    pending: Arc<Mutex<PendingBatch>>,
+}
+
+impl TransactionBatcher {
+    pub fn new(config: BatchConfig, node_id: NodeId, outbound: mpsc::UnboundedSender<NetworkMessage>) -> Self {
+        Self {
+            config,
+            node_id,
+            outbound,
+            pending: Arc::new(Mutex::new(PendingBatch::default())),
+        }
+    }
+
+    /// Whether transactions are held back to share a message
+    pub fn is_batching(&self) -> bool {
+        !self.config.interval.is_zero() && self.config.max_batch_size > 1
+    }
+
+    /// Queue a transaction for gossip; a transaction already in the batch is skipped
+    pub fn push(&self, tx: Transaction) {
+        if !self.is_batching() {
+            self.send(MessagePayload::Transaction(tx));
+            return;
+        }
+
+        let full = {
+            let mut pending = self.pending.lock().unwrap();
+            if !pending.hashes.insert(tx.hash()) {
+                return;
+            }
+            pending.transactions.push(tx);
+            pending.transactions.len() >= self.config.max_batch_size
+        };
+
+        if full {
+            self.flush();
+        }
+    }
+
+    /// Gossip the pending transactions now, returning how many were sent
+    pub fn flush(&self) -> usize {
+        let transactions = {
+            let mut pending = self.pending.lock().unwrap();
+            pending.hashes.clear();
+            std::mem::take(&mut pending.transactions)
+        };
+        if transactions.is_empty() {
+            return 0;
+        }
+
+        let count = transactions.len();
+        debug!("Gossiping a batch of {} transactions", count);
+        self.send(MessagePayload::TransactionBatch(transactions));
+        count
+    }
+
+    /// Number of transactions waiting for the next batch
+    pub fn len(&self) -> usize {
+        self.pending.lock().unwrap().transactions.len()
+    }
+
+    /// Check whether no transactions are waiting
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Spawn the task flushing the batch every interval until `shutdown`
+    pub fn start(&self, mut shutdown: Shutdown) -> Option<JoinHandle<()>> {
+        if !self.is_batching() {
+            return None;
+        }
+
+        let batcher = self.clone();
+        Some(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(batcher.config.interval);
+            loop {
+                tokio::select! {
+                    _ = shutdown.wait() => break,
+                    _ = interval.tick() => {}
+                }
+                batcher.flush();
+            }
+            // Whatever arrived during shutdown still goes out
+            batcher.flush();
+        }))
+    }
+
+    fn send(&self, payload: MessagePayload) {
+        if let Err(e) = self.outbound.send(NetworkMessage::new(self.node_id.clone(), payload)) {
+            warn!("Network is not accepting transaction gossip: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::SigningKey;
+    use uuid::Uuid;
+    use crate::mempool::{Mempool, MempoolConfig};
+    use crate::network::MessageRouter;
+    use crate::types::address_from_public_key;
+
+    fn create_test_transaction(seed: u8) -> Transaction {
+        let signing_key = SigningKey::from_bytes(&[seed; 32]);
+        let mut tx = Transaction {
+            id: Uuid::new_v4(),
+            from: address_from_public_key(&signing_key.verifying_key().to_bytes()),
+            to: [2u8; 20],
+            amount: 1000,
+            fee: 10,
+            nonce: 1,
+            timestamp: 1_000,
+            signature: [0u8; 64],
+            public_key: [0u8; 32],
+            data: vec![],
+        };
+        tx.sign(&signing_key);
+        tx
+    }
+
+    #[tokio::test]
+    async fn test_transactions_in_window_share_one_batch() {
+        let (outbound, mut gossip) = mpsc::unbounded_channel();
+        let config = BatchConfig { interval: Duration::from_millis(50), max_batch_size: 100 };
+        let batcher = TransactionBatcher::new(config, "node-a".to_string(), outbound);
+        batcher.start(Shutdown::never()).unwrap();
+
+        let transactions: Vec<Transaction> = (1..=10).map(create_test_transaction).collect();
+        for tx in &transactions {
+            batcher.push(tx.clone());
+        }
+        // Re-submitting within the window doesn't grow the batch
+        batcher.push(transactions[0].clone());
+
+        let message = tokio::time::timeout(Duration::from_secs(2), gossip.recv()).await.unwrap().unwrap();
+        let batch = match message.payload {
+            MessagePayload::TransactionBatch(batch) => batch,
+            other => panic!("expected a transaction batch, got {:?}", other),
+        };
+        assert_eq!(batch.len(), 10);
+        assert!(gossip.try_recv().is_err());
+        assert!(batcher.is_empty());
+
+        // The receiving node admits every transaction in the batch
+        let mempool = Arc::new(Mempool::new(MempoolConfig::default()));
+        let (consensus_sender, _consensus_receiver) = mpsc::unbounded_channel();
+        let router = MessageRouter::new(consensus_sender, mempool.clone());
+        router.route(NetworkMessage::new("node-a".to_string(), MessagePayload::TransactionBatch(batch)));
+        assert!(transactions.iter().all(|tx| mempool.contains(&tx.id)));
+    }
+
+    #[test]
+    fn test_full_batch_is_sent_without_waiting() {
+        let (outbound, mut gossip) = mpsc::unbounded_channel();
+        let config = BatchConfig { interval: Duration::from_secs(60), max_batch_size: 3 };
+        let batcher = TransactionBatcher::new(config, "node-a".to_string(), outbound);
+
+        for seed in 1..=4 {
+            batcher.push(create_test_transaction(seed));
+        }
+
+        match gossip.try_recv().unwrap().payload {
+            MessagePayload::TransactionBatch(batch) => assert_eq!(batch.len(), 3),
+            other => panic!("expected a transaction batch, got {:?}", other),
+        }
+        assert!(gossip.try_recv().is_err());
+        assert_eq!(batcher.len(), 1);
+    }
+
+    #[test]
+    fn test_zero_interval_gossips_each_transaction() {
+        let (outbound, mut gossip) = mpsc::unbounded_channel();
+        let config = BatchConfig { interval: Duration::ZERO, max_batch_size: 100 };
+        let batcher = TransactionBatcher::new(config, "node-a".to_string(), outbound);
+
+        batcher.push(create_test_transaction(1));
+        assert!(matches!(gossip.try_recv().unwrap().payload, MessagePayload::Transaction(_)));
+        assert!(batcher.start(Shutdown::never()).is_none());
+    }
+}
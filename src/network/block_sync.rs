@@ -0,0 +1,23 @@
+use libp2p::StreamProtocol;
+use serde::{Serialize, Deserialize};
+
+use crate::types::{Block, BlockHeight};
+use super::framing::FramedCodec;
+
+/// Protocol name for direct block requests between two peers
+pub const BLOCK_SYNC_PROTOCOL: StreamProtocol = StreamProtocol::new("/blockchain-node/block-sync/1.0.0");
+
+/// Ask a single peer for the block at a height
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BlockRequest {
+    pub height: BlockHeight,
+}
+
+/// A peer's answer; `None` if it doesn't have the height
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockResponse {
+    pub block: Option<Block>,
+}
+
+/// Length-prefixed bincode codec for `BlockRequest`/`BlockResponse`
+pub type BlockSyncCodec = FramedCodec<BlockRequest, BlockResponse>;
@@ -0,0 +1,18 @@
+use libp2p::StreamProtocol;
+use serde::{Serialize, Deserialize};
+
+use crate::types::NetworkMessage;
+use super::framing::FramedCodec;
+
+/// Protocol name for messages addressed to a single peer
+pub const DIRECT_PROTOCOL: StreamProtocol = StreamProtocol::new("/blockchain-node/direct/1.0.0");
+
+/// The receiver's verdict on a direct message
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DirectAck {
+    /// False if the message failed validation or was for another chain
+    pub accepted: bool,
+}
+
+/// Length-prefixed bincode codec carrying a `NetworkMessage` and its `DirectAck`
+pub type DirectCodec = FramedCodec<NetworkMessage, DirectAck>;
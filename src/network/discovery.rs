@@ -1,23 +1,33 @@
 use anyhow::Result;
-use libp2p::kad;
-use std::collections::HashSet;
-use crate::types::{NodeId, PeerInfo};
+use libp2p::{identity, kad, PeerId};
+use std::collections::{HashMap, HashSet};
+use crate::types::{NodeId, PeerInfo, PublicKey};
 
 /// Peer discovery handler
 #[derive(Clone)]
 pub struct DiscoveryHandler {
     discovered_peers: HashSet<NodeId>,
+    /// Peer ids of the known validator set
+    validator_peers: HashMap<PeerId, NodeId>,
 }
 
 impl DiscoveryHandler {
     pub fn new() -> Self {
         Self {
             discovered_peers: HashSet::new(),
+            validator_peers: HashMap::new(),
         }
     }
 
-    /// Handle Kademlia query results
-    pub async fn handle_kademlia_result(&mut self, result: kad::QueryResult) -> Result<()> {
+    /// Recognize these peer ids as validators
+    pub fn with_validator_peers(mut self, validator_peers: HashMap<PeerId, NodeId>) -> Self {
+        self.validator_peers = validator_peers;
+        self
+    }
+
+    /// Handle Kademlia query results, returning validators the query found
+    pub async fn handle_kademlia_result(&mut self, result: kad::QueryResult) -> Result<Vec<PeerId>> {
+        let mut found = Vec::new();
         match result {
             kad::QueryResult::Bootstrap(Ok(kad::BootstrapOk { peer, .. })) => {
                 self.discovered_peers.insert(peer.to_string());
@@ -25,11 +35,28 @@ impl DiscoveryHandler {
             kad::QueryResult::GetClosestPeers(Ok(kad::GetClosestPeersOk { peers, .. })) => {
                 for peer in peers {
                     self.discovered_peers.insert(peer.to_string());
+                    if self.is_validator(&peer) {
+                        found.push(peer);
+                    }
                 }
             }
             _ => {}
         }
-        Ok(())
+        Ok(found)
+    }
+
+    /// Whether a peer id belongs to the validator set
+    pub fn is_validator(&self, peer_id: &PeerId) -> bool {
+        self.validator_peers.contains_key(peer_id)
+    }
+
+    /// Validators not among `connected`, to look up in the DHT
+    pub fn missing_validators(&self, connected: &HashMap<PeerId, PeerInfo>) -> Vec<PeerId> {
+        self.validator_peers
+            .keys()
+            .filter(|peer_id| !connected.contains_key(peer_id))
+            .copied()
+            .collect()
     }
 
     /// Get discovered peers
@@ -37,3 +64,29 @@ impl DiscoveryHandler {
         self.discovered_peers.iter().cloned().collect()
     }
 }
+
+/// Peer id of a validator whose node key is `public_key`
+///
+/// Nodes use their validator key as libp2p identity, so the peer id follows from genesis.
+pub fn validator_peer_id(public_key: &PublicKey) -> Option<PeerId> {
+    let public_key = identity::ed25519::PublicKey::try_from_bytes(public_key).ok()?;
+    Some(identity::PublicKey::from(public_key).to_peer_id())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::NodeKey;
+
+    #[test]
+    fn test_validator_peer_id_matches_node_key() {
+        let key = NodeKey::generate();
+        assert_eq!(validator_peer_id(&key.public_key()), Some(key.peer_id()));
+
+        let handler = DiscoveryHandler::new()
+            .with_validator_peers(HashMap::from([(key.peer_id(), "validator-1".to_string())]));
+        assert!(handler.is_validator(&key.peer_id()));
+        assert!(!handler.is_validator(&PeerId::random()));
+        assert_eq!(handler.missing_validators(&HashMap::new()), vec![key.peer_id()]);
+    }
+}
@@ -0,0 +1,125 @@
+use std::fmt;
+use std::io;
+use std::marker::PhantomData;
+use async_trait::async_trait;
+use libp2p::{
+    futures::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    request_response, StreamProtocol,
+};
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::codec::decode_bounded;
+
+/// Length-prefixed bincode codec for a request-response protocol
+///
+/// Each frame is a big-endian `u32` length followed by the bincode encoding.
+pub struct FramedCodec<Req, Resp> {
+    max_message_size: u64,
+    _messages: PhantomData<fn() -> (Req, Resp)>,
+}
+
+impl<Req, Resp> FramedCodec<Req, Resp> {
+    /// Refuse frames larger than `max_message_size`, matching the gossip limit
+    pub fn new(max_message_size: u64) -> Self {
+        Self {
+            max_message_size,
+            _messages: PhantomData,
+        }
+    }
+
+    async fn read_frame<T, M>(&self, io: &mut T) -> io::Result<M>
+    where
+        T: AsyncRead + Unpin + Send,
+        M: DeserializeOwned,
+    {
+        let mut len = [0u8; 4];
+        io.read_exact(&mut len).await?;
+        let len = u32::from_be_bytes(len) as u64;
+
+        // Checked before allocating, so a bogus prefix can't exhaust memory
+        if len > self.max_message_size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Frame of {} bytes exceeds {}", len, self.max_message_size),
+            ));
+        }
+
+        let mut data = vec![0u8; len as usize];
+        io.read_exact(&mut data).await?;
+        decode_bounded(&data, self.max_message_size)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+    }
+
+    async fn write_frame<T, M>(&self, io: &mut T, message: &M) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+        M: Serialize,
+    {
+        let data = bincode::serialize(message)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        if data.len() as u64 > self.max_message_size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Frame of {} bytes exceeds {}", data.len(), self.max_message_size),
+            ));
+        }
+
+        io.write_all(&(data.len() as u32).to_be_bytes()).await?;
+        io.write_all(&data).await?;
+        io.close().await
+    }
+}
+
+// Manual impls so the message types needn't be `Clone` or `Debug` themselves
+impl<Req, Resp> Clone for FramedCodec<Req, Resp> {
+    fn clone(&self) -> Self {
+        Self::new(self.max_message_size)
+    }
+}
+
+impl<Req, Resp> fmt::Debug for FramedCodec<Req, Resp> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FramedCodec")
+            .field("max_message_size", &self.max_message_size)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl<Req, Resp> request_response::Codec for FramedCodec<Req, Resp>
+where
+    Req: Serialize + DeserializeOwned + Send + 'static,
+    Resp: Serialize + DeserializeOwned + Send + 'static,
+{
+    type Protocol = StreamProtocol;
+    type Request = Req;
+    type Response = Resp;
+
+    async fn read_request<T>(&mut self, _: &Self::Protocol, io: &mut T) -> io::Result<Self::Request>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        self.read_frame(io).await
+    }
+
+    async fn read_response<T>(&mut self, _: &Self::Protocol, io: &mut T) -> io::Result<Self::Response>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        self.read_frame(io).await
+    }
+
+    async fn write_request<T>(&mut self, _: &Self::Protocol, io: &mut T, request: Self::Request) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        self.write_frame(io, &request).await
+    }
+
+    async fn write_response<T>(&mut self, _: &Self::Protocol, io: &mut T, response: Self::Response) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        self.write_frame(io, &response).await
+    }
+}
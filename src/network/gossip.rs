@@ -1,6 +1,74 @@
-use anyhow::Result;
+use anyhow::{Result, anyhow};
+use libp2p::PeerId;
 use std::collections::HashMap;
-use crate::types::{NetworkMessage, NodeId};
+use std::sync::Arc;
+use crate::codec::{decode_bounded, DEFAULT_MAX_DECODED_SIZE};
+use crate::crypto::{default_scheme, SignatureScheme};
+use crate::types::{Block, ConsensusMessage, MessagePayload, NetworkMessage, NodeId, PublicKey};
+use super::scoring::Misbehavior;
+
+/// Most peers one discovery message may list, so a single message can't trigger a flood of dials
+pub const MAX_DISCOVERY_PEERS: usize = 32;
+
+/// Gossipsub mesh degree and message history
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeshConfig {
+    /// Peers kept in the mesh of each topic
+    pub mesh_n: usize,
+    /// Below this many mesh peers, more are grafted
+    pub mesh_n_low: usize,
+    /// Above this many mesh peers, some are pruned
+    pub mesh_n_high: usize,
+    /// Fraction of non-mesh peers sent gossip each heartbeat
+    pub gossip_factor: f64,
+    /// Heartbeats a message stays in the cache
+    pub history_length: usize,
+    /// Heartbeats of cached messages advertised in gossip
+    pub history_gossip: usize,
+}
+
+impl Default for MeshConfig {
+    fn default() -> Self {
+        Self {
+            mesh_n: 6,
+            mesh_n_low: 5,
+            mesh_n_high: 12,
+            gossip_factor: 0.25,
+            history_length: 5,
+            history_gossip: 3,
+        }
+    }
+}
+
+impl MeshConfig {
+    /// Check the parameters fit together
+    pub fn validate(&self) -> Result<()> {
+        if self.mesh_n_low == 0 {
+            return Err(anyhow!("Gossip mesh_n_low must be at least 1"));
+        }
+        if self.mesh_n_low > self.mesh_n || self.mesh_n > self.mesh_n_high {
+            return Err(anyhow!(
+                "Gossip mesh degrees must satisfy mesh_n_low <= mesh_n <= mesh_n_high, got {} <= {} <= {}",
+                self.mesh_n_low, self.mesh_n, self.mesh_n_high
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.gossip_factor) {
+            return Err(anyhow!("Gossip factor must be between 0 and 1, got {}", self.gossip_factor));
+        }
+        if self.history_gossip == 0 || self.history_gossip > self.history_length {
+            return Err(anyhow!(
+                "Gossip history_gossip must be between 1 and history_length ({}), got {}",
+                self.history_length, self.history_gossip
+            ));
+        }
+        Ok(())
+    }
+
+    /// Outbound mesh peers gossipsub insists on, capped so small meshes stay valid
+    pub fn mesh_outbound_min(&self) -> usize {
+        (self.mesh_n / 2).min(self.mesh_n_low).min(2)
+    }
+}
 
 /// Gossip protocol handler for efficient message propagation
 #[derive(Clone)]
@@ -10,6 +78,12 @@ pub struct GossipHandler {
     // Gossip parameters
     fanout: usize,
     gossip_factor: f64,
+    // Largest decoded message accepted from a peer
+    max_message_size: u64,
+    // Public keys of the nodes whose messages must be signed
+    peer_keys: Arc<HashMap<NodeId, PublicKey>>,
+    // Scheme carried transactions are signed under
+    signature_scheme: Arc<dyn SignatureScheme>,
 }
 
 impl GossipHandler {
@@ -18,11 +92,42 @@ impl GossipHandler {
             seen_messages: HashMap::new(),
             fanout: 6, // Number of peers to gossip to
             gossip_factor: 0.25, // Fraction of peers to gossip to
+            max_message_size: DEFAULT_MAX_DECODED_SIZE,
+            peer_keys: Arc::new(HashMap::new()),
+            signature_scheme: default_scheme(),
         }
     }
 
+    /// Require messages from these nodes to carry a valid signature
+    pub fn with_peer_keys(mut self, peer_keys: HashMap<NodeId, PublicKey>) -> Self {
+        self.peer_keys = Arc::new(peer_keys);
+        self
+    }
+
+    /// Whether consensus messages from `sender` would be dropped for want of its key
+    pub fn missing_peer_key(&self, sender: &NodeId) -> bool {
+        !self.peer_keys.is_empty() && !self.peer_keys.contains_key(sender)
+    }
+
+    /// Check `node_id`'s messages against `public_key`, as for a validator added by governance
+    pub fn add_peer_key(&mut self, node_id: NodeId, public_key: PublicKey) {
+        Arc::make_mut(&mut self.peer_keys).insert(node_id, public_key);
+    }
+
+    /// Check carried transactions' signatures under `scheme` instead of Ed25519
+    pub fn with_signature_scheme(mut self, scheme: Arc<dyn SignatureScheme>) -> Self {
+        self.signature_scheme = scheme;
+        self
+    }
+
+    /// Reject messages that would decode to more than this many bytes
+    pub fn with_max_message_size(mut self, max_message_size: u64) -> Self {
+        self.max_message_size = max_message_size;
+        self
+    }
+
     /// Handle incoming gossip message
-    pub async fn handle_message(&mut self, message: NetworkMessage) -> Result<bool> {
+    pub async fn handle_message(&mut self, message: &NetworkMessage) -> Result<bool> {
         let message_id = format!("{:?}", message.message_id);
         
         // Check if we've seen this message before
@@ -30,8 +135,8 @@ impl GossipHandler {
             return Ok(false); // Already seen, don't propagate
         }
 
-        // Mark as seen
-        self.seen_messages.insert(message_id, message.timestamp);
+        // Mark as seen; expiry uses our clock, not the sender's timestamp
+        self.seen_messages.insert(message_id, current_time_ms());
         
         // Clean up old messages periodically
         self.cleanup_old_messages();
@@ -39,16 +144,271 @@ impl GossipHandler {
         Ok(true) // New message, should propagate
     }
 
+    /// Decode and check a raw gossip payload, naming what was wrong with it
+    pub fn check_message(&self, data: &[u8]) -> Result<NetworkMessage, Misbehavior> {
+        let message: NetworkMessage = decode_bounded(data, self.max_message_size)
+            .map_err(|_| Misbehavior::MalformedMessage)?;
+
+        self.check_payload(&message)?;
+        Ok(message)
+    }
+
+    /// Check the blocks and transactions a decoded message carries
+    pub fn check_payload(&self, message: &NetworkMessage) -> Result<(), Misbehavior> {
+        match &message.payload {
+            MessagePayload::Consensus(ConsensusMessage::Propose { block, .. }) => {
+                self.validate_block(block)?
+            }
+            MessagePayload::BlockResponse { block: Some(block) } => self.validate_block(block)?,
+            MessagePayload::Transaction(tx) => {
+                if !tx.verify_with(self.signature_scheme.as_ref()) {
+                    return Err(Misbehavior::InvalidTransaction);
+                }
+            }
+            MessagePayload::TransactionBatch(transactions) => {
+                if !transactions.iter().all(|tx| tx.verify_with(self.signature_scheme.as_ref())) {
+                    return Err(Misbehavior::InvalidTransaction);
+                }
+            }
+            MessagePayload::PeerDiscovery { peers } => {
+                if peers.len() > MAX_DISCOVERY_PEERS {
+                    return Err(Misbehavior::OversizedPeerList);
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Check a message really comes from the node it names as sender
+    ///
+    /// `author` is the peer gossipsub says published the message, and `sender_peer`
+    /// the peer id the claimed sender announced when it connected, if known.
+    /// Nodes with a known key must sign. Peer discovery is only taken from nodes
+    /// with a known key, and once keys are configured, consensus messages from
+    /// nodes without one are refused.
+    pub fn check_sender(
+        &self,
+        message: &NetworkMessage,
+        author: Option<PeerId>,
+        sender_peer: Option<PeerId>,
+    ) -> Result<(), Misbehavior> {
+        // Forwarders differ from the author, but the author must be the sender
+        if let (Some(author), Some(sender_peer)) = (author, sender_peer) {
+            if author != sender_peer {
+                return Err(Misbehavior::SpoofedSender);
+            }
+        }
+
+        match self.peer_keys.get(&message.sender) {
+            Some(public_key) if !message.verify_signature(public_key) => Err(Misbehavior::InvalidSignature),
+            Some(_) => Ok(()),
+            None if matches!(message.payload, MessagePayload::PeerDiscovery { .. }) => Err(Misbehavior::InvalidSignature),
+            None if !self.peer_keys.is_empty() && matches!(message.payload, MessagePayload::Consensus(_)) => {
+                Err(Misbehavior::InvalidSignature)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Check block structure and transaction signatures
+    fn validate_block(&self, block: &Block) -> Result<(), Misbehavior> {
+        // Verifies the merkle root and every transaction
+        if !block.verify_with(self.signature_scheme.as_ref()) {
+            return Err(Misbehavior::InvalidBlock);
+        }
+
+        Ok(())
+    }
+
     /// Clean up old seen messages
     fn cleanup_old_messages(&mut self) {
-        let current_time = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_millis() as u64;
+        let current_time = current_time_ms();
         
         // Remove messages older than 5 minutes
         self.seen_messages.retain(|_, &mut timestamp| {
-            current_time - timestamp < 300_000
+            current_time.saturating_sub(timestamp) < 300_000
         });
     }
 }
+
+fn current_time_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{BlockHeader, PeerInfo, Transaction, address_from_public_key};
+    use ed25519_dalek::SigningKey;
+    use uuid::Uuid;
+
+    fn create_test_block() -> Block {
+        let signing_key = SigningKey::from_bytes(&[1u8; 32]);
+        let mut tx = Transaction {
+            id: Uuid::new_v4(),
+            from: address_from_public_key(&signing_key.verifying_key().to_bytes()),
+            to: [2u8; 20],
+            amount: 1000,
+            fee: 10,
+            nonce: 1,
+            timestamp: 1234567890,
+            signature: [0u8; 64],
+            public_key: [0u8; 32],
+            data: vec![],
+        };
+        tx.sign(&signing_key);
+
+        let mut block = Block {
+            header: BlockHeader {
+                height: 1,
+                previous_hash: [0; 32],
+                merkle_root: [0; 32],
+                state_root: [0; 32],
+                timestamp: 1234567890,
+                proposer: "validator-1".to_string(),
+                round: 0,
+                view: 0,
+                vrf_proof: None,
+            },
+            transactions: vec![tx],
+            signatures: vec![],
+        };
+        block.header.merkle_root = block.calculate_merkle_root();
+        block
+    }
+
+    fn encode_proposal(block: Block) -> Vec<u8> {
+        let message = NetworkMessage::new(
+            "validator-1".to_string(),
+            MessagePayload::Consensus(ConsensusMessage::Propose { block, round: 0, view: 0 }),
+        );
+        bincode::serialize(&message).unwrap()
+    }
+
+    #[test]
+    fn test_valid_block_message_accepted() {
+        let handler = GossipHandler::new();
+        let data = encode_proposal(create_test_block());
+
+        let message = handler.check_message(&data).unwrap();
+        assert!(matches!(message.payload, MessagePayload::Consensus(ConsensusMessage::Propose { .. })));
+    }
+
+    #[test]
+    fn test_invalid_block_message_rejected() {
+        let handler = GossipHandler::new();
+
+        // Tampered merkle root must not be relayed
+        let mut block = create_test_block();
+        block.header.merkle_root = [9; 32];
+        let data = encode_proposal(block);
+
+        assert_eq!(handler.check_message(&data).unwrap_err(), Misbehavior::InvalidBlock);
+        assert_eq!(handler.check_message(b"garbage").unwrap_err(), Misbehavior::MalformedMessage);
+    }
+
+    #[test]
+    fn test_oversized_message_rejected() {
+        let data = encode_proposal(create_test_block());
+        let handler = GossipHandler::new().with_max_message_size(data.len() as u64 / 2);
+
+        assert_eq!(handler.check_message(&data).unwrap_err(), Misbehavior::MalformedMessage);
+    }
+
+    #[test]
+    fn test_signed_message_accepted_and_spoofed_rejected() {
+        let validator_key = SigningKey::from_bytes(&[7u8; 32]);
+        let attacker_key = SigningKey::from_bytes(&[8u8; 32]);
+        let keys = HashMap::from([("validator-1".to_string(), validator_key.verifying_key().to_bytes())]);
+        let mut handler = GossipHandler::new().with_peer_keys(keys);
+        let validator_peer = PeerId::random();
+
+        let proposal = || NetworkMessage::new(
+            "validator-1".to_string(),
+            MessagePayload::Consensus(ConsensusMessage::Propose { block: create_test_block(), round: 0, view: 0 }),
+        );
+
+        let mut signed = proposal();
+        signed.sign(&validator_key);
+        assert!(handler.check_sender(&signed, Some(validator_peer), Some(validator_peer)).is_ok());
+
+        // Unsigned, signed by another key, or tampered after signing
+        assert_eq!(handler.check_sender(&proposal(), None, None), Err(Misbehavior::InvalidSignature));
+        let mut spoofed = proposal();
+        spoofed.sign(&attacker_key);
+        assert_eq!(handler.check_sender(&spoofed, None, None), Err(Misbehavior::InvalidSignature));
+        let mut tampered = signed.clone();
+        tampered.timestamp += 1;
+        assert_eq!(handler.check_sender(&tampered, None, None), Err(Misbehavior::InvalidSignature));
+
+        // A valid signature replayed under another peer's authorship
+        assert_eq!(
+            handler.check_sender(&signed, Some(PeerId::random()), Some(validator_peer)),
+            Err(Misbehavior::SpoofedSender)
+        );
+
+        // Consensus traffic from nodes without a known key is refused
+        let unknown = NetworkMessage::new(
+            "validator-9".to_string(),
+            MessagePayload::Consensus(ConsensusMessage::Propose { block: create_test_block(), round: 0, view: 0 }),
+        );
+        assert_eq!(handler.check_sender(&unknown, None, None), Err(Misbehavior::InvalidSignature));
+        let heartbeat = NetworkMessage::new("full-node".to_string(), MessagePayload::Heartbeat);
+        assert!(handler.check_sender(&heartbeat, None, None).is_ok());
+
+        // Until governance adds its key
+        let added_key = SigningKey::from_bytes(&[9u8; 32]);
+        assert!(handler.missing_peer_key(&"validator-9".to_string()));
+        handler.add_peer_key("validator-9".to_string(), added_key.verifying_key().to_bytes());
+        assert!(!handler.missing_peer_key(&"validator-9".to_string()));
+        let mut added = unknown.clone();
+        added.sign(&added_key);
+        assert!(handler.check_sender(&added, None, None).is_ok());
+    }
+
+    #[test]
+    fn test_unsigned_or_oversized_peer_discovery_rejected() {
+        let validator_key = SigningKey::from_bytes(&[7u8; 32]);
+        let keys = HashMap::from([("validator-1".to_string(), validator_key.verifying_key().to_bytes())]);
+        let handler = GossipHandler::new().with_peer_keys(keys);
+
+        let discovery = |sender: &str, count: usize| NetworkMessage::new(
+            sender.to_string(),
+            MessagePayload::PeerDiscovery {
+                peers: (0..count)
+                    .map(|i| PeerInfo {
+                        node_id: PeerId::random().to_string(),
+                        multiaddr: format!("/ip4/10.0.0.{}/tcp/30333", i % 250),
+                        is_validator: true,
+                        last_seen: 0,
+                    })
+                    .collect(),
+            },
+        );
+
+        let mut signed = discovery("validator-1", MAX_DISCOVERY_PEERS);
+        signed.sign(&validator_key);
+        assert!(handler.check_sender(&signed, None, None).is_ok());
+        assert!(handler.check_payload(&signed).is_ok());
+
+        // Unsigned, or from a node whose key we can't check
+        assert_eq!(
+            handler.check_sender(&discovery("validator-1", 1), None, None),
+            Err(Misbehavior::InvalidSignature)
+        );
+        let mut unknown = discovery("full-node", 1);
+        unknown.sign(&SigningKey::from_bytes(&[8u8; 32]));
+        assert_eq!(handler.check_sender(&unknown, None, None), Err(Misbehavior::InvalidSignature));
+
+        // One peer over the cap rejects the whole message, signed or not
+        let mut oversized = discovery("validator-1", MAX_DISCOVERY_PEERS + 1);
+        oversized.sign(&validator_key);
+        let data = bincode::serialize(&oversized).unwrap();
+        assert_eq!(handler.check_message(&data).unwrap_err(), Misbehavior::OversizedPeerList);
+    }
+}
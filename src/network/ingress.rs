@@ -0,0 +1,256 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use anyhow::{Result, anyhow};
+use prometheus::IntCounter;
+use tokio::sync::{mpsc, Mutex};
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+use crate::mempool::Mempool;
+use crate::shutdown::Shutdown;
+use crate::types::Transaction;
+
+/// Transaction ingress queue configuration
+#[derive(Debug, Clone)]
+pub struct IngressConfig {
+    /// Transactions buffered before new arrivals are dropped
+    pub capacity: usize,
+    /// Workers validating and admitting queued transactions
+    pub workers: usize,
+}
+
+impl Default for IngressConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 10_000,
+            workers: 4,
+        }
+    }
+}
+
+/// Ingress queue statistics
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct IngressStats {
+    pub queued: u64,
+    pub admitted: u64,
+    pub rejected: u64,
+    pub dropped: u64,
+}
+
+#[derive(Debug, Default)]
+struct IngressCounters {
+    queued: AtomicU64,
+    admitted: AtomicU64,
+    rejected: AtomicU64,
+    dropped: AtomicU64,
+}
+
+/// Bounded queue between the network and the mempool
+///
+/// Pushing never blocks, so a burst of gossiped transactions can't stall message
+/// processing; the worker pool absorbs the validation cost instead.
+#[derive(Clone)]
+pub struct IngressQueue {
+    config: IngressConfig,
+    mempool: Arc<Mempool>,
+    sender: mpsc::Sender<Transaction>,
+    receiver: Arc<StdMutex<Option<mpsc::Receiver<Transaction>>>>,
+    counters: Arc<IngressCounters>,
+    dropped_metric: Option<IntCounter>,
+}
+
+impl IngressQueue {
+    pub fn new(config: IngressConfig, mempool: Arc<Mempool>) -> Self {
+        let (sender, receiver) = mpsc::channel(config.capacity.max(1));
+
+        Self {
+            config,
+            mempool,
+            sender,
+            receiver: Arc::new(StdMutex::new(Some(receiver))),
+            counters: Arc::new(IngressCounters::default()),
+            dropped_metric: None,
+        }
+    }
+
+    /// Also count overflow drops in a Prometheus counter
+    pub fn with_drop_metric(mut self, counter: IntCounter) -> Self {
+        self.dropped_metric = Some(counter);
+        self
+    }
+
+    /// Spawn the worker pool draining the queue into the mempool until `shutdown`
+    pub fn start(&self, shutdown: Shutdown) -> Result<Vec<JoinHandle<()>>> {
+        let receiver = self.receiver.lock().unwrap().take()
+            .ok_or_else(|| anyhow!("Ingress queue already started"))?;
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        let mut workers = Vec::new();
+        for _ in 0..self.config.workers.max(1) {
+            let queue = self.clone();
+            let receiver = receiver.clone();
+            let mut shutdown = shutdown.clone();
+
+            workers.push(tokio::spawn(async move {
+                loop {
+                    // Only one worker waits on the channel at a time
+                    let tx = tokio::select! {
+                        _ = shutdown.wait() => break,
+                        tx = async { receiver.lock().await.recv().await } => match tx {
+                            Some(tx) => tx,
+                            None => break,
+                        },
+                    };
+
+                    // Signature checks are CPU-bound; keep them off the async workers
+                    let mempool = queue.mempool.clone();
+                    match tokio::task::spawn_blocking(move || mempool.add_transaction(tx)).await {
+                        Ok(Ok(true)) => {
+                            queue.counters.admitted.fetch_add(1, Ordering::Relaxed);
+                        }
+                        Ok(Ok(false)) => {
+                            queue.counters.rejected.fetch_add(1, Ordering::Relaxed);
+                        }
+                        Ok(Err(e)) => {
+                            queue.counters.rejected.fetch_add(1, Ordering::Relaxed);
+                            warn!("Failed to admit queued transaction: {}", e);
+                        }
+                        Err(e) => warn!("Ingress worker task failed: {}", e),
+                    }
+                }
+            }));
+        }
+
+        Ok(workers)
+    }
+
+    /// Queue a transaction for admission, dropping it if the queue is full
+    pub fn push(&self, tx: Transaction) -> bool {
+        match self.sender.try_send(tx) {
+            Ok(()) => {
+                self.counters.queued.fetch_add(1, Ordering::Relaxed);
+                true
+            }
+            Err(e) => {
+                debug!("Dropping transaction at ingress: {}", e);
+                self.counters.dropped.fetch_add(1, Ordering::Relaxed);
+                if let Some(counter) = &self.dropped_metric {
+                    counter.inc();
+                }
+                false
+            }
+        }
+    }
+
+    /// Number of transactions waiting for a worker
+    pub fn len(&self) -> usize {
+        self.sender.max_capacity() - self.sender.capacity()
+    }
+
+    /// Check whether no transactions are waiting
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get ingress statistics
+    pub fn get_stats(&self) -> IngressStats {
+        IngressStats {
+            queued: self.counters.queued.load(Ordering::Relaxed),
+            admitted: self.counters.admitted.load(Ordering::Relaxed),
+            rejected: self.counters.rejected.load(Ordering::Relaxed),
+            dropped: self.counters.dropped.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+    use ed25519_dalek::SigningKey;
+    use uuid::Uuid;
+    use crate::mempool::MempoolConfig;
+    use crate::network::MessageRouter;
+    use crate::types::{address_from_public_key, MessagePayload, NetworkMessage};
+
+    fn create_test_transaction(seed: u16) -> Transaction {
+        let mut secret = [1u8; 32];
+        secret[..2].copy_from_slice(&seed.to_le_bytes());
+        let signing_key = SigningKey::from_bytes(&secret);
+
+        let mut tx = Transaction {
+            id: Uuid::new_v4(),
+            from: address_from_public_key(&signing_key.verifying_key().to_bytes()),
+            to: [2u8; 20],
+            amount: 1000,
+            fee: 10,
+            nonce: 1,
+            timestamp: 1_000,
+            signature: [0u8; 64],
+            public_key: [0u8; 32],
+            data: vec![],
+        };
+        tx.sign(&signing_key);
+        tx
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_burst_is_queued_without_blocking_routing() {
+        let mempool = Arc::new(Mempool::new(MempoolConfig::default()));
+        let ingress = IngressQueue::new(IngressConfig { capacity: 1000, workers: 2 }, mempool.clone());
+        let (consensus_sender, mut consensus_receiver) = mpsc::unbounded_channel();
+        let router = MessageRouter::new(consensus_sender, mempool.clone()).with_ingress(ingress.clone());
+        ingress.start(Shutdown::never()).unwrap();
+
+        let burst: Vec<Transaction> = (0..500).map(create_test_transaction).collect();
+
+        // Routing the whole burst only enqueues, so it finishes well before validation could
+        let started = Instant::now();
+        for tx in &burst {
+            router.route(NetworkMessage::new("peer".to_string(), MessagePayload::Transaction(tx.clone())));
+        }
+        router.route(NetworkMessage::new("peer".to_string(), MessagePayload::Heartbeat));
+        let routing_time = started.elapsed();
+
+        assert_eq!(ingress.get_stats().queued, 500);
+        assert_eq!(ingress.get_stats().dropped, 0);
+        assert!(mempool.size() < burst.len());
+        assert!(routing_time < Duration::from_secs(1));
+        assert!(consensus_receiver.try_recv().is_err());
+
+        // The workers drain the queue in the background
+        let deadline = Instant::now() + Duration::from_secs(30);
+        while ingress.get_stats().admitted < 500 {
+            assert!(Instant::now() < deadline, "ingress workers did not drain the queue");
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(ingress.is_empty());
+        assert!(burst.iter().all(|tx| mempool.contains(&tx.id)));
+    }
+
+    #[tokio::test]
+    async fn test_overflow_is_dropped_and_counted() {
+        let mempool = Arc::new(Mempool::new(MempoolConfig::default()));
+        let counter = IntCounter::new("test_ingress_dropped_total", "dropped").unwrap();
+        let ingress = IngressQueue::new(IngressConfig { capacity: 3, workers: 1 }, mempool.clone())
+            .with_drop_metric(counter.clone());
+
+        // Not started, so nothing drains
+        for seed in 0..5 {
+            ingress.push(create_test_transaction(seed));
+        }
+
+        assert_eq!(ingress.len(), 3);
+        assert_eq!(ingress.get_stats().queued, 3);
+        assert_eq!(ingress.get_stats().dropped, 2);
+        assert_eq!(counter.get(), 2);
+
+        ingress.start(Shutdown::never()).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while ingress.get_stats().admitted < 3 {
+            assert!(Instant::now() < deadline, "ingress worker did not drain the queue");
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(mempool.size(), 3);
+    }
+}
@@ -0,0 +1,130 @@
+use libp2p::{multiaddr::Protocol, Multiaddr, PeerId};
+use std::collections::HashMap;
+use std::fmt;
+use std::net::IpAddr;
+
+/// Reason an inbound connection was refused
+#[derive(Debug, Clone, PartialEq)]
+pub enum LimitExceeded {
+    /// Already connected to `max_peers` peers
+    MaxPeers { max_peers: usize },
+    /// The remote host already has `max_per_ip` peers connected
+    MaxPeersPerIp { ip: IpAddr, max_per_ip: usize },
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LimitExceeded::MaxPeers { max_peers } => {
+                write!(f, "already connected to the maximum of {} peers", max_peers)
+            }
+            LimitExceeded::MaxPeersPerIp { ip, max_per_ip } => {
+                write!(f, "{} already has the maximum of {} peers connected", ip, max_per_ip)
+            }
+        }
+    }
+}
+
+/// Caps on how many peers we accept in total and from a single host
+///
+/// Existing peers always win: a limit only ever refuses the newcomer.
+#[derive(Debug, Clone)]
+pub struct ConnectionLimits {
+    max_peers: usize,
+    max_peers_per_ip: usize,
+}
+
+impl ConnectionLimits {
+    pub fn new(max_peers: usize, max_peers_per_ip: usize) -> Self {
+        Self {
+            max_peers,
+            max_peers_per_ip,
+        }
+    }
+
+    /// Decide whether a new inbound peer at `address` fits alongside the `connected` peers
+    ///
+    /// Another connection from a peer that is already connected is always allowed.
+    pub fn check_inbound(
+        &self,
+        peer_id: &PeerId,
+        address: &Multiaddr,
+        connected: &HashMap<PeerId, Multiaddr>,
+    ) -> Result<(), LimitExceeded> {
+        if connected.contains_key(peer_id) {
+            return Ok(());
+        }
+
+        if connected.len() >= self.max_peers {
+            return Err(LimitExceeded::MaxPeers { max_peers: self.max_peers });
+        }
+
+        if let Some(ip) = ip_of(address) {
+            let from_ip = connected.values().filter(|addr| ip_of(addr) == Some(ip)).count();
+            if from_ip >= self.max_peers_per_ip {
+                return Err(LimitExceeded::MaxPeersPerIp { ip, max_per_ip: self.max_peers_per_ip });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The IP address a multiaddress points at, if any
+fn ip_of(address: &Multiaddr) -> Option<IpAddr> {
+    address.iter().find_map(|protocol| match protocol {
+        Protocol::Ip4(ip) => Some(IpAddr::V4(ip)),
+        Protocol::Ip6(ip) => Some(IpAddr::V6(ip)),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn address(host: &str, port: u16) -> Multiaddr {
+        format!("/ip4/{}/tcp/{}", host, port).parse().unwrap()
+    }
+
+    #[test]
+    fn test_connections_beyond_max_peers_are_refused() {
+        let limits = ConnectionLimits::new(3, 10);
+        let mut connected = HashMap::new();
+
+        for i in 0..3 {
+            let peer = PeerId::random();
+            let addr = address(&format!("10.0.0.{}", i + 1), 9000);
+            assert!(limits.check_inbound(&peer, &addr, &connected).is_ok());
+            connected.insert(peer, addr);
+        }
+
+        assert_eq!(
+            limits.check_inbound(&PeerId::random(), &address("10.0.0.9", 9000), &connected),
+            Err(LimitExceeded::MaxPeers { max_peers: 3 })
+        );
+
+        // A second connection from a peer we already keep is fine
+        let (existing, addr) = connected.iter().next().map(|(p, a)| (*p, a.clone())).unwrap();
+        assert!(limits.check_inbound(&existing, &addr, &connected).is_ok());
+    }
+
+    #[test]
+    fn test_per_ip_cap() {
+        let limits = ConnectionLimits::new(100, 2);
+        let mut connected = HashMap::new();
+
+        for port in [9000, 9001] {
+            let peer = PeerId::random();
+            let addr = address("10.0.0.1", port);
+            assert!(limits.check_inbound(&peer, &addr, &connected).is_ok());
+            connected.insert(peer, addr);
+        }
+
+        assert!(matches!(
+            limits.check_inbound(&PeerId::random(), &address("10.0.0.1", 9002), &connected),
+            Err(LimitExceeded::MaxPeersPerIp { max_per_ip: 2, .. })
+        ));
+        assert!(limits.check_inbound(&PeerId::random(), &address("10.0.0.2", 9000), &connected).is_ok());
+    }
+}
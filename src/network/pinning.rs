@@ -0,0 +1,186 @@
+use anyhow::{Result, anyhow};
+use libp2p::{Multiaddr, PeerId};
+use std::collections::HashMap;
+use std::fmt;
+use crate::types::NodeId;
+
+use super::transport::extract_peer_id;
+
+/// Identify agent version prefix; the rest is the node id
+const AGENT_PREFIX: &str = "blockchain-node/";
+
+/// Expected identity and addresses of a pinned validator
+#[derive(Debug, Clone, PartialEq)]
+pub struct PeerPin {
+    pub peer_id: PeerId,
+    pub addresses: Vec<Multiaddr>,
+}
+
+/// Reason a connection was refused by the pinning policy
+#[derive(Debug, Clone, PartialEq)]
+pub enum PinViolation {
+    /// A pinned identity connected from an address it isn't pinned to
+    UnexpectedAddress { node_id: NodeId, address: Multiaddr },
+    /// A pinned validator id was claimed by a different peer id
+    UnexpectedPeerId { node_id: NodeId, expected: PeerId, actual: PeerId },
+}
+
+impl fmt::Display for PinViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PinViolation::UnexpectedAddress { node_id, address } => {
+                write!(f, "validator {} connected from unpinned address {}", node_id, address)
+            }
+            PinViolation::UnexpectedPeerId { node_id, expected, actual } => {
+                write!(f, "validator {} claimed by peer {} (pinned to {})", node_id, actual, expected)
+            }
+        }
+    }
+}
+
+/// Pins validator node ids to expected peer ids and addresses
+#[derive(Debug, Clone, Default)]
+pub struct PeerPinning {
+    pins: HashMap<NodeId, PeerPin>,
+    allow_identity_rotation: bool,
+}
+
+impl PeerPinning {
+    pub fn new(pins: HashMap<NodeId, PeerPin>, allow_identity_rotation: bool) -> Self {
+        Self {
+            pins,
+            allow_identity_rotation,
+        }
+    }
+
+    /// Parse `node_id=/ip4/.../tcp/.../p2p/<peer id>` entries; repeat a node id to pin several addresses
+    pub fn parse_pins(entries: &[String]) -> Result<HashMap<NodeId, PeerPin>> {
+        let mut pins: HashMap<NodeId, PeerPin> = HashMap::new();
+
+        for entry in entries {
+            let (node_id, addr) = entry.split_once('=')
+                .ok_or_else(|| anyhow!("Invalid peer pin '{}': expected node_id=multiaddr", entry))?;
+            let addr: Multiaddr = addr.parse()
+                .map_err(|e| anyhow!("Invalid peer pin address '{}': {}", addr, e))?;
+            let peer_id = extract_peer_id(&addr)
+                .ok_or_else(|| anyhow!("Peer pin '{}' has no /p2p peer id", entry))?;
+
+            match pins.get_mut(node_id) {
+                Some(pin) if pin.peer_id != peer_id => {
+                    return Err(anyhow!("Validator {} is pinned to more than one peer id", node_id));
+                }
+                Some(pin) => pin.addresses.push(addr),
+                None => {
+                    pins.insert(node_id.to_string(), PeerPin { peer_id, addresses: vec![addr] });
+                }
+            }
+        }
+
+        Ok(pins)
+    }
+
+    /// Check a peer that has identified itself as the given validator
+    pub fn check_identity(&self, node_id: &NodeId, peer_id: &PeerId, address: &Multiaddr) -> Result<(), PinViolation> {
+        let pin = match self.pins.get(node_id) {
+            Some(pin) => pin,
+            None => return Ok(()),
+        };
+
+        if !self.allow_identity_rotation && pin.peer_id != *peer_id {
+            return Err(PinViolation::UnexpectedPeerId {
+                node_id: node_id.clone(),
+                expected: pin.peer_id,
+                actual: *peer_id,
+            });
+        }
+
+        Self::check_address(node_id, pin, address)
+    }
+
+    /// Check a new connection from a pinned peer id before it has identified itself
+    pub fn check_connection(&self, peer_id: &PeerId, address: &Multiaddr) -> Result<(), PinViolation> {
+        match self.pins.iter().find(|(_, pin)| pin.peer_id == *peer_id) {
+            Some((node_id, pin)) => Self::check_address(node_id, pin, address),
+            None => Ok(()),
+        }
+    }
+
+    /// Only the host is compared, since inbound connections arrive from ephemeral ports
+    fn check_address(node_id: &NodeId, pin: &PeerPin, address: &Multiaddr) -> Result<(), PinViolation> {
+        let host = address.iter().next();
+        if pin.addresses.iter().any(|pinned| pinned.iter().next() == host && host.is_some()) {
+            return Ok(());
+        }
+
+        Err(PinViolation::UnexpectedAddress {
+            node_id: node_id.clone(),
+            address: address.clone(),
+        })
+    }
+}
+
+/// Identify agent version announcing a node id
+pub fn agent_version(node_id: &NodeId) -> String {
+    format!("{}{}", AGENT_PREFIX, node_id)
+}
+
+/// Node id announced in an identify agent version, if any
+pub fn node_id_from_agent_version(agent_version: &str) -> Option<NodeId> {
+    agent_version.strip_prefix(AGENT_PREFIX).map(|node_id| node_id.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pinned_validator() -> (PeerId, PeerPinning) {
+        let peer_id = PeerId::random();
+        let entry = format!("validator-1=/ip4/10.0.0.1/tcp/9000/p2p/{}", peer_id);
+        let pins = PeerPinning::parse_pins(&[entry]).unwrap();
+        (peer_id, PeerPinning::new(pins, false))
+    }
+
+    #[test]
+    fn test_pinned_identity_from_unpinned_address_is_rejected() {
+        let (peer_id, pinning) = pinned_validator();
+        let validator = "validator-1".to_string();
+
+        // Inbound connections use an ephemeral port, so only the host must match
+        let expected: Multiaddr = "/ip4/10.0.0.1/tcp/53124".parse().unwrap();
+        assert!(pinning.check_identity(&validator, &peer_id, &expected).is_ok());
+        assert!(pinning.check_connection(&peer_id, &expected).is_ok());
+
+        let unexpected: Multiaddr = "/ip4/10.0.0.66/tcp/9000".parse().unwrap();
+        assert_eq!(
+            pinning.check_identity(&validator, &peer_id, &unexpected),
+            Err(PinViolation::UnexpectedAddress { node_id: validator.clone(), address: unexpected.clone() })
+        );
+        assert!(pinning.check_connection(&peer_id, &unexpected).is_err());
+
+        // Unpinned validators are not restricted
+        assert!(pinning.check_identity(&"validator-2".to_string(), &PeerId::random(), &unexpected).is_ok());
+    }
+
+    #[test]
+    fn test_identity_rotation_policy() {
+        let (_, pinning) = pinned_validator();
+        let validator = "validator-1".to_string();
+        let rotated = PeerId::random();
+        let address: Multiaddr = "/ip4/10.0.0.1/tcp/9000".parse().unwrap();
+
+        assert!(matches!(
+            pinning.check_identity(&validator, &rotated, &address),
+            Err(PinViolation::UnexpectedPeerId { .. })
+        ));
+
+        let permissive = PeerPinning::new(pinning.pins.clone(), true);
+        assert!(permissive.check_identity(&validator, &rotated, &address).is_ok());
+    }
+
+    #[test]
+    fn test_agent_version_round_trip() {
+        let node_id = "validator-3".to_string();
+        assert_eq!(node_id_from_agent_version(&agent_version(&node_id)), Some(node_id));
+        assert_eq!(node_id_from_agent_version("rust-libp2p/0.44"), None);
+    }
+}
@@ -0,0 +1,248 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tracing::{debug, warn};
+
+use crate::mempool::Mempool;
+use super::ingress::IngressQueue;
+use crate::sync::SyncManager;
+use crate::types::{ConsensusMessage, MessagePayload, NetworkMessage, NodeId, Transaction};
+
+/// Routes inbound network messages to the components that consume them
+#[derive(Clone)]
+pub struct MessageRouter {
+    consensus_sender: mpsc::UnboundedSender<NetworkMessage>,
+    mempool: Arc<Mempool>,
+    sync: Option<SyncManager>,
+    ingress: Option<IngressQueue>,
+}
+
+impl MessageRouter {
+    pub fn new(consensus_sender: mpsc::UnboundedSender<NetworkMessage>, mempool: Arc<Mempool>) -> Self {
+        Self {
+            consensus_sender,
+            mempool,
+            sync: None,
+            ingress: None,
+        }
+    }
+
+    /// Answer block requests and catch up when peers are ahead
+    pub fn with_sync(mut self, sync: SyncManager) -> Self {
+        self.sync = Some(sync);
+        self
+    }
+
+    /// Queue gossiped transactions for the ingress workers instead of admitting them inline
+    pub fn with_ingress(mut self, ingress: IngressQueue) -> Self {
+        self.ingress = Some(ingress);
+        self
+    }
+
+    /// Deliver a message to consensus or the mempool based on its payload
+    pub fn route(&self, message: NetworkMessage) {
+        match &message.payload {
+            MessagePayload::Consensus(consensus_message) => {
+                // A proposal beyond our next height means the proposer has blocks we lack
+                if let (Some(sync), ConsensusMessage::Propose { block, .. }) = (&self.sync, consensus_message) {
+                    if let Some(peer_height) = block.header.height.checked_sub(1) {
+                        if let Err(e) = sync.observe_peer_height(&message.sender, peer_height) {
+                            warn!("Failed to start block sync: {}", e);
+                        }
+                    }
+                }
+
+                debug!("Routing consensus message from {}", message.sender);
+                if let Err(e) = self.consensus_sender.send(message) {
+                    warn!("Consensus engine is not accepting messages: {}", e);
+                }
+            }
+            MessagePayload::Transaction(tx) => self.admit(tx, &message.sender),
+            MessagePayload::TransactionBatch(transactions) => {
+                debug!("Routing batch of {} transactions from {}", transactions.len(), message.sender);
+                let mut seen = HashSet::new();
+                for tx in transactions {
+                    if seen.insert(tx.hash()) {
+                        self.admit(tx, &message.sender);
+                    }
+                }
+            }
+            MessagePayload::BlockRequest { .. } | MessagePayload::BlockResponse { .. } => {
+                match &self.sync {
+                    Some(sync) => {
+                        if let Err(e) = sync.handle_message(&message) {
+                            warn!("Failed to handle block sync message from {}: {}", message.sender, e);
+                        }
+                    }
+                    None => debug!("Ignoring block sync message from {}", message.sender),
+                }
+            }
+            // The network event loop dials the listed peers, since it owns the swarm
+            MessagePayload::PeerDiscovery { peers } => {
+                debug!("Received {} peers in discovery from {}", peers.len(), message.sender);
+            }
+            MessagePayload::Heartbeat => {}
+        }
+    }
+
+    /// Hand a gossiped transaction to the ingress queue, or the mempool without one
+    fn admit(&self, tx: &Transaction, sender: &NodeId) {
+        if let Some(ingress) = &self.ingress {
+            if !ingress.push(tx.clone()) {
+                debug!("Ingress queue full, dropped transaction {} from {}", tx.id, sender);
+            }
+            return;
+        }
+
+        match self.mempool.add_transaction(tx.clone()) {
+            Ok(true) => debug!("Added gossiped transaction {} from {}", tx.id, sender),
+            Ok(false) => debug!("Mempool rejected gossiped transaction {}", tx.id),
+            Err(e) => warn!("Failed to add gossiped transaction {}: {}", tx.id, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use ed25519_dalek::SigningKey;
+    use tempfile::TempDir;
+    use uuid::Uuid;
+    use crate::consensus::{ConsensusConfig, ConsensusEngine, ConsensusState, ViewChangeTimeout};
+    use crate::mempool::MempoolConfig;
+    use crate::storage::Storage;
+    use crate::types::{
+        address_from_public_key, Block, BlockHeader, ConsensusMessage, Transaction,
+    };
+
+    fn create_test_transaction() -> Transaction {
+        let signing_key = SigningKey::from_bytes(&[1u8; 32]);
+        let mut tx = Transaction {
+            id: Uuid::new_v4(),
+            from: address_from_public_key(&signing_key.verifying_key().to_bytes()),
+            to: [2u8; 20],
+            amount: 1000,
+            fee: 10,
+            nonce: 1,
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_millis() as u64,
+            signature: [0u8; 64],
+            public_key: [0u8; 32],
+            data: vec![],
+        };
+        tx.sign(&signing_key);
+        tx
+    }
+
+    fn create_test_engine(storage: Arc<Storage>, mempool: Arc<Mempool>) -> Arc<ConsensusEngine> {
+        let config = ConsensusConfig {
+            node_id: "validator-2".to_string(),
+            is_validator: false,
+            validator_set: vec![
+                "validator-1".to_string(),
+                "validator-2".to_string(),
+                "validator-3".to_string(),
+                "validator-4".to_string(),
+            ],
+            block_time_ms: 1000,
+            view_timeout: ViewChangeTimeout::default(),
+            max_block_size: 1024 * 1024,
+            max_transactions_per_block: 100,
+            leader_shuffle_epoch_length: 0,
+            dynamic_block_size: None,
+            block_interval: None,
+            backup_proposer_timeout_ms: None,
+            empty_block_timeout_ms: None,
+            min_transactions_per_block: None,
+            pbft_log_retention: 100,
+            dev_mode: false,
+            vrf_leader_election: false,
+        };
+
+        Arc::new(ConsensusEngine::new(config, storage, mempool).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_consensus_message_reaches_engine() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Arc::new(Storage::new(temp_dir.path()).unwrap());
+        let genesis = Block {
+            header: BlockHeader {
+                height: 0,
+                previous_hash: [0; 32],
+                merkle_root: [0; 32],
+                state_root: [0; 32],
+                timestamp: 0,
+                proposer: "genesis".to_string(),
+                round: 0,
+                view: 0,
+                vrf_proof: None,
+            },
+            transactions: vec![],
+            signatures: vec![],
+        };
+        storage.store_block(&genesis).unwrap();
+        let tx = create_test_transaction();
+        storage.state().set_balance(&tx.from, 1_000_000).unwrap();
+
+        let mempool = Arc::new(Mempool::new(MempoolConfig::default()));
+        let engine = create_test_engine(storage, mempool.clone());
+
+        let router = MessageRouter::new(engine.get_message_sender(), mempool);
+
+        let running = engine.clone();
+        tokio::spawn(async move {
+            let _ = running.start().await;
+        });
+
+        let mut block = Block {
+            header: BlockHeader {
+                height: 1,
+                previous_hash: genesis.hash(),
+                merkle_root: [0; 32],
+                state_root: [0; 32],
+                timestamp: 1_000,
+                proposer: "validator-1".to_string(),
+                round: 0,
+                view: 0,
+                vrf_proof: None,
+            },
+            transactions: vec![tx],
+            signatures: vec![],
+        };
+        block.header.merkle_root = block.calculate_merkle_root();
+
+        router.route(NetworkMessage::new(
+            "validator-1".to_string(),
+            MessagePayload::Consensus(ConsensusMessage::Propose { block, round: 0, view: 0 }),
+        ));
+
+        let mut state = engine.get_state();
+        for _ in 0..50 {
+            if state == ConsensusState::Preparing {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            state = engine.get_state();
+        }
+        assert_eq!(state, ConsensusState::Preparing);
+    }
+
+    #[test]
+    fn test_transaction_reaches_mempool() {
+        let mempool = Arc::new(Mempool::new(MempoolConfig::default()));
+        let (consensus_sender, _consensus_receiver) = mpsc::unbounded_channel();
+        let router = MessageRouter::new(consensus_sender, mempool.clone());
+
+        let tx = create_test_transaction();
+        router.route(NetworkMessage::new(
+            "peer".to_string(),
+            MessagePayload::Transaction(tx.clone()),
+        ));
+
+        assert!(mempool.contains(&tx.id));
+    }
+}
@@ -0,0 +1,199 @@
+use libp2p::PeerId;
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Something a peer sent that counts against its score
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Misbehavior {
+    /// A message that could not be deserialized
+    MalformedMessage,
+    /// A block failing structural or signature checks
+    InvalidBlock,
+    /// A transaction with a bad signature
+    InvalidTransaction,
+    /// A message id we already received
+    DuplicateGossip,
+    /// A message whose signature doesn't match its claimed sender
+    InvalidSignature,
+    /// A message claiming a sender other than the peer that published it
+    SpoofedSender,
+    /// A peer discovery message listing more peers than one message may carry
+    OversizedPeerList,
+}
+
+impl Misbehavior {
+    /// Score deducted for one occurrence
+    pub fn penalty(&self) -> i64 {
+        match self {
+            Misbehavior::MalformedMessage => 20,
+            Misbehavior::InvalidBlock => 50,
+            Misbehavior::InvalidTransaction => 20,
+            Misbehavior::DuplicateGossip => 5,
+            Misbehavior::InvalidSignature => 50,
+            Misbehavior::SpoofedSender => 50,
+            Misbehavior::OversizedPeerList => 20,
+        }
+    }
+}
+
+impl fmt::Display for Misbehavior {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Misbehavior::MalformedMessage => write!(f, "malformed message"),
+            Misbehavior::InvalidBlock => write!(f, "invalid block"),
+            Misbehavior::InvalidTransaction => write!(f, "invalid transaction"),
+            Misbehavior::DuplicateGossip => write!(f, "duplicate gossip"),
+            Misbehavior::InvalidSignature => write!(f, "message with an invalid signature"),
+            Misbehavior::SpoofedSender => write!(f, "message with a spoofed sender"),
+            Misbehavior::OversizedPeerList => write!(f, "peer discovery over the peer limit"),
+        }
+    }
+}
+
+/// Peer scoring configuration
+#[derive(Debug, Clone)]
+pub struct ScoringConfig {
+    /// Peers whose score falls to or below this are banned
+    pub ban_threshold: i64,
+    /// How long an automatic ban lasts
+    pub ban_duration: Duration,
+}
+
+impl Default for ScoringConfig {
+    fn default() -> Self {
+        Self {
+            ban_threshold: -100,
+            ban_duration: Duration::from_secs(3600),
+        }
+    }
+}
+
+/// Reputation of connected peers and the peers currently banned
+///
+/// Every peer starts at zero; misbehavior lowers the score, and crossing the
+/// threshold bans the peer and resets its score for when the ban lifts.
+#[derive(Debug, Default)]
+pub struct PeerScore {
+    config: ScoringConfig,
+    scores: HashMap<PeerId, i64>,
+    banned: HashMap<PeerId, Instant>,
+}
+
+impl PeerScore {
+    pub fn new(config: ScoringConfig) -> Self {
+        Self {
+            config,
+            scores: HashMap::new(),
+            banned: HashMap::new(),
+        }
+    }
+
+    /// Deduct a misbehavior from the peer's score, returning true if that bans it
+    pub fn record(&mut self, peer: &PeerId, misbehavior: Misbehavior) -> bool {
+        let score = self.scores.entry(*peer).or_insert(0);
+        *score -= misbehavior.penalty();
+
+        if *score > self.config.ban_threshold || self.is_banned(peer) {
+            return false;
+        }
+
+        self.ban(peer, self.config.ban_duration);
+        true
+    }
+
+    /// Ban a peer for `duration`, extending any shorter ban already in place
+    pub fn ban(&mut self, peer: &PeerId, duration: Duration) {
+        let until = Instant::now() + duration;
+        let expiry = self.banned.entry(*peer).or_insert(until);
+        *expiry = (*expiry).max(until);
+        self.scores.remove(peer);
+    }
+
+    /// Check whether a peer is banned; expired bans are lifted here
+    pub fn is_banned(&mut self, peer: &PeerId) -> bool {
+        match self.banned.get(peer) {
+            Some(until) if *until > Instant::now() => true,
+            Some(_) => {
+                self.banned.remove(peer);
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// Lift expired bans, returning the peers that are allowed back
+    pub fn expire_bans(&mut self) -> Vec<PeerId> {
+        let now = Instant::now();
+        let expired: Vec<PeerId> = self.banned
+            .iter()
+            .filter(|(_, until)| **until <= now)
+            .map(|(peer, _)| *peer)
+            .collect();
+
+        for peer in &expired {
+            self.banned.remove(peer);
+        }
+        expired
+    }
+
+    /// Currently banned peers
+    pub fn banned_peers(&self) -> Vec<PeerId> {
+        let now = Instant::now();
+        self.banned
+            .iter()
+            .filter(|(_, until)| **until > now)
+            .map(|(peer, _)| *peer)
+            .collect()
+    }
+
+    /// A peer's current score
+    pub fn score(&self, peer: &PeerId) -> i64 {
+        self.scores.get(peer).copied().unwrap_or(0)
+    }
+
+    /// Forget the score of a peer that disconnected; bans outlive the connection
+    pub fn remove(&mut self, peer: &PeerId) {
+        self.scores.remove(peer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repeated_misbehavior_bans_peer() {
+        let mut scores = PeerScore::new(ScoringConfig::default());
+        let peer = PeerId::random();
+
+        assert!(!scores.record(&peer, Misbehavior::MalformedMessage));
+        assert_eq!(scores.score(&peer), -20);
+        assert!(!scores.record(&peer, Misbehavior::DuplicateGossip));
+        assert_eq!(scores.score(&peer), -25);
+        assert!(!scores.is_banned(&peer));
+
+        assert!(!scores.record(&peer, Misbehavior::InvalidBlock));
+        assert!(scores.record(&peer, Misbehavior::InvalidBlock));
+        assert!(scores.is_banned(&peer));
+        assert_eq!(scores.banned_peers(), vec![peer]);
+
+        // Other peers are unaffected
+        assert_eq!(scores.score(&PeerId::random()), 0);
+    }
+
+    #[test]
+    fn test_ban_expires() {
+        let mut scores = PeerScore::new(ScoringConfig::default());
+        let peer = PeerId::random();
+
+        scores.ban(&peer, Duration::ZERO);
+        assert!(scores.banned_peers().is_empty());
+        assert_eq!(scores.expire_bans(), vec![peer]);
+        assert!(!scores.is_banned(&peer));
+
+        scores.ban(&peer, Duration::from_secs(60));
+        scores.ban(&peer, Duration::ZERO);
+        assert!(scores.is_banned(&peer));
+    }
+}
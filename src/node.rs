@@ -1,17 +1,31 @@
 use std::sync::Arc;
 use anyhow::{Result, anyhow};
-use tokio::sync::mpsc;
-use tracing::{info, error, warn};
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+use libp2p::PeerId;
+use tracing::{debug, info, error, warn};
 
 use crate::cli::Cli;
+use crate::crypto::{Ed25519Scheme, SignatureScheme};
 use crate::storage::Storage;
-use crate::mempool::{Mempool, MempoolConfig};
-use crate::consensus::{ConsensusEngine, ConsensusConfig};
-use crate::network::{NetworkManager, NetworkConfig};
-use crate::validation::Validator;
+use crate::mempool::{Mempool, MempoolConfig, ReputationConfig};
+use crate::consensus::{ConsensusEngine, ConsensusConfig, BlockIntervalConfig, ViewChangeTimeout};
+use crate::network::{
+    NetworkManager, NetworkConfig, MessageRouter, PeerPinning, IngressConfig, IngressQueue, MeshConfig, ScoringConfig,
+    BatchConfig, TransactionBatcher,
+};
+use crate::validation::{Validator, DynamicBlockSizeConfig, SenderAllowlist};
 use crate::metrics::MetricsServer;
-use crate::api::JsonRpcServer;
-use crate::types::{NetworkMessage, MessagePayload, NodeState, BlockHeight};
+use crate::api::{JsonRpcServer, RateLimit, RateLimitConfig, RpcTimeouts, to_hex};
+use crate::genesis::Genesis;
+use crate::keys::NodeKey;
+use crate::webhook::{WebhookNotifier, WebhookConfig};
+use crate::sync::{SyncManager, SyncConfig};
+use crate::shutdown::Shutdown;
+use crate::types::{NetworkMessage, MessagePayload, NodeState, BlockHeight, Address, NodeId};
+
+/// How long shutdown waits for tasks to finish before abandoning them
+const SHUTDOWN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
 
 /// Main blockchain node that orchestrates all components
 pub struct BlockchainNode {
@@ -23,6 +37,9 @@ pub struct BlockchainNode {
     consensus: Arc<ConsensusEngine>,
     network: Arc<NetworkManager>,
     validator: Arc<Validator>,
+    sync: SyncManager,
+    ingress: IngressQueue,
+    batcher: TransactionBatcher,
     
     // Services
     metrics_server: Option<MetricsServer>,
@@ -31,9 +48,16 @@ pub struct BlockchainNode {
     // Message channels
     network_receiver: Option<mpsc::UnboundedReceiver<NetworkMessage>>,
     consensus_sender: Option<mpsc::UnboundedSender<NetworkMessage>>,
+    sync_receiver: Option<mpsc::UnboundedReceiver<NetworkMessage>>,
+    gossip_receiver: Option<mpsc::UnboundedReceiver<NetworkMessage>>,
     
     // Node state
     is_running: Arc<std::sync::RwLock<bool>>,
+
+    // Stops every long-running task; their handles are awaited on shutdown
+    shutdown_sender: watch::Sender<bool>,
+    shutdown: Shutdown,
+    tasks: Vec<JoinHandle<()>>,
 }
 
 impl BlockchainNode {
@@ -41,10 +65,61 @@ impl BlockchainNode {
     pub async fn new(config: Cli) -> Result<Self> {
         info!("Initializing blockchain node: {}", config.node_id);
 
+        // Initialize metrics server if enabled
+        let metrics_server = if config.enable_metrics {
+            Some(MetricsServer::new(config.metrics_port)?.with_data_dir(config.db_path.clone()))
+        } else {
+            None
+        };
+
+        // Every component reports into the metrics server's collector
+        let metrics = metrics_server.as_ref().map(|server| server.metrics().clone());
+
         // Initialize storage
-        let storage = Arc::new(Storage::new(&config.db_path)?);
+        let mut storage = Storage::open_with(&config.db_path, &config.storage_config())?
+            .with_archive(config.is_archive())
+            .with_audit_log(config.audit_log);
+        if let Some(metrics) = &metrics {
+            storage = storage.with_metrics(metrics.clone());
+        }
+        if let Some(authority) = Self::parse_governance_authority(&config)? {
+            storage = storage.with_governance_authority(authority);
+            info!("Validator set governance enabled");
+        }
+        let storage = Arc::new(storage);
         info!("Storage initialized at: {:?}", config.db_path);
 
+        let (shutdown_sender, shutdown) = Shutdown::new();
+
+        // Seed the validator set, balances and block 0 from the genesis file
+        let genesis = Genesis::load(&config.genesis_file)?;
+        let genesis_hash = genesis.apply(&storage)?;
+        info!("Loaded genesis for chain {}: {}", genesis.chain_id, to_hex(&genesis_hash));
+
+        // A key file keeps the peer id stable and signs votes and gossip as this node
+        let node_key = config.key_file.as_deref().map(NodeKey::load).transpose()?;
+        let validator_keys = genesis.validator_keys()?;
+        if let Some(key) = &node_key {
+            info!("Loaded node key from {:?}; peer id {}", config.key_file, key.peer_id());
+            if let Some(expected) = validator_keys.get(&config.node_id) {
+                if *expected != key.public_key() {
+                    return Err(anyhow!(
+                        "Key file doesn't match the genesis public key of validator {}",
+                        config.node_id
+                    ));
+                }
+            }
+        }
+
+        // Both mempool admission and block validation enforce the same allowlist
+        let sender_allowlist = SenderAllowlist::from_hex(&config.get_allowed_senders())?;
+        if !sender_allowlist.is_permissionless() {
+            info!("Sender allowlist enabled; chain is permissioned");
+        }
+
+        // Every component checks transaction and vote signatures under the same scheme
+        let signature_scheme: Arc<dyn SignatureScheme> = Arc::new(Ed25519Scheme);
+
         // Initialize mempool
         let mempool_config = MempoolConfig {
             max_size: config.mempool_size,
@@ -53,59 +128,223 @@ impl BlockchainNode {
             max_tx_size: 1024 * 1024,
             cleanup_interval_secs: 60,
             max_age_secs: 3600,
+            sender_allowlist: sender_allowlist.clone(),
+            reputation: config.sender_reputation.then(ReputationConfig::default),
+            replacement_cooldown_ms: config.replacement_cooldown_ms,
+            replacement_fee_bump_percent: config.replacement_fee_bump_percent,
+            persist: config.persist_mempool,
+            committed_filter_capacity: config.committed_filter_capacity,
+            committed_filter_fp_rate: config.committed_filter_fp_rate,
+            ordering: config.tx_ordering,
+            cache_tx_sizes: config.cache_tx_sizes,
         };
-        let mempool = Arc::new(Mempool::new(mempool_config));
+        let mut mempool = Mempool::new(mempool_config)
+            .with_signature_scheme(signature_scheme.clone())
+            .with_state(storage.state().clone())
+            .with_storage(&storage);
+        if let Some(metrics) = &metrics {
+            mempool = mempool.with_metrics(metrics.clone());
+        }
+        let mempool = Arc::new(mempool);
+        let restored = mempool.restore(&storage)?;
+        if restored > 0 {
+            info!("Restored {} pending transactions from previous run", restored);
+        }
         info!("Mempool initialized with max size: {}", config.mempool_size);
 
         // Initialize validator
-        let validator = Arc::new(Validator::new((*storage).clone()));
+        let dynamic_block_size = if config.dynamic_block_size {
+            Some(DynamicBlockSizeConfig::default())
+        } else {
+            None
+        };
+
+        let mut validator = Validator::new((*storage).clone())
+            .with_signature_scheme(signature_scheme.clone())
+            .with_validator_keys(validator_keys.clone())
+            .with_sender_allowlist(sender_allowlist)
+            .with_median_time_past_window(config.median_time_past_window)
+            .with_max_data_bytes(config.max_data_bytes);
+        if let Some(dynamic) = &dynamic_block_size {
+            validator = validator.with_dynamic_block_size(dynamic.clone());
+        }
+        let validator = Arc::new(validator);
+
+        // Dev mode only skips voting when nobody else could vote
+        let commits_alone = config.dev_mode && genesis.validator_set() == [config.node_id.clone()];
+        if config.dev_mode && !commits_alone {
+            warn!("Dev mode needs {} to be the only genesis validator; running normal consensus", config.node_id);
+        }
+
+        // Peers drop unsigned votes, so a validator that has to vote needs its key
+        if config.is_validator() && node_key.is_none() && !commits_alone {
+            return Err(anyhow!("Validator {} needs a key file to sign its consensus votes", config.node_id));
+        }
 
         // Initialize consensus engine
         let consensus_config = ConsensusConfig {
             node_id: config.node_id.clone(),
             is_validator: config.is_validator(),
-            validator_set: Self::parse_validator_set(&config)?,
+            validator_set: genesis.validator_set(),
             block_time_ms: config.block_time_ms,
-            view_timeout_ms: 10000, // 10 seconds
+            view_timeout: ViewChangeTimeout {
+                base_timeout_ms: config.view_timeout_base_ms,
+                timeout_multiplier: config.view_timeout_multiplier,
+                max_timeout_ms: config.view_timeout_max_ms,
+            },
             max_block_size: 1024 * 1024 * 10, // 10MB
             max_transactions_per_block: 1000,
+            leader_shuffle_epoch_length: config.leader_shuffle_epoch,
+            dynamic_block_size,
+            block_interval: config.adaptive_block_time
+                .then(|| BlockIntervalConfig::with_target(config.block_time_ms)),
+            backup_proposer_timeout_ms: config.backup_proposer_timeout_ms,
+            empty_block_timeout_ms: config.empty_block_timeout_ms,
+            min_transactions_per_block: config.min_block_transactions,
+            pbft_log_retention: config.pbft_log_retention,
+            dev_mode: config.dev_mode,
+            vrf_leader_election: config.vrf_leader_election,
         };
 
-        let consensus = Arc::new(ConsensusEngine::new(
+        let mut consensus = ConsensusEngine::new(
             consensus_config,
             storage.clone(),
             mempool.clone(),
-        )?);
+        )?
+            .with_signature_scheme(signature_scheme.clone())
+            .with_validator_keys(validator_keys.clone())
+            .with_validator(validator.clone())
+            .with_shutdown(shutdown.clone());
+        if let Some(metrics) = &metrics {
+            consensus = consensus.with_metrics(metrics.clone());
+        }
+        if config.prevalidate_batch {
+            consensus = consensus.with_batch_prevalidation(validator.clone());
+        }
+        if let Some(key) = &node_key {
+            consensus = consensus.with_signing_key(key.signing_key());
+        }
+        if let Some(url) = &config.webhook_url {
+            consensus = consensus.with_webhook(WebhookNotifier::spawn(WebhookConfig::new(url.clone()))?);
+            info!("Commit webhook enabled: {}", url);
+        }
+        let consensus = Arc::new(consensus);
         info!("Consensus engine initialized");
 
         // Initialize network manager
         let network_config = NetworkConfig {
             node_id: config.node_id.clone(),
+            chain_id: genesis.chain_id.clone(),
             listen_addresses: vec![config.listen_addr.parse()?],
             bootstrap_peers: Self::parse_bootstrap_peers(&config)?,
             max_peers: config.max_peers,
+            max_peers_per_ip: config.max_peers_per_ip,
             connection_timeout: std::time::Duration::from_secs(10),
             heartbeat_interval: std::time::Duration::from_secs(30),
             gossip_heartbeat_interval: std::time::Duration::from_millis(700),
+            mesh: MeshConfig {
+                mesh_n: config.gossip_mesh_n,
+                mesh_n_low: config.gossip_mesh_n_low,
+                mesh_n_high: config.gossip_mesh_n_high,
+                gossip_factor: config.gossip_factor,
+                history_length: config.gossip_history_length,
+                history_gossip: config.gossip_history_gossip,
+            },
+            validate_gossip: true,
+            peer_pins: PeerPinning::parse_pins(&config.get_pinned_peers())?,
+            allow_identity_rotation: config.allow_identity_rotation,
+            max_message_size: config.max_message_size,
+            scoring: ScoringConfig::default(),
+            identity: node_key.as_ref().map(NodeKey::identity),
+            enable_relay: config.enable_relay,
+            relay_address: config.relay_addr.as_deref()
+                .map(|addr| addr.parse().map_err(|e| anyhow!("Invalid relay address '{}': {}", addr, e)))
+                .transpose()?,
         };
 
-        let network = Arc::new(NetworkManager::new(network_config).await?);
-        info!("Network manager initialized");
+        // Block sync requests and responses are queued here until the network starts
+        let (sync_sender, sync_receiver) = mpsc::unbounded_channel();
+        let sync_config = SyncConfig {
+            slow_response_threshold: std::time::Duration::from_millis(config.sync_slow_peer_threshold_ms),
+            slow_strikes: config.sync_slow_peer_strikes,
+            lag_threshold: config.sync_lag_threshold,
+            ..SyncConfig::default()
+        };
+        let sync = SyncManager::new(
+            sync_config,
+            config.node_id.clone(),
+            storage.clone(),
+            validator.clone(),
+            sync_sender,
+        );
+
+        // Gossiped transactions are validated by a worker pool, off the network task
+        let ingress_config = IngressConfig {
+            capacity: config.ingress_queue_size,
+            workers: config.ingress_workers,
+        };
+        let mut ingress = IngressQueue::new(ingress_config, mempool.clone());
+        if let Some(metrics) = &metrics {
+            ingress = ingress.with_drop_metric(metrics.ingress_dropped.clone());
+        }
 
-        // Initialize metrics server if enabled
-        let metrics_server = if config.enable_metrics {
-            Some(MetricsServer::new(config.metrics_port)?)
-        } else {
-            None
+        // Transactions submitted over RPC are gossiped in batches
+        let (gossip_sender, gossip_receiver) = mpsc::unbounded_channel();
+        let batch_config = BatchConfig {
+            interval: std::time::Duration::from_millis(config.tx_batch_interval_ms),
+            max_batch_size: config.tx_batch_size,
         };
+        let batcher = TransactionBatcher::new(batch_config, config.node_id.clone(), gossip_sender);
+
+        // Inbound consensus messages go to the engine, transactions to the mempool
+        let router = MessageRouter::new(consensus.get_message_sender(), mempool.clone())
+            .with_sync(sync.clone())
+            .with_ingress(ingress.clone());
+        let mut network = NetworkManager::new(network_config, router).await?
+            .with_signature_scheme(signature_scheme)
+            .with_storage(storage.clone())
+            .with_validators(&validator_keys)
+            .with_shutdown(shutdown.clone());
+        if let Some(key) = &node_key {
+            network = network
+                .with_signing_key(key.signing_key())
+                .with_peer_keys(validator_keys);
+        }
+        if let Some(metrics) = &metrics {
+            network = network.with_metrics(metrics.clone());
+        }
+        let network = Arc::new(network);
+        info!("Network manager initialized");
 
         // Initialize JSON-RPC server
-        let rpc_server = Some(JsonRpcServer::new(
+        let rpc_timeouts = RpcTimeouts {
+            read_timeout: std::time::Duration::from_millis(config.rpc_read_timeout_ms),
+            write_timeout: std::time::Duration::from_millis(config.rpc_write_timeout_ms),
+            ..Default::default()
+        };
+        let rate_limit = |per_second: f64, burst: u32| (per_second > 0.0).then_some(RateLimit { per_second, burst });
+        let rpc_rate_limits = RateLimitConfig {
+            read: rate_limit(config.rpc_read_rate_limit, config.rpc_read_burst),
+            write: rate_limit(config.rpc_write_rate_limit, config.rpc_write_burst),
+            per_ip: config.rpc_rate_limit_per_ip,
+        };
+
+        let mut rpc_server = JsonRpcServer::new(
             config.rpc_port,
             storage.clone(),
             mempool.clone(),
             consensus.clone(),
-        )?);
+            rpc_timeouts,
+        )?
+            .with_network(network.clone())
+            .with_gossip(batcher.clone())
+            .with_sync(sync.clone())
+            .with_validator(validator.clone())
+            .with_rate_limits(rpc_rate_limits);
+        if let Some(key) = &node_key {
+            rpc_server = rpc_server.with_signing_key(key.signing_key());
+        }
+        let rpc_server = Some(rpc_server);
 
         Ok(Self {
             config,
@@ -114,11 +353,19 @@ impl BlockchainNode {
             consensus,
             network,
             validator,
+            sync,
+            ingress,
+            batcher,
             metrics_server,
             rpc_server,
             network_receiver: None,
             consensus_sender: None,
+            sync_receiver: Some(sync_receiver),
+            gossip_receiver: Some(gossip_receiver),
             is_running: Arc::new(std::sync::RwLock::new(false)),
+            shutdown_sender,
+            shutdown,
+            tasks: Vec::new(),
         })
     }
 
@@ -147,17 +394,21 @@ impl BlockchainNode {
         // Connect network and consensus
         self.setup_message_routing().await?;
 
+        // Start admitting queued transactions before the network delivers any
+        let workers = self.ingress.start(self.shutdown.clone())?;
+        self.tasks.extend(workers);
+
         // Start network manager
-        // Note: This is simplified - in practice, you'd need to handle the async nature properly
+        self.network.start().await?;
         info!("Network manager started");
 
         // Start consensus engine
         let consensus = self.consensus.clone();
-        tokio::spawn(async move {
+        self.tasks.push(tokio::spawn(async move {
             if let Err(e) = consensus.start().await {
                 error!("Consensus engine failed: {}", e);
             }
-        });
+        }));
         info!("Consensus engine started");
 
         // Start message processing
@@ -184,31 +435,76 @@ impl BlockchainNode {
 
     /// Start message processing loops
     async fn start_message_processing(&mut self) -> Result<()> {
-        // This would set up the message routing between network and consensus
-        // For now, it's a placeholder
+        // Publish block sync traffic through the network event loop
+        let mut sync_receiver = self.sync_receiver.take()
+            .ok_or_else(|| anyhow!("Message processing already started"))?;
+        let network = self.network.clone();
+        let sync = self.sync.clone();
+        let mut shutdown = self.shutdown.clone();
+        self.tasks.push(tokio::spawn(async move {
+            loop {
+                let message = tokio::select! {
+                    _ = shutdown.wait() => break,
+                    message = sync_receiver.recv() => match message {
+                        Some(message) => message,
+                        None => break,
+                    },
+                };
+
+                // Requests for a chosen peer go to it directly once we know its peer id
+                if let MessagePayload::BlockRequest { height, peer: Some(node_id) } = &message.payload {
+                    if let Some(peer_id) = network.peer_id_for(node_id).await {
+                        tokio::spawn(request_block_directly(network.clone(), sync.clone(), node_id.clone(), peer_id, *height));
+                        continue;
+                    }
+                }
+
+                if let Err(e) = network.broadcast_message(message).await {
+                    warn!("Failed to send block sync message: {:#}", e);
+                }
+            }
+        }));
+
+        // Publish transaction gossip as the batcher releases it
+        let mut gossip_receiver = self.gossip_receiver.take()
+            .ok_or_else(|| anyhow!("Message processing already started"))?;
+        let network = self.network.clone();
+        let mut shutdown = self.shutdown.clone();
+        self.tasks.push(tokio::spawn(async move {
+            loop {
+                let message = tokio::select! {
+                    _ = shutdown.wait() => break,
+                    message = gossip_receiver.recv() => match message {
+                        Some(message) => message,
+                        None => break,
+                    },
+                };
+
+                if let Err(e) = network.broadcast_message(message).await {
+                    debug!("Failed to gossip transactions: {:#}", e);
+                }
+            }
+        }));
+        self.tasks.extend(self.batcher.start(self.shutdown.clone()));
+
         info!("Message processing started");
         Ok(())
     }
 
     /// Start periodic maintenance tasks
-    async fn start_periodic_tasks(&self) -> Result<()> {
+    async fn start_periodic_tasks(&mut self) -> Result<()> {
         let storage = self.storage.clone();
         let mempool = self.mempool.clone();
-        let is_running = self.is_running.clone();
 
         // Spawn periodic cleanup task
-        tokio::spawn(async move {
+        let mut shutdown = self.shutdown.clone();
+        self.tasks.push(tokio::spawn(async move {
             let mut interval = tokio::time::interval(std::time::Duration::from_secs(60));
             
             loop {
-                interval.tick().await;
-                
-                // Check if node is still running
-                {
-                    let running = is_running.read().unwrap();
-                    if !*running {
-                        break;
-                    }
+                tokio::select! {
+                    _ = shutdown.wait() => break,
+                    _ = interval.tick() => {}
                 }
 
                 // Perform cleanup tasks
@@ -218,7 +514,66 @@ impl BlockchainNode {
 
                 // Additional periodic tasks can be added here
             }
-        });
+        }));
+
+        // Spawn mempool expiry task
+        let mut shutdown = self.shutdown.clone();
+        self.tasks.push(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(mempool.cleanup_interval());
+
+            loop {
+                tokio::select! {
+                    _ = shutdown.wait() => break,
+                    _ = interval.tick() => {}
+                }
+
+                match mempool.prune_expired() {
+                    Ok(0) => {}
+                    Ok(pruned) => info!("Evicted {} expired transactions from the mempool", pruned),
+                    Err(e) => warn!("Failed to prune expired transactions: {}", e),
+                }
+            }
+        }));
+
+        // Spawn block sync retry task
+        let sync = self.sync.clone();
+        let mut shutdown = self.shutdown.clone();
+        self.tasks.push(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(1));
+
+            loop {
+                tokio::select! {
+                    _ = shutdown.wait() => break,
+                    _ = interval.tick() => {}
+                }
+
+                if let Err(e) = sync.retry_timed_out() {
+                    warn!("Failed to retry block requests: {}", e);
+                }
+            }
+        }));
+
+        // Spawn RocksDB metrics refresh task
+        if let Some(metrics_server) = &self.metrics_server {
+            let metrics = metrics_server.metrics().clone();
+            let storage = self.storage.clone();
+            let mut shutdown = self.shutdown.clone();
+
+            self.tasks.push(tokio::spawn(async move {
+                let mut interval = tokio::time::interval(std::time::Duration::from_secs(10));
+
+                loop {
+                    tokio::select! {
+                        _ = shutdown.wait() => break,
+                        _ = interval.tick() => {}
+                    }
+
+                    if let Err(e) = metrics.update_storage_metrics(&storage) {
+                        warn!("Failed to update storage metrics: {}", e);
+                    }
+                }
+            }));
+        }
 
         info!("Periodic tasks started");
         Ok(())
@@ -238,10 +593,31 @@ impl BlockchainNode {
             is_leader: consensus_stats.current_leader.as_ref() == Some(&self.config.node_id),
             connected_peers: network_stats.connected_peers,
             mempool_size: mempool_stats.total_transactions,
+            is_syncing: self.is_syncing()?,
         })
     }
 
+    /// Check whether the node trails the highest height its peers have announced
+    pub fn is_syncing(&self) -> Result<bool> {
+        self.sync.is_lagging()
+    }
+
+    /// Stop proposing, rotate leadership away and persist the mempool ahead of shutdown
+    pub async fn drain(&self) -> Result<()> {
+        info!("Draining blockchain node...");
+
+        self.consensus.drain().await?;
+
+        let persisted = self.mempool.persist(&self.storage)?;
+        info!("Drain complete; persisted {} pending transactions", persisted);
+
+        Ok(())
+    }
+
     /// Shutdown the node gracefully
+    ///
+    /// Stops every task and waits for it to finish before storage is touched,
+    /// so nothing writes while pending transactions are persisted.
     pub async fn shutdown(&mut self) -> Result<()> {
         info!("Shutting down blockchain node...");
 
@@ -251,6 +627,9 @@ impl BlockchainNode {
             *is_running = false;
         }
 
+        // Signal every loop to stop
+        let _ = self.shutdown_sender.send(true);
+
         // Shutdown services
         if let Some(rpc_server) = &mut self.rpc_server {
             rpc_server.shutdown().await?;
@@ -260,8 +639,28 @@ impl BlockchainNode {
             metrics_server.shutdown().await?;
         }
 
-        // Clear mempool
-        self.mempool.clear()?;
+        // Wait for in-flight work to drain
+        let tasks = std::mem::take(&mut self.tasks);
+        let abort_handles: Vec<_> = tasks.iter().map(|task| task.abort_handle()).collect();
+        let network = self.network.clone();
+        let stopped = async move {
+            for task in tasks {
+                if let Err(e) = task.await {
+                    warn!("Task failed during shutdown: {}", e);
+                }
+            }
+            network.stopped().await;
+        };
+        if tokio::time::timeout(SHUTDOWN_TIMEOUT, stopped).await.is_err() {
+            warn!("Tasks still running after {:?}; aborting them", SHUTDOWN_TIMEOUT);
+            for handle in abort_handles {
+                handle.abort();
+            }
+        }
+
+        // Keep pending transactions for the next run
+        let persisted = self.mempool.persist(&self.storage)?;
+        info!("Persisted {} pending transactions", persisted);
 
         // Compact storage
         self.storage.compact()?;
@@ -270,16 +669,19 @@ impl BlockchainNode {
         Ok(())
     }
 
-    /// Parse validator set from configuration
-    fn parse_validator_set(config: &Cli) -> Result<Vec<String>> {
-        // For now, return a default validator set
-        // In practice, this would be loaded from genesis file or configuration
-        Ok(vec![
-            "validator-1".to_string(),
-            "validator-2".to_string(),
-            "validator-3".to_string(),
-            "validator-4".to_string(),
-        ])
+    /// Parse the governance authority address, if configured
+    pub(crate) fn parse_governance_authority(config: &Cli) -> Result<Option<Address>> {
+        let authority = match &config.governance_authority {
+            Some(authority) => authority,
+            None => return Ok(None),
+        };
+
+        let bytes = hex::decode(authority.trim_start_matches("0x"))
+            .map_err(|e| anyhow!("Invalid governance authority '{}': {}", authority, e))?;
+        let address: Address = bytes.try_into()
+            .map_err(|_| anyhow!("Governance authority '{}' is not a 20-byte address", authority))?;
+
+        Ok(Some(address))
     }
 
     /// Parse bootstrap peers from configuration
@@ -321,6 +723,27 @@ impl BlockchainNode {
     }
 }
 
+/// Fetch a block from one peer and hand the answer to the sync manager
+///
+/// On failure the sync manager's request times out and is retried, possibly elsewhere.
+async fn request_block_directly(
+    network: Arc<NetworkManager>,
+    sync: SyncManager,
+    node_id: NodeId,
+    peer_id: PeerId,
+    height: BlockHeight,
+) {
+    match network.request_block(peer_id, height).await {
+        Ok(block) => {
+            let response = NetworkMessage::new(node_id, MessagePayload::BlockResponse { block });
+            if let Err(e) = sync.handle_message(&response) {
+                warn!("Failed to handle block {} from {}: {}", height, response.sender, e);
+            }
+        }
+        Err(e) => warn!("Direct block request for height {} failed: {:#}", height, e),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -331,6 +754,7 @@ mod tests {
         
         let mut config = Cli::parse_from(&[
             "blockchain-node",
+            "--mode", "observer",
             "--node-id", "test-node",
             "--db-path", temp_dir.path().to_str().unwrap(),
         ]);
@@ -344,6 +768,19 @@ mod tests {
         assert!(node.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_validator_without_key_file_refuses_to_start() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = Cli::parse_from(&[
+            "blockchain-node",
+            "--node-id", "validator-1",
+            "--db-path", temp_dir.path().to_str().unwrap(),
+        ]);
+
+        let error = BlockchainNode::new(config).await.err().unwrap();
+        assert!(error.to_string().contains("needs a key file"));
+    }
+
     #[tokio::test]
     async fn test_node_state() {
         let node = create_test_node().await.unwrap();
@@ -352,4 +789,32 @@ mod tests {
         assert_eq!(state.current_height, 0);
         assert_eq!(state.connected_peers, 0);
     }
+
+    #[tokio::test]
+    async fn test_start_then_shutdown_stops_all_tasks() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = Cli::parse_from(&[
+            "blockchain-node",
+            "--mode", "observer",
+            "--node-id", "test-node",
+            "--db-path", temp_dir.path().to_str().unwrap(),
+            "--listen-addr", "/ip4/127.0.0.1/tcp/0",
+            "--rpc-port", "0",
+            "--metrics-port", "0",
+        ]);
+        let mut node = BlockchainNode::new(config).await.unwrap();
+
+        node.start().await.unwrap();
+        let handles: Vec<_> = node.tasks.iter().map(|task| task.abort_handle()).collect();
+        assert!(!handles.is_empty());
+
+        tokio::time::timeout(std::time::Duration::from_secs(5), node.shutdown())
+            .await
+            .expect("shutdown hung")
+            .unwrap();
+
+        assert!(node.tasks.is_empty());
+        assert!(handles.iter().all(|handle| handle.is_finished()));
+        assert!(!node.is_running());
+    }
 }
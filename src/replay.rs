@@ -0,0 +1,221 @@
+use anyhow::{Result, anyhow};
+use serde::{Serialize, Deserialize};
+use std::path::Path;
+
+use crate::cli::{Cli, ExportBlocksArgs, ReplayBlocksArgs};
+use crate::genesis::Genesis;
+use crate::node::BlockchainNode;
+use crate::storage::{Storage, StorageError};
+use crate::types::{Block, BlockHeight};
+use crate::validation::{DynamicBlockSizeConfig, SenderAllowlist, Validator};
+
+/// Bumped whenever the dump layout changes
+const DUMP_VERSION: u32 = 2;
+
+/// Consecutive canonical blocks written by `export-blocks`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockDump {
+    pub version: u32,
+    /// Blocks in height order
+    pub blocks: Vec<Block>,
+}
+
+/// Write blocks `from..=to` to a dump file, returning how many were written
+pub fn export_blocks<P: AsRef<Path>>(storage: &Storage, from: BlockHeight, to: BlockHeight, path: P) -> Result<usize> {
+    let path = path.as_ref();
+    if from > to {
+        return Err(anyhow!("Block range is empty: --from {} is after --to {}", from, to));
+    }
+
+    let blocks = storage.blocks().get_blocks_range(from, to)?;
+    if blocks.is_empty() {
+        return Err(StorageError::NotFound(format!("Blocks {}..={}", from, to)).into());
+    }
+
+    let count = blocks.len();
+    let dump = BlockDump { version: DUMP_VERSION, blocks };
+    let serialized = bincode::serialize(&dump)
+        .map_err(|e| StorageError::Serialization { what: "block dump", reason: e.to_string() })?;
+    std::fs::write(path, serialized)
+        .map_err(|e| anyhow!("Failed to write {}: {}", path.display(), e))?;
+
+    Ok(count)
+}
+
+/// Read a dump file written by `export_blocks`
+pub fn read_dump<P: AsRef<Path>>(path: P) -> Result<BlockDump> {
+    let path = path.as_ref();
+    let data = std::fs::read(path)
+        .map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+    let dump: BlockDump = bincode::deserialize(&data)
+        .map_err(|e| StorageError::Corrupt { what: "block dump", reason: e.to_string() })?;
+
+    if dump.version != DUMP_VERSION {
+        return Err(StorageError::Corrupt {
+            what: "block dump",
+            reason: format!("unsupported version {} (expected {})", dump.version, DUMP_VERSION),
+        }.into());
+    }
+    Ok(dump)
+}
+
+/// Validate and commit the blocks of a dump in order, as synced blocks are applied
+///
+/// Blocks the database already holds are skipped, so a dump starting at genesis
+/// replays onto a node initialized from the same genesis file. Replay stops at
+/// the first block that fails validation, lacks a quorum of commit signatures or
+/// whose transfers don't produce the state root in its header, leaving the blocks
+/// before it committed. Returns the number of blocks committed.
+pub fn replay_blocks(storage: &Storage, validator: &Validator, dump: &BlockDump) -> Result<usize> {
+    let mut replayed = 0;
+
+    for block in &dump.blocks {
+        let height = block.header.height;
+        if storage.blocks().get_block(height)?.is_some_and(|existing| existing.hash() == block.hash()) {
+            continue;
+        }
+
+        let checked = validator.validate_committed_block(block).and_then(|()| {
+            let state_root = storage.state().preview_state_root(block)?;
+            if state_root != block.header.state_root {
+                return Err(anyhow!(
+                    "transfers produce state root {} but the header commits to {}",
+                    hex::encode(state_root), hex::encode(block.header.state_root)
+                ));
+            }
+            Ok(())
+        });
+        if let Err(e) = checked {
+            return Err(anyhow!(
+                "Block {} at height {} failed validation after {} replayed blocks: {}",
+                hex::encode(block.hash()), height, replayed, e
+            ));
+        }
+
+        storage.commit_block(block)?;
+        replayed += 1;
+    }
+
+    Ok(replayed)
+}
+
+/// Run the `export-blocks` subcommand against the database at `db_path`
+pub fn run_export(db_path: &Path, args: &ExportBlocksArgs) -> Result<()> {
+    let storage = Storage::open_read_only(db_path)?;
+    let count = export_blocks(&storage, args.from, args.to, &args.out)?;
+    println!("Wrote {} blocks ({}..={}) to {}", count, args.from, args.to, args.out.display());
+    Ok(())
+}
+
+/// Run the `replay-blocks` subcommand, validating with the node's own settings
+pub fn run_replay(cli: &Cli, args: &ReplayBlocksArgs) -> Result<()> {
+    let mut storage = Storage::open_with(&cli.db_path, &cli.storage_config())?
+        .with_archive(cli.is_archive())
+        .with_audit_log(cli.audit_log);
+    if let Some(authority) = BlockchainNode::parse_governance_authority(cli)? {
+        storage = storage.with_governance_authority(authority);
+    }
+    let genesis = Genesis::load(&cli.genesis_file)?;
+    genesis.apply(&storage)?;
+
+    let mut validator = Validator::new(storage.clone())
+        .with_validator_keys(genesis.validator_keys()?)
+        .with_sender_allowlist(SenderAllowlist::from_hex(&cli.get_allowed_senders())?)
+        .with_median_time_past_window(cli.median_time_past_window)
+        .with_max_data_bytes(cli.max_data_bytes);
+    if cli.dynamic_block_size {
+        validator = validator.with_dynamic_block_size(DynamicBlockSizeConfig::default());
+    }
+
+    let dump = read_dump(&args.input)?;
+    let replayed = replay_blocks(&storage, &validator, &dump)?;
+    let height = storage.get_latest_height()?.unwrap_or(0);
+    println!(
+        "Replayed {} blocks; head is at height {} with state root {}",
+        replayed, height, hex::encode(storage.state().calculate_state_root()?)
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    use crate::test_fixtures::{build_chain, sender, sign_commit, validator_keys};
+
+    /// Validator checking commits from the single test validator
+    fn create_validator(storage: &Storage) -> Validator {
+        Validator::new(storage.clone()).with_validator_keys(validator_keys())
+    }
+
+    fn create_funded_storage(dir: &TempDir) -> Storage {
+        let storage = Storage::new(dir.path()).unwrap();
+        storage.state().set_balance(&sender(), 1_000_000).unwrap();
+        storage
+    }
+
+    #[test]
+    fn test_exported_chain_replays_to_same_state_root() {
+        let source_dir = TempDir::new().unwrap();
+        let source = create_funded_storage(&source_dir);
+        build_chain(&source, 5);
+
+        let dump_dir = TempDir::new().unwrap();
+        let path = dump_dir.path().join("blocks.bin");
+        assert_eq!(export_blocks(&source, 0, 4, &path).unwrap(), 5);
+        assert!(export_blocks(&source, 3, 1, &path).is_err());
+
+        let replay_dir = TempDir::new().unwrap();
+        let replayed = create_funded_storage(&replay_dir);
+        let validator = create_validator(&replayed);
+        let dump = read_dump(&path).unwrap();
+        assert_eq!(replay_blocks(&replayed, &validator, &dump).unwrap(), 5);
+
+        assert_eq!(replayed.get_latest_height().unwrap(), Some(4));
+        assert_eq!(replayed.get_head_hash().unwrap(), source.get_head_hash().unwrap());
+        assert_eq!(
+            replayed.state().calculate_state_root().unwrap(),
+            source.state().calculate_state_root().unwrap(),
+        );
+
+        // Replaying again finds every block already stored
+        assert_eq!(replay_blocks(&replayed, &validator, &dump).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_replay_stops_at_first_invalid_block() {
+        let source_dir = TempDir::new().unwrap();
+        let source = create_funded_storage(&source_dir);
+        build_chain(&source, 4);
+
+        let dump_dir = TempDir::new().unwrap();
+        let path = dump_dir.path().join("blocks.bin");
+        export_blocks(&source, 0, 3, &path).unwrap();
+
+        // Raising an amount after signing invalidates the transaction in block 2
+        let mut dump = read_dump(&path).unwrap();
+        dump.blocks[2].transactions[0].amount += 1;
+
+        let replay_dir = TempDir::new().unwrap();
+        let replayed = create_funded_storage(&replay_dir);
+        let validator = create_validator(&replayed);
+        let error = replay_blocks(&replayed, &validator, &dump).unwrap_err();
+        assert!(error.to_string().contains("at height 2"), "{}", error);
+        assert_eq!(replayed.get_latest_height().unwrap(), Some(1));
+
+        // A block whose transfers disagree with its state root is caught too
+        let mut dump = read_dump(&path).unwrap();
+        dump.blocks[2].header.state_root = [9; 32];
+        sign_commit(&mut dump.blocks[2]);
+        let error = replay_blocks(&replayed, &validator, &dump).unwrap_err();
+        assert!(error.to_string().contains("state root"), "{}", error);
+        assert_eq!(replayed.get_latest_height().unwrap(), Some(1));
+
+        // As is a block missing its commit signatures
+        let mut dump = read_dump(&path).unwrap();
+        dump.blocks[2].signatures.clear();
+        let error = replay_blocks(&replayed, &validator, &dump).unwrap_err();
+        assert!(error.to_string().contains("consensus signatures"), "{}", error);
+        assert_eq!(replayed.get_latest_height().unwrap(), Some(1));
+    }
+}
@@ -0,0 +1,59 @@
+use tokio::sync::watch;
+
+/// Signal long-running loops select on so they can exit cleanly
+///
+/// Cloned into every task; the node holds the matching `watch::Sender` and
+/// sends `true` to stop them all.
+#[derive(Debug, Clone)]
+pub struct Shutdown {
+    receiver: watch::Receiver<bool>,
+}
+
+impl Shutdown {
+    /// Create a signal and the sender that triggers it
+    pub fn new() -> (watch::Sender<bool>, Self) {
+        let (sender, receiver) = watch::channel(false);
+        (sender, Self { receiver })
+    }
+
+    /// A signal that never fires, for components run without a node
+    pub fn never() -> Self {
+        let (_, receiver) = watch::channel(false);
+        Self { receiver }
+    }
+
+    /// Wait until shutdown is signalled
+    pub async fn wait(&mut self) {
+        // A dropped sender can never signal, so keep waiting rather than stop early
+        if self.receiver.wait_for(|stop| *stop).await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    }
+
+    /// Check whether shutdown has been signalled
+    pub fn is_triggered(&self) -> bool {
+        *self.receiver.borrow()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_wait_returns_once_signalled() {
+        let (sender, shutdown) = Shutdown::new();
+        let mut waiter = shutdown.clone();
+        let task = tokio::spawn(async move { waiter.wait().await });
+
+        assert!(!shutdown.is_triggered());
+        sender.send(true).unwrap();
+        tokio::time::timeout(Duration::from_secs(1), task).await.unwrap().unwrap();
+        assert!(shutdown.is_triggered());
+
+        // A signal that was never wired up doesn't fire
+        let mut never = Shutdown::never();
+        assert!(tokio::time::timeout(Duration::from_millis(50), never.wait()).await.is_err());
+    }
+}
@@ -0,0 +1,131 @@
+use anyhow::Result;
+use rocksdb::{DB, Direction, IteratorMode, WriteBatch};
+use serde::{Serialize, Deserialize};
+use std::sync::Arc;
+use uuid::Uuid;
+use crate::types::{Address, BlockHeight};
+use super::StorageError;
+
+const CF_AUDIT: &str = "audit";
+
+/// A single balance/nonce change made by a committed transaction
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AuditRecord {
+    pub block_height: BlockHeight,
+    pub tx_id: Uuid,
+    pub address: Address,
+    pub old_balance: u64,
+    pub new_balance: u64,
+    pub old_nonce: u64,
+    pub new_nonce: u64,
+}
+
+/// Position of a record in the audit log: block height, then order within the block
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub struct AuditCursor {
+    pub height: BlockHeight,
+    pub index: u32,
+}
+
+/// One page of the audit log, with the cursor of the next record if there is one
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditPage {
+    pub records: Vec<AuditRecord>,
+    pub next: Option<AuditCursor>,
+}
+
+/// Append-only log of state mutations, keyed by block height and order within the block
+#[derive(Clone)]
+pub struct AuditStore {
+    db: Arc<DB>,
+}
+
+impl AuditStore {
+    pub fn new(db: Arc<DB>) -> Result<Self> {
+        Ok(Self { db })
+    }
+
+    /// Add a block's audit records to a write batch, in the order they were applied
+    ///
+    /// Records already logged at this height, by a block a reorg rolled back, are kept
+    /// and the new ones follow them.
+    pub fn stage_records(&self, height: BlockHeight, records: &[AuditRecord], batch: &mut WriteBatch) -> Result<()> {
+        let cf = self.db.cf_handle(CF_AUDIT)
+            .ok_or(StorageError::MissingColumnFamily(CF_AUDIT))?;
+
+        let first = self.next_index(height)?;
+        for (offset, record) in records.iter().enumerate() {
+            let index = u32::try_from(offset).ok()
+                .and_then(|offset| first.checked_add(offset))
+                .ok_or_else(|| StorageError::InvalidState(format!("Too many audit records at height {}", height)))?;
+            let serialized = bincode::serialize(record)
+                .map_err(|e| StorageError::Serialization { what: "audit record", reason: e.to_string() })?;
+            batch.put_cf(cf, self.record_key(AuditCursor { height, index }), serialized);
+        }
+
+        Ok(())
+    }
+
+    /// Read up to `limit` records starting at `from`
+    pub fn get_records(&self, from: AuditCursor, limit: usize) -> Result<AuditPage> {
+        let cf = self.db.cf_handle(CF_AUDIT)
+            .ok_or(StorageError::MissingColumnFamily(CF_AUDIT))?;
+
+        let start = self.record_key(from);
+        let iter = self.db.iterator_cf(cf, IteratorMode::From(&start, Direction::Forward));
+
+        let mut records = Vec::new();
+        for item in iter {
+            let (key, value) = item?;
+
+            if records.len() >= limit {
+                return Ok(AuditPage { records, next: Some(self.parse_key(&key)?) });
+            }
+
+            let record = bincode::deserialize(&value)
+                .map_err(|e| StorageError::Corrupt { what: "audit record", reason: e.to_string() })?;
+            records.push(record);
+        }
+
+        Ok(AuditPage { records, next: None })
+    }
+
+    /// Index after the last record logged at `height`
+    fn next_index(&self, height: BlockHeight) -> Result<u32> {
+        let cf = self.db.cf_handle(CF_AUDIT)
+            .ok_or(StorageError::MissingColumnFamily(CF_AUDIT))?;
+
+        let last = self.record_key(AuditCursor { height, index: u32::MAX });
+        match self.db.iterator_cf(cf, IteratorMode::From(&last, Direction::Reverse)).next() {
+            Some(item) => {
+                let (key, _) = item?;
+                let cursor = self.parse_key(&key)?;
+                if cursor.height != height {
+                    return Ok(0);
+                }
+                cursor.index.checked_add(1)
+                    .ok_or_else(|| StorageError::InvalidState(format!("Too many audit records at height {}", height)).into())
+            }
+            None => Ok(0),
+        }
+    }
+
+    /// Big-endian so records iterate in commit order
+    fn record_key(&self, cursor: AuditCursor) -> Vec<u8> {
+        let mut key = Vec::with_capacity(12);
+        key.extend_from_slice(&cursor.height.to_be_bytes());
+        key.extend_from_slice(&cursor.index.to_be_bytes());
+        key
+    }
+
+    fn parse_key(&self, key: &[u8]) -> Result<AuditCursor> {
+        if key.len() != 12 {
+            return Err(StorageError::Corrupt { what: "audit record key", reason: format!("expected 12 bytes, got {}", key.len()) }.into());
+        }
+
+        Ok(AuditCursor {
+            height: BlockHeight::from_be_bytes(key[..8].try_into().unwrap()),
+            index: u32::from_be_bytes(key[8..].try_into().unwrap()),
+        })
+    }
+}
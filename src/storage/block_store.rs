@@ -1,11 +1,83 @@
-use anyhow::{Result, anyhow};
-use rocksdb::{DB, IteratorMode};
+use anyhow::Result;
+use bincode::Options;
+use rocksdb::{DB, Direction, IteratorMode, WriteBatch};
+use serde::{Serialize, Deserialize};
 use std::sync::Arc;
-use crate::types::{Block, Hash, BlockHeight};
+use crate::types::{Block, BlockHeader, Hash, BlockHeight, NodeId, Timestamp, Transaction, ValidatorSignature};
+use super::{decode, StorageError, StorageSnapshot, MAX_PAGE_SIZE};
 
 const CF_BLOCKS: &str = "blocks";
+/// Key prefix of canonical blocks, by height; they sort before the hash index
+const HEIGHT_PREFIX: &[u8] = b"block_";
+/// Key prefix of blocks on competing branches, by hash
+const SIDE_PREFIX: &[u8] = b"side_";
+/// Key prefix of headers imported from a snapshot without their block bodies
+const HEADER_PREFIX: &[u8] = b"header_";
+
+/// Resume point for paged block queries: the last height already returned
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BlockCursor {
+    pub last_height: BlockHeight,
+}
+
+/// Block header as encoded before schema version 4 added `vrf_proof`
+#[derive(Deserialize)]
+struct LegacyBlockHeader {
+    height: BlockHeight,
+    previous_hash: Hash,
+    merkle_root: Hash,
+    state_root: Hash,
+    timestamp: Timestamp,
+    proposer: NodeId,
+    round: u64,
+    view: u64,
+}
+
+impl From<LegacyBlockHeader> for BlockHeader {
+    fn from(header: LegacyBlockHeader) -> Self {
+        Self {
+            height: header.height,
+            previous_hash: header.previous_hash,
+            merkle_root: header.merkle_root,
+            state_root: header.state_root,
+            timestamp: header.timestamp,
+            proposer: header.proposer,
+            round: header.round,
+            view: header.view,
+            vrf_proof: None,
+        }
+    }
+}
+
+/// Block as encoded before schema version 4
+#[derive(Deserialize)]
+struct LegacyBlock {
+    header: LegacyBlockHeader,
+    transactions: Vec<Transaction>,
+    signatures: Vec<ValidatorSignature>,
+}
+
+impl From<LegacyBlock> for Block {
+    fn from(block: LegacyBlock) -> Self {
+        Self {
+            header: block.header.into(),
+            transactions: block.transactions,
+            signatures: block.signatures,
+        }
+    }
+}
+
+/// Decode a value written by an older build, rejecting any that doesn't match its layout exactly
+fn decode_legacy<T: for<'de> Deserialize<'de>>(data: &[u8], what: &'static str) -> Result<T> {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .reject_trailing_bytes()
+        .deserialize(data)
+        .map_err(|e| StorageError::Corrupt { what, reason: e.to_string() }.into())
+}
 
 /// Block storage implementation
+#[derive(Clone)]
 pub struct BlockStore {
     db: Arc<DB>,
 }
@@ -17,45 +89,124 @@ impl BlockStore {
 
     /// Store a block by height
     pub fn put_block(&self, block: &Block) -> Result<()> {
+        let mut batch = WriteBatch::default();
+        self.stage_block(block, &mut batch)?;
+
+        self.db.write(batch)
+            .map_err(|e| StorageError::Database { action: "store block", source: e }.into())
+    }
+
+    /// Add a block and its hash index to a write batch
+    pub fn stage_block(&self, block: &Block, batch: &mut WriteBatch) -> Result<()> {
         let cf = self.db.cf_handle(CF_BLOCKS)
-            .ok_or_else(|| anyhow!("Blocks column family not found"))?;
+            .ok_or(StorageError::MissingColumnFamily(CF_BLOCKS))?;
         
         let key = self.height_key(block.header.height);
         let serialized = bincode::serialize(block)
-            .map_err(|e| anyhow!("Failed to serialize block: {}", e))?;
+            .map_err(|e| StorageError::Serialization { what: "block", reason: e.to_string() })?;
         
-        self.db.put_cf(cf, &key, &serialized)
-            .map_err(|e| anyhow!("Failed to store block: {}", e))?;
+        batch.put_cf(cf, &key, &serialized);
 
         // Also store by hash for quick lookups
         let hash_key = self.hash_key(&block.hash());
-        self.db.put_cf(cf, &hash_key, &key)
-            .map_err(|e| anyhow!("Failed to store block hash index: {}", e))?;
+        batch.put_cf(cf, &hash_key, &key);
 
         Ok(())
     }
 
+    /// Add a block off the canonical chain to a write batch, keyed by hash
+    pub fn stage_side_block(&self, block: &Block, batch: &mut WriteBatch) -> Result<()> {
+        let cf = self.db.cf_handle(CF_BLOCKS)
+            .ok_or(StorageError::MissingColumnFamily(CF_BLOCKS))?;
+
+        let serialized = bincode::serialize(block)
+            .map_err(|e| StorageError::Serialization { what: "block", reason: e.to_string() })?;
+        batch.put_cf(cf, self.side_key(&block.hash()), &serialized);
+
+        Ok(())
+    }
+
+    /// Move a canonical block to the side branches in a write batch, freeing its height
+    pub fn stage_demote(&self, block: &Block, batch: &mut WriteBatch) -> Result<()> {
+        let cf = self.db.cf_handle(CF_BLOCKS)
+            .ok_or(StorageError::MissingColumnFamily(CF_BLOCKS))?;
+
+        batch.delete_cf(cf, self.height_key(block.header.height));
+        batch.delete_cf(cf, self.hash_key(&block.hash()));
+        self.stage_side_block(block, batch)
+    }
+
+    /// Move a side block onto the canonical chain at its height in a write batch
+    pub fn stage_promote(&self, block: &Block, batch: &mut WriteBatch) -> Result<()> {
+        let cf = self.db.cf_handle(CF_BLOCKS)
+            .ok_or(StorageError::MissingColumnFamily(CF_BLOCKS))?;
+
+        batch.delete_cf(cf, self.side_key(&block.hash()));
+        self.stage_block(block, batch)
+    }
+
+    /// Add the hash and header of a block whose body isn't stored to a write batch
+    pub fn stage_header(&self, hash: &Hash, header: &BlockHeader, batch: &mut WriteBatch) -> Result<()> {
+        let cf = self.db.cf_handle(CF_BLOCKS)
+            .ok_or(StorageError::MissingColumnFamily(CF_BLOCKS))?;
+
+        let serialized = bincode::serialize(&(hash, header))
+            .map_err(|e| StorageError::Serialization { what: "block header", reason: e.to_string() })?;
+        batch.put_cf(cf, self.header_key(header.height), &serialized);
+
+        Ok(())
+    }
+
+    /// Get the hash and header of the canonical block at a height, with or without its body
+    pub fn get_header(&self, height: BlockHeight) -> Result<Option<(Hash, BlockHeader)>> {
+        if let Some(block) = self.get_block(height)? {
+            return Ok(Some((block.hash(), block.header)));
+        }
+
+        let cf = self.db.cf_handle(CF_BLOCKS)
+            .ok_or(StorageError::MissingColumnFamily(CF_BLOCKS))?;
+        match self.db.get_cf(cf, self.header_key(height))? {
+            Some(data) => {
+                let entry = bincode::deserialize(&data)
+                    .map_err(|e| StorageError::Corrupt { what: "block header", reason: e.to_string() })?;
+                Ok(Some(entry))
+            }
+            None => Ok(None),
+        }
+    }
+
     /// Get a block by height
     pub fn get_block(&self, height: BlockHeight) -> Result<Option<Block>> {
         let cf = self.db.cf_handle(CF_BLOCKS)
-            .ok_or_else(|| anyhow!("Blocks column family not found"))?;
+            .ok_or(StorageError::MissingColumnFamily(CF_BLOCKS))?;
         
         let key = self.height_key(height);
         
         match self.db.get_cf(cf, &key)? {
             Some(data) => {
                 let block = bincode::deserialize(&data)
-                    .map_err(|e| anyhow!("Failed to deserialize block: {}", e))?;
+                    .map_err(|e| StorageError::Corrupt { what: "block", reason: e.to_string() })?;
                 Ok(Some(block))
             }
             None => Ok(None),
         }
     }
 
+    /// Get a block by height as of a snapshot
+    pub fn get_block_in(&self, snapshot: &StorageSnapshot, height: BlockHeight) -> Result<Option<Block>> {
+        decode(snapshot.get_cf(CF_BLOCKS, self.height_key(height))?, "block")
+    }
+
+    /// Get a block by height, failing with `StorageError::NotFound` if it isn't stored
+    pub fn require_block(&self, height: BlockHeight) -> Result<Block> {
+        self.get_block(height)?
+            .ok_or_else(|| StorageError::NotFound(format!("Block at height {}", height)).into())
+    }
+
     /// Get a block by hash
     pub fn get_block_by_hash(&self, hash: &Hash) -> Result<Option<Block>> {
         let cf = self.db.cf_handle(CF_BLOCKS)
-            .ok_or_else(|| anyhow!("Blocks column family not found"))?;
+            .ok_or(StorageError::MissingColumnFamily(CF_BLOCKS))?;
         
         let hash_key = self.hash_key(hash);
         
@@ -66,7 +217,7 @@ impl BlockStore {
                 match self.db.get_cf(cf, &height_key)? {
                     Some(data) => {
                         let block = bincode::deserialize(&data)
-                            .map_err(|e| anyhow!("Failed to deserialize block: {}", e))?;
+                            .map_err(|e| StorageError::Corrupt { what: "block", reason: e.to_string() })?;
                         Ok(Some(block))
                     }
                     None => Ok(None),
@@ -76,10 +227,41 @@ impl BlockStore {
         }
     }
 
+    /// Get a block by hash as of a snapshot
+    pub fn get_block_by_hash_in(&self, snapshot: &StorageSnapshot, hash: &Hash) -> Result<Option<Block>> {
+        match snapshot.get_cf(CF_BLOCKS, self.hash_key(hash))? {
+            Some(height_key) => decode(snapshot.get_cf(CF_BLOCKS, height_key)?, "block"),
+            None => Ok(None),
+        }
+    }
+
+    /// Get a block on a competing branch by hash
+    pub fn get_side_block(&self, hash: &Hash) -> Result<Option<Block>> {
+        let cf = self.db.cf_handle(CF_BLOCKS)
+            .ok_or(StorageError::MissingColumnFamily(CF_BLOCKS))?;
+
+        match self.db.get_cf(cf, self.side_key(hash))? {
+            Some(data) => {
+                let block = bincode::deserialize(&data)
+                    .map_err(|e| StorageError::Corrupt { what: "block", reason: e.to_string() })?;
+                Ok(Some(block))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Get a block by hash, whether it is canonical or on a competing branch
+    pub fn find_block(&self, hash: &Hash) -> Result<Option<Block>> {
+        match self.get_block_by_hash(hash)? {
+            Some(block) => Ok(Some(block)),
+            None => self.get_side_block(hash),
+        }
+    }
+
     /// Check if a block exists at given height
     pub fn has_block(&self, height: BlockHeight) -> Result<bool> {
         let cf = self.db.cf_handle(CF_BLOCKS)
-            .ok_or_else(|| anyhow!("Blocks column family not found"))?;
+            .ok_or(StorageError::MissingColumnFamily(CF_BLOCKS))?;
         
         let key = self.height_key(height);
         Ok(self.db.get_cf(cf, &key)?.is_some())
@@ -87,47 +269,162 @@ impl BlockStore {
 
     /// Get block range
     pub fn get_blocks_range(&self, start: BlockHeight, end: BlockHeight) -> Result<Vec<Block>> {
+        self.iter_range(start, end)?.collect()
+    }
+
+    /// Get up to `limit` blocks in height order, from `start` or just after `cursor`
+    ///
+    /// Returns a cursor for the next page while more blocks remain. The limit is
+    /// capped at `MAX_PAGE_SIZE`.
+    pub fn get_blocks_range_paged(
+        &self,
+        start: BlockHeight,
+        limit: usize,
+        cursor: Option<BlockCursor>,
+    ) -> Result<(Vec<Block>, Option<BlockCursor>)> {
+        let from = match cursor {
+            Some(cursor) => match cursor.last_height.checked_add(1) {
+                Some(height) => height,
+                None => return Ok((Vec::new(), None)),
+            },
+            None => start,
+        };
+        let limit = limit.min(MAX_PAGE_SIZE);
+
+        // Read one extra block to learn whether another page follows
+        let mut blocks = self.iter_range(from, BlockHeight::MAX)?
+            .take(limit + 1)
+            .collect::<Result<Vec<Block>>>()?;
+
+        let next = if blocks.len() > limit {
+            blocks.truncate(limit);
+            blocks.last().map(|block| BlockCursor { last_height: block.header.height })
+        } else {
+            None
+        };
+
+        Ok((blocks, next))
+    }
+
+    /// Iterate stored blocks from `start` to `end` inclusive in height order
+    ///
+    /// Blocks are read from the database one at a time, so arbitrarily long
+    /// ranges can be walked without holding them in memory.
+    pub fn iter_range(&self, start: BlockHeight, end: BlockHeight) -> Result<impl Iterator<Item = Result<Block>> + '_> {
+        self.iter_decoded(start, end, |value| {
+            bincode::deserialize(value)
+                .map_err(|e| StorageError::Corrupt { what: "block", reason: e.to_string() }.into())
+        })
+    }
+
+    /// Iterate every canonical block of a database not yet migrated to schema version 4
+    pub fn iter_legacy(&self) -> Result<impl Iterator<Item = Result<Block>> + '_> {
+        self.iter_decoded(0, BlockHeight::MAX, |value| {
+            decode_legacy::<LegacyBlock>(value, "legacy block").map(Block::from)
+        })
+    }
+
+    /// Re-encode every block and body-less header written before schema version 4 into a write batch
+    ///
+    /// Hashes are unchanged, since headers without a VRF proof hash as they
+    /// did before, so the hash index needs no rewriting. Returns the entries staged.
+    pub fn stage_upgrade_encoding(&self, batch: &mut WriteBatch) -> Result<usize> {
         let cf = self.db.cf_handle(CF_BLOCKS)
-            .ok_or_else(|| anyhow!("Blocks column family not found"))?;
-        
-        let mut blocks = Vec::new();
-        
-        for height in start..=end {
-            if let Some(block) = self.get_block(height)? {
-                blocks.push(block);
+            .ok_or(StorageError::MissingColumnFamily(CF_BLOCKS))?;
+
+        let mut upgraded = 0;
+        for item in self.db.iterator_cf(cf, IteratorMode::Start) {
+            let (key, value) = item
+                .map_err(|e| StorageError::Database { action: "read block", source: e })?;
+
+            let serialized = if key.starts_with(HEIGHT_PREFIX) || key.starts_with(SIDE_PREFIX) {
+                let block = Block::from(decode_legacy::<LegacyBlock>(&value, "legacy block")?);
+                bincode::serialize(&block)
+            } else if key.starts_with(HEADER_PREFIX) {
+                let (hash, header) = decode_legacy::<(Hash, LegacyBlockHeader)>(&value, "legacy block header")?;
+                bincode::serialize(&(hash, BlockHeader::from(header)))
+            } else {
+                continue;
             }
+            .map_err(|e| StorageError::Serialization { what: "block", reason: e.to_string() })?;
+
+            batch.put_cf(cf, &key, &serialized);
+            upgraded += 1;
         }
+
+        Ok(upgraded)
+    }
+
+    /// Store a block the way builds before schema version 4 encoded it
+    #[cfg(test)]
+    pub(super) fn put_legacy_block(&self, block: &Block) -> Result<()> {
+        let cf = self.db.cf_handle(CF_BLOCKS)
+            .ok_or(StorageError::MissingColumnFamily(CF_BLOCKS))?;
+
+        let header = &block.header;
+        let legacy_header = (
+            header.height, &header.previous_hash, &header.merkle_root, &header.state_root,
+            header.timestamp, &header.proposer, header.round, header.view,
+        );
+        let serialized = bincode::serialize(&(legacy_header, &block.transactions, &block.signatures))?;
+        let key = self.height_key(header.height);
+        self.db.put_cf(cf, &key, &serialized)?;
+        self.db.put_cf(cf, self.hash_key(&block.hash()), &key)?;
+
+        Ok(())
+    }
+
+    /// Iterate blocks from `start` to `end` inclusive, decoding each with `decode`
+    fn iter_decoded(
+        &self,
+        start: BlockHeight,
+        end: BlockHeight,
+        decode: fn(&[u8]) -> Result<Block>,
+    ) -> Result<impl Iterator<Item = Result<Block>> + '_> {
+        let cf = self.db.cf_handle(CF_BLOCKS)
+            .ok_or(StorageError::MissingColumnFamily(CF_BLOCKS))?;
         
-        Ok(blocks)
+        let start_key = self.height_key(start);
+        let iter = self.db.iterator_cf(cf, IteratorMode::From(&start_key, Direction::Forward));
+        
+        Ok(iter.map_while(move |item| {
+            let (key, value) = match item {
+                Ok(entry) => entry,
+                Err(e) => return Some(Err(StorageError::Database { action: "read block", source: e }.into())),
+            };
+            
+            // Stop past the end height or on reaching the hash index entries
+            match self.parse_height_key(&key) {
+                Ok(height) if height <= end => Some(decode(&value)),
+                _ => None,
+            }
+        }))
     }
 
     /// Get the latest block
     pub fn get_latest_block(&self) -> Result<Option<Block>> {
         let cf = self.db.cf_handle(CF_BLOCKS)
-            .ok_or_else(|| anyhow!("Blocks column family not found"))?;
-        
-        let iter = self.db.iterator_cf(cf, IteratorMode::End);
+            .ok_or(StorageError::MissingColumnFamily(CF_BLOCKS))?;
         
-        for item in iter {
-            let (key, value) = item?;
-            
-            // Skip hash index entries (they start with 'h')
-            if key.starts_with(b"h") {
-                continue;
+        // Seek past the last height key; hash index and side entries sort after it
+        let mut last_key = HEIGHT_PREFIX.to_vec();
+        last_key.push(0xff);
+        let mut iter = self.db.iterator_cf(cf, IteratorMode::From(&last_key, Direction::Reverse));
+
+        match iter.next().transpose()? {
+            Some((key, value)) if key.starts_with(HEIGHT_PREFIX) => {
+                let block = bincode::deserialize(&value)
+                    .map_err(|e| StorageError::Corrupt { what: "block", reason: e.to_string() })?;
+                Ok(Some(block))
             }
-            
-            let block = bincode::deserialize(&value)
-                .map_err(|e| anyhow!("Failed to deserialize block: {}", e))?;
-            return Ok(Some(block));
+            _ => Ok(None),
         }
-        
-        Ok(None)
     }
 
     /// Count total number of blocks
     pub fn count_blocks(&self) -> Result<u64> {
         let cf = self.db.cf_handle(CF_BLOCKS)
-            .ok_or_else(|| anyhow!("Blocks column family not found"))?;
+            .ok_or(StorageError::MissingColumnFamily(CF_BLOCKS))?;
         
         let mut count = 0;
         let iter = self.db.iterator_cf(cf, IteratorMode::Start);
@@ -135,8 +432,8 @@ impl BlockStore {
         for item in iter {
             let (key, _) = item?;
             
-            // Only count height-based keys (skip hash index entries)
-            if !key.starts_with(b"h") {
+            // Only count height-based keys (skip hash index and side entries)
+            if key.starts_with(HEIGHT_PREFIX) {
                 count += 1;
             }
         }
@@ -147,7 +444,7 @@ impl BlockStore {
     /// Delete a block (use with caution)
     pub fn delete_block(&self, height: BlockHeight) -> Result<()> {
         let cf = self.db.cf_handle(CF_BLOCKS)
-            .ok_or_else(|| anyhow!("Blocks column family not found"))?;
+            .ok_or(StorageError::MissingColumnFamily(CF_BLOCKS))?;
         
         // First get the block to find its hash
         if let Some(block) = self.get_block(height)? {
@@ -157,7 +454,7 @@ impl BlockStore {
         
         let key = self.height_key(height);
         self.db.delete_cf(cf, &key)
-            .map_err(|e| anyhow!("Failed to delete block: {}", e))?;
+            .map_err(|e| StorageError::Database { action: "delete block", source: e })?;
         
         Ok(())
     }
@@ -165,7 +462,7 @@ impl BlockStore {
     /// Get all block heights
     pub fn get_all_heights(&self) -> Result<Vec<BlockHeight>> {
         let cf = self.db.cf_handle(CF_BLOCKS)
-            .ok_or_else(|| anyhow!("Blocks column family not found"))?;
+            .ok_or(StorageError::MissingColumnFamily(CF_BLOCKS))?;
         
         let mut heights = Vec::new();
         let iter = self.db.iterator_cf(cf, IteratorMode::Start);
@@ -173,8 +470,8 @@ impl BlockStore {
         for item in iter {
             let (key, _) = item?;
             
-            // Only process height-based keys (skip hash index entries)
-            if !key.starts_with(b"h") {
+            // Only process height-based keys (skip hash index and side entries)
+            if key.starts_with(HEIGHT_PREFIX) {
                 if let Ok(height) = self.parse_height_key(&key) {
                     heights.push(height);
                 }
@@ -198,14 +495,30 @@ impl BlockStore {
         key
     }
 
+    /// Create a key for storing blocks off the canonical chain
+    fn side_key(&self, hash: &Hash) -> Vec<u8> {
+        let mut key = Vec::with_capacity(SIDE_PREFIX.len() + 32);
+        key.extend_from_slice(SIDE_PREFIX);
+        key.extend_from_slice(hash);
+        key
+    }
+
+    /// Create a key for storing headers without bodies; heights are big-endian so keys sort by height
+    fn header_key(&self, height: BlockHeight) -> Vec<u8> {
+        let mut key = Vec::with_capacity(HEADER_PREFIX.len() + 8);
+        key.extend_from_slice(HEADER_PREFIX);
+        key.extend_from_slice(&height.to_be_bytes());
+        key
+    }
+
     /// Parse height from height key
     fn parse_height_key(&self, key: &[u8]) -> Result<BlockHeight> {
         let key_str = String::from_utf8_lossy(key);
         if let Some(height_str) = key_str.strip_prefix("block_") {
             height_str.parse::<BlockHeight>()
-                .map_err(|e| anyhow!("Failed to parse height from key: {}", e))
+                .map_err(|e| StorageError::Corrupt { what: "height key", reason: e.to_string() }.into())
         } else {
-            Err(anyhow!("Invalid height key format"))
+            Err(StorageError::Corrupt { what: "height key", reason: "missing block prefix".to_string() }.into())
         }
     }
 }
@@ -242,6 +555,7 @@ mod tests {
                 proposer: "test-node".to_string(),
                 round: 1,
                 view: 1,
+                vrf_proof: None,
             },
             transactions: vec![],
             signatures: vec![],
@@ -269,6 +583,18 @@ mod tests {
         assert_eq!(store.count_blocks().unwrap(), 1);
     }
 
+    #[test]
+    fn test_missing_block_is_not_found() {
+        let (_temp_dir, db) = create_test_db();
+        let store = BlockStore::new(db).unwrap();
+        store.put_block(&create_test_block(1)).unwrap();
+
+        assert_eq!(store.require_block(1).unwrap(), create_test_block(1));
+
+        let error = store.require_block(2).unwrap_err();
+        assert!(matches!(error.downcast_ref::<StorageError>(), Some(StorageError::NotFound(_))));
+    }
+
     #[test]
     fn test_block_store_by_hash() {
         let (_temp_dir, db) = create_test_db();
@@ -282,4 +608,39 @@ mod tests {
         assert!(retrieved.is_some());
         assert_eq!(retrieved.unwrap(), block);
     }
+
+    #[test]
+    fn test_paging_visits_every_block_once() {
+        let (_temp_dir, db) = create_test_db();
+        let store = BlockStore::new(db).unwrap();
+
+        for height in 0..1000 {
+            store.put_block(&create_test_block(height)).unwrap();
+        }
+
+        let mut seen = Vec::new();
+        let mut cursor = None;
+        let mut pages = 0;
+        loop {
+            let (blocks, next) = store.get_blocks_range_paged(0, 100, cursor).unwrap();
+            assert!(blocks.len() <= 100);
+            seen.extend(blocks.iter().map(|block| block.header.height));
+            pages += 1;
+
+            match next {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+
+        assert_eq!(pages, 10);
+        assert_eq!(seen, (0..1000).collect::<Vec<_>>());
+
+        // The limit is capped server-side and the start height is honoured
+        let (blocks, next) = store.get_blocks_range_paged(0, usize::MAX, None).unwrap();
+        assert_eq!(blocks.len(), MAX_PAGE_SIZE.min(1000));
+        assert!(next.is_none());
+        let (blocks, _) = store.get_blocks_range_paged(995, 100, None).unwrap();
+        assert_eq!(blocks.len(), 5);
+    }
 }
@@ -0,0 +1,77 @@
+use anyhow::Result;
+use rocksdb::{DB, IteratorMode, WriteBatch};
+use std::sync::Arc;
+use uuid::Uuid;
+use crate::types::Transaction;
+use super::StorageError;
+
+const CF_MEMPOOL: &str = "mempool";
+
+/// Pending transactions kept on disk so the mempool survives a restart
+#[derive(Clone)]
+pub struct MempoolStore {
+    db: Arc<DB>,
+}
+
+impl MempoolStore {
+    pub fn new(db: Arc<DB>) -> Result<Self> {
+        Ok(Self { db })
+    }
+
+    /// Store a pending transaction
+    pub fn put_transaction(&self, tx: &Transaction) -> Result<()> {
+        let cf = self.db.cf_handle(CF_MEMPOOL)
+            .ok_or(StorageError::MissingColumnFamily(CF_MEMPOOL))?;
+
+        let serialized = bincode::serialize(tx)
+            .map_err(|e| StorageError::Serialization { what: "pending transaction", reason: e.to_string() })?;
+
+        self.db.put_cf(cf, tx.id.as_bytes(), &serialized)
+            .map_err(|e| StorageError::Database { action: "store pending transaction", source: e }.into())
+    }
+
+    /// Delete a pending transaction
+    pub fn delete_transaction(&self, tx_id: &Uuid) -> Result<()> {
+        let cf = self.db.cf_handle(CF_MEMPOOL)
+            .ok_or(StorageError::MissingColumnFamily(CF_MEMPOOL))?;
+
+        self.db.delete_cf(cf, tx_id.as_bytes())
+            .map_err(|e| StorageError::Database { action: "delete pending transaction", source: e }.into())
+    }
+
+    /// Get every stored pending transaction
+    pub fn get_transactions(&self) -> Result<Vec<Transaction>> {
+        let cf = self.db.cf_handle(CF_MEMPOOL)
+            .ok_or(StorageError::MissingColumnFamily(CF_MEMPOOL))?;
+
+        let mut transactions = Vec::new();
+        for item in self.db.iterator_cf(cf, IteratorMode::Start) {
+            let (_, value) = item?;
+            let tx = bincode::deserialize(&value)
+                .map_err(|e| StorageError::Corrupt { what: "pending transaction", reason: e.to_string() })?;
+            transactions.push(tx);
+        }
+
+        Ok(transactions)
+    }
+
+    /// Replace the stored transactions with `transactions` in one write
+    pub fn replace_all(&self, transactions: &[Transaction]) -> Result<()> {
+        let cf = self.db.cf_handle(CF_MEMPOOL)
+            .ok_or(StorageError::MissingColumnFamily(CF_MEMPOOL))?;
+
+        let mut batch = WriteBatch::default();
+        for item in self.db.iterator_cf(cf, IteratorMode::Start) {
+            let (key, _) = item?;
+            batch.delete_cf(cf, key);
+        }
+        for tx in transactions {
+            let serialized = bincode::serialize(tx)
+                .map_err(|e| StorageError::Serialization { what: "pending transaction", reason: e.to_string() })?;
+            batch.put_cf(cf, tx.id.as_bytes(), &serialized);
+        }
+
+        self.db.write(batch)
+            .map_err(|e| StorageError::Database { action: "store pending transactions", source: e }.into())
+    }
+}
@@ -1,64 +1,350 @@
-use anyhow::{Result, anyhow};
-use rocksdb::{DB, Options, ColumnFamily, ColumnFamilyDescriptor};
+use anyhow::Result;
+use rocksdb::backup::{BackupEngine, BackupEngineOptions, RestoreOptions};
+use rocksdb::{properties, Cache, DB, DBIteratorWithThreadMode, Env, IteratorMode, Options, ColumnFamily, ColumnFamilyDescriptor, WriteBatch};
 use serde::{Serialize, Deserialize};
+use std::collections::HashSet;
+use std::ffi::CStr;
 use std::path::Path;
 use std::sync::Arc;
-use crate::types::{Block, Transaction, Hash, BlockHeight, NodeState};
+use std::time::Instant;
+use uuid::Uuid;
+use crate::consensus::governance::{self, ValidatorSet};
+use crate::metrics::NodeMetrics;
+use crate::types::{Address, Block, Transaction, Hash, BlockHeight, NodeState};
 
+pub mod audit_store;
 pub mod block_store;
+pub mod mempool_store;
+pub mod receipt_store;
+pub mod snapshot;
 pub mod state_store;
 pub mod transaction_store;
+pub mod tuning;
 
+use audit_store::AuditStore;
 use block_store::BlockStore;
+use mempool_store::MempoolStore;
+use receipt_store::ReceiptStore;
 use state_store::StateStore;
 use transaction_store::TransactionStore;
+pub use tuning::{Compression, StorageConfig};
 
 /// Column family names
 const CF_BLOCKS: &str = "blocks";
 const CF_TRANSACTIONS: &str = "transactions";
 const CF_STATE: &str = "state";
 const CF_METADATA: &str = "metadata";
+const CF_AUDIT: &str = "audit";
+const CF_MEMPOOL: &str = "mempool";
+const CF_RECEIPTS: &str = "receipts";
+
+const ALL_CFS: [&str; 7] = [
+    CF_BLOCKS, CF_TRANSACTIONS, CF_STATE, CF_METADATA, CF_AUDIT, CF_MEMPOOL, CF_RECEIPTS,
+];
+
+/// Metadata key of the schema version the database was last migrated to
+const SCHEMA_VERSION_KEY: &str = "schema_version";
+
+/// Metadata key of the validator set in effect at the head
+const VALIDATOR_SET_KEY: &str = "validator_set";
+
+/// Metadata key of the validator set a block's governance actions replaced
+fn validator_set_undo_key(height: BlockHeight) -> String {
+    format!("validator_set_undo_{:020}", height)
+}
+
+/// Schema version written by this build; version 1 predates versioning
+const SCHEMA_VERSION: u32 = 4;
+
+/// An upgrade step taking the schema from the previous version to `version`
+struct Migration {
+    version: u32,
+    description: &'static str,
+    run: fn(&Storage) -> Result<()>,
+}
+
+/// Upgrade steps in version order; a step may run again if it is interrupted
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 2,
+        description: "index transactions by sender, recipient and time",
+        run: reindex_transactions,
+    },
+    Migration {
+        version: 3,
+        description: "write receipts for stored blocks",
+        run: backfill_receipts,
+    },
+    Migration {
+        version: 4,
+        description: "encode stored blocks with the VRF proof header field",
+        run: upgrade_block_encoding,
+    },
+];
+
+fn reindex_transactions(storage: &Storage) -> Result<()> {
+    let indexed = storage.transaction_store.reindex()?;
+    tracing::info!("Indexed {} stored transactions", indexed);
+    Ok(())
+}
+
+fn backfill_receipts(storage: &Storage) -> Result<()> {
+    // Runs before version 4, while blocks are still in the older encoding
+    for block in storage.block_store.iter_legacy()? {
+        let mut batch = WriteBatch::default();
+        storage.receipt_store.stage_receipts(&block?, &mut batch)?;
+        storage.db.write(batch)
+            .map_err(|e| StorageError::Database { action: "write receipts", source: e })?;
+    }
+    Ok(())
+}
+
+fn upgrade_block_encoding(storage: &Storage) -> Result<()> {
+    // One batch that also records the new version, so a rerun never meets a mix of encodings
+    let mut batch = WriteBatch::default();
+    let upgraded = storage.block_store.stage_upgrade_encoding(&mut batch)?;
+    storage.stage_metadata(SCHEMA_VERSION_KEY, &4u32, &mut batch)?;
+    storage.db.write(batch)
+        .map_err(|e| StorageError::Database { action: "re-encode blocks", source: e })?;
+    tracing::info!("Re-encoded {} stored blocks and headers", upgraded);
+    Ok(())
+}
+
+/// Storage errors
+///
+/// Raised inside `anyhow::Result` like `ValidationError`; callers that need to
+/// tell them apart use `downcast_ref::<StorageError>()`.
+#[derive(Debug, thiserror::Error)]
+pub enum StorageError {
+    #[error("{0} not found")]
+    NotFound(String),
+    #[error("{0} column family not found")]
+    MissingColumnFamily(&'static str),
+    #[error("Corrupt {what}: {reason}")]
+    Corrupt { what: &'static str, reason: String },
+    #[error("Failed to serialize {what}: {reason}")]
+    Serialization { what: &'static str, reason: String },
+    #[error("Failed to {action}: {source}")]
+    Database { action: &'static str, source: rocksdb::Error },
+    #[error("Historical state unavailable: {0}")]
+    HistoryUnavailable(String),
+    #[error("Invalid state transition: {0}")]
+    InvalidState(String),
+    #[error("Backup failed: {0}")]
+    Backup(String),
+    #[error("Snapshot failed: {0}")]
+    Snapshot(String),
+}
+
+/// How committing a block changed the canonical chain
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChainUpdate {
+    /// The block was applied on top of the head
+    Extended { state_root: Hash },
+    /// The block completed a longer branch: `depth` canonical blocks were rolled
+    /// back and the branch replayed in their place. `orphaned` holds the rolled-back
+    /// transactions the branch doesn't include, in their original order.
+    Reorged { state_root: Hash, depth: u64, orphaned: Vec<Transaction> },
+    /// The block was kept by hash on a branch no longer than the canonical chain
+    SideBranch,
+}
+
+impl ChainUpdate {
+    /// State root of the new head, unless the head didn't move
+    pub fn state_root(&self) -> Option<Hash> {
+        match self {
+            ChainUpdate::Extended { state_root } | ChainUpdate::Reorged { state_root, .. } => Some(*state_root),
+            ChainUpdate::SideBranch => None,
+        }
+    }
+}
+
+/// Largest page any paged query returns, whatever limit the caller asks for
+pub const MAX_PAGE_SIZE: usize = 1000;
 
 /// Main storage interface for the blockchain node
+#[derive(Clone)]
 pub struct Storage {
     db: Arc<DB>,
     block_store: BlockStore,
     state_store: StateStore,
     transaction_store: TransactionStore,
+    audit_store: AuditStore,
+    mempool_store: MempoolStore,
+    receipt_store: ReceiptStore,
+    // Record every account change made by committed blocks
+    audit_log: bool,
+    // Sender whose governance transactions change the stored validator set
+    governance_authority: Option<Address>,
+    // Kept to read the statistics collected by RocksDB
+    opts: Options,
+    // Counts and times block writes, when metrics are enabled
+    metrics: Option<Arc<NodeMetrics>>,
 }
 
 impl Storage {
-    /// Create a new storage instance
+    /// Create a new storage instance with the default RocksDB tuning
+    ///
+    /// Missing column families are created and a database written by an older
+    /// build is migrated to the current schema version.
     pub fn new<P: AsRef<Path>>(db_path: P) -> Result<Self> {
+        Self::open_with(db_path, &StorageConfig::default())
+    }
+
+    /// Create a new storage instance, tuning each column family from `config`
+    pub fn open_with<P: AsRef<Path>>(db_path: P, config: &StorageConfig) -> Result<Self> {
+        config.validate()?;
+        let db_path = db_path.as_ref();
         let mut opts = Options::default();
         opts.create_if_missing(true);
         opts.create_missing_column_families(true);
+        opts.enable_statistics();
 
-        // Define column families
-        let cfs = vec![
-            ColumnFamilyDescriptor::new(CF_BLOCKS, Options::default()),
-            ColumnFamilyDescriptor::new(CF_TRANSACTIONS, Options::default()),
-            ColumnFamilyDescriptor::new(CF_STATE, Options::default()),
-            ColumnFamilyDescriptor::new(CF_METADATA, Options::default()),
-        ];
+        // Every existing column family must be opened, including any this build doesn't use
+        let mut names: Vec<String> = ALL_CFS.iter().map(|name| name.to_string()).collect();
+        for existing in DB::list_cf(&opts, db_path).unwrap_or_default() {
+            if !names.contains(&existing) {
+                names.push(existing);
+            }
+        }
+        let cache = Cache::new_lru_cache(config.block_cache_size);
+        let cfs = names.into_iter().map(|name| {
+            let cf_opts = config.cf_options(&name, &cache);
+            ColumnFamilyDescriptor::new(name, cf_opts)
+        });
 
         let db = DB::open_cf_descriptors(&opts, db_path, cfs)
-            .map_err(|e| anyhow!("Failed to open database: {}", e))?;
-        
-        let db = Arc::new(db);
+            .map_err(|e| StorageError::Database { action: "open database", source: e })?;
+
+        let storage = Self::from_db(Arc::new(db), opts)?;
+        storage.migrate()?;
+        Ok(storage)
+    }
 
+    /// Open an existing database without write access
+    ///
+    /// Column families the database lacks aren't created; reads from them fail
+    /// with `StorageError::MissingColumnFamily`.
+    pub fn open_read_only<P: AsRef<Path>>(db_path: P) -> Result<Self> {
+        let db_path = db_path.as_ref();
+        let mut opts = Options::default();
+        opts.enable_statistics();
+
+        let existing = DB::list_cf(&opts, db_path)
+            .map_err(|e| StorageError::Database { action: "list column families", source: e })?;
+        let db = DB::open_cf_for_read_only(&opts, db_path, existing, false)
+            .map_err(|e| StorageError::Database { action: "open database read-only", source: e })?;
+
+        Self::from_db(Arc::new(db), opts)
+    }
+
+    fn from_db(db: Arc<DB>, opts: Options) -> Result<Self> {
         let block_store = BlockStore::new(db.clone())?;
         let state_store = StateStore::new(db.clone())?;
         let transaction_store = TransactionStore::new(db.clone())?;
+        let audit_store = AuditStore::new(db.clone())?;
+        let mempool_store = MempoolStore::new(db.clone())?;
+        let receipt_store = ReceiptStore::new(db.clone())?;
 
         Ok(Self {
             db,
             block_store,
             state_store,
             transaction_store,
+            audit_store,
+            mempool_store,
+            receipt_store,
+            audit_log: false,
+            governance_authority: None,
+            opts,
+            metrics: None,
         })
     }
 
+    /// Get the schema version the database was last migrated to
+    pub fn schema_version(&self) -> Result<Option<u32>> {
+        self.get_metadata(SCHEMA_VERSION_KEY)
+    }
+
+    /// Bring the database up to `SCHEMA_VERSION` by running the migrations it is missing
+    ///
+    /// A fresh database starts at the current version; one written before
+    /// versioning was added is treated as version 1.
+    fn migrate(&self) -> Result<()> {
+        let version = match self.schema_version()? {
+            Some(version) => version,
+            None if self.is_empty()? => SCHEMA_VERSION,
+            None => 1,
+        };
+        if version > SCHEMA_VERSION {
+            return Err(StorageError::InvalidState(format!(
+                "database schema version {} is newer than this build supports ({})", version, SCHEMA_VERSION
+            )).into());
+        }
+
+        for migration in MIGRATIONS.iter().filter(|migration| migration.version > version) {
+            tracing::info!("Migrating database to schema version {}: {}", migration.version, migration.description);
+            (migration.run)(self)?;
+            self.put_metadata(SCHEMA_VERSION_KEY, &migration.version)?;
+        }
+
+        self.put_metadata(SCHEMA_VERSION_KEY, &SCHEMA_VERSION)
+    }
+
+    /// Check no column family holds any data
+    fn is_empty(&self) -> Result<bool> {
+        for name in ALL_CFS {
+            let cf = self.db.cf_handle(name)
+                .ok_or(StorageError::MissingColumnFamily(name))?;
+            if self.db.iterator_cf(cf, rocksdb::IteratorMode::Start).next().is_some() {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Keep account history so state can be read at past heights
+    pub fn with_archive(mut self, archive: bool) -> Self {
+        self.state_store = self.state_store.with_archive(archive);
+        self
+    }
+
+    /// Write an audit record for every balance and nonce change applied by `commit_block`
+    pub fn with_audit_log(mut self, audit_log: bool) -> Self {
+        self.audit_log = audit_log;
+        self
+    }
+
+    /// Apply validator set changes from governance transactions `authority` sends in committed blocks
+    ///
+    /// Only takes effect once a validator set is stored with `put_validator_set`.
+    pub fn with_governance_authority(mut self, authority: Address) -> Self {
+        self.governance_authority = Some(authority);
+        self
+    }
+
+    /// Report the count and latency of block writes
+    pub fn with_metrics(mut self, metrics: Arc<NodeMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Run a write operation, recording it in the storage metrics
+    fn timed<T>(&self, operation: impl FnOnce() -> Result<T>) -> Result<T> {
+        let started = Instant::now();
+        let result = operation();
+        if let Some(metrics) = &self.metrics {
+            metrics.storage_operations.inc();
+            metrics.storage_latency.observe(started.elapsed().as_secs_f64());
+        }
+        result
+    }
+
+    /// Check if committed blocks are written to the audit log
+    pub fn audit_log_enabled(&self) -> bool {
+        self.audit_log
+    }
+
     /// Get block store
     pub fn blocks(&self) -> &BlockStore {
         &self.block_store
@@ -74,18 +360,281 @@ impl Storage {
         &self.transaction_store
     }
 
+    /// Get audit store
+    pub fn audit(&self) -> &AuditStore {
+        &self.audit_store
+    }
+
+    /// Get pending transaction store
+    pub fn mempool(&self) -> &MempoolStore {
+        &self.mempool_store
+    }
+
+    /// Get transaction receipt store
+    pub fn receipts(&self) -> &ReceiptStore {
+        &self.receipt_store
+    }
+
+    /// Take a point-in-time view of every column family
+    ///
+    /// Reads through the snapshot see either all of a committed block or none of
+    /// it, so a request can read several stores without racing block commits.
+    pub fn snapshot(&self) -> StorageSnapshot<'_> {
+        StorageSnapshot::new(&self.db)
+    }
+
     /// Store a complete block with all its data
     pub fn store_block(&self, block: &Block) -> Result<()> {
-        // Store the block
-        self.block_store.put_block(block)?;
+        self.timed(|| {
+            // Store the block
+            self.block_store.put_block(block)?;
+
+            // Store all transactions in the block
+            for tx in &block.transactions {
+                self.transaction_store.put_transaction(tx)?;
+            }
+
+            // Update latest block height
+            self.put_metadata("latest_height", &block.header.height)?;
+            self.put_metadata("head_hash", &block.hash())?;
+
+            Ok(())
+        })
+    }
+
+    /// Store a committed block and apply its transfers in a single atomic write
+    ///
+    /// A block competing with the canonical chain, at an occupied height or on top
+    /// of a side block, is kept by hash instead, and the node reorgs onto its
+    /// branch once that is longer than the canonical chain. A block that overdraws
+    /// any account is rejected before anything is written, as is one whose parent
+    /// is neither the head nor another stored block right below it. The first
+    /// block of an empty chain is taken as its root.
+    pub fn commit_block(&self, block: &Block) -> Result<ChainUpdate> {
+        self.timed(|| self.apply_block(block))
+    }
+
+    /// Store a block on whichever branch it belongs to; see `commit_block`
+    fn apply_block(&self, block: &Block) -> Result<ChainUpdate> {
+        let height = block.header.height;
+        let replaces_canonical = self.block_store.get_block(height)?
+            .is_some_and(|existing| existing.hash() != block.hash());
+        let extends_side_branch = height > 0
+            && self.block_store.get_side_block(&block.header.previous_hash)?.is_some();
+
+        if !replaces_canonical && !extends_side_branch {
+            let extends_head = match self.get_latest_height()? {
+                Some(latest) => height == latest + 1
+                    && self.get_head_hash()? == Some(block.header.previous_hash),
+                None => true,
+            };
+            if !extends_head {
+                return Err(StorageError::InvalidState(format!(
+                    "block {} at height {} does not extend the head", hex::encode(block.hash()), height
+                )).into());
+            }
+            return self.extend_chain(block).map(|state_root| ChainUpdate::Extended { state_root });
+        }
+
+        // A competing block must fork from a stored block right below it
+        let parent_height = self.block_store.find_block(&block.header.previous_hash)?
+            .map(|parent| parent.header.height);
+        if height == 0 || parent_height != Some(height - 1) {
+            return Err(StorageError::InvalidState(format!(
+                "block {} at height {} has no stored parent", hex::encode(block.hash()), height
+            )).into());
+        }
 
-        // Store all transactions in the block
+        // The longer branch wins; on a tie the branch seen first stays canonical
+        if height > self.get_latest_height()?.unwrap_or(0) {
+            return self.switch_to(block);
+        }
+
+        let mut batch = WriteBatch::default();
+        self.block_store.stage_side_block(block, &mut batch)?;
+        self.db.write(batch)
+            .map_err(|e| StorageError::Database { action: "store side block", source: e })?;
+
+        Ok(ChainUpdate::SideBranch)
+    }
+
+    /// Make a stored block the head, rolling back to its common ancestor with the
+    /// canonical chain and replaying its branch from there
+    ///
+    /// Only the last `state_store::UNDO_DEPTH` blocks can be rolled back.
+    pub fn reorg_to(&self, new_head: &Hash) -> Result<ChainUpdate> {
+        let head = self.block_store.find_block(new_head)?
+            .ok_or_else(|| StorageError::NotFound(format!("Block {}", hex::encode(new_head))))?;
+        self.switch_to(&head)
+    }
+
+    /// Get the hash of the head of the canonical chain
+    pub fn get_head_hash(&self) -> Result<Option<Hash>> {
+        self.get_metadata("head_hash")
+    }
+
+    /// Apply a block on top of the canonical chain
+    fn extend_chain(&self, block: &Block) -> Result<Hash> {
+        let mut batch = WriteBatch::default();
+        let (state_root, audit_records) = self.state_store.stage_block_audited(block, &mut batch)?;
+        if self.audit_log {
+            self.audit_store.stage_records(block.header.height, &audit_records, &mut batch)?;
+        }
+
+        self.block_store.stage_block(block, &mut batch)?;
         for tx in &block.transactions {
-            self.transaction_store.put_transaction(tx)?;
+            self.transaction_store.stage_transaction(tx, &mut batch)?;
+        }
+        self.receipt_store.stage_receipts(block, &mut batch)?;
+        self.stage_governance(block, &mut self.validator_set()?, &mut batch)?;
+        self.stage_head(block, &mut batch)?;
+
+        self.db.write(batch)
+            .map_err(|e| StorageError::Database { action: "commit block", source: e })?;
+
+        Ok(state_root)
+    }
+
+    /// Switch the canonical chain to the branch ending at `head` in a single atomic write
+    ///
+    /// The audit log is append-only: it keeps the records of rolled-back blocks and
+    /// gains the branch's after them.
+    fn switch_to(&self, head: &Block) -> Result<ChainUpdate> {
+        // Walk back from the new head to the first block on the canonical chain
+        let mut branch = Vec::new();
+        let mut cursor = head.clone();
+        let ancestor = loop {
+            if self.block_store.get_block_by_hash(&cursor.hash())?.is_some() {
+                break cursor;
+            }
+            if cursor.header.height == 0 {
+                return Err(StorageError::InvalidState(
+                    format!("branch of block {} shares no ancestor with the canonical chain", hex::encode(head.hash()))
+                ).into());
+            }
+            let parent = self.block_store.find_block(&cursor.header.previous_hash)?
+                .ok_or_else(|| StorageError::NotFound(format!("Parent block {}", hex::encode(cursor.header.previous_hash))))?;
+            branch.push(std::mem::replace(&mut cursor, parent));
+        };
+
+        let tip = self.get_latest_height()?.unwrap_or(ancestor.header.height);
+        let rolled_back = self.block_store.get_blocks_range(ancestor.header.height + 1, tip)?;
+
+        let mut batch = WriteBatch::default();
+        let heights: Vec<BlockHeight> = rolled_back.iter().rev().map(|block| block.header.height).collect();
+        let mut pending = self.state_store.stage_rollback(&heights, &mut batch)?;
+        let mut validator_set = self.stage_validator_set_rollback(&heights, &mut batch)?;
+        for block in &rolled_back {
+            self.block_store.stage_demote(block, &mut batch)?;
+            for tx in &block.transactions {
+                self.transaction_store.stage_delete(tx, &mut batch)?;
+            }
+            self.receipt_store.stage_remove_receipts(block, &mut batch)?;
+        }
+
+        // Later writes in a batch win, so the branch replaces what was just removed
+        let mut state_root = self.state_store.pending_state_root(&pending)?;
+        for block in branch.iter().rev() {
+            let audit_records;
+            (state_root, audit_records) = self.state_store.stage_block_over(block, &mut pending, &mut batch)?;
+            if self.audit_log {
+                self.audit_store.stage_records(block.header.height, &audit_records, &mut batch)?;
+            }
+            self.block_store.stage_promote(block, &mut batch)?;
+            for tx in &block.transactions {
+                self.transaction_store.stage_transaction(tx, &mut batch)?;
+            }
+            self.receipt_store.stage_receipts(block, &mut batch)?;
+            self.stage_governance(block, &mut validator_set, &mut batch)?;
+        }
+        if let Some(validator_set) = &validator_set {
+            self.stage_metadata(VALIDATOR_SET_KEY, validator_set, &mut batch)?;
+        }
+        self.stage_head(branch.first().unwrap_or(&ancestor), &mut batch)?;
+
+        self.db.write(batch)
+            .map_err(|e| StorageError::Database { action: "reorg chain", source: e })?;
+
+        let replayed: HashSet<Uuid> = branch.iter()
+            .flat_map(|block| block.transactions.iter().map(|tx| tx.id))
+            .collect();
+        let orphaned = rolled_back.iter()
+            .flat_map(|block| block.transactions.iter())
+            .filter(|tx| !replayed.contains(&tx.id))
+            .cloned()
+            .collect();
+
+        Ok(ChainUpdate::Reorged { state_root, depth: rolled_back.len() as u64, orphaned })
+    }
+
+    /// Get the validator set in effect at the head, if one is stored
+    pub fn validator_set(&self) -> Result<Option<ValidatorSet>> {
+        self.get_metadata(VALIDATOR_SET_KEY)
+    }
+
+    /// Store the validator set in effect at the head, such as the genesis set
+    pub fn put_validator_set(&self, validator_set: &ValidatorSet) -> Result<()> {
+        self.put_metadata(VALIDATOR_SET_KEY, validator_set)
+    }
+
+    /// Apply a block's governance actions to `validator_set` in a write batch
+    ///
+    /// The set the block replaced is kept, so a reorg can roll the change back.
+    fn stage_governance(&self, block: &Block, validator_set: &mut Option<ValidatorSet>, batch: &mut WriteBatch) -> Result<()> {
+        let (Some(authority), Some(current)) = (&self.governance_authority, validator_set.as_ref()) else {
+            return Ok(());
+        };
+
+        if let Some(updated) = governance::apply_block(current, block, authority) {
+            tracing::info!("Validator set changed at height {}: {:?}", block.header.height, updated.node_ids());
+            self.stage_metadata(&validator_set_undo_key(block.header.height), current, batch)?;
+            self.stage_metadata(VALIDATOR_SET_KEY, &updated, batch)?;
+            *validator_set = Some(updated);
+        }
+
+        Ok(())
+    }
+
+    /// Restore the validator set from before the blocks at `heights`, newest first, in a write batch
+    fn stage_validator_set_rollback(&self, heights: &[BlockHeight], batch: &mut WriteBatch) -> Result<Option<ValidatorSet>> {
+        let metadata_cf = self.db.cf_handle(CF_METADATA)
+            .ok_or(StorageError::MissingColumnFamily(CF_METADATA))?;
+
+        let mut validator_set = self.validator_set()?;
+        for &height in heights {
+            let key = validator_set_undo_key(height);
+            if let Some(previous) = self.get_metadata(&key)? {
+                validator_set = Some(previous);
+                batch.delete_cf(metadata_cf, key.as_bytes());
+            }
         }
 
-        // Update latest block height
-        self.put_metadata("latest_height", &block.header.height)?;
+        Ok(validator_set)
+    }
+
+    /// Add a metadata write to a batch
+    fn stage_metadata<T: Serialize>(&self, key: &str, value: &T, batch: &mut WriteBatch) -> Result<()> {
+        let metadata_cf = self.db.cf_handle(CF_METADATA)
+            .ok_or(StorageError::MissingColumnFamily(CF_METADATA))?;
+
+        let serialized = bincode::serialize(value)
+            .map_err(|e| StorageError::Serialization { what: "metadata", reason: e.to_string() })?;
+        batch.put_cf(metadata_cf, key.as_bytes(), &serialized);
+
+        Ok(())
+    }
+
+    /// Point the chain head at a block in a write batch
+    fn stage_head(&self, block: &Block, batch: &mut WriteBatch) -> Result<()> {
+        let metadata_cf = self.db.cf_handle(CF_METADATA)
+            .ok_or(StorageError::MissingColumnFamily(CF_METADATA))?;
+
+        let height = bincode::serialize(&block.header.height)
+            .map_err(|e| StorageError::Serialization { what: "metadata", reason: e.to_string() })?;
+        let head_hash = bincode::serialize(&block.hash())
+            .map_err(|e| StorageError::Serialization { what: "metadata", reason: e.to_string() })?;
+        batch.put_cf(metadata_cf, b"latest_height", &height);
+        batch.put_cf(metadata_cf, b"head_hash", &head_hash);
 
         Ok(())
     }
@@ -108,13 +657,13 @@ impl Storage {
     /// Store metadata
     pub fn put_metadata<T: Serialize>(&self, key: &str, value: &T) -> Result<()> {
         let cf = self.db.cf_handle(CF_METADATA)
-            .ok_or_else(|| anyhow!("Metadata column family not found"))?;
+            .ok_or(StorageError::MissingColumnFamily(CF_METADATA))?;
         
         let serialized = bincode::serialize(value)
-            .map_err(|e| anyhow!("Failed to serialize metadata: {}", e))?;
+            .map_err(|e| StorageError::Serialization { what: "metadata", reason: e.to_string() })?;
         
         self.db.put_cf(cf, key.as_bytes(), &serialized)
-            .map_err(|e| anyhow!("Failed to store metadata: {}", e))?;
+            .map_err(|e| StorageError::Database { action: "store metadata", source: e })?;
 
         Ok(())
     }
@@ -122,18 +671,28 @@ impl Storage {
     /// Get metadata
     pub fn get_metadata<T: for<'de> Deserialize<'de>>(&self, key: &str) -> Result<Option<T>> {
         let cf = self.db.cf_handle(CF_METADATA)
-            .ok_or_else(|| anyhow!("Metadata column family not found"))?;
+            .ok_or(StorageError::MissingColumnFamily(CF_METADATA))?;
         
         match self.db.get_cf(cf, key.as_bytes())? {
             Some(data) => {
                 let value = bincode::deserialize(&data)
-                    .map_err(|e| anyhow!("Failed to deserialize metadata: {}", e))?;
+                    .map_err(|e| StorageError::Corrupt { what: "metadata", reason: e.to_string() })?;
                 Ok(Some(value))
             }
             None => Ok(None),
         }
     }
 
+    /// Get metadata as of a snapshot
+    pub fn get_metadata_in<T: for<'de> Deserialize<'de>>(&self, snapshot: &StorageSnapshot, key: &str) -> Result<Option<T>> {
+        decode(snapshot.get_cf(CF_METADATA, key.as_bytes())?, "metadata")
+    }
+
+    /// Get the latest block height as of a snapshot
+    pub fn get_latest_height_in(&self, snapshot: &StorageSnapshot) -> Result<Option<BlockHeight>> {
+        self.get_metadata_in(snapshot, "latest_height")
+    }
+
     /// Get database statistics
     pub fn get_stats(&self) -> Result<StorageStats> {
         let latest_height = self.get_latest_height()?.unwrap_or(0);
@@ -148,11 +707,53 @@ impl Storage {
         })
     }
 
-    /// Estimate database size
-    fn estimate_db_size(&self) -> Result<u64> {
-        // This is a simplified estimation
-        // In production, you'd want more accurate size calculation
-        Ok(0) // Placeholder
+    /// Read RocksDB internal properties, summed across column families
+    pub fn get_rocksdb_stats(&self) -> Result<RocksDbStats> {
+        let mut stats = RocksDbStats::default();
+
+        for name in ALL_CFS {
+            let cf = self.db.cf_handle(name)
+                .ok_or(StorageError::MissingColumnFamily(name))?;
+            let property = |prop: &CStr| -> Result<u64> {
+                Ok(self.db.property_int_value_cf(cf, prop)
+                    .map_err(|e| StorageError::Database { action: "read RocksDB property", source: e })?
+                    .unwrap_or(0))
+            };
+
+            stats.pending_compaction_bytes += property(properties::ESTIMATE_PENDING_COMPACTION_BYTES)?;
+            stats.memtable_bytes += property(properties::CUR_SIZE_ALL_MEM_TABLES)?;
+            stats.estimated_keys += property(properties::ESTIMATE_NUM_KEYS)?;
+            // Every column family reports the one shared cache
+            stats.block_cache_usage_bytes = stats.block_cache_usage_bytes.max(property(properties::BLOCK_CACHE_USAGE)?);
+        }
+
+        if let Some(statistics) = self.opts.get_statistics() {
+            stats.block_cache_hits = ticker_count(&statistics, "rocksdb.block.cache.hit");
+            stats.block_cache_misses = ticker_count(&statistics, "rocksdb.block.cache.miss");
+        }
+
+        Ok(stats)
+    }
+
+    /// Estimate the database size in bytes, summed across column families
+    ///
+    /// Counts SST files plus memtables, whose contents are on disk in the WAL
+    /// until they are flushed.
+    pub fn estimate_db_size(&self) -> Result<u64> {
+        let mut total = 0;
+
+        for name in ALL_CFS {
+            let cf = self.db.cf_handle(name)
+                .ok_or(StorageError::MissingColumnFamily(name))?;
+
+            for property in [properties::TOTAL_SST_FILES_SIZE, properties::CUR_SIZE_ALL_MEM_TABLES] {
+                total += self.db.property_int_value_cf(cf, property)
+                    .map_err(|e| StorageError::Database { action: "read RocksDB property", source: e })?
+                    .unwrap_or(0);
+            }
+        }
+
+        Ok(total)
     }
 
     /// Compact the database
@@ -161,10 +762,42 @@ impl Storage {
         Ok(())
     }
 
-    /// Create a backup of the database
+    /// Create a backup of the database and verify it
+    ///
+    /// Repeated backups into the same directory are incremental: SST files
+    /// already backed up are shared rather than copied again.
     pub fn backup<P: AsRef<Path>>(&self, backup_path: P) -> Result<()> {
-        // Implementation would depend on RocksDB backup API
-        // This is a placeholder
+        let mut engine = open_backup_engine(backup_path.as_ref())?;
+
+        // Flush memtables so the backup doesn't depend on replaying the WAL
+        engine.create_new_backup_flush(&self.db, true)
+            .map_err(|e| StorageError::Database { action: "create backup", source: e })?;
+
+        let backup_id = engine.get_backup_info()
+            .iter()
+            .map(|info| info.backup_id)
+            .max()
+            .ok_or_else(|| StorageError::NotFound("Backup after creation".to_string()))?;
+        engine.verify_backup(backup_id)
+            .map_err(|e| StorageError::Backup(format!("backup {} failed verification: {}", backup_id, e)))?;
+
+        Ok(())
+    }
+
+    /// Restore the latest backup into a new database directory
+    pub fn restore_from_backup<P: AsRef<Path>>(backup_path: P, db_path: P) -> Result<()> {
+        let db_path = db_path.as_ref();
+        // Never overwrite a live database
+        if db_path.join("CURRENT").exists() {
+            return Err(StorageError::Backup(
+                format!("refusing to restore over existing database at {}", db_path.display())
+            ).into());
+        }
+
+        let mut engine = open_backup_engine(backup_path.as_ref())?;
+        engine.restore_from_latest_backup(db_path, db_path, &RestoreOptions::default())
+            .map_err(|e| StorageError::Database { action: "restore backup", source: e })?;
+
         Ok(())
     }
 
@@ -175,6 +808,49 @@ impl Storage {
     }
 }
 
+/// A consistent view of the database, taken by `Storage::snapshot`
+///
+/// The store methods ending in `_in` read through it.
+pub struct StorageSnapshot<'a> {
+    db: &'a DB,
+    snapshot: rocksdb::Snapshot<'a>,
+}
+
+impl<'a> StorageSnapshot<'a> {
+    pub(crate) fn new(db: &'a DB) -> Self {
+        Self { db, snapshot: db.snapshot() }
+    }
+
+    /// Read a raw value from a column family
+    pub(crate) fn get_cf(&self, name: &'static str, key: impl AsRef<[u8]>) -> Result<Option<Vec<u8>>> {
+        let cf = self.db.cf_handle(name)
+            .ok_or(StorageError::MissingColumnFamily(name))?;
+
+        self.snapshot.get_cf(cf, key)
+            .map_err(|e| StorageError::Database { action: "read snapshot", source: e }.into())
+    }
+
+    /// Iterate a column family
+    pub(crate) fn iterator_cf(&self, name: &'static str, mode: IteratorMode) -> Result<DBIteratorWithThreadMode<'_, DB>> {
+        let cf = self.db.cf_handle(name)
+            .ok_or(StorageError::MissingColumnFamily(name))?;
+
+        Ok(self.snapshot.iterator_cf(cf, mode))
+    }
+}
+
+/// Deserialize a value read from a column family
+pub(crate) fn decode<T: for<'de> Deserialize<'de>>(data: Option<Vec<u8>>, what: &'static str) -> Result<Option<T>> {
+    match data {
+        Some(data) => {
+            let value = bincode::deserialize(&data)
+                .map_err(|e| StorageError::Corrupt { what, reason: e.to_string() })?;
+            Ok(Some(value))
+        }
+        None => Ok(None),
+    }
+}
+
 /// Storage statistics
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StorageStats {
@@ -184,11 +860,56 @@ pub struct StorageStats {
     pub db_size_bytes: u64,
 }
 
+/// RocksDB internal statistics
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RocksDbStats {
+    pub pending_compaction_bytes: u64,
+    pub memtable_bytes: u64,
+    pub estimated_keys: u64,
+    pub block_cache_usage_bytes: u64,
+    pub block_cache_hits: u64,
+    pub block_cache_misses: u64,
+}
+
+impl RocksDbStats {
+    /// Fraction of block cache lookups served from the cache
+    pub fn block_cache_hit_ratio(&self) -> f64 {
+        let lookups = self.block_cache_hits + self.block_cache_misses;
+        if lookups == 0 {
+            return 0.0;
+        }
+        self.block_cache_hits as f64 / lookups as f64
+    }
+}
+
+/// Open the backup engine for a backup directory, creating it if needed
+fn open_backup_engine(backup_path: &Path) -> Result<BackupEngine> {
+    let options = BackupEngineOptions::new(backup_path)
+        .map_err(|e| StorageError::Database { action: "open backup directory", source: e })?;
+    let env = Env::new()
+        .map_err(|e| StorageError::Database { action: "create RocksDB environment", source: e })?;
+
+    BackupEngine::open(&options, &env)
+        .map_err(|e| StorageError::Database { action: "open backup engine", source: e }.into())
+}
+
+/// Extract a ticker from the statistics dump, e.g. `rocksdb.block.cache.hit COUNT : 42`
+fn ticker_count(statistics: &str, ticker: &str) -> u64 {
+    statistics
+        .lines()
+        .find_map(|line| {
+            let rest = line.strip_prefix(ticker)?.trim_start();
+            rest.strip_prefix("COUNT :")?.trim().parse().ok()
+        })
+        .unwrap_or(0)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use tempfile::TempDir;
-    use crate::types::{BlockHeader, Transaction, ValidatorSignature};
+    use crate::types::{Address, BlockHeader, Transaction, ValidatorSignature};
+    use receipt_store::ReceiptStatus;
     use uuid::Uuid;
 
     fn create_test_block() -> Block {
@@ -202,6 +923,7 @@ mod tests {
                 proposer: "test-node".to_string(),
                 round: 1,
                 view: 1,
+                vrf_proof: None,
             },
             transactions: vec![],
             signatures: vec![],
@@ -230,4 +952,531 @@ mod tests {
         assert!(retrieved.is_some());
         assert_eq!(retrieved.unwrap(), block);
     }
+
+    fn create_transfer(from: Address, to: Address, amount: u64, nonce: u64) -> Transaction {
+        Transaction {
+            id: Uuid::new_v4(),
+            from,
+            to,
+            amount,
+            fee: 1,
+            nonce,
+            timestamp: 1234567890,
+            signature: [0u8; 64],
+            public_key: [0u8; 32],
+            data: vec![],
+        }
+    }
+
+    #[test]
+    fn test_commit_block_applies_transfers() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::new(temp_dir.path()).unwrap();
+        let (alice, bob, carol) = ([1u8; 20], [2u8; 20], [3u8; 20]);
+        storage.state().set_balance(&alice, 1000).unwrap();
+
+        let mut block = create_test_block();
+        block.transactions = vec![
+            create_transfer(alice, bob, 300, 1),
+            create_transfer(alice, carol, 100, 2),
+            create_transfer(bob, carol, 50, 1),
+        ];
+
+        let state_root = storage.commit_block(&block).unwrap().state_root().unwrap();
+
+        let state = storage.state();
+        assert_eq!(state.get_balance(&alice).unwrap(), 1000 - 301 - 101);
+        assert_eq!(state.get_balance(&bob).unwrap(), 300 - 51);
+        assert_eq!(state.get_balance(&carol).unwrap(), 150);
+        assert_eq!(state.get_nonce(&alice).unwrap(), 2);
+        assert_eq!(state.get_nonce(&bob).unwrap(), 1);
+        assert_eq!(state.get_nonce(&carol).unwrap(), 0);
+
+        assert_eq!(state_root, state.calculate_state_root().unwrap());
+        assert_eq!(state.get_state_root(1).unwrap(), Some(state_root));
+        assert_eq!(storage.get_latest_height().unwrap(), Some(1));
+        assert!(storage.transactions().get_transaction(&block.transactions[2].id).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_commit_block_writes_receipts() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::new(temp_dir.path()).unwrap();
+        let (alice, bob) = ([1u8; 20], [2u8; 20]);
+        storage.state().set_balance(&alice, 1000).unwrap();
+
+        let mut block = create_test_block();
+        block.transactions = vec![
+            create_transfer(alice, bob, 300, 1),
+            Transaction { fee: 7, ..create_transfer(alice, bob, 100, 2) },
+        ];
+        storage.commit_block(&block).unwrap();
+
+        for (index, tx) in block.transactions.iter().enumerate() {
+            let receipt = storage.receipts().get_receipt(&tx.id).unwrap().unwrap();
+            assert_eq!(receipt.tx_id, tx.id);
+            assert_eq!(receipt.block_height, 1);
+            assert_eq!(receipt.index, index as u32);
+            assert_eq!(receipt.status, ReceiptStatus::Success);
+            assert_eq!(receipt.fee_paid, tx.fee);
+        }
+        assert!(storage.receipts().get_receipt(&Uuid::new_v4()).unwrap().is_none());
+
+        // A rejected block leaves no receipts behind
+        let mut overdraft = create_test_block();
+        overdraft.header.height = 2;
+        overdraft.header.previous_hash = block.hash();
+        overdraft.transactions = vec![create_transfer(bob, alice, 10_000, 1)];
+        assert!(storage.commit_block(&overdraft).is_err());
+        assert!(storage.receipts().get_receipt(&overdraft.transactions[0].id).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_snapshot_reads_never_see_partial_block() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::new(temp_dir.path()).unwrap();
+        let (alice, bob) = ([1u8; 20], [2u8; 20]);
+        storage.state().set_balance(&alice, 1_000_000).unwrap();
+
+        // A snapshot taken before a commit keeps reading the chain as it was
+        let snapshot = storage.snapshot();
+        let mut block = create_test_block();
+        block.transactions = vec![create_transfer(alice, bob, 300, 1)];
+        storage.commit_block(&block).unwrap();
+
+        let tx_id = block.transactions[0].id;
+        assert_eq!(storage.get_latest_height_in(&snapshot).unwrap(), None);
+        assert!(storage.blocks().get_block_in(&snapshot, 1).unwrap().is_none());
+        assert!(storage.blocks().get_block_by_hash_in(&snapshot, &block.hash()).unwrap().is_none());
+        assert!(storage.transactions().get_transaction_in(&snapshot, &tx_id).unwrap().is_none());
+        assert!(storage.receipts().get_receipt_in(&snapshot, &tx_id).unwrap().is_none());
+        assert_eq!(storage.state().get_account_in(&snapshot, &alice).unwrap().unwrap().balance, 1_000_000);
+        assert!(storage.state().get_account_in(&snapshot, &bob).unwrap().is_none());
+        drop(snapshot);
+
+        assert_eq!(storage.get_latest_height().unwrap(), Some(1));
+        assert_eq!(storage.state().get_balance(&bob).unwrap(), 300);
+
+        // Snapshots taken while blocks commit see each block whole or not at all
+        let writer = {
+            let storage = storage.clone();
+            let mut parent = block.clone();
+            std::thread::spawn(move || {
+                for height in 2..=50 {
+                    let block = create_chain_block(&parent, "test-node", vec![create_transfer(alice, bob, 300, height)]);
+                    storage.commit_block(&block).unwrap();
+                    parent = block;
+                }
+            })
+        };
+        while !writer.is_finished() {
+            let snapshot = storage.snapshot();
+            let height = storage.get_latest_height_in(&snapshot).unwrap().unwrap();
+            let tip = storage.blocks().get_block_in(&snapshot, height).unwrap().unwrap();
+            let tx_id = tip.transactions[0].id;
+
+            assert!(storage.blocks().get_block_in(&snapshot, height + 1).unwrap().is_none());
+            assert!(storage.transactions().get_transaction_in(&snapshot, &tx_id).unwrap().is_some());
+            assert_eq!(storage.receipts().get_receipt_in(&snapshot, &tx_id).unwrap().unwrap().block_height, height);
+            assert_eq!(storage.state().get_account_in(&snapshot, &bob).unwrap().unwrap().balance, 300 * height);
+        }
+        writer.join().unwrap();
+    }
+
+    #[test]
+    fn test_overdraft_block_is_rejected_before_apply() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::new(temp_dir.path()).unwrap();
+        let (alice, bob) = ([1u8; 20], [2u8; 20]);
+        storage.state().set_balance(&alice, 500).unwrap();
+
+        // The first transfer is affordable, the second overdraws
+        let mut block = create_test_block();
+        block.transactions = vec![
+            create_transfer(alice, bob, 300, 1),
+            create_transfer(alice, bob, 300, 2),
+        ];
+
+        assert!(storage.commit_block(&block).is_err());
+
+        assert_eq!(storage.state().get_balance(&alice).unwrap(), 500);
+        assert_eq!(storage.state().get_balance(&bob).unwrap(), 0);
+        assert_eq!(storage.state().get_nonce(&alice).unwrap(), 0);
+        assert!(storage.blocks().get_block(1).unwrap().is_none());
+        assert_eq!(storage.get_latest_height().unwrap(), None);
+    }
+
+    fn create_chain_block(parent: &Block, proposer: &str, transactions: Vec<Transaction>) -> Block {
+        let mut block = create_test_block();
+        block.header.height = parent.header.height + 1;
+        block.header.previous_hash = parent.hash();
+        block.header.proposer = proposer.to_string();
+        block.transactions = transactions;
+        block
+    }
+
+    #[test]
+    fn test_longer_competing_branch_triggers_reorg() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::new(temp_dir.path()).unwrap();
+        let (alice, bob, carol) = ([1u8; 20], [2u8; 20], [3u8; 20]);
+        storage.state().set_balance(&alice, 1000).unwrap();
+
+        let genesis = create_test_block();
+        let a2 = create_chain_block(&genesis, "validator-1", vec![create_transfer(alice, bob, 300, 1)]);
+        let a3 = create_chain_block(&a2, "validator-1", vec![create_transfer(alice, carol, 100, 2)]);
+        for block in [&genesis, &a2, &a3] {
+            assert!(matches!(storage.commit_block(block).unwrap(), ChainUpdate::Extended { .. }));
+        }
+        let balances_before = storage.state().get_all_accounts().unwrap();
+
+        // A competing branch no longer than the canonical chain is only kept by hash
+        let b2 = create_chain_block(&genesis, "validator-2", vec![create_transfer(alice, carol, 500, 1)]);
+        let b3 = create_chain_block(&b2, "validator-2", vec![]);
+        assert_eq!(storage.commit_block(&b2).unwrap(), ChainUpdate::SideBranch);
+        assert_eq!(storage.commit_block(&b3).unwrap(), ChainUpdate::SideBranch);
+        assert_eq!(storage.state().get_all_accounts().unwrap(), balances_before);
+        assert_eq!(storage.blocks().get_block(2).unwrap(), Some(a2.clone()));
+        assert_eq!(storage.blocks().find_block(&b2.hash()).unwrap(), Some(b2.clone()));
+        assert_eq!(storage.get_head_hash().unwrap(), Some(a3.hash()));
+
+        // Outgrowing the canonical chain rolls back a2 and a3 and replays b2..b4
+        let b4 = create_chain_block(&b3, "validator-2", vec![create_transfer(carol, bob, 50, 1)]);
+        let update = storage.commit_block(&b4).unwrap();
+        let state_root = match update {
+            ChainUpdate::Reorged { state_root, depth, orphaned } => {
+                assert_eq!(depth, 2);
+                // Both of alice's rolled-back transfers are missing from the new branch
+                assert_eq!(orphaned, vec![a2.transactions[0].clone(), a3.transactions[0].clone()]);
+                state_root
+            }
+            other => panic!("expected a reorg, got {:?}", other),
+        };
+
+        let state = storage.state();
+        assert_eq!(state.get_balance(&alice).unwrap(), 1000 - 501);
+        assert_eq!(state.get_balance(&bob).unwrap(), 50);
+        assert_eq!(state.get_balance(&carol).unwrap(), 500 - 51);
+        assert_eq!(state.get_nonce(&alice).unwrap(), 1);
+        assert_eq!(state_root, state.calculate_state_root().unwrap());
+        assert_eq!(state.get_state_root(4).unwrap(), Some(state_root));
+
+        // The height index follows the new branch and the old one is kept by hash
+        for (height, block) in [(1, &genesis), (2, &b2), (3, &b3), (4, &b4)] {
+            assert_eq!(storage.blocks().get_block(height).unwrap().as_ref(), Some(block));
+        }
+        assert_eq!(storage.get_latest_height().unwrap(), Some(4));
+        assert_eq!(storage.get_head_hash().unwrap(), Some(b4.hash()));
+        assert_eq!(storage.blocks().count_blocks().unwrap(), 4);
+        assert!(storage.blocks().get_block_by_hash(&a2.hash()).unwrap().is_none());
+        assert_eq!(storage.blocks().get_side_block(&a3.hash()).unwrap(), Some(a3.clone()));
+
+        // Rolled-back transactions are gone, replayed ones are committed
+        let dropped = &a2.transactions[0];
+        assert!(storage.transactions().get_transaction(&dropped.id).unwrap().is_none());
+        assert!(storage.receipts().get_receipt(&dropped.id).unwrap().is_none());
+        assert_eq!(storage.receipts().get_receipt(&b2.transactions[0].id).unwrap().unwrap().block_height, 2);
+
+        // State matches a node that only ever saw the new branch
+        let fresh_dir = TempDir::new().unwrap();
+        let fresh = Storage::new(fresh_dir.path()).unwrap();
+        fresh.state().set_balance(&alice, 1000).unwrap();
+        for block in [&genesis, &b2, &b3, &b4] {
+            fresh.commit_block(block).unwrap();
+        }
+        assert_eq!(fresh.state().calculate_state_root().unwrap(), state_root);
+        assert_eq!(fresh.state().get_all_accounts().unwrap(), state.get_all_accounts().unwrap());
+    }
+
+    #[test]
+    fn test_governance_follows_the_canonical_chain() {
+        use crate::consensus::governance::GovernanceAction;
+
+        let temp_dir = TempDir::new().unwrap();
+        let (authority, bob) = ([1u8; 20], [2u8; 20]);
+        let storage = Storage::new(temp_dir.path()).unwrap().with_governance_authority(authority);
+        storage.state().set_balance(&authority, 1000).unwrap();
+        let genesis_set = ValidatorSet::new(vec![
+            ("validator-1".to_string(), [1u8; 32]),
+            ("validator-2".to_string(), [2u8; 32]),
+        ]);
+        storage.put_validator_set(&genesis_set).unwrap();
+
+        let governance = |action: GovernanceAction, nonce| Transaction {
+            data: action.encode(),
+            ..create_transfer(authority, bob, 1, nonce)
+        };
+        let add = GovernanceAction::AddValidator { node_id: "validator-3".to_string(), public_key: [3u8; 32] };
+        let remove = GovernanceAction::RemoveValidator("validator-2".to_string());
+
+        let genesis = create_test_block();
+        let a2 = create_chain_block(&genesis, "validator-1", vec![governance(add, 1)]);
+        storage.commit_block(&genesis).unwrap();
+        storage.commit_block(&a2).unwrap();
+        let added = storage.validator_set().unwrap().unwrap();
+        assert_eq!(added.node_ids(), vec!["validator-1", "validator-2", "validator-3"]);
+        assert_eq!(added.keys().get("validator-3"), Some(&[3u8; 32]));
+
+        // A side branch leaves the set alone until it becomes canonical
+        let b2 = create_chain_block(&genesis, "validator-2", vec![governance(remove, 1)]);
+        assert_eq!(storage.commit_block(&b2).unwrap(), ChainUpdate::SideBranch);
+        assert_eq!(storage.validator_set().unwrap(), Some(added));
+
+        // The reorg undoes a2's addition and applies b2's removal
+        let b3 = create_chain_block(&b2, "validator-1", vec![]);
+        assert!(matches!(storage.commit_block(&b3).unwrap(), ChainUpdate::Reorged { depth: 1, .. }));
+        assert_eq!(storage.validator_set().unwrap().unwrap().node_ids(), vec!["validator-1"]);
+
+        // Rolling b2 back in turn restores the genesis set
+        let a3 = create_chain_block(&a2, "validator-1", vec![]);
+        let a4 = create_chain_block(&a3, "validator-1", vec![]);
+        storage.commit_block(&a3).unwrap();
+        assert!(matches!(storage.commit_block(&a4).unwrap(), ChainUpdate::Reorged { depth: 2, .. }));
+        assert_eq!(
+            storage.validator_set().unwrap().unwrap().node_ids(),
+            vec!["validator-1", "validator-2", "validator-3"]
+        );
+    }
+
+    #[test]
+    fn test_block_not_on_a_stored_parent_is_rejected() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::new(temp_dir.path()).unwrap();
+        let (alice, bob) = ([1u8; 20], [2u8; 20]);
+        storage.state().set_balance(&alice, 1000).unwrap();
+
+        let genesis = create_test_block();
+        let a2 = create_chain_block(&genesis, "validator-1", vec![]);
+        storage.commit_block(&genesis).unwrap();
+        storage.commit_block(&a2).unwrap();
+
+        // Skipping a height, an unknown parent, or a fork from nowhere
+        let mut gap = create_chain_block(&a2, "validator-2", vec![create_transfer(alice, bob, 300, 1)]);
+        gap.header.height = 4;
+        let mut unknown_parent = create_chain_block(&a2, "validator-2", vec![create_transfer(alice, bob, 300, 1)]);
+        unknown_parent.header.previous_hash = [7; 32];
+        let mut dangling_fork = create_chain_block(&genesis, "validator-2", vec![]);
+        dangling_fork.header.previous_hash = [7; 32];
+
+        for block in [&gap, &unknown_parent, &dangling_fork] {
+            let error = storage.commit_block(block).unwrap_err();
+            assert!(matches!(error.downcast_ref::<StorageError>(), Some(StorageError::InvalidState(_))), "{}", error);
+            assert!(storage.blocks().find_block(&block.hash()).unwrap().is_none());
+        }
+        assert_eq!(storage.get_head_hash().unwrap(), Some(a2.hash()));
+        assert_eq!(storage.state().get_balance(&alice).unwrap(), 1000);
+
+        let a3 = create_chain_block(&a2, "validator-1", vec![create_transfer(alice, bob, 300, 1)]);
+        assert!(matches!(storage.commit_block(&a3).unwrap(), ChainUpdate::Extended { .. }));
+    }
+
+    #[test]
+    fn test_audit_log_records_state_changes() {
+        use audit_store::{AuditCursor, AuditRecord};
+
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::new(temp_dir.path()).unwrap().with_audit_log(true);
+        let (alice, bob) = ([1u8; 20], [2u8; 20]);
+        storage.state().set_balance(&alice, 1000).unwrap();
+        storage.state().set_balance(&bob, 40).unwrap();
+
+        let mut block = create_test_block();
+        block.transactions = vec![
+            create_transfer(alice, bob, 300, 1),
+            create_transfer(alice, bob, 100, 2),
+        ];
+        storage.commit_block(&block).unwrap();
+
+        let (first, second) = (block.transactions[0].id, block.transactions[1].id);
+        let record = |tx_id, address, old_balance, new_balance, old_nonce, new_nonce| AuditRecord {
+            block_height: 1,
+            tx_id,
+            address,
+            old_balance,
+            new_balance,
+            old_nonce,
+            new_nonce,
+        };
+        let expected = vec![
+            record(first, alice, 1000, 699, 0, 1),
+            record(first, bob, 40, 340, 0, 0),
+            record(second, alice, 699, 598, 1, 2),
+            record(second, bob, 340, 440, 0, 0),
+        ];
+
+        let all = storage.audit().get_records(AuditCursor::default(), 100).unwrap();
+        assert_eq!(all.records, expected);
+        assert_eq!(all.next, None);
+
+        // Pages resume exactly where the previous one stopped
+        let page = storage.audit().get_records(AuditCursor::default(), 3).unwrap();
+        assert_eq!(page.records, expected[..3]);
+        let next = page.next.unwrap();
+        assert_eq!(next, AuditCursor { height: 1, index: 3 });
+        assert_eq!(storage.audit().get_records(next, 3).unwrap().records, expected[3..]);
+    }
+
+    #[test]
+    fn test_audit_log_keeps_rolled_back_and_replayed_records() {
+        use audit_store::AuditCursor;
+
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::new(temp_dir.path()).unwrap().with_audit_log(true);
+        let (alice, bob, carol) = ([1u8; 20], [2u8; 20], [3u8; 20]);
+        storage.state().set_balance(&alice, 1000).unwrap();
+
+        let genesis = create_test_block();
+        let a2 = create_chain_block(&genesis, "validator-1", vec![create_transfer(alice, bob, 300, 1)]);
+        let b2 = create_chain_block(&genesis, "validator-2", vec![create_transfer(alice, carol, 500, 1)]);
+        let b3 = create_chain_block(&b2, "validator-2", vec![]);
+        for block in [&genesis, &a2, &b2, &b3] {
+            storage.commit_block(block).unwrap();
+        }
+
+        // a2's records stay at height 2 and b2's follow them
+        let records = storage.audit().get_records(AuditCursor { height: 2, index: 0 }, 100).unwrap().records;
+        let tx_ids: Vec<Uuid> = records.iter().map(|record| record.tx_id).collect();
+        assert_eq!(tx_ids, vec![
+            a2.transactions[0].id,
+            a2.transactions[0].id,
+            b2.transactions[0].id,
+            b2.transactions[0].id,
+        ]);
+        assert_eq!(records[2].address, alice);
+        assert_eq!(records[2].new_balance, 1000 - 501);
+    }
+
+    #[test]
+    fn test_audit_log_disabled_by_default() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::new(temp_dir.path()).unwrap();
+        storage.state().set_balance(&[1u8; 20], 1000).unwrap();
+
+        let mut block = create_test_block();
+        block.transactions = vec![create_transfer([1u8; 20], [2u8; 20], 300, 1)];
+        storage.commit_block(&block).unwrap();
+
+        let page = storage.audit().get_records(audit_store::AuditCursor::default(), 100).unwrap();
+        assert!(page.records.is_empty());
+    }
+
+    #[test]
+    fn test_database_from_older_build_is_migrated() {
+        let temp_dir = TempDir::new().unwrap();
+        let (alice, bob) = ([1u8; 20], [2u8; 20]);
+        let tx = create_transfer(alice, bob, 300, 1);
+        let mut block = create_test_block();
+        block.transactions = vec![tx.clone()];
+
+        // Only the original column families, no schema version and no transaction indexes
+        {
+            let mut opts = Options::default();
+            opts.create_if_missing(true);
+            opts.create_missing_column_families(true);
+            let db = Arc::new(DB::open_cf(&opts, temp_dir.path(), [CF_BLOCKS, CF_TRANSACTIONS, CF_STATE, CF_METADATA]).unwrap());
+
+            BlockStore::new(db.clone()).unwrap().put_legacy_block(&block).unwrap();
+            let transactions = db.cf_handle(CF_TRANSACTIONS).unwrap();
+            db.put_cf(transactions, format!("tx_{}", tx.id), bincode::serialize(&tx).unwrap()).unwrap();
+            let metadata = db.cf_handle(CF_METADATA).unwrap();
+            db.put_cf(metadata, b"latest_height", bincode::serialize(&1u64).unwrap()).unwrap();
+        }
+
+        let storage = Storage::new(temp_dir.path()).unwrap();
+        assert_eq!(storage.schema_version().unwrap(), Some(SCHEMA_VERSION));
+        for name in ALL_CFS {
+            assert!(storage.db.cf_handle(name).is_some());
+        }
+
+        // Existing data survives and the new structures are populated from it
+        assert_eq!(storage.blocks().get_block(1).unwrap(), Some(block.clone()));
+        assert_eq!(storage.blocks().get_block_by_hash(&block.hash()).unwrap(), Some(block.clone()));
+        assert_eq!(storage.get_latest_height().unwrap(), Some(1));
+        assert_eq!(storage.transactions().get_transaction(&tx.id).unwrap(), Some(tx.clone()));
+        assert_eq!(storage.transactions().get_transactions_by_sender(&alice).unwrap(), vec![tx.clone()]);
+        assert_eq!(storage.receipts().get_receipt(&tx.id).unwrap().unwrap().block_height, 1);
+
+        // Reopening an up-to-date database runs nothing again
+        drop(storage);
+        let reopened = Storage::new(temp_dir.path()).unwrap();
+        assert_eq!(reopened.schema_version().unwrap(), Some(SCHEMA_VERSION));
+        assert_eq!(reopened.transactions().count_transactions().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_fresh_database_starts_at_current_schema() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::new(temp_dir.path()).unwrap();
+        assert_eq!(storage.schema_version().unwrap(), Some(SCHEMA_VERSION));
+
+        // A database from a newer build is refused rather than misread
+        storage.put_metadata(SCHEMA_VERSION_KEY, &(SCHEMA_VERSION + 1)).unwrap();
+        drop(storage);
+        assert!(Storage::new(temp_dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_backup_and_restore() {
+        let db_dir = TempDir::new().unwrap();
+        let backup_dir = TempDir::new().unwrap();
+        let storage = Storage::new(db_dir.path()).unwrap();
+
+        let store_blocks = |heights: std::ops::RangeInclusive<u64>| {
+            for height in heights {
+                let mut block = create_test_block();
+                block.header.height = height;
+                storage.store_block(&block).unwrap();
+            }
+        };
+
+        store_blocks(1..=5);
+        storage.backup(backup_dir.path()).unwrap();
+        store_blocks(6..=10);
+        storage.backup(backup_dir.path()).unwrap();
+
+        // Both backups are kept, sharing the SST files they have in common
+        let engine = open_backup_engine(backup_dir.path()).unwrap();
+        assert_eq!(engine.get_backup_info().len(), 2);
+        assert!(backup_dir.path().join("shared").is_dir());
+
+        let restore_dir = TempDir::new().unwrap();
+        let restore_path = restore_dir.path().join("db");
+        Storage::restore_from_backup(backup_dir.path(), restore_path.as_path()).unwrap();
+
+        let restored = Storage::new(&restore_path).unwrap();
+        assert_eq!(restored.get_latest_height().unwrap(), Some(10));
+        for height in 1..=10 {
+            assert_eq!(restored.blocks().get_block(height).unwrap(), storage.blocks().get_block(height).unwrap());
+        }
+
+        // Restoring over a live database is refused
+        assert!(Storage::restore_from_backup(backup_dir.path(), db_dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_db_size_grows_with_writes() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::new(temp_dir.path()).unwrap();
+
+        let store_blocks = |heights: std::ops::RangeInclusive<u64>| {
+            for height in heights {
+                let mut block = create_test_block();
+                block.header.height = height;
+                block.transactions = vec![create_transfer([1u8; 20], [2u8; 20], height, height)];
+                storage.store_block(&block).unwrap();
+            }
+        };
+
+        store_blocks(1..=50);
+        let size = storage.get_stats().unwrap().db_size_bytes;
+        assert!(size > 0);
+
+        store_blocks(51..=100);
+        let grown = storage.get_stats().unwrap().db_size_bytes;
+        assert!(grown > size);
+
+        // Flushed data is still counted once it moves into SST files
+        storage.db.flush_cf(storage.db.cf_handle(CF_BLOCKS).unwrap()).unwrap();
+        assert!(storage.estimate_db_size().unwrap() > 0);
+    }
 }
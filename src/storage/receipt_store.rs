@@ -0,0 +1,96 @@
+use anyhow::Result;
+use rocksdb::{DB, WriteBatch};
+use serde::{Serialize, Deserialize};
+use std::sync::Arc;
+use uuid::Uuid;
+use crate::types::{Block, BlockHeight};
+use super::{decode, StorageError, StorageSnapshot};
+
+const CF_RECEIPTS: &str = "receipts";
+
+/// Outcome of a committed transaction
+///
+/// A block that would fail any of its transfers is rejected whole, so every
+/// transaction that makes it into a block succeeded.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReceiptStatus {
+    Success,
+}
+
+/// Where a transaction was included and what it paid
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TransactionReceipt {
+    pub tx_id: Uuid,
+    pub block_height: BlockHeight,
+    /// Position of the transaction within its block
+    pub index: u32,
+    pub status: ReceiptStatus,
+    pub fee_paid: u64,
+}
+
+/// Receipts of committed transactions, keyed by transaction id
+#[derive(Clone)]
+pub struct ReceiptStore {
+    db: Arc<DB>,
+}
+
+impl ReceiptStore {
+    pub fn new(db: Arc<DB>) -> Result<Self> {
+        Ok(Self { db })
+    }
+
+    /// Add a receipt for every transaction in a block to a write batch
+    pub fn stage_receipts(&self, block: &Block, batch: &mut WriteBatch) -> Result<()> {
+        let cf = self.db.cf_handle(CF_RECEIPTS)
+            .ok_or(StorageError::MissingColumnFamily(CF_RECEIPTS))?;
+
+        for (index, tx) in block.transactions.iter().enumerate() {
+            let receipt = TransactionReceipt {
+                tx_id: tx.id,
+                block_height: block.header.height,
+                index: u32::try_from(index)
+                    .map_err(|_| StorageError::InvalidState(format!("Too many transactions in block {}", block.header.height)))?,
+                status: ReceiptStatus::Success,
+                fee_paid: tx.fee,
+            };
+            let serialized = bincode::serialize(&receipt)
+                .map_err(|e| StorageError::Serialization { what: "receipt", reason: e.to_string() })?;
+            batch.put_cf(cf, tx.id.as_bytes(), serialized);
+        }
+
+        Ok(())
+    }
+
+    /// Add the removal of every receipt in a block to a write batch
+    pub fn stage_remove_receipts(&self, block: &Block, batch: &mut WriteBatch) -> Result<()> {
+        let cf = self.db.cf_handle(CF_RECEIPTS)
+            .ok_or(StorageError::MissingColumnFamily(CF_RECEIPTS))?;
+
+        for tx in &block.transactions {
+            batch.delete_cf(cf, tx.id.as_bytes());
+        }
+
+        Ok(())
+    }
+
+    /// Get the receipt of a committed transaction
+    pub fn get_receipt(&self, tx_id: &Uuid) -> Result<Option<TransactionReceipt>> {
+        let cf = self.db.cf_handle(CF_RECEIPTS)
+            .ok_or(StorageError::MissingColumnFamily(CF_RECEIPTS))?;
+
+        match self.db.get_cf(cf, tx_id.as_bytes())? {
+            Some(data) => {
+                let receipt = bincode::deserialize(&data)
+                    .map_err(|e| StorageError::Corrupt { what: "receipt", reason: e.to_string() })?;
+                Ok(Some(receipt))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Get the receipt of a committed transaction as of a snapshot
+    pub fn get_receipt_in(&self, snapshot: &StorageSnapshot, tx_id: &Uuid) -> Result<Option<TransactionReceipt>> {
+        decode(snapshot.get_cf(CF_RECEIPTS, tx_id.as_bytes())?, "receipt")
+    }
+}
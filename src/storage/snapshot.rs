@@ -0,0 +1,300 @@
+use anyhow::Result;
+use rocksdb::WriteBatch;
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::cli::{SnapshotAction, SnapshotArgs};
+use crate::types::{Address, Block, BlockHeader, BlockHeight, Hash};
+use super::state_store::{state_root, AccountState};
+use super::{Storage, StorageError};
+
+/// Bumped whenever the snapshot layout changes
+const SNAPSHOT_VERSION: u32 = 2;
+
+/// Account state and header chain at a checkpoint height
+///
+/// A node imports it to start at the checkpoint instead of replaying the chain
+/// from genesis, trusting the checkpoint block it was given.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub version: u32,
+    pub height: BlockHeight,
+    /// Root of `accounts`, which must match the checkpoint block's header
+    pub state_root: Hash,
+    /// Hash and header of every stored block below the checkpoint, oldest first
+    pub headers: Vec<(Hash, BlockHeader)>,
+    /// The block at `height`, which the node builds on after import
+    pub checkpoint: Block,
+    pub accounts: BTreeMap<Address, AccountState>,
+}
+
+impl Snapshot {
+    /// Check the accounts hash to the checkpoint's state root and the headers link up to it
+    pub fn verify(&self) -> Result<()> {
+        if self.version != SNAPSHOT_VERSION {
+            return Err(StorageError::Snapshot(
+                format!("unsupported snapshot version {} (expected {})", self.version, SNAPSHOT_VERSION)
+            ).into());
+        }
+        if self.checkpoint.header.height != self.height {
+            return Err(corrupt(format!(
+                "checkpoint block is at height {}, not {}", self.checkpoint.header.height, self.height
+            )));
+        }
+
+        let root = state_root(&self.accounts)?;
+        if root != self.state_root || root != self.checkpoint.header.state_root {
+            return Err(corrupt(format!(
+                "accounts hash to {} but the checkpoint header commits to {}",
+                hex::encode(root), hex::encode(self.checkpoint.header.state_root)
+            )));
+        }
+
+        let chain = self.headers.iter()
+            .map(|(hash, header)| (*hash, header))
+            .chain(std::iter::once((self.checkpoint.hash(), &self.checkpoint.header)));
+        let mut parent: Option<(Hash, BlockHeight)> = None;
+        for (hash, header) in chain {
+            if let Some((parent_hash, parent_height)) = parent {
+                if header.height != parent_height + 1 || header.previous_hash != parent_hash {
+                    return Err(corrupt(format!("header at height {} doesn't follow its parent", header.height)));
+                }
+            }
+            parent = Some((hash, header.height));
+        }
+
+        Ok(())
+    }
+}
+
+fn corrupt(reason: String) -> anyhow::Error {
+    StorageError::Corrupt { what: "snapshot", reason }.into()
+}
+
+impl Storage {
+    /// Write the account state and header chain at `height` to a snapshot file
+    ///
+    /// Exporting below the latest height reads archived history, so needs an
+    /// archive node. Returns the state root the snapshot commits to.
+    pub fn export_snapshot<P: AsRef<Path>>(&self, path: P, height: BlockHeight) -> Result<Hash> {
+        let path = path.as_ref();
+        let checkpoint = self.block_store.require_block(height)?;
+        let latest = self.get_latest_height()?.unwrap_or(0);
+
+        let mut accounts = BTreeMap::new();
+        for (address, current) in self.state_store.get_all_accounts()? {
+            let state = if height == latest {
+                Some(current)
+            } else {
+                self.state_store.get_account_at(&address, height)?
+            };
+            if let Some(state) = state {
+                accounts.insert(address, state);
+            }
+        }
+
+        let mut headers = Vec::new();
+        for below in 0..height {
+            if let Some(entry) = self.block_store.get_header(below)? {
+                headers.push(entry);
+            }
+        }
+
+        let snapshot = Snapshot {
+            version: SNAPSHOT_VERSION,
+            height,
+            state_root: state_root(&accounts)?,
+            headers,
+            checkpoint,
+            accounts,
+        };
+        snapshot.verify()?;
+
+        let serialized = bincode::serialize(&snapshot)
+            .map_err(|e| StorageError::Serialization { what: "snapshot", reason: e.to_string() })?;
+        std::fs::write(path, serialized)
+            .map_err(|e| StorageError::Snapshot(format!("failed to write {}: {}", path.display(), e)))?;
+
+        Ok(snapshot.state_root)
+    }
+
+    /// Start an empty database at the checkpoint in a snapshot file
+    ///
+    /// The snapshot is verified before anything is written. Blocks below the
+    /// checkpoint keep only their headers. Returns the checkpoint height.
+    pub fn import_snapshot<P: AsRef<Path>>(&self, path: P) -> Result<BlockHeight> {
+        let path = path.as_ref();
+        if let Some(latest) = self.get_latest_height()? {
+            return Err(StorageError::Snapshot(
+                format!("refusing to import over an existing chain at height {}", latest)
+            ).into());
+        }
+
+        let data = std::fs::read(path)
+            .map_err(|e| StorageError::Snapshot(format!("failed to read {}: {}", path.display(), e)))?;
+        let snapshot: Snapshot = bincode::deserialize(&data)
+            .map_err(|e| corrupt(e.to_string()))?;
+        snapshot.verify()?;
+
+        let mut batch = WriteBatch::default();
+        self.state_store.stage_accounts(&snapshot.accounts, snapshot.height, &mut batch)?;
+        for (hash, header) in &snapshot.headers {
+            self.block_store.stage_header(hash, header, &mut batch)?;
+        }
+        self.block_store.stage_block(&snapshot.checkpoint, &mut batch)?;
+        for tx in &snapshot.checkpoint.transactions {
+            self.transaction_store.stage_transaction(tx, &mut batch)?;
+        }
+        self.receipt_store.stage_receipts(&snapshot.checkpoint, &mut batch)?;
+        self.stage_head(&snapshot.checkpoint, &mut batch)?;
+
+        self.db.write(batch)
+            .map_err(|e| StorageError::Database { action: "import snapshot", source: e })?;
+
+        // A later --genesis recognises the chain instead of re-applying initial balances
+        let genesis = match snapshot.headers.first() {
+            Some((hash, header)) if header.height == 0 => Some(*hash),
+            _ if snapshot.height == 0 => Some(snapshot.checkpoint.hash()),
+            _ => None,
+        };
+        if let Some(hash) = genesis {
+            self.set_genesis_hash(&hash)?;
+        }
+
+        Ok(snapshot.height)
+    }
+}
+
+/// Run the `snapshot` subcommand against the database at `db_path`
+pub fn run(db_path: &Path, args: &SnapshotArgs) -> Result<()> {
+    match &args.action {
+        SnapshotAction::Export { height, output } => {
+            let storage = Storage::open_read_only(db_path)?;
+            let height = match height {
+                Some(height) => *height,
+                None => storage.get_latest_height()?
+                    .ok_or_else(|| StorageError::NotFound("Latest block".to_string()))?,
+            };
+
+            let root = storage.export_snapshot(output, height)?;
+            println!("Wrote snapshot at height {} (state root {}) to {}", height, hex::encode(root), output.display());
+        }
+        SnapshotAction::Import { input } => {
+            let storage = Storage::new(db_path)?;
+            let height = storage.import_snapshot(input)?;
+            println!("Imported snapshot; the chain starts at height {}", height);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    use uuid::Uuid;
+    use crate::types::Transaction;
+
+    fn create_transfer(from: Address, to: Address, amount: u64, nonce: u64) -> Transaction {
+        Transaction {
+            id: Uuid::new_v4(),
+            from,
+            to,
+            amount,
+            fee: 1,
+            nonce,
+            timestamp: 1234567890,
+            signature: [0u8; 64],
+            public_key: [0u8; 32],
+            data: vec![],
+        }
+    }
+
+    /// Commit `transfers` as a chain of blocks from height 0, with real state roots
+    fn build_chain(storage: &Storage, transfers: Vec<Transaction>) {
+        let mut previous_hash = [0; 32];
+        for (height, tx) in transfers.into_iter().enumerate() {
+            let mut block = Block {
+                header: BlockHeader {
+                    height: height as BlockHeight,
+                    previous_hash,
+                    merkle_root: [0; 32],
+                    state_root: [0; 32],
+                    timestamp: 1234567890 + height as u64,
+                    proposer: "test-node".to_string(),
+                    round: 0,
+                    view: 0,
+                    vrf_proof: None,
+                },
+                transactions: vec![tx],
+                signatures: vec![],
+            };
+            block.header.state_root = storage.state().preview_state_root(&block).unwrap();
+            storage.commit_block(&block).unwrap();
+            previous_hash = block.hash();
+        }
+    }
+
+    #[test]
+    fn test_snapshot_round_trip_at_height() {
+        let source_dir = TempDir::new().unwrap();
+        let storage = Storage::new(source_dir.path()).unwrap().with_archive(true);
+        let (alice, bob, carol) = ([1u8; 20], [2u8; 20], [3u8; 20]);
+        storage.state().set_balance(&alice, 1000).unwrap();
+        build_chain(&storage, vec![
+            create_transfer(alice, bob, 300, 1),
+            create_transfer(bob, carol, 100, 1),
+            create_transfer(alice, carol, 50, 2),
+        ]);
+
+        let snapshot_dir = TempDir::new().unwrap();
+        for height in [1, 2] {
+            let path = snapshot_dir.path().join(format!("snapshot-{}.bin", height));
+            let root = storage.export_snapshot(&path, height).unwrap();
+            assert_eq!(Some(root), storage.state().get_state_root(height).unwrap());
+
+            let imported_dir = TempDir::new().unwrap();
+            let imported = Storage::new(imported_dir.path()).unwrap();
+            assert_eq!(imported.import_snapshot(&path).unwrap(), height);
+
+            for address in [alice, bob, carol] {
+                assert_eq!(
+                    imported.state().get_account(&address).unwrap(),
+                    storage.state().get_account_at(&address, height).unwrap(),
+                );
+            }
+            assert_eq!(imported.state().calculate_state_root().unwrap(), root);
+            assert_eq!(imported.get_latest_height().unwrap(), Some(height));
+            assert_eq!(imported.blocks().get_block(height).unwrap(), storage.blocks().get_block(height).unwrap());
+            assert_eq!(imported.blocks().get_header(0).unwrap(), storage.blocks().get_header(0).unwrap());
+            assert_eq!(imported.get_genesis_hash().unwrap(), storage.blocks().get_header(0).unwrap().map(|(hash, _)| hash));
+
+            // Importing twice would overwrite the chain
+            assert!(imported.import_snapshot(&path).is_err());
+        }
+    }
+
+    #[test]
+    fn test_tampered_snapshot_is_rejected() {
+        let source_dir = TempDir::new().unwrap();
+        let storage = Storage::new(source_dir.path()).unwrap();
+        storage.state().set_balance(&[1u8; 20], 1000).unwrap();
+        build_chain(&storage, vec![create_transfer([1u8; 20], [2u8; 20], 300, 1)]);
+
+        let snapshot_dir = TempDir::new().unwrap();
+        let path = snapshot_dir.path().join("snapshot.bin");
+        storage.export_snapshot(&path, 0).unwrap();
+
+        let mut snapshot: Snapshot = bincode::deserialize(&std::fs::read(&path).unwrap()).unwrap();
+        snapshot.accounts.get_mut(&[2u8; 20]).unwrap().balance = 1_000_000;
+        std::fs::write(&path, bincode::serialize(&snapshot).unwrap()).unwrap();
+
+        let imported_dir = TempDir::new().unwrap();
+        let imported = Storage::new(imported_dir.path()).unwrap();
+        let error = imported.import_snapshot(&path).unwrap_err();
+        assert!(matches!(error.downcast_ref::<StorageError>(), Some(StorageError::Corrupt { .. })));
+        assert_eq!(imported.get_latest_height().unwrap(), None);
+    }
+}
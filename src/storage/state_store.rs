@@ -1,11 +1,22 @@
-use anyhow::{Result, anyhow};
-use rocksdb::DB;
+use anyhow::Result;
+use rocksdb::{DB, Direction, IteratorMode, WriteBatch};
 use std::sync::Arc;
-use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap};
 use serde::{Serialize, Deserialize};
-use crate::types::{Hash, Address, BlockHeight};
+use sha2::{Digest, Sha256};
+use crate::types::{merkle_proof, merkle_root, verify_merkle_proof, Block, Hash, Address, BlockHeight, MerkleProof};
+use super::audit_store::AuditRecord;
+use super::{decode, StorageError, StorageSnapshot};
 
 const CF_STATE: &str = "state";
+/// Height of the first block whose prior account states were archived
+const HISTORY_START_KEY: &[u8] = b"hist_start";
+/// Number of recent blocks kept with undo records, bounding how deep a reorg can go
+pub const UNDO_DEPTH: BlockHeight = 1024;
+
+/// Account states staged in a write batch but not yet written; `None` deletes the account
+pub type PendingAccounts = HashMap<Address, Option<AccountState>>;
 
 /// Account state information
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -16,44 +27,144 @@ pub struct AccountState {
     pub storage_root: Hash,
 }
 
+/// An account's state with the path proving it is under a state root
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AccountProof {
+    pub address: Address,
+    pub account: AccountState,
+    pub proof: MerkleProof,
+}
+
+impl AccountProof {
+    /// Check the account is committed to by `state_root`
+    pub fn verify(&self, state_root: &Hash) -> bool {
+        match bincode::serialize(&self.account) {
+            Ok(encoded) => verify_merkle_proof(&account_leaf(&self.address, &encoded), &self.proof, state_root),
+            Err(_) => false,
+        }
+    }
+}
+
+/// State root over a complete set of accounts
+///
+/// The root is a merkle tree over the accounts sorted by address, so it doesn't
+/// depend on the order accounts were written in.
+pub fn state_root(accounts: &BTreeMap<Address, AccountState>) -> Result<Hash> {
+    let leaves = accounts
+        .iter()
+        .map(|(address, state)| {
+            let encoded = bincode::serialize(state)
+                .map_err(|e| StorageError::Serialization { what: "account state", reason: e.to_string() })?;
+            Ok(account_leaf(address, &encoded))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(merkle_root(&leaves))
+}
+
+/// Leaf hash of an account: its address followed by its encoded state
+fn account_leaf(address: &Address, encoded: &[u8]) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update(address);
+    hasher.update(encoded);
+    hasher.finalize().into()
+}
+
+/// A single account mutation applied through `StateStore::apply_batch`
+#[derive(Debug, Clone, PartialEq)]
+pub enum StateOp {
+    SetBalance { address: Address, balance: u64 },
+    IncrementNonce { address: Address },
+    SetAccount { address: Address, state: AccountState },
+}
+
 /// State store for managing account states and world state
+#[derive(Clone)]
 pub struct StateStore {
     db: Arc<DB>,
+    // Keep the prior state of every account a block touches
+    archive: bool,
 }
 
 impl StateStore {
     pub fn new(db: Arc<DB>) -> Result<Self> {
-        Ok(Self { db })
+        Ok(Self { db, archive: false })
+    }
+
+    /// Record account history as blocks are applied, for queries at past heights
+    pub fn with_archive(mut self, archive: bool) -> Self {
+        self.archive = archive;
+        self
     }
 
     /// Get account state
     pub fn get_account(&self, address: &Address) -> Result<Option<AccountState>> {
         let cf = self.db.cf_handle(CF_STATE)
-            .ok_or_else(|| anyhow!("State column family not found"))?;
+            .ok_or(StorageError::MissingColumnFamily(CF_STATE))?;
         
         let key = self.account_key(address);
         
         match self.db.get_cf(cf, &key)? {
             Some(data) => {
                 let state = bincode::deserialize(&data)
-                    .map_err(|e| anyhow!("Failed to deserialize account state: {}", e))?;
+                    .map_err(|e| StorageError::Corrupt { what: "account state", reason: e.to_string() })?;
                 Ok(Some(state))
             }
             None => Ok(None),
         }
     }
 
+    /// Get account state as of a snapshot
+    pub fn get_account_in(&self, snapshot: &StorageSnapshot, address: &Address) -> Result<Option<AccountState>> {
+        decode(snapshot.get_cf(CF_STATE, self.account_key(address))?, "account state")
+    }
+
+    /// Get account state as of the given block height
+    ///
+    /// History is an undo log: each block records the state its accounts had
+    /// before it ran, so the state at `height` is the earliest record after it,
+    /// or the current state if no later block touched the account.
+    pub fn get_account_at(&self, address: &Address, height: BlockHeight) -> Result<Option<AccountState>> {
+        // The history and current state must agree if a block commits between the reads
+        self.get_account_at_in(&StorageSnapshot::new(&self.db), address, height)
+    }
+
+    /// Get account state as of the given block height, reading through a snapshot
+    pub fn get_account_at_in(&self, snapshot: &StorageSnapshot, address: &Address, height: BlockHeight) -> Result<Option<AccountState>> {
+        let history_start = match snapshot.get_cf(CF_STATE, HISTORY_START_KEY)? {
+            Some(data) => BlockHeight::from_be_bytes(data.as_slice().try_into()
+                .map_err(|_| StorageError::Corrupt { what: "history start height", reason: format!("expected 8 bytes, got {}", data.len()) })?),
+            None => return Err(StorageError::HistoryUnavailable("state is not archived; run an archive node".to_string()).into()),
+        };
+        // Blocks applied before archiving began can't be rolled back
+        if height.saturating_add(1) < history_start {
+            return Err(StorageError::HistoryUnavailable(
+                format!("state at height {} predates archived history (from {})", height, history_start)
+            ).into());
+        }
+
+        let prefix = self.history_prefix(address);
+        let start = self.history_key(address, height.saturating_add(1));
+        let mut iter = snapshot.iterator_cf(CF_STATE, IteratorMode::From(&start, Direction::Forward))?;
+
+        match iter.next().transpose()? {
+            Some((key, value)) if key.starts_with(&prefix) => bincode::deserialize(&value)
+                .map_err(|e| StorageError::Corrupt { what: "account history", reason: e.to_string() }.into()),
+            _ => self.get_account_in(snapshot, address),
+        }
+    }
+
     /// Set account state
     pub fn set_account(&self, address: &Address, state: &AccountState) -> Result<()> {
         let cf = self.db.cf_handle(CF_STATE)
-            .ok_or_else(|| anyhow!("State column family not found"))?;
+            .ok_or(StorageError::MissingColumnFamily(CF_STATE))?;
         
         let key = self.account_key(address);
         let serialized = bincode::serialize(state)
-            .map_err(|e| anyhow!("Failed to serialize account state: {}", e))?;
+            .map_err(|e| StorageError::Serialization { what: "account state", reason: e.to_string() })?;
         
         self.db.put_cf(cf, &key, &serialized)
-            .map_err(|e| anyhow!("Failed to store account state: {}", e))?;
+            .map_err(|e| StorageError::Database { action: "store account state", source: e })?;
 
         Ok(())
     }
@@ -90,26 +201,56 @@ impl StateStore {
 
     /// Transfer balance between accounts
     pub fn transfer(&self, from: &Address, to: &Address, amount: u64) -> Result<()> {
+        self.apply_batch(&self.transfer_ops(from, to, amount)?)
+    }
+
+    /// Transfer balance and increment the sender nonce in one atomic write
+    pub fn execute_transfer(&self, from: &Address, to: &Address, amount: u64) -> Result<()> {
+        let mut ops = self.transfer_ops(from, to, amount)?;
+        ops.push(StateOp::IncrementNonce { address: *from });
+        self.apply_batch(&ops)
+    }
+
+    fn transfer_ops(&self, from: &Address, to: &Address, amount: u64) -> Result<Vec<StateOp>> {
         // Get current balances
         let from_balance = self.get_balance(from)?;
         let to_balance = self.get_balance(to)?;
 
         // Check sufficient balance
         if from_balance < amount {
-            return Err(anyhow!("Insufficient balance"));
+            return Err(StorageError::InvalidState("Insufficient balance".to_string()).into());
         }
 
-        // Update balances
-        self.set_balance(from, from_balance - amount)?;
-        self.set_balance(to, to_balance + amount)?;
+        // A self-transfer leaves the balance unchanged
+        if from == to {
+            return Ok(Vec::new());
+        }
 
-        Ok(())
+        let credited = to_balance.checked_add(amount)
+            .ok_or_else(|| StorageError::InvalidState("Balance overflow".to_string()))?;
+
+        Ok(vec![
+            StateOp::SetBalance { address: *from, balance: from_balance - amount },
+            StateOp::SetBalance { address: *to, balance: credited },
+        ])
+    }
+
+    /// Apply a sequence of state mutations in one atomic write
+    ///
+    /// Ops are resolved in order, so later ops see the effects of earlier ones.
+    /// If any op fails, nothing is written.
+    pub fn apply_batch(&self, ops: &[StateOp]) -> Result<()> {
+        let mut batch = WriteBatch::default();
+        self.stage_ops(ops, &mut batch)?;
+
+        self.db.write(batch)
+            .map_err(|e| StorageError::Database { action: "apply state batch", source: e }.into())
     }
 
     /// Get state root hash for a given block height
     pub fn get_state_root(&self, height: BlockHeight) -> Result<Option<Hash>> {
         let cf = self.db.cf_handle(CF_STATE)
-            .ok_or_else(|| anyhow!("State column family not found"))?;
+            .ok_or(StorageError::MissingColumnFamily(CF_STATE))?;
         
         let key = self.state_root_key(height);
         
@@ -120,7 +261,7 @@ impl StateStore {
                     hash.copy_from_slice(&data);
                     Ok(Some(hash))
                 } else {
-                    Err(anyhow!("Invalid state root hash length"))
+                    Err(StorageError::Corrupt { what: "state root", reason: format!("expected 32 bytes, got {}", data.len()) }.into())
                 }
             }
             None => Ok(None),
@@ -130,43 +271,353 @@ impl StateStore {
     /// Set state root hash for a given block height
     pub fn set_state_root(&self, height: BlockHeight, root: &Hash) -> Result<()> {
         let cf = self.db.cf_handle(CF_STATE)
-            .ok_or_else(|| anyhow!("State column family not found"))?;
+            .ok_or(StorageError::MissingColumnFamily(CF_STATE))?;
         
         let key = self.state_root_key(height);
         
         self.db.put_cf(cf, &key, root)
-            .map_err(|e| anyhow!("Failed to store state root: {}", e))?;
+            .map_err(|e| StorageError::Database { action: "store state root", source: e })?;
 
         Ok(())
     }
 
-    /// Calculate current state root (simplified Merkle tree)
+    /// Calculate the current state root
     pub fn calculate_state_root(&self) -> Result<Hash> {
-        use sha2::{Digest, Sha256};
-        
+        self.state_root_with(&BTreeMap::new())
+    }
+
+    /// Prove an account's current state against `calculate_state_root`
+    ///
+    /// Returns `None` for accounts that have never been written.
+    pub fn get_account_proof(&self, address: &Address) -> Result<Option<AccountProof>> {
+        let accounts = self.encoded_accounts(&BTreeMap::new())?;
+        let index = match accounts.keys().position(|candidate| candidate == address) {
+            Some(index) => index,
+            None => return Ok(None),
+        };
+
+        let account = bincode::deserialize(&accounts[address])
+            .map_err(|e| StorageError::Corrupt { what: "account state", reason: e.to_string() })?;
+        let leaves: Vec<Hash> = accounts.iter().map(|(address, encoded)| account_leaf(address, encoded)).collect();
+        let proof = merkle_proof(&leaves, index)
+            .ok_or_else(|| StorageError::NotFound(format!("Account {:x?} in state tree", address)))?;
+
+        Ok(Some(AccountProof { address: *address, account, proof }))
+    }
+
+    /// State root a block would produce if applied now, without writing anything
+    pub fn preview_state_root(&self, block: &Block) -> Result<Hash> {
+        self.stage_block(block, &mut WriteBatch::default())
+    }
+
+    /// Execute a block's transfers and write the resulting state atomically
+    pub fn apply_block(&self, block: &Block) -> Result<Hash> {
+        let mut batch = WriteBatch::default();
+        let state_root = self.stage_block(block, &mut batch)?;
+
+        self.db.write(batch)
+            .map_err(|e| StorageError::Database { action: "apply block state", source: e })?;
+
+        Ok(state_root)
+    }
+
+    /// Execute a block's transfers into a write batch, returning the new state root
+    ///
+    /// Nothing is staged if any transaction overdraws its sender.
+    pub fn stage_block(&self, block: &Block, batch: &mut WriteBatch) -> Result<Hash> {
+        self.stage_block_audited(block, batch).map(|(state_root, _)| state_root)
+    }
+
+    /// Like `stage_block`, also returning one audit record per account change in apply order
+    pub fn stage_block_audited(&self, block: &Block, batch: &mut WriteBatch) -> Result<(Hash, Vec<AuditRecord>)> {
+        self.stage_block_over(block, &mut PendingAccounts::new(), batch)
+    }
+
+    /// Like `stage_block_audited`, on top of account changes staged earlier in the batch
+    ///
+    /// `pending` is updated with the block's changes, so consecutive blocks can be
+    /// staged into one batch.
+    pub fn stage_block_over(
+        &self,
+        block: &Block,
+        pending: &mut PendingAccounts,
+        batch: &mut WriteBatch,
+    ) -> Result<(Hash, Vec<AuditRecord>)> {
         let cf = self.db.cf_handle(CF_STATE)
-            .ok_or_else(|| anyhow!("State column family not found"))?;
-        
-        let mut hasher = Sha256::new();
-        let iter = self.db.iterator_cf(cf, rocksdb::IteratorMode::Start);
-        
+            .ok_or(StorageError::MissingColumnFamily(CF_STATE))?;
+
+        // Later transactions see the effects of earlier ones in the same block
+        let mut accounts: HashMap<Address, AccountState> = HashMap::new();
+        let mut audit = Vec::with_capacity(block.transactions.len() * 2);
+        let record = |tx_id, address, before: (u64, u64), after: &AccountState| AuditRecord {
+            block_height: block.header.height,
+            tx_id,
+            address,
+            old_balance: before.0,
+            new_balance: after.balance,
+            old_nonce: before.1,
+            new_nonce: after.nonce,
+        };
+
+        for tx in &block.transactions {
+            let total = tx.amount.checked_add(tx.fee)
+                .ok_or_else(|| StorageError::InvalidState(format!("Transaction {} amount overflows", tx.id)))?;
+
+            // The fee is debited but not credited to anyone
+            let sender = self.cached_account(&mut accounts, pending, &tx.from)?;
+            let before = (sender.balance, sender.nonce);
+            sender.balance = sender.balance.checked_sub(total)
+                .ok_or_else(|| StorageError::InvalidState(format!("Insufficient balance for transaction {}", tx.id)))?;
+            sender.nonce += 1;
+            audit.push(record(tx.id, tx.from, before, sender));
+
+            let recipient = self.cached_account(&mut accounts, pending, &tx.to)?;
+            let before = (recipient.balance, recipient.nonce);
+            recipient.balance = recipient.balance.checked_add(tx.amount)
+                .ok_or_else(|| StorageError::InvalidState(format!("Balance overflow for transaction {}", tx.id)))?;
+            audit.push(record(tx.id, tx.to, before, recipient));
+        }
+
+        let previous = accounts.keys()
+            .map(|address| Ok((*address, self.pending_account(pending, address)?)))
+            .collect::<Result<Vec<_>>>()?;
+        self.stage_undo(block.header.height, &previous, batch)?;
+        if self.archive {
+            self.stage_history(block.header.height, &previous, batch)?;
+        }
+
+        let ops: Vec<StateOp> = accounts
+            .iter()
+            .map(|(address, state)| StateOp::SetAccount { address: *address, state: state.clone() })
+            .collect();
+        self.stage_ops(&ops, batch)?;
+        pending.extend(accounts.into_iter().map(|(address, state)| (address, Some(state))));
+
+        let state_root = self.pending_state_root(pending)?;
+        batch.put_cf(cf, self.state_root_key(block.header.height), state_root);
+
+        Ok((state_root, audit))
+    }
+
+    /// Roll back blocks into a write batch, newest first, from their undo records
+    ///
+    /// Returns the restored account states for staging replacement blocks on top.
+    /// Only the last `UNDO_DEPTH` blocks can be rolled back.
+    pub fn stage_rollback(&self, heights: &[BlockHeight], batch: &mut WriteBatch) -> Result<PendingAccounts> {
+        let cf = self.db.cf_handle(CF_STATE)
+            .ok_or(StorageError::MissingColumnFamily(CF_STATE))?;
+
+        let mut restored = PendingAccounts::new();
+        for &height in heights {
+            let data = self.db.get_cf(cf, self.undo_key(height))?
+                .ok_or_else(|| StorageError::HistoryUnavailable(
+                    format!("no undo record for block {}; reorgs are limited to {} blocks", height, UNDO_DEPTH)
+                ))?;
+            let previous: Vec<(Address, Option<AccountState>)> = bincode::deserialize(&data)
+                .map_err(|e| StorageError::Corrupt { what: "undo record", reason: e.to_string() })?;
+
+            for (address, state) in previous {
+                if self.archive {
+                    batch.delete_cf(cf, self.history_key(&address, height));
+                }
+                restored.insert(address, state);
+            }
+            batch.delete_cf(cf, self.undo_key(height));
+            batch.delete_cf(cf, self.state_root_key(height));
+        }
+
+        for (address, state) in &restored {
+            match state {
+                Some(state) => {
+                    let serialized = bincode::serialize(state)
+                        .map_err(|e| StorageError::Serialization { what: "account state", reason: e.to_string() })?;
+                    batch.put_cf(cf, self.account_key(address), &serialized);
+                }
+                None => batch.delete_cf(cf, self.account_key(address)),
+            }
+        }
+
+        Ok(restored)
+    }
+
+    /// State root over stored accounts with pending account states layered on top
+    pub fn pending_state_root(&self, pending: &PendingAccounts) -> Result<Hash> {
+        let encoded = pending
+            .iter()
+            .map(|(address, state)| {
+                let encoded = state.as_ref()
+                    .map(bincode::serialize)
+                    .transpose()
+                    .map_err(|e| StorageError::Serialization { what: "account state", reason: e.to_string() })?;
+                Ok((*address, encoded))
+            })
+            .collect::<Result<BTreeMap<_, _>>>()?;
+
+        self.state_root_with(&encoded)
+    }
+
+    /// Add a complete account set and its state root at `height` to a write batch
+    ///
+    /// Meant for an empty database: accounts not in the set are left as they are.
+    pub fn stage_accounts(
+        &self,
+        accounts: &BTreeMap<Address, AccountState>,
+        height: BlockHeight,
+        batch: &mut WriteBatch,
+    ) -> Result<Hash> {
+        let cf = self.db.cf_handle(CF_STATE)
+            .ok_or(StorageError::MissingColumnFamily(CF_STATE))?;
+
+        for (address, state) in accounts {
+            let serialized = bincode::serialize(state)
+                .map_err(|e| StorageError::Serialization { what: "account state", reason: e.to_string() })?;
+            batch.put_cf(cf, self.account_key(address), &serialized);
+        }
+
+        let root = state_root(accounts)?;
+        batch.put_cf(cf, self.state_root_key(height), root);
+        Ok(root)
+    }
+
+    /// Record the state accounts had before the block at `height`, so a reorg can undo it
+    fn stage_undo(
+        &self,
+        height: BlockHeight,
+        previous: &[(Address, Option<AccountState>)],
+        batch: &mut WriteBatch,
+    ) -> Result<()> {
+        let cf = self.db.cf_handle(CF_STATE)
+            .ok_or(StorageError::MissingColumnFamily(CF_STATE))?;
+
+        let serialized = bincode::serialize(previous)
+            .map_err(|e| StorageError::Serialization { what: "undo record", reason: e.to_string() })?;
+        batch.put_cf(cf, self.undo_key(height), serialized);
+
+        if let Some(expired) = height.checked_sub(UNDO_DEPTH) {
+            batch.delete_cf(cf, self.undo_key(expired));
+        }
+
+        Ok(())
+    }
+
+    /// Archive the state accounts had before the block at `height`
+    fn stage_history(
+        &self,
+        height: BlockHeight,
+        previous: &[(Address, Option<AccountState>)],
+        batch: &mut WriteBatch,
+    ) -> Result<()> {
+        let cf = self.db.cf_handle(CF_STATE)
+            .ok_or(StorageError::MissingColumnFamily(CF_STATE))?;
+
+        if self.db.get_cf(cf, HISTORY_START_KEY)?.is_none() {
+            batch.put_cf(cf, HISTORY_START_KEY, height.to_be_bytes());
+        }
+
+        for (address, state) in previous {
+            let serialized = bincode::serialize(state)
+                .map_err(|e| StorageError::Serialization { what: "account history", reason: e.to_string() })?;
+            batch.put_cf(cf, self.history_key(address, height), serialized);
+        }
+
+        Ok(())
+    }
+
+    /// Resolve ops against current state and add the resulting accounts to a write batch
+    ///
+    /// The batch is untouched if any op fails.
+    fn stage_ops(&self, ops: &[StateOp], batch: &mut WriteBatch) -> Result<()> {
+        let cf = self.db.cf_handle(CF_STATE)
+            .ok_or(StorageError::MissingColumnFamily(CF_STATE))?;
+
+        let unwritten = PendingAccounts::new();
+        let mut accounts: HashMap<Address, AccountState> = HashMap::new();
+        for op in ops {
+            match op {
+                StateOp::SetBalance { address, balance } => {
+                    self.cached_account(&mut accounts, &unwritten, address)?.balance = *balance;
+                }
+                StateOp::IncrementNonce { address } => {
+                    let account = self.cached_account(&mut accounts, &unwritten, address)?;
+                    account.nonce = account.nonce.checked_add(1)
+                        .ok_or_else(|| StorageError::InvalidState(format!("Nonce overflow for account {:x?}", address)))?;
+                }
+                StateOp::SetAccount { address, state } => {
+                    accounts.insert(*address, state.clone());
+                }
+            }
+        }
+
+        for (address, state) in &accounts {
+            let key = self.account_key(address);
+            let serialized = bincode::serialize(state)
+                .map_err(|e| StorageError::Serialization { what: "account state", reason: e.to_string() })?;
+            batch.put_cf(cf, &key, &serialized);
+        }
+
+        Ok(())
+    }
+
+    /// Account state as modified so far by the ops or block being staged
+    fn cached_account<'a>(
+        &self,
+        accounts: &'a mut HashMap<Address, AccountState>,
+        pending: &PendingAccounts,
+        address: &Address,
+    ) -> Result<&'a mut AccountState> {
+        match accounts.entry(*address) {
+            Entry::Occupied(entry) => Ok(entry.into_mut()),
+            Entry::Vacant(entry) => Ok(entry.insert(self.pending_account(pending, address)?.unwrap_or_default())),
+        }
+    }
+
+    /// Account state with changes staged earlier in the batch taking precedence
+    fn pending_account(&self, pending: &PendingAccounts, address: &Address) -> Result<Option<AccountState>> {
+        match pending.get(address) {
+            Some(state) => Ok(state.clone()),
+            None => self.get_account(address),
+        }
+    }
+
+    /// State root over stored accounts with pending encoded writes and deletions layered on top
+    fn state_root_with(&self, pending: &BTreeMap<Address, Option<Vec<u8>>>) -> Result<Hash> {
+        let leaves: Vec<Hash> = self.encoded_accounts(pending)?
+            .iter()
+            .map(|(address, encoded)| account_leaf(address, encoded))
+            .collect();
+
+        Ok(merkle_root(&leaves))
+    }
+
+    /// Every stored account, encoded and sorted by address, with `pending` layered on top
+    fn encoded_accounts(&self, pending: &BTreeMap<Address, Option<Vec<u8>>>) -> Result<BTreeMap<Address, Vec<u8>>> {
+        let cf = self.db.cf_handle(CF_STATE)
+            .ok_or(StorageError::MissingColumnFamily(CF_STATE))?;
+
+        let mut accounts = BTreeMap::new();
+        let iter = self.db.iterator_cf(cf, IteratorMode::From(b"acc_", Direction::Forward));
+
         for item in iter {
             let (key, value) = item?;
-            
-            // Only hash account keys (skip state root keys)
-            if key.starts_with(b"acc_") {
-                hasher.update(&key);
-                hasher.update(&value);
+            if !key.starts_with(b"acc_") {
+                break;
             }
+            accounts.insert(self.parse_account_key(&key)?, value.to_vec());
         }
-        
-        Ok(hasher.finalize().into())
+        for (address, encoded) in pending {
+            match encoded {
+                Some(encoded) => accounts.insert(*address, encoded.clone()),
+                None => accounts.remove(address),
+            };
+        }
+
+        Ok(accounts)
     }
 
     /// Get all accounts (for debugging/testing)
     pub fn get_all_accounts(&self) -> Result<HashMap<Address, AccountState>> {
         let cf = self.db.cf_handle(CF_STATE)
-            .ok_or_else(|| anyhow!("State column family not found"))?;
+            .ok_or(StorageError::MissingColumnFamily(CF_STATE))?;
         
         let mut accounts = HashMap::new();
         let iter = self.db.iterator_cf(cf, rocksdb::IteratorMode::Start);
@@ -202,6 +653,29 @@ impl StateStore {
         key
     }
 
+    /// Create account history key prefix
+    fn history_prefix(&self, address: &Address) -> Vec<u8> {
+        let mut key = Vec::with_capacity(33);
+        key.extend_from_slice(b"hist_");
+        key.extend_from_slice(address);
+        key
+    }
+
+    /// Create account history key; heights are big-endian so keys sort by height
+    fn history_key(&self, address: &Address, height: BlockHeight) -> Vec<u8> {
+        let mut key = self.history_prefix(address);
+        key.extend_from_slice(&height.to_be_bytes());
+        key
+    }
+
+    /// Create undo record key; heights are big-endian so keys sort by height
+    fn undo_key(&self, height: BlockHeight) -> Vec<u8> {
+        let mut key = Vec::with_capacity(13);
+        key.extend_from_slice(b"undo_");
+        key.extend_from_slice(&height.to_be_bytes());
+        key
+    }
+
     /// Create state root key
     fn state_root_key(&self, height: BlockHeight) -> Vec<u8> {
         format!("root_{:016}", height).into_bytes()
@@ -214,7 +688,7 @@ impl StateStore {
             address.copy_from_slice(&key[4..]);
             Ok(address)
         } else {
-            Err(anyhow!("Invalid account key format"))
+            Err(StorageError::Corrupt { what: "account key", reason: "expected acc_ prefix and a 20-byte address".to_string() }.into())
         }
     }
 }
@@ -293,4 +767,106 @@ mod tests {
         assert_eq!(store.get_balance(&from).unwrap(), 700);
         assert_eq!(store.get_balance(&to).unwrap(), 800);
     }
+
+    #[test]
+    fn test_execute_transfer_increments_nonce() {
+        let (_temp_dir, db) = create_test_db();
+        let store = StateStore::new(db).unwrap();
+
+        let from = [1u8; 20];
+        let to = [2u8; 20];
+        store.set_balance(&from, 1000).unwrap();
+
+        store.execute_transfer(&from, &to, 300).unwrap();
+
+        assert_eq!(store.get_balance(&from).unwrap(), 700);
+        assert_eq!(store.get_balance(&to).unwrap(), 300);
+        assert_eq!(store.get_nonce(&from).unwrap(), 1);
+        assert_eq!(store.get_nonce(&to).unwrap(), 0);
+
+        assert!(store.execute_transfer(&from, &to, 701).is_err());
+        assert_eq!(store.get_nonce(&from).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_state_root_is_independent_of_insertion_order() {
+        let accounts: Vec<(Address, AccountState)> = (1..=5u8)
+            .map(|i| ([i; 20], AccountState { balance: i as u64 * 100, nonce: i as u64, ..Default::default() }))
+            .collect();
+
+        let (_first_dir, first_db) = create_test_db();
+        let first = StateStore::new(first_db).unwrap();
+        for (address, state) in &accounts {
+            first.set_account(address, state).unwrap();
+        }
+
+        let (_second_dir, second_db) = create_test_db();
+        let second = StateStore::new(second_db).unwrap();
+        for (address, state) in accounts.iter().rev() {
+            second.set_account(address, state).unwrap();
+        }
+
+        let root = first.calculate_state_root().unwrap();
+        assert_eq!(root, second.calculate_state_root().unwrap());
+        assert_eq!(root, state_root(&accounts.iter().cloned().collect()).unwrap());
+
+        // Any change to any account moves the root
+        second.set_balance(&[3u8; 20], 301).unwrap();
+        assert_ne!(root, second.calculate_state_root().unwrap());
+    }
+
+    #[test]
+    fn test_account_proof_verifies_against_state_root() {
+        let (_temp_dir, db) = create_test_db();
+        let store = StateStore::new(db).unwrap();
+        for i in 1..=5u8 {
+            store.set_balance(&[i; 20], i as u64 * 100).unwrap();
+        }
+        let root = store.calculate_state_root().unwrap();
+
+        let proof = store.get_account_proof(&[3u8; 20]).unwrap().unwrap();
+        assert_eq!(proof.account.balance, 300);
+        assert!(proof.verify(&root));
+
+        // A forged balance or a stale root doesn't verify
+        let mut forged = proof.clone();
+        forged.account.balance = 1_000_000;
+        assert!(!forged.verify(&root));
+        store.set_balance(&[1u8; 20], 1).unwrap();
+        assert!(!proof.verify(&store.calculate_state_root().unwrap()));
+
+        assert!(store.get_account_proof(&[9u8; 20]).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_apply_batch_is_all_or_nothing() {
+        let (_temp_dir, db) = create_test_db();
+        let store = StateStore::new(db).unwrap();
+
+        let from = [1u8; 20];
+        let to = [2u8; 20];
+        let exhausted = [3u8; 20];
+        store.set_balance(&from, 1000).unwrap();
+        store.set_account(&exhausted, &AccountState { nonce: u64::MAX, ..Default::default() }).unwrap();
+
+        // The debit and credit succeed, then the last op fails
+        let ops = vec![
+            StateOp::SetBalance { address: from, balance: 700 },
+            StateOp::SetBalance { address: to, balance: 300 },
+            StateOp::IncrementNonce { address: from },
+            StateOp::IncrementNonce { address: exhausted },
+        ];
+        assert!(store.apply_batch(&ops).is_err());
+
+        assert_eq!(store.get_balance(&from).unwrap(), 1000);
+        assert_eq!(store.get_balance(&to).unwrap(), 0);
+        assert_eq!(store.get_nonce(&from).unwrap(), 0);
+        assert_eq!(store.get_account(&to).unwrap(), None);
+
+        // Without the failing op the same batch lands in full
+        store.apply_batch(&ops[..3]).unwrap();
+        assert_eq!(store.get_balance(&from).unwrap(), 700);
+        assert_eq!(store.get_balance(&to).unwrap(), 300);
+        assert_eq!(store.get_nonce(&from).unwrap(), 1);
+    }
 }
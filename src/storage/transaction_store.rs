@@ -1,37 +1,75 @@
-use anyhow::{Result, anyhow};
-use rocksdb::DB;
+use anyhow::Result;
+use rocksdb::{DB, Direction, IteratorMode, WriteBatch};
+use serde::{Serialize, Deserialize};
 use std::sync::Arc;
+#[cfg(test)]
+use std::sync::atomic::{AtomicU64, Ordering};
 use uuid::Uuid;
-use crate::types::{Transaction, Hash, BlockHeight};
+use crate::types::{Address, Transaction, Hash, BlockHeight};
+use super::{decode, StorageError, StorageSnapshot, MAX_PAGE_SIZE};
 
 const CF_TRANSACTIONS: &str = "transactions";
+/// Key prefix of transaction records; everything else in the column family is an index
+const TX_PREFIX: &[u8] = b"tx_";
+const SENDER_INDEX_PREFIX: &[u8] = b"sender_";
+const RECIPIENT_INDEX_PREFIX: &[u8] = b"recipient_";
+const TIME_INDEX_PREFIX: &[u8] = b"time_";
+/// Writes per batch when rebuilding the indexes
+const REINDEX_BATCH_SIZE: usize = 10_000;
+
+/// Resume point for paged recent-transaction queries: the last transaction already returned
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TransactionCursor {
+    pub timestamp: u64,
+    pub id: Uuid,
+}
 
 /// Transaction storage implementation
+#[derive(Clone)]
 pub struct TransactionStore {
     db: Arc<DB>,
+    // Number of index entries read by per-address lookups
+    #[cfg(test)]
+    keys_visited: Arc<AtomicU64>,
 }
 
 impl TransactionStore {
     pub fn new(db: Arc<DB>) -> Result<Self> {
-        Ok(Self { db })
+        Ok(Self {
+            db,
+            #[cfg(test)]
+            keys_visited: Arc::new(AtomicU64::new(0)),
+        })
     }
 
     /// Store a transaction
     pub fn put_transaction(&self, tx: &Transaction) -> Result<()> {
+        let mut batch = WriteBatch::default();
+        self.stage_transaction(tx, &mut batch)?;
+
+        self.db.write(batch)
+            .map_err(|e| StorageError::Database { action: "store transaction", source: e }.into())
+    }
+
+    /// Add a transaction and its hash index to a write batch
+    pub fn stage_transaction(&self, tx: &Transaction, batch: &mut WriteBatch) -> Result<()> {
         let cf = self.db.cf_handle(CF_TRANSACTIONS)
-            .ok_or_else(|| anyhow!("Transactions column family not found"))?;
+            .ok_or(StorageError::MissingColumnFamily(CF_TRANSACTIONS))?;
         
         let key = self.tx_key(&tx.id);
         let serialized = bincode::serialize(tx)
-            .map_err(|e| anyhow!("Failed to serialize transaction: {}", e))?;
+            .map_err(|e| StorageError::Serialization { what: "transaction", reason: e.to_string() })?;
         
-        self.db.put_cf(cf, &key, &serialized)
-            .map_err(|e| anyhow!("Failed to store transaction: {}", e))?;
+        batch.put_cf(cf, &key, &serialized);
 
         // Also store by hash for quick lookups
         let hash_key = self.hash_key(&tx.hash());
-        self.db.put_cf(cf, &hash_key, &key)
-            .map_err(|e| anyhow!("Failed to store transaction hash index: {}", e))?;
+        batch.put_cf(cf, &hash_key, &key);
+
+        // Index by sender and recipient so per-address queries scan only that address
+        batch.put_cf(cf, self.address_index_key(SENDER_INDEX_PREFIX, &tx.from, tx), &key);
+        batch.put_cf(cf, self.address_index_key(RECIPIENT_INDEX_PREFIX, &tx.to, tx), &key);
+        batch.put_cf(cf, self.time_index_key(tx.timestamp, &tx.id), &key);
 
         Ok(())
     }
@@ -39,24 +77,29 @@ impl TransactionStore {
     /// Get a transaction by ID
     pub fn get_transaction(&self, tx_id: &Uuid) -> Result<Option<Transaction>> {
         let cf = self.db.cf_handle(CF_TRANSACTIONS)
-            .ok_or_else(|| anyhow!("Transactions column family not found"))?;
+            .ok_or(StorageError::MissingColumnFamily(CF_TRANSACTIONS))?;
         
         let key = self.tx_key(tx_id);
         
         match self.db.get_cf(cf, &key)? {
             Some(data) => {
                 let tx = bincode::deserialize(&data)
-                    .map_err(|e| anyhow!("Failed to deserialize transaction: {}", e))?;
+                    .map_err(|e| StorageError::Corrupt { what: "transaction", reason: e.to_string() })?;
                 Ok(Some(tx))
             }
             None => Ok(None),
         }
     }
 
+    /// Get a transaction by ID as of a snapshot
+    pub fn get_transaction_in(&self, snapshot: &StorageSnapshot, tx_id: &Uuid) -> Result<Option<Transaction>> {
+        decode(snapshot.get_cf(CF_TRANSACTIONS, self.tx_key(tx_id))?, "transaction")
+    }
+
     /// Get a transaction by hash
     pub fn get_transaction_by_hash(&self, hash: &Hash) -> Result<Option<Transaction>> {
         let cf = self.db.cf_handle(CF_TRANSACTIONS)
-            .ok_or_else(|| anyhow!("Transactions column family not found"))?;
+            .ok_or(StorageError::MissingColumnFamily(CF_TRANSACTIONS))?;
         
         let hash_key = self.hash_key(hash);
         
@@ -67,7 +110,7 @@ impl TransactionStore {
                 match self.db.get_cf(cf, &tx_key)? {
                     Some(data) => {
                         let tx = bincode::deserialize(&data)
-                            .map_err(|e| anyhow!("Failed to deserialize transaction: {}", e))?;
+                            .map_err(|e| StorageError::Corrupt { what: "transaction", reason: e.to_string() })?;
                         Ok(Some(tx))
                     }
                     None => Ok(None),
@@ -80,72 +123,54 @@ impl TransactionStore {
     /// Check if a transaction exists
     pub fn has_transaction(&self, tx_id: &Uuid) -> Result<bool> {
         let cf = self.db.cf_handle(CF_TRANSACTIONS)
-            .ok_or_else(|| anyhow!("Transactions column family not found"))?;
+            .ok_or(StorageError::MissingColumnFamily(CF_TRANSACTIONS))?;
         
         let key = self.tx_key(tx_id);
         Ok(self.db.get_cf(cf, &key)?.is_some())
     }
 
-    /// Get transactions by sender address
-    pub fn get_transactions_by_sender(&self, sender: &[u8; 20]) -> Result<Vec<Transaction>> {
-        let cf = self.db.cf_handle(CF_TRANSACTIONS)
-            .ok_or_else(|| anyhow!("Transactions column family not found"))?;
-        
-        let mut transactions = Vec::new();
-        let iter = self.db.iterator_cf(cf, rocksdb::IteratorMode::Start);
-        
-        for item in iter {
-            let (key, value) = item?;
-            
-            // Skip hash index entries
-            if key.starts_with(b"h") {
-                continue;
-            }
-            
-            if let Ok(tx) = bincode::deserialize::<Transaction>(&value) {
-                if tx.from == *sender {
-                    transactions.push(tx);
-                }
-            }
-        }
-        
-        // Sort by timestamp
-        transactions.sort_by_key(|tx| tx.timestamp);
-        Ok(transactions)
+    /// Get transactions by sender address, oldest first
+    pub fn get_transactions_by_sender(&self, sender: &Address) -> Result<Vec<Transaction>> {
+        self.get_indexed_transactions(SENDER_INDEX_PREFIX, sender)
+    }
+
+    /// Get transactions by recipient address, oldest first
+    pub fn get_transactions_by_recipient(&self, recipient: &Address) -> Result<Vec<Transaction>> {
+        self.get_indexed_transactions(RECIPIENT_INDEX_PREFIX, recipient)
     }
 
-    /// Get transactions by recipient address
-    pub fn get_transactions_by_recipient(&self, recipient: &[u8; 20]) -> Result<Vec<Transaction>> {
+    /// Follow one address's index entries, which are ordered by timestamp
+    fn get_indexed_transactions(&self, index: &[u8], address: &Address) -> Result<Vec<Transaction>> {
         let cf = self.db.cf_handle(CF_TRANSACTIONS)
-            .ok_or_else(|| anyhow!("Transactions column family not found"))?;
-        
+            .ok_or(StorageError::MissingColumnFamily(CF_TRANSACTIONS))?;
+
+        let prefix = self.address_index_prefix(index, address);
         let mut transactions = Vec::new();
-        let iter = self.db.iterator_cf(cf, rocksdb::IteratorMode::Start);
-        
-        for item in iter {
-            let (key, value) = item?;
-            
-            // Skip hash index entries
-            if key.starts_with(b"h") {
-                continue;
-            }
-            
-            if let Ok(tx) = bincode::deserialize::<Transaction>(&value) {
-                if tx.to == *recipient {
-                    transactions.push(tx);
-                }
+
+        for item in self.db.prefix_iterator_cf(cf, &prefix) {
+            let (key, tx_key) = item?;
+            #[cfg(test)]
+            self.keys_visited.fetch_add(1, Ordering::Relaxed);
+
+            // Without a prefix extractor the iterator runs past the prefix
+            if !key.starts_with(&prefix) {
+                break;
             }
+
+            let data = self.db.get_cf(cf, &tx_key)?
+                .ok_or_else(|| StorageError::Corrupt { what: "transaction index", reason: "entry points at a missing transaction".to_string() })?;
+            let tx = bincode::deserialize(&data)
+                .map_err(|e| StorageError::Corrupt { what: "transaction", reason: e.to_string() })?;
+            transactions.push(tx);
         }
-        
-        // Sort by timestamp
-        transactions.sort_by_key(|tx| tx.timestamp);
+
         Ok(transactions)
     }
 
     /// Get recent transactions (last N transactions)
     pub fn get_recent_transactions(&self, limit: usize) -> Result<Vec<Transaction>> {
         let cf = self.db.cf_handle(CF_TRANSACTIONS)
-            .ok_or_else(|| anyhow!("Transactions column family not found"))?;
+            .ok_or(StorageError::MissingColumnFamily(CF_TRANSACTIONS))?;
         
         let mut transactions = Vec::new();
         let iter = self.db.iterator_cf(cf, rocksdb::IteratorMode::End);
@@ -153,8 +178,8 @@ impl TransactionStore {
         for item in iter {
             let (key, value) = item?;
             
-            // Skip hash index entries
-            if key.starts_with(b"h") {
+            // Skip index entries
+            if !key.starts_with(TX_PREFIX) {
                 continue;
             }
             
@@ -171,10 +196,93 @@ impl TransactionStore {
         Ok(transactions)
     }
 
+    /// Get up to `limit` transactions, newest first, starting just after `cursor`
+    ///
+    /// Returns a cursor for the next page while older transactions remain. The
+    /// limit is capped at `MAX_PAGE_SIZE`.
+    pub fn get_recent_transactions_paged(
+        &self,
+        limit: usize,
+        cursor: Option<TransactionCursor>,
+    ) -> Result<(Vec<Transaction>, Option<TransactionCursor>)> {
+        let cf = self.db.cf_handle(CF_TRANSACTIONS)
+            .ok_or(StorageError::MissingColumnFamily(CF_TRANSACTIONS))?;
+        let limit = limit.min(MAX_PAGE_SIZE);
+
+        let start = match &cursor {
+            Some(cursor) => self.time_index_key(cursor.timestamp, &cursor.id),
+            None => self.time_index_key(u64::MAX, &Uuid::from_bytes([0xff; 16])),
+        };
+
+        let mut transactions = Vec::new();
+        for item in self.db.iterator_cf(cf, IteratorMode::From(&start, Direction::Reverse)) {
+            let (key, tx_key) = item?;
+            if !key.starts_with(TIME_INDEX_PREFIX) {
+                break;
+            }
+            // The cursor's own entry was returned on the previous page
+            if cursor.is_some() && *key == *start {
+                continue;
+            }
+
+            // Read one extra entry to learn whether another page follows
+            if transactions.len() == limit {
+                let next = transactions.last().map(|tx: &Transaction| TransactionCursor {
+                    timestamp: tx.timestamp,
+                    id: tx.id,
+                });
+                return Ok((transactions, next));
+            }
+
+            let data = self.db.get_cf(cf, &tx_key)?
+                .ok_or_else(|| StorageError::Corrupt { what: "transaction index", reason: "entry points at a missing transaction".to_string() })?;
+            let tx = bincode::deserialize(&data)
+                .map_err(|e| StorageError::Corrupt { what: "transaction", reason: e.to_string() })?;
+            transactions.push(tx);
+        }
+
+        Ok((transactions, None))
+    }
+
+    /// Rebuild the hash, address and time indexes of every stored transaction
+    ///
+    /// Returns the number of transactions indexed.
+    pub fn reindex(&self) -> Result<usize> {
+        let cf = self.db.cf_handle(CF_TRANSACTIONS)
+            .ok_or(StorageError::MissingColumnFamily(CF_TRANSACTIONS))?;
+
+        let mut indexed = 0;
+        let mut batch = WriteBatch::default();
+        let iter = self.db.iterator_cf(cf, IteratorMode::From(TX_PREFIX, Direction::Forward));
+
+        for item in iter {
+            let (key, value) = item?;
+            if !key.starts_with(TX_PREFIX) {
+                break;
+            }
+
+            let tx: Transaction = bincode::deserialize(&value)
+                .map_err(|e| StorageError::Corrupt { what: "transaction", reason: e.to_string() })?;
+            self.stage_transaction(&tx, &mut batch)?;
+            indexed += 1;
+
+            // Bound the batch size on large databases
+            if batch.len() >= REINDEX_BATCH_SIZE {
+                self.db.write(std::mem::take(&mut batch))
+                    .map_err(|e| StorageError::Database { action: "write transaction indexes", source: e })?;
+            }
+        }
+
+        self.db.write(batch)
+            .map_err(|e| StorageError::Database { action: "write transaction indexes", source: e })?;
+
+        Ok(indexed)
+    }
+
     /// Count total number of transactions
     pub fn count_transactions(&self) -> Result<u64> {
         let cf = self.db.cf_handle(CF_TRANSACTIONS)
-            .ok_or_else(|| anyhow!("Transactions column family not found"))?;
+            .ok_or(StorageError::MissingColumnFamily(CF_TRANSACTIONS))?;
         
         let mut count = 0;
         let iter = self.db.iterator_cf(cf, rocksdb::IteratorMode::Start);
@@ -182,8 +290,8 @@ impl TransactionStore {
         for item in iter {
             let (key, _) = item?;
             
-            // Only count transaction keys (skip hash index entries)
-            if !key.starts_with(b"h") {
+            // Only count transaction keys (skip index entries)
+            if key.starts_with(TX_PREFIX) {
                 count += 1;
             }
         }
@@ -194,25 +302,40 @@ impl TransactionStore {
     /// Delete a transaction (use with caution)
     pub fn delete_transaction(&self, tx_id: &Uuid) -> Result<()> {
         let cf = self.db.cf_handle(CF_TRANSACTIONS)
-            .ok_or_else(|| anyhow!("Transactions column family not found"))?;
+            .ok_or(StorageError::MissingColumnFamily(CF_TRANSACTIONS))?;
         
-        // First get the transaction to find its hash
-        if let Some(tx) = self.get_transaction(tx_id)? {
-            let hash_key = self.hash_key(&tx.hash());
-            self.db.delete_cf(cf, &hash_key)?;
+        let mut batch = WriteBatch::default();
+
+        // First get the transaction to find its index entries
+        match self.get_transaction(tx_id)? {
+            Some(tx) => self.stage_delete(&tx, &mut batch)?,
+            None => batch.delete_cf(cf, self.tx_key(tx_id)),
         }
+
+        self.db.write(batch)
+            .map_err(|e| StorageError::Database { action: "delete transaction", source: e })?;
         
-        let key = self.tx_key(tx_id);
-        self.db.delete_cf(cf, &key)
-            .map_err(|e| anyhow!("Failed to delete transaction: {}", e))?;
-        
+        Ok(())
+    }
+
+    /// Add the removal of a transaction and its index entries to a write batch
+    pub fn stage_delete(&self, tx: &Transaction, batch: &mut WriteBatch) -> Result<()> {
+        let cf = self.db.cf_handle(CF_TRANSACTIONS)
+            .ok_or(StorageError::MissingColumnFamily(CF_TRANSACTIONS))?;
+
+        batch.delete_cf(cf, self.hash_key(&tx.hash()));
+        batch.delete_cf(cf, self.address_index_key(SENDER_INDEX_PREFIX, &tx.from, tx));
+        batch.delete_cf(cf, self.address_index_key(RECIPIENT_INDEX_PREFIX, &tx.to, tx));
+        batch.delete_cf(cf, self.time_index_key(tx.timestamp, &tx.id));
+        batch.delete_cf(cf, self.tx_key(&tx.id));
+
         Ok(())
     }
 
     /// Get all transaction IDs
     pub fn get_all_transaction_ids(&self) -> Result<Vec<Uuid>> {
         let cf = self.db.cf_handle(CF_TRANSACTIONS)
-            .ok_or_else(|| anyhow!("Transactions column family not found"))?;
+            .ok_or(StorageError::MissingColumnFamily(CF_TRANSACTIONS))?;
         
         let mut ids = Vec::new();
         let iter = self.db.iterator_cf(cf, rocksdb::IteratorMode::Start);
@@ -220,8 +343,8 @@ impl TransactionStore {
         for item in iter {
             let (key, _) = item?;
             
-            // Only process transaction keys (skip hash index entries)
-            if !key.starts_with(b"h") {
+            // Only process transaction keys (skip index entries)
+            if key.starts_with(TX_PREFIX) {
                 if let Ok(id) = self.parse_tx_key(&key) {
                     ids.push(id);
                 }
@@ -244,14 +367,39 @@ impl TransactionStore {
         key
     }
 
+    /// Prefix of one address's entries in a sender or recipient index
+    fn address_index_prefix(&self, index: &[u8], address: &Address) -> Vec<u8> {
+        let mut key = Vec::with_capacity(index.len() + 20 + 8 + 16);
+        key.extend_from_slice(index);
+        key.extend_from_slice(address);
+        key
+    }
+
+    /// Index key ordering an address's transactions by timestamp, then id
+    fn address_index_key(&self, index: &[u8], address: &Address, tx: &Transaction) -> Vec<u8> {
+        let mut key = self.address_index_prefix(index, address);
+        key.extend_from_slice(&tx.timestamp.to_be_bytes());
+        key.extend_from_slice(tx.id.as_bytes());
+        key
+    }
+
+    /// Index key ordering all transactions by timestamp, then id
+    fn time_index_key(&self, timestamp: u64, tx_id: &Uuid) -> Vec<u8> {
+        let mut key = Vec::with_capacity(TIME_INDEX_PREFIX.len() + 8 + 16);
+        key.extend_from_slice(TIME_INDEX_PREFIX);
+        key.extend_from_slice(&timestamp.to_be_bytes());
+        key.extend_from_slice(tx_id.as_bytes());
+        key
+    }
+
     /// Parse transaction ID from transaction key
     fn parse_tx_key(&self, key: &[u8]) -> Result<Uuid> {
         let key_str = String::from_utf8_lossy(key);
         if let Some(id_str) = key_str.strip_prefix("tx_") {
             Uuid::parse_str(id_str)
-                .map_err(|e| anyhow!("Failed to parse UUID from key: {}", e))
+                .map_err(|e| StorageError::Corrupt { what: "transaction key", reason: e.to_string() }.into())
         } else {
-            Err(anyhow!("Invalid transaction key format"))
+            Err(StorageError::Corrupt { what: "transaction key", reason: "missing tx_ prefix".to_string() }.into())
         }
     }
 }
@@ -286,6 +434,7 @@ mod tests {
             nonce: 1,
             timestamp: 1234567890,
             signature: [0u8; 64],
+            public_key: [0u8; 32],
             data: vec![],
         }
     }
@@ -324,4 +473,104 @@ mod tests {
         assert!(retrieved.is_some());
         assert_eq!(retrieved.unwrap(), tx);
     }
+
+    #[test]
+    fn test_address_lookups_use_index() {
+        let (_temp_dir, db) = create_test_db();
+        let store = TransactionStore::new(db).unwrap();
+
+        let (alice, bob, carol) = ([1u8; 20], [2u8; 20], [3u8; 20]);
+        let create = |from, to, timestamp| Transaction {
+            from,
+            to,
+            timestamp,
+            ..create_test_transaction()
+        };
+        let transactions = vec![
+            create(alice, bob, 300),
+            create(alice, carol, 100),
+            create(bob, alice, 200),
+            create(alice, bob, 200),
+            create(carol, bob, 50),
+        ];
+        for tx in &transactions {
+            store.put_transaction(tx).unwrap();
+        }
+
+        // Same results as filtering every stored transaction and sorting by timestamp
+        let expected = |matches: &dyn Fn(&Transaction) -> bool| {
+            let mut expected: Vec<Transaction> = transactions.iter().filter(|tx| matches(tx)).cloned().collect();
+            expected.sort_by_key(|tx| (tx.timestamp, tx.id));
+            expected
+        };
+        for address in [alice, bob, carol] {
+            assert_eq!(store.get_transactions_by_sender(&address).unwrap(), expected(&|tx| tx.from == address));
+            assert_eq!(store.get_transactions_by_recipient(&address).unwrap(), expected(&|tx| tx.to == address));
+        }
+        assert!(store.get_transactions_by_sender(&[9u8; 20]).unwrap().is_empty());
+
+        // Index entries are not mistaken for transactions
+        assert_eq!(store.count_transactions().unwrap(), 5);
+        assert_eq!(store.get_all_transaction_ids().unwrap().len(), 5);
+        assert_eq!(store.get_recent_transactions(10).unwrap().len(), 5);
+
+        store.delete_transaction(&transactions[0].id).unwrap();
+        assert_eq!(store.get_transactions_by_sender(&alice).unwrap(), vec![transactions[1].clone(), transactions[3].clone()]);
+        assert_eq!(store.get_transactions_by_recipient(&bob).unwrap(), vec![transactions[4].clone(), transactions[3].clone()]);
+    }
+
+    #[test]
+    fn test_address_lookup_reads_only_that_address() {
+        let (_temp_dir, db) = create_test_db();
+        let store = TransactionStore::new(db).unwrap();
+
+        // Many senders on both sides of the target in key order
+        for sender in (0..=u8::MAX).filter(|&sender| sender != 42) {
+            for _ in 0..4 {
+                store.put_transaction(&Transaction { from: [sender; 20], ..create_test_transaction() }).unwrap();
+            }
+        }
+        for _ in 0..3 {
+            store.put_transaction(&Transaction { from: [42; 20], ..create_test_transaction() }).unwrap();
+        }
+
+        assert_eq!(store.get_transactions_by_sender(&[42; 20]).unwrap().len(), 3);
+        // The sender's own entries plus the one key that ends the scan
+        assert!(store.keys_visited.load(Ordering::Relaxed) <= 3 + 1);
+    }
+
+    #[test]
+    fn test_recent_transactions_paging() {
+        let (_temp_dir, db) = create_test_db();
+        let store = TransactionStore::new(db).unwrap();
+
+        // Timestamps repeat so ties across page boundaries are exercised
+        let mut transactions: Vec<Transaction> = (0..1000)
+            .map(|i| Transaction { timestamp: 1_000 + i / 3, ..create_test_transaction() })
+            .collect();
+        for tx in &transactions {
+            store.put_transaction(tx).unwrap();
+        }
+
+        let mut seen = Vec::new();
+        let mut cursor = None;
+        loop {
+            let (page, next) = store.get_recent_transactions_paged(100, cursor).unwrap();
+            assert!(page.len() <= 100);
+            seen.extend(page.into_iter().map(|tx| tx.id));
+
+            match next {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+
+        // Every transaction exactly once, newest first
+        transactions.sort_by_key(|tx| std::cmp::Reverse((tx.timestamp, tx.id)));
+        assert_eq!(seen, transactions.iter().map(|tx| tx.id).collect::<Vec<_>>());
+
+        let (page, next) = store.get_recent_transactions_paged(usize::MAX, None).unwrap();
+        assert_eq!(page.len(), MAX_PAGE_SIZE);
+        assert!(next.is_none());
+    }
 }
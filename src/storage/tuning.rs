@@ -0,0 +1,205 @@
+use anyhow::{anyhow, Result};
+use rocksdb::{BlockBasedOptions, Cache, DBCompressionType, DataBlockIndexType, Options};
+use serde::Deserialize;
+use std::str::FromStr;
+
+use super::{CF_BLOCKS, CF_STATE};
+
+/// Compression applied to a column family's SST files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    None,
+    Snappy,
+    Lz4,
+    Zstd,
+}
+
+impl Compression {
+    fn to_rocksdb(self) -> DBCompressionType {
+        match self {
+            Compression::None => DBCompressionType::None,
+            Compression::Snappy => DBCompressionType::Snappy,
+            Compression::Lz4 => DBCompressionType::Lz4,
+            Compression::Zstd => DBCompressionType::Zstd,
+        }
+    }
+}
+
+impl FromStr for Compression {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "none" => Ok(Compression::None),
+            "snappy" => Ok(Compression::Snappy),
+            "lz4" => Ok(Compression::Lz4),
+            "zstd" => Ok(Compression::Zstd),
+            other => Err(anyhow!("unknown compression {:?}; expected none, snappy, lz4 or zstd", other)),
+        }
+    }
+}
+
+/// RocksDB tuning applied when the database is opened
+///
+/// Every column family gets a bloom filter and reads through one shared block
+/// cache. The append-only blocks column family takes larger write buffers and
+/// its own compression; state is set up for point lookups.
+#[derive(Debug, Clone)]
+pub struct StorageConfig {
+    /// Memtable size per column family before it is flushed, in bytes
+    pub write_buffer_size: usize,
+    /// Memtables a column family may fill before writes stall
+    pub max_write_buffer_number: i32,
+    /// Block cache shared by every column family, in bytes
+    pub block_cache_size: usize,
+    /// Compression of the blocks column family
+    pub block_compression: Compression,
+    /// Compression of every other column family
+    pub compression: Compression,
+    /// Bloom filter bits per key; zero disables the filters
+    pub bloom_filter_bits: f64,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            write_buffer_size: 64 * 1024 * 1024,
+            max_write_buffer_number: 4,
+            block_cache_size: 256 * 1024 * 1024,
+            block_compression: Compression::Zstd,
+            compression: Compression::Lz4,
+            bloom_filter_bits: 10.0,
+        }
+    }
+}
+
+impl StorageConfig {
+    /// Check the settings can open a database
+    pub fn validate(&self) -> Result<()> {
+        if self.write_buffer_size == 0 {
+            return Err(anyhow!("write buffer size must be greater than zero"));
+        }
+        if self.max_write_buffer_number < 1 {
+            return Err(anyhow!("max write buffer number must be at least 1"));
+        }
+        if !(self.bloom_filter_bits >= 0.0 && self.bloom_filter_bits.is_finite()) {
+            return Err(anyhow!("bloom filter bits must be a non-negative number"));
+        }
+        Ok(())
+    }
+
+    /// Options for one column family, reading through `cache`
+    pub(crate) fn cf_options(&self, name: &str, cache: &Cache) -> Options {
+        let mut table = BlockBasedOptions::default();
+        table.set_block_cache(cache);
+        table.set_cache_index_and_filter_blocks(true);
+        if self.bloom_filter_bits > 0.0 {
+            table.set_bloom_filter(self.bloom_filter_bits, false);
+        }
+
+        let mut opts = Options::default();
+        opts.set_max_write_buffer_number(self.max_write_buffer_number);
+
+        match name {
+            // Blocks are written once in height order and rarely read back
+            CF_BLOCKS => {
+                opts.set_write_buffer_size(self.write_buffer_size * 2);
+                opts.set_compression_type(self.block_compression.to_rocksdb());
+            }
+            // Accounts are read one key at a time while blocks are validated
+            CF_STATE => {
+                table.set_data_block_index_type(DataBlockIndexType::BinaryAndHash);
+                opts.set_write_buffer_size(self.write_buffer_size);
+                opts.set_compression_type(self.compression.to_rocksdb());
+            }
+            _ => {
+                opts.set_write_buffer_size(self.write_buffer_size);
+                opts.set_compression_type(self.compression.to_rocksdb());
+            }
+        }
+
+        opts.set_block_based_table_factory(&table);
+        opts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::path::Path;
+    use tempfile::TempDir;
+    use crate::storage::Storage;
+
+    /// Compression of each column family, from the newest OPTIONS file RocksDB wrote
+    fn cf_compression(db_path: &Path) -> HashMap<String, String> {
+        let options_file = std::fs::read_dir(db_path).unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.file_name().unwrap().to_string_lossy().starts_with("OPTIONS-"))
+            .max()
+            .unwrap();
+
+        let mut compression = HashMap::new();
+        let mut section = None;
+        for line in std::fs::read_to_string(options_file).unwrap().lines() {
+            let line = line.trim();
+            if line.starts_with('[') {
+                section = line.strip_prefix("[CFOptions \"")
+                    .and_then(|rest| rest.strip_suffix("\"]"))
+                    .map(str::to_string);
+            } else if let (Some(cf), Some(value)) = (&section, line.strip_prefix("compression=")) {
+                compression.insert(cf.clone(), value.to_string());
+            }
+        }
+        compression
+    }
+
+    #[test]
+    fn test_custom_config_sets_compression_per_column_family() {
+        let temp_dir = TempDir::new().unwrap();
+        let account = [1u8; 20];
+        let config = StorageConfig {
+            write_buffer_size: 8 * 1024 * 1024,
+            max_write_buffer_number: 2,
+            block_cache_size: 16 * 1024 * 1024,
+            block_compression: Compression::Zstd,
+            compression: Compression::None,
+            bloom_filter_bits: 0.0,
+        };
+
+        let storage = Storage::open_with(temp_dir.path(), &config).unwrap();
+        storage.state().set_balance(&account, 100).unwrap();
+        drop(storage);
+
+        let compression = cf_compression(temp_dir.path());
+        assert_eq!(compression["blocks"], "kZSTD");
+        assert_eq!(compression["state"], "kNoCompression");
+
+        // Reopening with the settings swapped recompresses new files per column family
+        let config = StorageConfig {
+            block_compression: Compression::None,
+            compression: Compression::Lz4,
+            bloom_filter_bits: 10.0,
+            ..config
+        };
+        let storage = Storage::open_with(temp_dir.path(), &config).unwrap();
+        assert_eq!(storage.state().get_balance(&account).unwrap(), 100);
+        drop(storage);
+
+        let compression = cf_compression(temp_dir.path());
+        assert_eq!(compression["blocks"], "kNoCompression");
+        assert_eq!(compression["state"], "kLZ4Compression");
+        assert_eq!(compression["receipts"], "kLZ4Compression");
+
+        let invalid = StorageConfig { max_write_buffer_number: 0, ..StorageConfig::default() };
+        assert!(Storage::open_with(TempDir::new().unwrap().path(), &invalid).is_err());
+    }
+
+    #[test]
+    fn test_compression_parses_case_insensitively() {
+        assert_eq!("ZSTD".parse::<Compression>().unwrap(), Compression::Zstd);
+        assert_eq!("none".parse::<Compression>().unwrap(), Compression::None);
+        assert!("gzip".parse::<Compression>().is_err());
+    }
+}
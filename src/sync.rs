@@ -0,0 +1,820 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use anyhow::{Result, anyhow};
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+
+use crate::storage::Storage;
+use crate::types::{Block, BlockHeight, Hash, MessagePayload, NetworkMessage, NodeId};
+use crate::validation::Validator;
+
+/// Block sync configuration
+#[derive(Debug, Clone)]
+pub struct SyncConfig {
+    /// Maximum number of outstanding block requests
+    pub max_in_flight: usize,
+    /// How long to wait for a response before requesting a height again
+    pub request_timeout: Duration,
+    /// Responses slower than this count against the peer
+    pub slow_response_threshold: Duration,
+    /// Consecutive slow responses or timeouts before a peer is deprioritized
+    pub slow_strikes: u32,
+    /// Blocks the local chain may trail the highest peer before the node reports itself syncing
+    pub lag_threshold: BlockHeight,
+    /// Blocks held while their parent is missing; the oldest is evicted beyond this
+    pub max_orphans: usize,
+}
+
+impl Default for SyncConfig {
+    fn default() -> Self {
+        Self {
+            max_in_flight: 16,
+            request_timeout: Duration::from_secs(5),
+            slow_response_threshold: Duration::from_secs(2),
+            slow_strikes: 3,
+            lag_threshold: 2,
+            max_orphans: 256,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct InFlight {
+    sent_at: Instant,
+    // Peer asked to answer; `None` if no peer was known to have the height
+    peer: Option<NodeId>,
+}
+
+#[derive(Debug, Default)]
+struct PeerSyncStats {
+    // Highest height the peer is known to have
+    height: BlockHeight,
+    // Moving average of the peer's response times
+    avg_latency: Option<Duration>,
+    // Consecutive slow responses or timeouts
+    strikes: u32,
+    slow: bool,
+}
+
+#[derive(Debug, Default)]
+struct SyncState {
+    // Highest height a peer is known to have
+    target_height: Option<BlockHeight>,
+    // Next height that has not been requested yet
+    next_request: BlockHeight,
+    // Outstanding requests, when they were sent and to whom
+    in_flight: HashMap<BlockHeight, InFlight>,
+    // Blocks received ahead of the local tip, waiting for their parent
+    orphans: OrphanBuffer,
+    // Peers we can request blocks from
+    peers: HashMap<NodeId, PeerSyncStats>,
+}
+
+/// Blocks whose parent isn't stored yet, indexed by the parent they wait for
+///
+/// Storing a block releases exactly its children, so blocks arriving in any
+/// order are committed once the chain reaches them. When full, the oldest
+/// arrival is evicted to make room.
+#[derive(Debug, Default)]
+struct OrphanBuffer {
+    capacity: usize,
+    blocks: HashMap<Hash, Block>,
+    // Held children of each missing parent
+    children: HashMap<Hash, Vec<Hash>>,
+    // Held block hashes, oldest arrival first
+    arrivals: VecDeque<Hash>,
+}
+
+impl OrphanBuffer {
+    fn new(capacity: usize) -> Self {
+        Self { capacity, ..Self::default() }
+    }
+
+    /// Hold a block until its parent is stored, returning any block evicted to make room
+    fn insert(&mut self, block: Block) -> Option<Block> {
+        let hash = block.hash();
+        if self.capacity == 0 || self.blocks.contains_key(&hash) {
+            return None;
+        }
+
+        let evicted = if self.blocks.len() >= self.capacity {
+            self.arrivals.front().copied().and_then(|oldest| self.remove(&oldest))
+        } else {
+            None
+        };
+
+        self.children.entry(block.header.previous_hash).or_default().push(hash);
+        self.arrivals.push_back(hash);
+        self.blocks.insert(hash, block);
+        evicted
+    }
+
+    /// Release the held blocks that build on `parent`
+    fn take_children(&mut self, parent: &Hash) -> Vec<Block> {
+        let hashes = self.children.remove(parent).unwrap_or_default();
+        self.arrivals.retain(|held| !hashes.contains(held));
+        hashes.iter().filter_map(|hash| self.blocks.remove(hash)).collect()
+    }
+
+    fn remove(&mut self, hash: &Hash) -> Option<Block> {
+        let block = self.blocks.remove(hash)?;
+        self.arrivals.retain(|held| held != hash);
+
+        let parent = block.header.previous_hash;
+        if let Some(siblings) = self.children.get_mut(&parent) {
+            siblings.retain(|held| held != hash);
+            if siblings.is_empty() {
+                self.children.remove(&parent);
+            }
+        }
+        Some(block)
+    }
+
+    /// Drop blocks below `height`, which the local chain has already passed
+    fn prune_below(&mut self, height: BlockHeight) {
+        let stale: Vec<Hash> = self.blocks
+            .iter()
+            .filter(|(_, block)| block.header.height < height)
+            .map(|(hash, _)| *hash)
+            .collect();
+        for hash in &stale {
+            self.remove(hash);
+        }
+    }
+
+    fn holds_height(&self, height: BlockHeight) -> bool {
+        self.blocks.values().any(|block| block.header.height == height)
+    }
+}
+
+impl SyncState {
+    /// Pick the peer to ask for a height: fast responders first, slow peers only as a fallback
+    fn select_peer(&self, height: BlockHeight) -> Option<NodeId> {
+        let load = |peer: &NodeId| {
+            self.in_flight.values().filter(|request| request.peer.as_ref() == Some(peer)).count()
+        };
+
+        // Unmeasured peers rank as fastest so they get probed
+        self.peers
+            .iter()
+            .filter(|(_, stats)| stats.height >= height)
+            .min_by_key(|(peer, stats)| (stats.slow, stats.avg_latency.unwrap_or_default(), load(*peer), (*peer).clone()))
+            .map(|(peer, _)| peer.clone())
+    }
+}
+
+/// Downloads missing blocks from peers and answers their block requests
+#[derive(Clone)]
+pub struct SyncManager {
+    config: SyncConfig,
+    node_id: NodeId,
+    storage: Arc<Storage>,
+    validator: Arc<Validator>,
+    outbound: mpsc::UnboundedSender<NetworkMessage>,
+    state: Arc<RwLock<SyncState>>,
+}
+
+impl SyncManager {
+    pub fn new(
+        config: SyncConfig,
+        node_id: NodeId,
+        storage: Arc<Storage>,
+        validator: Arc<Validator>,
+        outbound: mpsc::UnboundedSender<NetworkMessage>,
+    ) -> Self {
+        let state = SyncState {
+            orphans: OrphanBuffer::new(config.max_orphans),
+            ..SyncState::default()
+        };
+        Self {
+            config,
+            node_id,
+            storage,
+            validator,
+            outbound,
+            state: Arc::new(RwLock::new(state)),
+        }
+    }
+
+    /// Handle a block sync message from the network
+    pub fn handle_message(&self, message: &NetworkMessage) -> Result<()> {
+        match &message.payload {
+            // Requests addressed to another peer are theirs to answer
+            MessagePayload::BlockRequest { height, peer } => match peer {
+                Some(peer) if *peer != self.node_id => Ok(()),
+                _ => self.handle_block_request(*height),
+            },
+            MessagePayload::BlockResponse { block: Some(block) } => {
+                self.handle_block_response(&message.sender, block.clone()).map(|_| ())
+            }
+            // The peer doesn't have the height; the request times out and is retried
+            MessagePayload::BlockResponse { block: None } => Ok(()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Record that a peer has blocks up to the given height and start catching up
+    pub fn observe_peer_height(&self, peer: &NodeId, height: BlockHeight) -> Result<()> {
+        {
+            let mut state = self.state.write().unwrap();
+            if state.target_height < Some(height) {
+                state.target_height = Some(height);
+            }
+
+            let stats = state.peers.entry(peer.clone()).or_default();
+            stats.height = stats.height.max(height);
+        }
+
+        self.request_blocks()
+    }
+
+    /// Answer a peer's request from local storage
+    pub fn handle_block_request(&self, height: BlockHeight) -> Result<()> {
+        let block = self.storage.blocks().get_block(height)?;
+        self.send(MessagePayload::BlockResponse { block })
+    }
+
+    /// Validate and store a block downloaded from a peer, returning how many blocks were applied
+    pub fn handle_block_response(&self, peer: &NodeId, block: Block) -> Result<usize> {
+        let height = block.header.height;
+        let needed = self.next_needed_height()?;
+
+        {
+            let mut state = self.state.write().unwrap();
+            let requested = match state.in_flight.remove(&height) {
+                Some(request) => {
+                    // Only time the peer that was asked; others answered someone else's request
+                    if request.peer.as_ref().is_none_or(|asked| asked == peer) {
+                        self.record_response(&mut state, peer, request.sent_at.elapsed());
+                    }
+                    true
+                }
+                None => false,
+            };
+
+            // Unsolicited blocks past the target must not crowd requested ones out of the buffer
+            if !requested && state.target_height.is_none_or(|target| height > target) {
+                debug!("Dropping unsolicited block at height {} from {}", height, peer);
+                return Ok(0);
+            }
+
+            // Already applied
+            if height < needed {
+                return Ok(0);
+            }
+
+            // Blocks ahead of the tip wait until their parent is stored; an evicted
+            // block's height is requested again
+            if let Some(evicted) = state.orphans.insert(block) {
+                let evicted_height = evicted.header.height;
+                debug!("Orphan buffer full, evicted block at height {}", evicted_height);
+                state.next_request = state.next_request.min(evicted_height);
+            }
+        }
+
+        let applied = self.apply_buffered()?;
+        self.request_blocks()?;
+        Ok(applied)
+    }
+
+    /// Re-request heights whose responses never arrived
+    ///
+    /// Also applies held blocks whose parent was stored some other way, such as
+    /// through consensus.
+    pub fn retry_timed_out(&self) -> Result<()> {
+        self.apply_buffered()?;
+
+        let retries: Vec<(BlockHeight, Option<NodeId>)> = {
+            let mut state = self.state.write().unwrap();
+            let timeout = self.config.request_timeout;
+            let expired: Vec<(BlockHeight, Option<NodeId>)> = state.in_flight
+                .iter()
+                .filter(|(_, request)| request.sent_at.elapsed() >= timeout)
+                .map(|(height, request)| (*height, request.peer.clone()))
+                .collect();
+
+            let mut retries = Vec::with_capacity(expired.len());
+            for (height, peer) in expired {
+                // A timeout counts as a slow response, so the retry may go elsewhere
+                if let Some(peer) = peer {
+                    self.record_strike(&mut state, &peer);
+                }
+
+                let peer = state.select_peer(height);
+                state.in_flight.insert(height, InFlight { sent_at: Instant::now(), peer: peer.clone() });
+                retries.push((height, peer));
+            }
+            retries
+        };
+
+        for (height, peer) in retries {
+            debug!("Block request for height {} timed out, retrying via {:?}", height, peer);
+            self.send(MessagePayload::BlockRequest { height, peer })?;
+        }
+
+        self.request_blocks()
+    }
+
+    /// Check whether the local chain is behind a known peer
+    pub fn is_syncing(&self) -> Result<bool> {
+        let target = self.state.read().unwrap().target_height;
+        Ok(match target {
+            Some(target) => self.next_needed_height()? <= target,
+            None => false,
+        })
+    }
+
+    /// Blocks between the local tip and the highest height seen from peers
+    pub fn blocks_behind(&self) -> Result<BlockHeight> {
+        let target = match self.state.read().unwrap().target_height {
+            Some(target) => target,
+            None => return Ok(0),
+        };
+        Ok((target + 1).saturating_sub(self.next_needed_height()?))
+    }
+
+    /// Check whether the node trails its peers by more than the lag threshold
+    ///
+    /// Unlike `is_syncing`, a block or two of gossip delay doesn't count.
+    pub fn is_lagging(&self) -> Result<bool> {
+        Ok(self.blocks_behind()? > self.config.lag_threshold)
+    }
+
+    /// Get the highest height a peer is known to have
+    pub fn target_height(&self) -> Option<BlockHeight> {
+        self.state.read().unwrap().target_height
+    }
+
+    /// Peers currently deprioritized for responding slowly
+    pub fn slow_peers(&self) -> Vec<NodeId> {
+        let state = self.state.read().unwrap();
+        let mut peers: Vec<NodeId> = state.peers
+            .iter()
+            .filter(|(_, stats)| stats.slow)
+            .map(|(peer, _)| peer.clone())
+            .collect();
+        peers.sort();
+        peers
+    }
+
+    /// Average block response time of a peer, if it has answered any request
+    pub fn peer_latency(&self, peer: &NodeId) -> Option<Duration> {
+        self.state.read().unwrap().peers.get(peer).and_then(|stats| stats.avg_latency)
+    }
+
+    /// Fold a response time into the peer's average and slow-peer strikes
+    fn record_response(&self, state: &mut SyncState, peer: &NodeId, latency: Duration) {
+        let stats = state.peers.entry(peer.clone()).or_default();
+        stats.avg_latency = Some(match stats.avg_latency {
+            Some(avg) => (avg * 3 + latency) / 4,
+            None => latency,
+        });
+
+        if latency > self.config.slow_response_threshold {
+            self.record_strike(state, peer);
+        } else if stats.strikes > 0 || stats.slow {
+            if stats.slow {
+                info!("Peer {} is responding promptly again, restoring it for sync", peer);
+            }
+            stats.strikes = 0;
+            stats.slow = false;
+        }
+    }
+
+    fn record_strike(&self, state: &mut SyncState, peer: &NodeId) {
+        let stats = state.peers.entry(peer.clone()).or_default();
+        stats.strikes += 1;
+
+        if !stats.slow && stats.strikes >= self.config.slow_strikes {
+            stats.slow = true;
+            warn!("Peer {} is consistently slow to answer block requests, deprioritizing it", peer);
+        }
+    }
+
+    /// Height of the next block the local chain needs
+    fn next_needed_height(&self) -> Result<BlockHeight> {
+        Ok(match self.storage.get_latest_height()? {
+            Some(height) => height + 1,
+            None => 0,
+        })
+    }
+
+    /// Fill the request window with the next missing heights
+    fn request_blocks(&self) -> Result<()> {
+        let needed = self.next_needed_height()?;
+
+        let requests: Vec<(BlockHeight, Option<NodeId>)> = {
+            let mut state = self.state.write().unwrap();
+            let target = match state.target_height {
+                Some(target) => target,
+                None => return Ok(()),
+            };
+
+            state.next_request = state.next_request.max(needed);
+
+            let mut requests = Vec::new();
+            while state.in_flight.len() < self.config.max_in_flight && state.next_request <= target {
+                let height = state.next_request;
+                state.next_request += 1;
+
+                if state.orphans.holds_height(height) || state.in_flight.contains_key(&height) {
+                    continue;
+                }
+
+                let peer = state.select_peer(height);
+                state.in_flight.insert(height, InFlight { sent_at: Instant::now(), peer: peer.clone() });
+                requests.push((height, peer));
+            }
+            requests
+        };
+
+        for (height, peer) in requests {
+            self.send(MessagePayload::BlockRequest { height, peer })?;
+        }
+
+        Ok(())
+    }
+
+    /// Apply held blocks that build on the local head, then their children in turn
+    fn apply_buffered(&self) -> Result<usize> {
+        let mut applied = 0;
+
+        loop {
+            let needed = self.next_needed_height()?;
+            let head = self.storage.get_head_hash()?.unwrap_or([0; 32]);
+            let children = {
+                let mut state = self.state.write().unwrap();
+                state.orphans.prune_below(needed);
+                state.orphans.take_children(&head)
+            };
+            if children.is_empty() {
+                break;
+            }
+
+            // Peers may have sent competing children; the first valid one extends the chain
+            let mut extended = false;
+            for block in children {
+                if extended || block.header.height != needed {
+                    continue;
+                }
+                match self.validator.validate_committed_block(&block) {
+                    Ok(()) => {
+                        self.storage.commit_block(&block)?;
+                        applied += 1;
+                        extended = true;
+                    }
+                    Err(e) => warn!("Rejected synced block at height {}: {}", needed, e),
+                }
+            }
+
+            if !extended {
+                // Ask again; the next response may come from an honest peer
+                let mut state = self.state.write().unwrap();
+                state.next_request = state.next_request.min(needed);
+                break;
+            }
+        }
+
+        if applied > 0 {
+            let height = self.next_needed_height()? - 1;
+            info!("Synced {} blocks, local height is now {}", applied, height);
+        }
+
+        Ok(applied)
+    }
+
+    fn send(&self, payload: MessagePayload) -> Result<()> {
+        self.outbound
+            .send(NetworkMessage::new(self.node_id.clone(), payload))
+            .map_err(|e| anyhow!("Failed to queue sync message: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    use crate::test_fixtures::{build_chain, create_transfer, now_ms, sender, sign_commit, validator_keys};
+    use crate::types::BlockHeader;
+
+    fn create_sync_node(temp_dir: &TempDir, node_id: &str, config: SyncConfig)
+        -> (Arc<Storage>, SyncManager, mpsc::UnboundedReceiver<NetworkMessage>)
+    {
+        let storage = Arc::new(Storage::new(temp_dir.path()).unwrap());
+        storage.state().set_balance(&sender(), 1_000_000).unwrap();
+
+        let validator = Arc::new(Validator::new((*storage).clone()).with_validator_keys(validator_keys()));
+        let (outbound, receiver) = mpsc::unbounded_channel();
+        let sync = SyncManager::new(config, node_id.to_string(), storage.clone(), validator, outbound);
+        (storage, sync, receiver)
+    }
+
+    fn drain(receiver: &mut mpsc::UnboundedReceiver<NetworkMessage>) -> Vec<NetworkMessage> {
+        let mut messages = Vec::new();
+        while let Ok(message) = receiver.try_recv() {
+            messages.push(message);
+        }
+        messages
+    }
+
+    /// Make a peer's outstanding requests look like they were sent `delay` ago
+    fn delay_responses(sync: &SyncManager, peer: &NodeId, delay: Duration) {
+        let mut state = sync.state.write().unwrap();
+        for request in state.in_flight.values_mut() {
+            if request.peer.as_ref() == Some(peer) {
+                request.sent_at = request.sent_at.checked_sub(delay).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn test_fresh_node_syncs_from_seeded_node() {
+        let seeded_dir = TempDir::new().unwrap();
+        let fresh_dir = TempDir::new().unwrap();
+        let config = SyncConfig {
+            max_in_flight: 8,
+            request_timeout: Duration::ZERO,
+            ..SyncConfig::default()
+        };
+
+        let (seeded_storage, seeded, mut seeded_out) = create_sync_node(&seeded_dir, "seeded", config.clone());
+        let (fresh_storage, fresh, mut fresh_out) = create_sync_node(&fresh_dir, "fresh", config);
+        build_chain(&seeded_storage, 100);
+
+        fresh.observe_peer_height(&"seeded".to_string(), 99).unwrap();
+        assert!(fresh.is_syncing().unwrap());
+
+        let mut rounds = 0;
+        let mut dropped_one = false;
+        while fresh.is_syncing().unwrap() {
+            rounds += 1;
+            assert!(rounds < 1000, "sync did not converge");
+
+            let requests = drain(&mut fresh_out);
+            assert!(requests.len() <= 8);
+            for request in &requests {
+                seeded.handle_message(request).unwrap();
+            }
+
+            // Deliver responses newest first, losing one to leave a gap
+            let mut responses = drain(&mut seeded_out);
+            responses.reverse();
+            if !dropped_one && responses.len() > 1 {
+                responses.remove(1);
+                dropped_one = true;
+            }
+            for response in &responses {
+                fresh.handle_message(response).unwrap();
+            }
+
+            if requests.is_empty() && responses.is_empty() {
+                fresh.retry_timed_out().unwrap();
+            }
+        }
+
+        assert!(dropped_one);
+        assert_eq!(fresh_storage.get_latest_height().unwrap(), Some(99));
+        for height in [0, 50, 99] {
+            let expected = seeded_storage.blocks().get_block(height).unwrap().unwrap();
+            let synced = fresh_storage.blocks().get_block(height).unwrap().unwrap();
+            assert_eq!(synced.hash(), expected.hash());
+        }
+    }
+
+    #[test]
+    fn test_invalid_block_is_not_stored() {
+        let temp_dir = TempDir::new().unwrap();
+        let (storage, sync, _outbound) = create_sync_node(&temp_dir, "fresh", SyncConfig::default());
+        let peer = "peer".to_string();
+        sync.observe_peer_height(&peer, 0).unwrap();
+
+        let mut block = Block {
+            header: BlockHeader {
+                height: 0,
+                previous_hash: [0; 32],
+                merkle_root: [0; 32],
+                state_root: [0; 32],
+                timestamp: now_ms(),
+                proposer: "validator-1".to_string(),
+                round: 0,
+                view: 0,
+                vrf_proof: None,
+            },
+            transactions: vec![create_transfer(1)],
+            signatures: vec![],
+        };
+        // Wrong merkle root
+        block.header.merkle_root = [9; 32];
+        sign_commit(&mut block);
+
+        assert_eq!(sync.handle_block_response(&peer, block.clone()).unwrap(), 0);
+        assert_eq!(storage.get_latest_height().unwrap(), None);
+
+        // A well-formed block without a quorum of commit signatures is refused too
+        block.header.merkle_root = block.calculate_merkle_root();
+        block.signatures.clear();
+        assert_eq!(sync.handle_block_response(&peer, block.clone()).unwrap(), 0);
+        assert_eq!(storage.get_latest_height().unwrap(), None);
+
+        sign_commit(&mut block);
+        assert_eq!(sync.handle_block_response(&peer, block).unwrap(), 1);
+        assert_eq!(storage.get_latest_height().unwrap(), Some(0));
+    }
+
+    #[test]
+    fn test_lagging_past_threshold() {
+        let dir = TempDir::new().unwrap();
+        let config = SyncConfig { lag_threshold: 3, ..SyncConfig::default() };
+        let (storage, sync, _out) = create_sync_node(&dir, "node", config);
+        assert_eq!(sync.blocks_behind().unwrap(), 0);
+        assert!(!sync.is_lagging().unwrap());
+
+        // Local tip at height 9
+        build_chain(&storage, 10);
+        let peer = "peer".to_string();
+
+        sync.observe_peer_height(&peer, 12).unwrap();
+        assert_eq!(sync.blocks_behind().unwrap(), 3);
+        assert!(sync.is_syncing().unwrap());
+        assert!(!sync.is_lagging().unwrap());
+
+        sync.observe_peer_height(&peer, 13).unwrap();
+        assert_eq!(sync.blocks_behind().unwrap(), 4);
+        assert!(sync.is_lagging().unwrap());
+
+        // Peers reporting lower heights don't lower the target
+        sync.observe_peer_height(&"other".to_string(), 5).unwrap();
+        assert!(sync.is_lagging().unwrap());
+    }
+
+    #[test]
+    fn test_slow_peer_is_deprioritized() {
+        let seeded_dir = TempDir::new().unwrap();
+        let fresh_dir = TempDir::new().unwrap();
+        let config = SyncConfig {
+            max_in_flight: 4,
+            request_timeout: Duration::from_secs(60),
+            slow_response_threshold: Duration::from_millis(500),
+            slow_strikes: 2,
+            lag_threshold: 2,
+            max_orphans: 256,
+        };
+
+        // Two peers serving the same chain, one of them slowly
+        let (seeded_storage, fast, mut fast_out) = create_sync_node(&seeded_dir, "fast", config.clone());
+        build_chain(&seeded_storage, 40);
+        let (slow_sender, mut slow_out) = mpsc::unbounded_channel();
+        let slow_validator = Arc::new(Validator::new((*seeded_storage).clone()));
+        let slow = SyncManager::new(config.clone(), "slow".to_string(), seeded_storage.clone(), slow_validator, slow_sender);
+        let (fresh_storage, fresh, mut fresh_out) = create_sync_node(&fresh_dir, "fresh", config);
+
+        let fast_id = "fast".to_string();
+        let slow_id = "slow".to_string();
+        fresh.observe_peer_height(&fast_id, 39).unwrap();
+        fresh.observe_peer_height(&slow_id, 39).unwrap();
+
+        let mut rounds = 0;
+        let mut flagged_round = None;
+        let mut late_slow_requests = 0;
+        while fresh.is_syncing().unwrap() {
+            rounds += 1;
+            assert!(rounds < 100, "sync did not converge");
+
+            // Both peers see every request, but only answer their own
+            for request in drain(&mut fresh_out) {
+                let to_slow = matches!(&request.payload, MessagePayload::BlockRequest { peer: Some(peer), .. } if *peer == slow_id);
+                // Requests drained here were made last round, possibly before the peer was flagged
+                if to_slow && flagged_round.is_some_and(|flagged| rounds > flagged + 1) {
+                    late_slow_requests += 1;
+                }
+                fast.handle_message(&request).unwrap();
+                slow.handle_message(&request).unwrap();
+            }
+
+            for response in drain(&mut fast_out) {
+                fresh.handle_message(&response).unwrap();
+            }
+
+            let slow_responses = drain(&mut slow_out);
+            delay_responses(&fresh, &slow_id, Duration::from_secs(2));
+            for response in &slow_responses {
+                fresh.handle_message(response).unwrap();
+            }
+
+            if flagged_round.is_none() && !fresh.slow_peers().is_empty() {
+                flagged_round = Some(rounds);
+            }
+        }
+
+        assert_eq!(fresh_storage.get_latest_height().unwrap(), Some(39));
+        assert_eq!(fresh.slow_peers(), vec![slow_id.clone()]);
+        assert!(flagged_round.is_some());
+        assert_eq!(late_slow_requests, 0);
+        assert!(fresh.peer_latency(&fast_id).unwrap() < fresh.peer_latency(&slow_id).unwrap());
+    }
+
+    /// A fresh node holding only the genesis block of a seeded chain of `length` blocks
+    fn create_connected_pair(seeded_dir: &TempDir, fresh_dir: &TempDir, length: u64, config: SyncConfig)
+        -> (Arc<Storage>, Arc<Storage>, SyncManager, mpsc::UnboundedReceiver<NetworkMessage>)
+    {
+        let (seeded_storage, _seeded, _seeded_out) = create_sync_node(seeded_dir, "seeded", config.clone());
+        build_chain(&seeded_storage, length);
+        let (fresh_storage, fresh, fresh_out) = create_sync_node(fresh_dir, "fresh", config);
+        fresh_storage.commit_block(&seeded_storage.blocks().get_block(0).unwrap().unwrap()).unwrap();
+        (seeded_storage, fresh_storage, fresh, fresh_out)
+    }
+
+    #[test]
+    fn test_blocks_delivered_in_reverse_are_committed() {
+        let seeded_dir = TempDir::new().unwrap();
+        let fresh_dir = TempDir::new().unwrap();
+        let (seeded_storage, fresh_storage, fresh, _fresh_out) =
+            create_connected_pair(&seeded_dir, &fresh_dir, 4, SyncConfig::default());
+        let peer = "seeded".to_string();
+        fresh.observe_peer_height(&peer, 3).unwrap();
+
+        // Blocks 3 and 2 have no stored parent yet and are held
+        for height in [3, 2] {
+            let block = seeded_storage.blocks().get_block(height).unwrap().unwrap();
+            assert_eq!(fresh.handle_block_response(&peer, block).unwrap(), 0);
+            assert_eq!(fresh_storage.get_latest_height().unwrap(), Some(0));
+        }
+        assert_eq!(fresh.state.read().unwrap().orphans.blocks.len(), 2);
+
+        // Block 1 connects the chain and releases its held descendants
+        let block = seeded_storage.blocks().get_block(1).unwrap().unwrap();
+        assert_eq!(fresh.handle_block_response(&peer, block).unwrap(), 3);
+        assert_eq!(fresh_storage.get_latest_height().unwrap(), Some(3));
+        assert_eq!(fresh_storage.get_head_hash().unwrap(), seeded_storage.get_head_hash().unwrap());
+        assert!(fresh.state.read().unwrap().orphans.blocks.is_empty());
+        assert!(!fresh.is_syncing().unwrap());
+    }
+
+    #[test]
+    fn test_full_orphan_buffer_evicts_oldest_and_requests_it_again() {
+        let seeded_dir = TempDir::new().unwrap();
+        let fresh_dir = TempDir::new().unwrap();
+        let config = SyncConfig { max_orphans: 2, ..SyncConfig::default() };
+        let (seeded_storage, fresh_storage, fresh, mut fresh_out) =
+            create_connected_pair(&seeded_dir, &fresh_dir, 5, config);
+        let peer = "seeded".to_string();
+        fresh.observe_peer_height(&peer, 4).unwrap();
+        drain(&mut fresh_out);
+
+        // Holding block 2 evicts block 4, the oldest arrival
+        for height in [4, 3, 2] {
+            let block = seeded_storage.blocks().get_block(height).unwrap().unwrap();
+            assert_eq!(fresh.handle_block_response(&peer, block).unwrap(), 0);
+        }
+        assert_eq!(fresh.state.read().unwrap().orphans.blocks.len(), 2);
+        assert!(!fresh.state.read().unwrap().orphans.holds_height(4));
+
+        let block = seeded_storage.blocks().get_block(1).unwrap().unwrap();
+        assert_eq!(fresh.handle_block_response(&peer, block).unwrap(), 3);
+        assert_eq!(fresh_storage.get_latest_height().unwrap(), Some(3));
+
+        // The evicted height is requested again and completes the chain
+        let requested: Vec<BlockHeight> = drain(&mut fresh_out)
+            .iter()
+            .filter_map(|message| match message.payload {
+                MessagePayload::BlockRequest { height, .. } => Some(height),
+                _ => None,
+            })
+            .collect();
+        assert!(requested.contains(&4), "{:?}", requested);
+
+        let block = seeded_storage.blocks().get_block(4).unwrap().unwrap();
+        assert_eq!(fresh.handle_block_response(&peer, block).unwrap(), 1);
+        assert_eq!(fresh_storage.get_head_hash().unwrap(), seeded_storage.get_head_hash().unwrap());
+    }
+
+    #[test]
+    fn test_unsolicited_block_past_target_is_not_buffered() {
+        let seeded_dir = TempDir::new().unwrap();
+        let fresh_dir = TempDir::new().unwrap();
+        let config = SyncConfig { max_orphans: 2, ..SyncConfig::default() };
+        let (seeded_storage, fresh_storage, fresh, _fresh_out) =
+            create_connected_pair(&seeded_dir, &fresh_dir, 5, config);
+        let peer = "seeded".to_string();
+        fresh.observe_peer_height(&peer, 3).unwrap();
+
+        for height in [3, 2] {
+            let block = seeded_storage.blocks().get_block(height).unwrap().unwrap();
+            assert_eq!(fresh.handle_block_response(&peer, block).unwrap(), 0);
+        }
+
+        // Block 4 was never requested and lies past the target, so it evicts nothing
+        let block = seeded_storage.blocks().get_block(4).unwrap().unwrap();
+        assert_eq!(fresh.handle_block_response(&"other".to_string(), block).unwrap(), 0);
+        {
+            let state = fresh.state.read().unwrap();
+            assert!(!state.orphans.holds_height(4));
+            assert!(state.orphans.holds_height(3) && state.orphans.holds_height(2));
+        }
+
+        let block = seeded_storage.blocks().get_block(1).unwrap().unwrap();
+        assert_eq!(fresh.handle_block_response(&peer, block).unwrap(), 3);
+        assert_eq!(fresh_storage.get_latest_height().unwrap(), Some(3));
+    }
+}
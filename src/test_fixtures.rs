@@ -0,0 +1,97 @@
+//! Signed chain fixtures shared by the sync and replay tests
+
+use ed25519_dalek::SigningKey;
+use std::collections::HashMap;
+use uuid::Uuid;
+use crate::crypto::Ed25519Scheme;
+use crate::storage::Storage;
+use crate::types::{
+    address_from_public_key, sign_vote, Address, Block, BlockHeader, NodeId, PublicKey, Transaction,
+    ValidatorSignature, VoteType,
+};
+
+pub(crate) fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+/// Key of the account every fixture transfer is sent from
+pub(crate) fn signing_key() -> SigningKey {
+    SigningKey::from_bytes(&[7u8; 32])
+}
+
+pub(crate) fn sender() -> Address {
+    address_from_public_key(&signing_key().verifying_key().to_bytes())
+}
+
+/// Key of `validator-1`, the only validator
+pub(crate) fn validator_key() -> SigningKey {
+    SigningKey::from_bytes(&[100u8; 32])
+}
+
+/// A single validator, so its commit signature alone is a quorum
+pub(crate) fn validator_keys() -> HashMap<NodeId, PublicKey> {
+    HashMap::from([("validator-1".to_string(), validator_key().verifying_key().to_bytes())])
+}
+
+pub(crate) fn sign_commit(block: &mut Block) {
+    let key = validator_key();
+    let header = &block.header;
+    let signature = sign_vote(&Ed25519Scheme, &key.to_bytes(), &block.hash(), header.view, header.round, &VoteType::Commit);
+    block.signatures = vec![ValidatorSignature {
+        validator_id: "validator-1".to_string(),
+        signature,
+        public_key: key.verifying_key().to_bytes(),
+    }];
+}
+
+/// Transfer of `100 * nonce` from `sender()`, signed
+pub(crate) fn create_transfer(nonce: u64) -> Transaction {
+    let mut tx = Transaction {
+        id: Uuid::new_v4(),
+        from: sender(),
+        to: [2u8; 20],
+        amount: 100 * nonce,
+        fee: 1,
+        nonce,
+        timestamp: now_ms(),
+        signature: [0u8; 64],
+        public_key: [0u8; 32],
+        data: vec![],
+    };
+    tx.sign(&signing_key());
+    tx
+}
+
+/// Commit `length` signed blocks from height 0, one transfer each, with real state roots
+///
+/// `sender()` must be funded in `storage` first.
+pub(crate) fn build_chain(storage: &Storage, length: u64) {
+    let start = now_ms() - 10_000;
+    let mut previous_hash = [0; 32];
+
+    for height in 0..length {
+        let mut block = Block {
+            header: BlockHeader {
+                height,
+                previous_hash,
+                merkle_root: [0; 32],
+                state_root: [0; 32],
+                timestamp: start + height,
+                proposer: "validator-1".to_string(),
+                round: 0,
+                view: 0,
+                vrf_proof: None,
+            },
+            transactions: vec![create_transfer(height + 1)],
+            signatures: vec![],
+        };
+        block.header.merkle_root = block.calculate_merkle_root();
+        block.header.state_root = storage.state().preview_state_root(&block).unwrap();
+        sign_commit(&mut block);
+        storage.commit_block(&block).unwrap();
+        previous_hash = block.hash();
+    }
+}
@@ -1,8 +1,13 @@
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use ed25519_dalek::SigningKey;
 use std::collections::HashMap;
 use uuid::Uuid;
 
+use crate::consensus::governance::GOVERNANCE_PREFIX;
+use crate::consensus::vrf::VrfProof;
+use crate::crypto::{Ed25519Scheme, SecretKey, SignatureScheme};
+
 pub type Hash = [u8; 32];
 pub type Address = [u8; 20];
 pub type Signature = [u8; 64];
@@ -30,6 +35,8 @@ pub struct BlockHeader {
     pub proposer: NodeId,
     pub round: u64,
     pub view: u64,
+    /// Proposer's VRF proof for its slot, under VRF leader election
+    pub vrf_proof: Option<VrfProof>,
 }
 
 /// Transaction structure
@@ -43,9 +50,24 @@ pub struct Transaction {
     pub nonce: u64,
     pub timestamp: Timestamp,
     pub signature: Signature,
+    pub public_key: PublicKey,
     pub data: Vec<u8>,
 }
 
+/// What a transaction does, which follows from its data
+///
+/// Value and data don't mix: a transaction carrying data other than a
+/// governance action moves no value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TransactionKind {
+    /// Moves value to another account, without data
+    Transfer,
+    /// Records arbitrary data on chain without moving value
+    DataCarrier,
+    /// Changes the validator set with a governance action
+    ValidatorUpdate,
+}
+
 /// Validator signature for consensus
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct ValidatorSignature {
@@ -82,7 +104,7 @@ pub enum ConsensusMessage {
 }
 
 /// Vote types in BFT consensus
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum VoteType {
     Prepare,
     Commit,
@@ -93,8 +115,12 @@ pub enum VoteType {
 pub struct NetworkMessage {
     pub message_id: Uuid,
     pub sender: NodeId,
+    /// Network the sender belongs to; filled in by the network layer on publish
+    pub chain_id: String,
     pub timestamp: Timestamp,
     pub payload: MessagePayload,
+    /// Sender's Ed25519 signature over `signing_payload`
+    pub signature: Option<Signature>,
 }
 
 /// Different types of network messages
@@ -102,7 +128,10 @@ pub struct NetworkMessage {
 pub enum MessagePayload {
     Consensus(ConsensusMessage),
     Transaction(Transaction),
-    BlockRequest { height: BlockHeight },
+    /// Several transactions gossiped in one message
+    TransactionBatch(Vec<Transaction>),
+    /// Ask for a block; `peer` names the node that should answer, or `None` for any
+    BlockRequest { height: BlockHeight, peer: Option<NodeId> },
     BlockResponse { block: Option<Block> },
     PeerDiscovery { peers: Vec<PeerInfo> },
     Heartbeat,
@@ -126,19 +155,43 @@ pub struct NodeState {
     pub is_leader: bool,
     pub connected_peers: usize,
     pub mempool_size: usize,
+    pub is_syncing: bool,
 }
 
 impl Block {
     /// Calculate the hash of the block
+    ///
+    /// Covers the header and transactions but not the signatures, so attaching
+    /// the commit signatures leaves the hash validators voted on unchanged.
+    /// Headers without a VRF proof hash as they did before the field existed.
     pub fn hash(&self) -> Hash {
-        let serialized = bincode::serialize(self).expect("Failed to serialize block");
+        let header = &self.header;
+        let fields = (
+            header.height, &header.previous_hash, &header.merkle_root, &header.state_root,
+            header.timestamp, &header.proposer, header.round, header.view,
+        );
+        let serialized = match &header.vrf_proof {
+            Some(proof) => bincode::serialize(&((fields, proof), &self.transactions)),
+            None => bincode::serialize(&(fields, &self.transactions)),
+        }
+        .expect("Failed to serialize block");
         let mut hasher = Sha256::new();
         hasher.update(&serialized);
         hasher.finalize().into()
     }
 
-    /// Verify block integrity
+    /// Payload each commit signature in `signatures` covers
+    pub fn commit_payload(&self) -> Hash {
+        vote_signing_payload(&self.hash(), self.header.view, self.header.round, &VoteType::Commit)
+    }
+
+    /// Verify block integrity, with transactions signed under Ed25519
     pub fn verify(&self) -> bool {
+        self.verify_with(&Ed25519Scheme)
+    }
+
+    /// Verify block integrity, checking transaction signatures under `scheme`
+    pub fn verify_with(&self, scheme: &dyn SignatureScheme) -> bool {
         // Verify merkle root
         let calculated_merkle = self.calculate_merkle_root();
         if calculated_merkle != self.header.merkle_root {
@@ -147,7 +200,7 @@ impl Block {
 
         // Verify all transactions
         for tx in &self.transactions {
-            if !tx.verify() {
+            if !tx.verify_with(scheme) {
                 return false;
             }
         }
@@ -155,31 +208,152 @@ impl Block {
         true
     }
 
+    /// Sum of all transaction fees, or `None` if the sum overflows `u64`
+    ///
+    /// Fee arithmetic is integer-only so every node computes the same total.
+    /// Any fractional split of fees (treasury, burn) must round each share down
+    /// and assign the remainder to burn.
+    pub fn total_fees(&self) -> Option<u64> {
+        self.transactions
+            .iter()
+            .try_fold(0u64, |total, tx| total.checked_add(tx.fee))
+    }
+
     /// Calculate merkle root of transactions
+    ///
+    /// An empty block's root is the hash of empty input, so it can't be mistaken
+    /// for a header whose root was never filled in.
     pub fn calculate_merkle_root(&self) -> Hash {
-        if self.transactions.is_empty() {
-            return [0; 32];
-        }
+        let hashes: Vec<Hash> = self.transactions.iter().map(|tx| tx.hash()).collect();
+        merkle_root(&hashes)
+    }
 
-        let mut hashes: Vec<Hash> = self.transactions.iter().map(|tx| tx.hash()).collect();
-
-        while hashes.len() > 1 {
-            let mut next_level = Vec::new();
-            for chunk in hashes.chunks(2) {
-                let mut hasher = Sha256::new();
-                hasher.update(&chunk[0]);
-                if chunk.len() > 1 {
-                    hasher.update(&chunk[1]);
-                } else {
-                    hasher.update(&chunk[0]); // Duplicate if odd number
-                }
-                next_level.push(hasher.finalize().into());
-            }
-            hashes = next_level;
-        }
+    /// Sibling hashes proving the transaction at `tx_index` is under the merkle root
+    pub fn merkle_proof(&self, tx_index: usize) -> Option<MerkleProof> {
+        let hashes: Vec<Hash> = self.transactions.iter().map(|tx| tx.hash()).collect();
+        merkle_proof(&hashes, tx_index)
+    }
+}
+
+/// Path from a leaf hash up to a merkle root
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MerkleProof {
+    /// Position of the leaf, e.g. of the transaction in the block
+    pub index: usize,
+    /// Sibling hashes from the leaf level upwards
+    pub siblings: Vec<Hash>,
+}
+
+/// Merkle root over `leaves`; the hash of empty input if there are none
+pub fn merkle_root(leaves: &[Hash]) -> Hash {
+    if leaves.is_empty() {
+        return Sha256::digest([]).into();
+    }
 
-        hashes[0]
+    let mut hashes = leaves.to_vec();
+    while hashes.len() > 1 {
+        hashes = merkle_level_up(&hashes);
     }
+
+    hashes[0]
+}
+
+/// Sibling hashes proving the leaf at `leaf_index` is under `merkle_root(leaves)`
+pub fn merkle_proof(leaves: &[Hash], leaf_index: usize) -> Option<MerkleProof> {
+    if leaf_index >= leaves.len() {
+        return None;
+    }
+
+    let mut hashes = leaves.to_vec();
+    let mut index = leaf_index;
+    let mut siblings = Vec::new();
+
+    while hashes.len() > 1 {
+        // The last node of an odd level is paired with itself
+        siblings.push(*hashes.get(index ^ 1).unwrap_or(&hashes[index]));
+        hashes = merkle_level_up(&hashes);
+        index /= 2;
+    }
+
+    Some(MerkleProof { index: leaf_index, siblings })
+}
+
+/// Check that `leaf` sits at `proof.index` under `root`
+pub fn verify_merkle_proof(leaf: &Hash, proof: &MerkleProof, root: &Hash) -> bool {
+    let mut hash = *leaf;
+    let mut index = proof.index;
+
+    for sibling in &proof.siblings {
+        hash = if index % 2 == 0 {
+            merkle_parent(&hash, sibling)
+        } else {
+            merkle_parent(sibling, &hash)
+        };
+        index /= 2;
+    }
+
+    // Leftover index bits mean the proof is too short for the claimed position
+    index == 0 && hash == *root
+}
+
+fn merkle_parent(left: &Hash, right: &Hash) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// Hash pairs of nodes into the next level, duplicating the last node if the count is odd
+fn merkle_level_up(hashes: &[Hash]) -> Vec<Hash> {
+    hashes
+        .chunks(2)
+        .map(|chunk| merkle_parent(&chunk[0], chunk.get(1).unwrap_or(&chunk[0])))
+        .collect()
+}
+
+/// Derive the account address controlled by an Ed25519 public key
+pub fn address_from_public_key(public_key: &PublicKey) -> Address {
+    let digest: Hash = Sha256::digest(public_key).into();
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&digest[12..]);
+    address
+}
+
+/// Digest a validator signs when voting for a block in a given view and round
+pub fn vote_signing_payload(block_hash: &Hash, view: u64, round: u64, vote_type: &VoteType) -> Hash {
+    let serialized = bincode::serialize(&(block_hash, view, round, vote_type))
+        .expect("Failed to serialize vote");
+    Sha256::digest(&serialized).into()
+}
+
+/// Sign a vote with the validator's key under `scheme`
+pub fn sign_vote(
+    scheme: &dyn SignatureScheme,
+    secret_key: &SecretKey,
+    block_hash: &Hash,
+    view: u64,
+    round: u64,
+    vote_type: &VoteType,
+) -> Signature {
+    scheme.sign(secret_key, &vote_signing_payload(block_hash, view, round, vote_type))
+}
+
+/// Check a vote signature against the validator's public key under `scheme`
+pub fn verify_vote(
+    scheme: &dyn SignatureScheme,
+    public_key: &PublicKey,
+    block_hash: &Hash,
+    view: u64,
+    round: u64,
+    vote_type: &VoteType,
+    signature: &Signature,
+) -> bool {
+    scheme.verify(public_key, &vote_signing_payload(block_hash, view, round, vote_type), signature)
+}
+
+/// Check an Ed25519 signature over a 32-byte payload
+pub fn verify_payload(public_key: &PublicKey, payload: &Hash, signature: &Signature) -> bool {
+    Ed25519Scheme.verify(public_key, payload, signature)
 }
 
 impl Transaction {
@@ -194,11 +368,50 @@ impl Transaction {
         hasher.finalize().into()
     }
 
-    /// Verify transaction signature (simplified)
+    /// Sign the transaction hash with an Ed25519 key, recording the signer's public key
+    pub fn sign(&mut self, signing_key: &SigningKey) {
+        self.sign_with(&Ed25519Scheme, &signing_key.to_bytes());
+    }
+
+    /// Sign the transaction hash under `scheme`, recording the signer's public key
+    pub fn sign_with(&mut self, scheme: &dyn SignatureScheme, secret_key: &SecretKey) {
+        self.public_key = scheme.public_key_from(secret_key);
+        self.signature = scheme.sign(secret_key, &self.hash());
+    }
+
+    /// Classify the transaction by its data
+    pub fn kind(&self) -> TransactionKind {
+        if self.data.is_empty() {
+            TransactionKind::Transfer
+        } else if self.data.starts_with(GOVERNANCE_PREFIX) {
+            TransactionKind::ValidatorUpdate
+        } else {
+            TransactionKind::DataCarrier
+        }
+    }
+
+    /// Verify basic fields and the Ed25519 signature over the transaction hash
+    ///
+    /// The rules specific to data-carrying kinds are left to the validator.
     pub fn verify(&self) -> bool {
-        // In a real implementation, this would verify the cryptographic signature
-        // For now, we'll do basic validation
-        self.amount > 0 && self.fee > 0 && self.from != self.to
+        self.verify_with(&Ed25519Scheme)
+    }
+
+    /// Verify basic fields and the signature over the transaction hash under `scheme`
+    pub fn verify_with(&self, scheme: &dyn SignatureScheme) -> bool {
+        if self.fee == 0 {
+            return false;
+        }
+        if self.kind() == TransactionKind::Transfer && (self.amount == 0 || self.from == self.to) {
+            return false;
+        }
+
+        // The signing key must control the sender address
+        if address_from_public_key(&self.public_key) != self.from {
+            return false;
+        }
+
+        scheme.verify(&self.public_key, &self.hash(), &self.signature)
     }
 
     /// Get transaction priority for mempool ordering
@@ -212,11 +425,143 @@ impl NetworkMessage {
         Self {
             message_id: Uuid::new_v4(),
             sender,
+            chain_id: String::new(),
             timestamp: std::time::SystemTime::now()
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap()
                 .as_millis() as u64,
             payload,
+            signature: None,
+        }
+    }
+
+    /// Hash of everything but the signature, which is what the sender signs
+    ///
+    /// Covers the chain id so a signed message can't be replayed on another network.
+    pub fn signing_payload(&self) -> Hash {
+        let serialized = bincode::serialize(&(&self.message_id, &self.sender, &self.chain_id, self.timestamp, &self.payload))
+            .expect("Failed to serialize network message");
+        Sha256::digest(serialized).into()
+    }
+
+    /// Sign the message as its sender
+    pub fn sign(&mut self, signing_key: &SigningKey) {
+        self.signature = Some(Ed25519Scheme.sign(&signing_key.to_bytes(), &self.signing_payload()));
+    }
+
+    /// Check the message was signed by `public_key`; unsigned messages fail
+    pub fn verify_signature(&self, public_key: &PublicKey) -> bool {
+        match &self.signature {
+            Some(signature) => verify_payload(public_key, &self.signing_payload(), signature),
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_signed_transaction() -> (Transaction, SigningKey) {
+        let signing_key = SigningKey::from_bytes(&[7u8; 32]);
+        let mut tx = Transaction {
+            id: Uuid::new_v4(),
+            from: address_from_public_key(&signing_key.verifying_key().to_bytes()),
+            to: [2u8; 20],
+            amount: 1000,
+            fee: 10,
+            nonce: 1,
+            timestamp: 1_000,
+            signature: [0u8; 64],
+            public_key: [0u8; 32],
+            data: vec![],
+        };
+        tx.sign(&signing_key);
+        (tx, signing_key)
+    }
+
+    #[test]
+    fn test_signed_transaction_verifies() {
+        let (tx, _) = create_signed_transaction();
+        assert!(tx.verify());
+    }
+
+    #[test]
+    fn test_tampered_transaction_fails_verification() {
+        let (tx, _) = create_signed_transaction();
+
+        let mut tampered = tx.clone();
+        tampered.amount += 1;
+        assert!(!tampered.verify());
+
+        let mut tampered = tx.clone();
+        tampered.nonce += 1;
+        assert!(!tampered.verify());
+
+        let mut garbage = tx.clone();
+        garbage.signature = [0xAB; 64];
+        assert!(!garbage.verify());
+    }
+
+    #[test]
+    fn test_signature_must_match_sender() {
+        let (mut tx, _) = create_signed_transaction();
+
+        // A valid signature from a key that doesn't control `from`
+        let other_key = SigningKey::from_bytes(&[9u8; 32]);
+        tx.sign(&other_key);
+        assert!(!tx.verify());
+    }
+
+    #[test]
+    fn test_merkle_proofs_verify_against_root() {
+        let (tx, signing_key) = create_signed_transaction();
+        let transactions: Vec<Transaction> = (0..5)
+            .map(|nonce| {
+                let mut tx = Transaction { id: Uuid::new_v4(), nonce, ..tx.clone() };
+                tx.sign(&signing_key);
+                tx
+            })
+            .collect();
+
+        let block = Block {
+            header: BlockHeader {
+                height: 1,
+                previous_hash: [0; 32],
+                merkle_root: [0; 32],
+                state_root: [0; 32],
+                timestamp: 1_000,
+                proposer: "validator-1".to_string(),
+                round: 0,
+                view: 0,
+                vrf_proof: None,
+            },
+            transactions,
+            signatures: vec![],
+        };
+        let root = block.calculate_merkle_root();
+
+        // Five leaves exercise the odd-node duplication at two levels
+        for (index, tx) in block.transactions.iter().enumerate() {
+            let proof = block.merkle_proof(index).unwrap();
+            assert!(verify_merkle_proof(&tx.hash(), &proof, &root));
         }
+        assert!(block.merkle_proof(5).is_none());
+
+        let proof = block.merkle_proof(1).unwrap();
+        let tx_hash = block.transactions[1].hash();
+        assert!(!verify_merkle_proof(&block.transactions[2].hash(), &proof, &root));
+
+        let mut tampered = proof.clone();
+        tampered.siblings[0][0] ^= 1;
+        assert!(!verify_merkle_proof(&tx_hash, &tampered, &root));
+
+        let mut moved = proof.clone();
+        moved.index = 0;
+        assert!(!verify_merkle_proof(&tx_hash, &moved, &root));
+
+        let mut truncated = proof;
+        truncated.siblings.pop();
+        assert!(!verify_merkle_proof(&tx_hash, &truncated, &root));
     }
 }
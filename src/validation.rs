@@ -1,8 +1,22 @@
 use anyhow::{Result, anyhow};
-use std::collections::HashSet;
-use crate::types::{Block, Transaction, BlockHeader, Hash, Address, BlockHeight};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use crate::crypto::{default_scheme, SignatureScheme};
+use crate::types::{
+    Block, Transaction, TransactionKind, BlockHeader, Hash, Address, BlockHeight, NodeId, PublicKey,
+};
+use crate::consensus::governance::GovernanceAction;
 use crate::storage::Storage;
 
+/// Blocks whose median timestamp a new block must exceed, unless configured otherwise
+pub const DEFAULT_MEDIAN_TIME_PAST_WINDOW: usize = 11;
+
+/// Largest transaction data accepted, unless configured otherwise
+pub const DEFAULT_MAX_DATA_BYTES: usize = 64 * 1024;
+
+/// How far ahead of local time a block timestamp may be
+const MAX_FUTURE_BLOCK_TIME_MS: u64 = 300_000;
+
 /// Block validation errors
 #[derive(Debug, thiserror::Error)]
 pub enum ValidationError {
@@ -26,16 +40,199 @@ pub enum ValidationError {
     InsufficientBalance,
     #[error("Invalid nonce: expected {expected}, got {actual}")]
     InvalidNonce { expected: u64, actual: u64 },
+    #[error("Sender not allowlisted: {0}")]
+    SenderNotAllowed(String),
+}
+
+/// Senders permitted to transact on a permissioned chain; empty means permissionless
+#[derive(Debug, Clone, Default)]
+pub struct SenderAllowlist {
+    senders: HashSet<Address>,
+}
+
+impl SenderAllowlist {
+    pub fn new(senders: impl IntoIterator<Item = Address>) -> Self {
+        Self {
+            senders: senders.into_iter().collect(),
+        }
+    }
+
+    /// Build an allowlist from `0x`-prefixed hex addresses
+    pub fn from_hex(addresses: &[String]) -> Result<Self> {
+        let mut senders = HashSet::new();
+
+        for address in addresses {
+            let digits = address.strip_prefix("0x").unwrap_or(address);
+            let parsed: Address = hex::decode(digits)
+                .ok()
+                .and_then(|bytes| bytes.try_into().ok())
+                .ok_or_else(|| anyhow!("Invalid allowlist address '{}'", address))?;
+            senders.insert(parsed);
+        }
+
+        Ok(Self { senders })
+    }
+
+    /// Check whether the chain accepts transactions from any sender
+    pub fn is_permissionless(&self) -> bool {
+        self.senders.is_empty()
+    }
+
+    /// Check whether a sender may submit transactions
+    pub fn is_allowed(&self, sender: &Address) -> bool {
+        self.is_permissionless() || self.senders.contains(sender)
+    }
+
+    /// Reject transactions from senders outside the allowlist
+    pub fn check(&self, tx: &Transaction) -> Result<(), ValidationError> {
+        if self.is_allowed(&tx.from) {
+            Ok(())
+        } else {
+            Err(ValidationError::SenderNotAllowed(format!("{:x?}", tx.from)))
+        }
+    }
+}
+
+/// Block size band adjusted deterministically from recent block fullness
+#[derive(Debug, Clone)]
+pub struct DynamicBlockSizeConfig {
+    pub min_size: usize,
+    pub max_size: usize,
+    pub window: u64,
+    pub growth_percent: u64,
+}
+
+impl Default for DynamicBlockSizeConfig {
+    fn default() -> Self {
+        Self {
+            min_size: 1024 * 1024,      // 1MB floor
+            max_size: 1024 * 1024 * 10, // 10MB ceiling
+            window: 100,
+            growth_percent: 125,
+        }
+    }
+}
+
+impl DynamicBlockSizeConfig {
+    /// Compute the size limit from the serialized sizes of the preceding blocks
+    pub fn limit_from_sizes(&self, recent_sizes: &[usize]) -> usize {
+        if recent_sizes.is_empty() {
+            return self.min_size;
+        }
+
+        // The median resists a few outlier blocks pushing the limit around
+        let mut sorted = recent_sizes.to_vec();
+        sorted.sort_unstable();
+        let median = sorted[sorted.len() / 2];
+
+        let target = median.saturating_mul(self.growth_percent as usize) / 100;
+        target.clamp(self.min_size, self.max_size)
+    }
+
+    /// Compute the size limit for a block at the given height from on-chain data
+    pub fn limit_at_height(&self, storage: &Storage, height: BlockHeight) -> Result<usize> {
+        let start = height.saturating_sub(self.window);
+        let mut sizes = Vec::new();
+
+        for prev_height in start..height {
+            if let Some(block) = storage.blocks().get_block(prev_height)? {
+                let size = bincode::serialized_size(&block)
+                    .map_err(|e| anyhow!("Failed to measure block: {}", e))?;
+                sizes.push(size as usize);
+            }
+        }
+
+        Ok(self.limit_from_sizes(&sizes))
+    }
 }
 
 /// Comprehensive blockchain validator
 pub struct Validator {
     storage: Storage,
+    dynamic_block_size: Option<DynamicBlockSizeConfig>,
+    sender_allowlist: SenderAllowlist,
+    median_time_past_window: usize,
+    max_data_bytes: usize,
+    signature_scheme: Arc<dyn SignatureScheme>,
+    validator_keys: Arc<HashMap<NodeId, PublicKey>>,
 }
 
 impl Validator {
     pub fn new(storage: Storage) -> Self {
-        Self { storage }
+        Self {
+            storage,
+            dynamic_block_size: None,
+            sender_allowlist: SenderAllowlist::default(),
+            median_time_past_window: DEFAULT_MEDIAN_TIME_PAST_WINDOW,
+            max_data_bytes: DEFAULT_MAX_DATA_BYTES,
+            signature_scheme: default_scheme(),
+            validator_keys: Arc::new(HashMap::new()),
+        }
+    }
+
+    /// Only accept transactions from allowlisted senders
+    pub fn with_sender_allowlist(mut self, allowlist: SenderAllowlist) -> Self {
+        self.sender_allowlist = allowlist;
+        self
+    }
+
+    /// Check transaction and commit signatures under `scheme` instead of Ed25519
+    pub fn with_signature_scheme(mut self, scheme: Arc<dyn SignatureScheme>) -> Self {
+        self.signature_scheme = scheme;
+        self
+    }
+
+    /// Enforce a dynamic block size limit instead of the fixed maximum
+    pub fn with_dynamic_block_size(mut self, config: DynamicBlockSizeConfig) -> Self {
+        self.dynamic_block_size = Some(config);
+        self
+    }
+
+    /// Require block timestamps to exceed the median of this many preceding blocks
+    pub fn with_median_time_past_window(mut self, window: usize) -> Self {
+        self.median_time_past_window = window.max(1);
+        self
+    }
+
+    /// Reject transactions carrying more than this many bytes of data
+    pub fn with_max_data_bytes(mut self, max_data_bytes: usize) -> Self {
+        self.max_data_bytes = max_data_bytes;
+        self
+    }
+
+    /// Check the commit signatures of finished blocks against these validator keys
+    /// while storage holds no validator set of its own
+    pub fn with_validator_keys(mut self, validator_keys: HashMap<NodeId, PublicKey>) -> Self {
+        self.validator_keys = Arc::new(validator_keys);
+        self
+    }
+
+    /// Median timestamp of the canonical blocks up to and including `height`
+    ///
+    /// Looks back over the configured window, stopping early at genesis or at
+    /// the first height with no stored header.
+    pub fn median_time_past(&self, height: BlockHeight) -> Result<Option<u64>> {
+        let mut timestamps = Vec::with_capacity(self.median_time_past_window);
+        for past in (0..=height).rev().take(self.median_time_past_window) {
+            match self.storage.blocks().get_header(past)? {
+                Some((_, header)) => timestamps.push(header.timestamp),
+                None => break,
+            }
+        }
+        if timestamps.is_empty() {
+            return Ok(None);
+        }
+
+        timestamps.sort_unstable();
+        Ok(Some(timestamps[timestamps.len() / 2]))
+    }
+
+    /// Get the maximum serialized size allowed for a block at the given height
+    pub fn block_size_limit(&self, height: BlockHeight) -> Result<usize> {
+        match &self.dynamic_block_size {
+            Some(config) => config.limit_at_height(&self.storage, height),
+            None => Ok(1024 * 1024 * 10), // 10MB limit
+        }
     }
 
     /// Validate a complete block
@@ -60,21 +257,16 @@ impl Validator {
 
     /// Validate block structure and basic integrity
     fn validate_block_structure(&self, block: &Block) -> Result<()> {
-        // Check if block has transactions
-        if block.transactions.is_empty() {
-            return Err(anyhow!("Block must contain at least one transaction"));
-        }
-
-        // Check block size limits (simplified)
+        // Check block size limits
         let serialized = bincode::serialize(block)
             .map_err(|e| anyhow!("Failed to serialize block: {}", e))?;
         
-        if serialized.len() > 1024 * 1024 * 10 { // 10MB limit
+        if serialized.len() > self.block_size_limit(block.header.height)? {
             return Err(anyhow!("Block size exceeds maximum limit"));
         }
 
         // Verify block hash integrity
-        if !block.verify() {
+        if !block.verify_with(self.signature_scheme.as_ref()) {
             return Err(ValidationError::InvalidBlockHash.into());
         }
 
@@ -89,7 +281,7 @@ impl Validator {
             .unwrap()
             .as_millis() as u64;
         
-        if header.timestamp > current_time + 300_000 { // 5 minutes tolerance
+        if header.timestamp > current_time + MAX_FUTURE_BLOCK_TIME_MS {
             return Err(ValidationError::InvalidTimestamp(
                 "Block timestamp too far in future".to_string()
             ).into());
@@ -119,6 +311,8 @@ impl Validator {
     fn validate_block_transactions(&self, block: &Block) -> Result<()> {
         let mut seen_tx_hashes = HashSet::new();
         let mut account_nonces: std::collections::HashMap<Address, u64> = std::collections::HashMap::new();
+        // Balances as left by the transactions checked so far
+        let mut projected_balances: HashMap<Address, u64> = HashMap::new();
 
         for tx in &block.transactions {
             // Check for duplicate transactions within block
@@ -149,36 +343,53 @@ impl Validator {
 
             account_nonces.insert(tx.from, tx.nonce);
 
-            // Validate balance (simplified - doesn't account for all state changes)
-            let balance = self.storage.state().get_balance(&tx.from)?;
-            if balance < tx.amount + tx.fee {
-                return Err(ValidationError::InsufficientBalance.into());
-            }
+            // Spend from the projected balance so funds can't be used twice in one block
+            let total = tx.amount.checked_add(tx.fee)
+                .ok_or(ValidationError::InsufficientBalance)?;
+            let balance = self.projected_balance(&mut projected_balances, &tx.from)?;
+            *balance = balance.checked_sub(total)
+                .ok_or(ValidationError::InsufficientBalance)?;
+
+            // Later transactions can spend what earlier ones received, as when the block is applied
+            let recipient = self.projected_balance(&mut projected_balances, &tx.to)?;
+            *recipient = recipient.checked_add(tx.amount)
+                .ok_or_else(|| anyhow!("Balance overflow for transaction {}", tx.id))?;
         }
 
         Ok(())
     }
 
-    /// Validate individual transaction
-    pub fn validate_transaction(&self, tx: &Transaction) -> Result<()> {
-        // Basic transaction validation
-        if !tx.verify() {
-            return Err(ValidationError::InvalidSignature.into());
+    /// An account's balance after the block transactions validated so far
+    fn projected_balance<'a>(
+        &self,
+        balances: &'a mut HashMap<Address, u64>,
+        address: &Address,
+    ) -> Result<&'a mut u64> {
+        match balances.entry(*address) {
+            std::collections::hash_map::Entry::Occupied(entry) => Ok(entry.into_mut()),
+            std::collections::hash_map::Entry::Vacant(entry) => {
+                Ok(entry.insert(self.storage.state().get_balance(address)?))
+            }
         }
+    }
 
-        // Check transaction fields
-        if tx.amount == 0 && tx.data.is_empty() {
-            return Err(anyhow!("Transaction must transfer value or contain data"));
-        }
+    /// Validate individual transaction
+    pub fn validate_transaction(&self, tx: &Transaction) -> Result<()> {
+        // Checked first, so oversized data is turned away before it is hashed
+        self.validate_transaction_kind(tx)?;
 
         if tx.fee == 0 {
             return Err(anyhow!("Transaction must include fee"));
         }
 
-        if tx.from == tx.to && tx.data.is_empty() {
-            return Err(anyhow!("Self-transfer without data is not allowed"));
+        // Basic transaction validation
+        if !tx.verify_with(self.signature_scheme.as_ref()) {
+            return Err(ValidationError::InvalidSignature.into());
         }
 
+        // Permissioned chains only admit allowlisted senders
+        self.sender_allowlist.check(tx)?;
+
         // Validate timestamp (not too old or too far in future)
         let current_time = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
@@ -196,6 +407,42 @@ impl Validator {
         Ok(())
     }
 
+    /// Check the data limit and the rules of the transaction's kind
+    pub fn validate_transaction_kind(&self, tx: &Transaction) -> Result<()> {
+        if tx.data.len() > self.max_data_bytes {
+            return Err(ValidationError::TransactionValidation(format!(
+                "data is {} bytes, over the limit of {}", tx.data.len(), self.max_data_bytes
+            )).into());
+        }
+
+        match tx.kind() {
+            TransactionKind::Transfer => {
+                if tx.amount == 0 {
+                    return Err(anyhow!("Transfer must move a non-zero amount"));
+                }
+                if tx.from == tx.to {
+                    return Err(anyhow!("Self-transfer without data is not allowed"));
+                }
+            }
+            TransactionKind::DataCarrier => {
+                if tx.amount != 0 {
+                    return Err(ValidationError::TransactionValidation(format!(
+                        "data transaction must not move value, got amount {}", tx.amount
+                    )).into());
+                }
+            }
+            TransactionKind::ValidatorUpdate => {
+                if GovernanceAction::from_transaction(tx).is_none() {
+                    return Err(ValidationError::TransactionValidation(
+                        "validator update data is not a governance action".to_string()
+                    ).into());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     /// Validate merkle root
     fn validate_merkle_root(&self, block: &Block) -> Result<()> {
         let calculated_root = block.calculate_merkle_root();
@@ -214,8 +461,7 @@ impl Validator {
 
         // Get previous block
         let prev_height = block.header.height - 1;
-        let prev_block = self.storage.blocks().get_block(prev_height)?
-            .ok_or_else(|| anyhow!("Previous block not found"))?;
+        let prev_block = self.storage.blocks().require_block(prev_height)?;
 
         // Validate previous hash
         let prev_hash = prev_block.hash();
@@ -223,11 +469,15 @@ impl Validator {
             return Err(ValidationError::InvalidPreviousHash.into());
         }
 
-        // Validate timestamp ordering
-        if block.header.timestamp <= prev_block.header.timestamp {
-            return Err(ValidationError::InvalidTimestamp(
-                "Block timestamp must be greater than previous block".to_string()
-            ).into());
+        // Compared with the median of recent blocks, so one skewed timestamp can't
+        // move the lower bound; the upper bound is checked with the header
+        if let Some(median) = self.median_time_past(prev_height)? {
+            if block.header.timestamp <= median {
+                return Err(ValidationError::InvalidTimestamp(format!(
+                    "Block timestamp {} must be greater than the median of recent blocks {}",
+                    block.header.timestamp, median
+                )).into());
+            }
         }
 
         Ok(())
@@ -235,9 +485,11 @@ impl Validator {
 
     /// Validate transaction against current state
     pub fn validate_transaction_against_state(&self, tx: &Transaction) -> Result<()> {
-        // Check balance
+        // Check balance; a cost past u64::MAX is more than any balance
+        let total = tx.amount.checked_add(tx.fee)
+            .ok_or(ValidationError::InsufficientBalance)?;
         let balance = self.storage.state().get_balance(&tx.from)?;
-        if balance < tx.amount + tx.fee {
+        if balance < total {
             return Err(ValidationError::InsufficientBalance.into());
         }
 
@@ -253,6 +505,30 @@ impl Validator {
         Ok(())
     }
 
+    /// Validate a transaction that follows others from the same sender earlier in a batch
+    ///
+    /// `previous_nonce` is the nonce of the sender's preceding transaction and `spent`
+    /// the amount plus fees already committed to by the sender in the batch.
+    pub fn validate_transaction_in_sequence(&self, tx: &Transaction, previous_nonce: u64, spent: u64) -> Result<()> {
+        // A sum past u64::MAX is more than any balance
+        let total = spent.checked_add(tx.amount)
+            .and_then(|total| total.checked_add(tx.fee))
+            .ok_or(ValidationError::InsufficientBalance)?;
+        let balance = self.storage.state().get_balance(&tx.from)?;
+        if balance < total {
+            return Err(ValidationError::InsufficientBalance.into());
+        }
+
+        if tx.nonce != previous_nonce + 1 {
+            return Err(ValidationError::InvalidNonce {
+                expected: previous_nonce + 1,
+                actual: tx.nonce,
+            }.into());
+        }
+
+        Ok(())
+    }
+
     /// Validate a batch of transactions for mempool
     pub fn validate_transaction_batch(&self, transactions: &[Transaction]) -> Result<Vec<bool>> {
         let mut results = Vec::with_capacity(transactions.len());
@@ -268,30 +544,63 @@ impl Validator {
 
     /// Quick validation for mempool admission
     pub fn quick_validate_transaction(&self, tx: &Transaction) -> bool {
+        self.quick_check_transaction(tx).is_ok()
+    }
+
+    /// Quick validation for mempool admission, reporting why a transaction fails it
+    pub fn quick_check_transaction(&self, tx: &Transaction) -> Result<()> {
         // Fast validation without state checks
-        tx.verify() && 
-        tx.fee > 0 && 
-        (tx.amount > 0 || !tx.data.is_empty()) &&
-        tx.from != tx.to
+        self.validate_transaction_kind(tx)?;
+        if tx.fee == 0 {
+            return Err(anyhow!("Transaction must include fee"));
+        }
+        if !tx.verify_with(self.signature_scheme.as_ref()) {
+            return Err(ValidationError::InvalidSignature.into());
+        }
+        Ok(())
     }
 
-    /// Validate consensus signatures on block
-    pub fn validate_consensus_signatures(&self, block: &Block, validator_set: &[String]) -> Result<()> {
-        if block.signatures.is_empty() {
-            return Err(anyhow!("Block must have consensus signatures"));
+    /// Validate a block received already committed, such as from sync or a dump
+    ///
+    /// On top of `validate_block`, the block must carry commit signatures from a
+    /// quorum of the validator set stored at the head, which follows governance
+    /// changes, or of the configured validator keys if storage holds no set. With
+    /// neither every such block is rejected.
+    pub fn validate_committed_block(&self, block: &Block) -> Result<()> {
+        self.validate_block(block)?;
+        match self.storage.validator_set()? {
+            Some(validator_set) => self.validate_consensus_signatures(block, &validator_set.keys()),
+            None => self.validate_consensus_signatures(block, &self.validator_keys),
         }
+    }
 
-        // Check if we have enough signatures (2/3 + 1 for BFT)
-        let required_signatures = (validator_set.len() * 2 / 3) + 1;
-        if block.signatures.len() < required_signatures {
-            return Err(anyhow!("Insufficient consensus signatures"));
+    /// Validate the commit signatures on a block against the validator set's public keys
+    pub fn validate_consensus_signatures(&self, block: &Block, validator_keys: &HashMap<NodeId, PublicKey>) -> Result<()> {
+        if block.signatures.is_empty() {
+            return Err(anyhow!("Block must have consensus signatures"));
         }
 
-        // Validate each signature (simplified)
+        // Every signature must be a valid commit vote from a known validator
+        let payload = block.commit_payload();
+        let mut signers = HashSet::new();
         for sig in &block.signatures {
-            if !validator_set.contains(&sig.validator_id) {
-                return Err(anyhow!("Invalid validator signature"));
+            let public_key = validator_keys.get(&sig.validator_id)
+                .ok_or_else(|| anyhow!("Signature from unknown validator {}", sig.validator_id))?;
+
+            if sig.public_key != *public_key || !self.signature_scheme.verify(public_key, &payload, &sig.signature) {
+                return Err(anyhow!("Invalid consensus signature from {}", sig.validator_id));
             }
+            signers.insert(&sig.validator_id);
+        }
+
+        // Distinct signers must reach the BFT quorum (2/3 + 1)
+        let required_signatures = (validator_keys.len() * 2 / 3) + 1;
+        if signers.len() < required_signatures {
+            return Err(anyhow!(
+                "Insufficient consensus signatures: {} of {} required",
+                signers.len(),
+                required_signatures
+            ));
         }
 
         Ok(())
@@ -311,7 +620,8 @@ pub struct ValidationStats {
 mod tests {
     use super::*;
     use tempfile::TempDir;
-    use crate::types::{BlockHeader, ValidatorSignature};
+    use crate::types::{BlockHeader, ValidatorSignature, address_from_public_key};
+    use ed25519_dalek::SigningKey;
     use uuid::Uuid;
 
     fn create_test_storage() -> Storage {
@@ -319,10 +629,19 @@ mod tests {
         Storage::new(temp_dir.path()).unwrap()
     }
 
+    fn test_address(sender: u8) -> Address {
+        let signing_key = SigningKey::from_bytes(&[sender; 32]);
+        address_from_public_key(&signing_key.verifying_key().to_bytes())
+    }
+
     fn create_test_transaction() -> Transaction {
-        Transaction {
+        create_transaction_from(1)
+    }
+
+    fn create_transaction_from(sender: u8) -> Transaction {
+        let mut tx = Transaction {
             id: Uuid::new_v4(),
-            from: [1u8; 20],
+            from: test_address(sender),
             to: [2u8; 20],
             amount: 1000,
             fee: 10,
@@ -332,8 +651,11 @@ mod tests {
                 .unwrap()
                 .as_millis() as u64,
             signature: [0u8; 64],
+            public_key: [0u8; 32],
             data: vec![],
-        }
+        };
+        tx.sign(&SigningKey::from_bytes(&[sender; 32]));
+        tx
     }
 
     fn create_test_block() -> Block {
@@ -351,6 +673,7 @@ mod tests {
                 proposer: "test-node".to_string(),
                 round: 1,
                 view: 1,
+                vrf_proof: None,
             },
             transactions: vec![tx],
             signatures: vec![],
@@ -377,5 +700,295 @@ mod tests {
         
         let block = create_test_block();
         assert!(validator.validate_block_structure(&block).is_ok());
+
+        // Empty blocks are valid as long as they carry the empty merkle root
+        let mut empty = create_test_block();
+        empty.transactions.clear();
+        assert!(validator.validate_block_structure(&empty).is_err());
+        empty.header.merkle_root = empty.calculate_merkle_root();
+        assert_ne!(empty.header.merkle_root, [0; 32]);
+        assert!(validator.validate_block_structure(&empty).is_ok());
+    }
+
+    fn validator_keys() -> Vec<(String, SigningKey)> {
+        (1..=4)
+            .map(|i| (format!("validator-{}", i), SigningKey::from_bytes(&[100 + i as u8; 32])))
+            .collect()
+    }
+
+    fn commit_signature(block: &Block, validator_id: &str, key: &SigningKey) -> ValidatorSignature {
+        ValidatorSignature {
+            validator_id: validator_id.to_string(),
+            signature: crate::types::sign_vote(&crate::crypto::Ed25519Scheme, &key.to_bytes(), &block.hash(), block.header.view, block.header.round, &crate::types::VoteType::Commit),
+            public_key: key.verifying_key().to_bytes(),
+        }
+    }
+
+    #[test]
+    fn test_consensus_signatures() {
+        let validator = Validator::new(create_test_storage());
+        let keys = validator_keys();
+        let key_map: HashMap<NodeId, PublicKey> = keys.iter()
+            .map(|(id, key)| (id.clone(), key.verifying_key().to_bytes()))
+            .collect();
+
+        let mut block = create_test_block();
+        for (id, key) in keys.iter().take(3) {
+            block.signatures.push(commit_signature(&block, id, key));
+        }
+        assert!(validator.validate_consensus_signatures(&block, &key_map).is_ok());
+
+        // Two signatures, one of them repeated, fall short of the 3-of-4 quorum
+        let mut insufficient = block.clone();
+        insufficient.signatures.truncate(2);
+        insufficient.signatures.push(insufficient.signatures[1].clone());
+        assert!(validator.validate_consensus_signatures(&insufficient, &key_map).is_err());
+
+        // A signature by the wrong key, claiming to be validator-3
+        let mut forged = block.clone();
+        let (_, outsider) = &keys[3];
+        forged.signatures[2] = ValidatorSignature {
+            public_key: key_map["validator-3"],
+            ..commit_signature(&block, "validator-3", outsider)
+        };
+        assert!(validator.validate_consensus_signatures(&forged, &key_map).is_err());
+
+        // Valid signatures over a different round don't carry over
+        let mut replayed = block.clone();
+        replayed.header.round += 1;
+        assert!(validator.validate_consensus_signatures(&replayed, &key_map).is_err());
+    }
+
+    #[test]
+    fn test_sender_allowlist() {
+        let storage = create_test_storage();
+        let allowlist = SenderAllowlist::new(vec![test_address(1)]);
+        let validator = Validator::new(storage).with_sender_allowlist(allowlist);
+
+        let listed = create_test_transaction();
+        assert!(validator.validate_transaction(&listed).is_ok());
+
+        let unlisted = create_transaction_from(3);
+        assert!(validator.validate_transaction(&unlisted).is_err());
+
+        let mut block = create_test_block();
+        block.transactions = vec![unlisted];
+        block.header.merkle_root = block.calculate_merkle_root();
+        assert!(validator.validate_block_transactions(&block).is_err());
+    }
+
+    /// A transaction from sender 1 with `amount` and `data`, signed after they are set
+    fn create_transaction_with(amount: u64, data: Vec<u8>) -> Transaction {
+        let mut tx = create_test_transaction();
+        tx.amount = amount;
+        tx.data = data;
+        tx.sign(&SigningKey::from_bytes(&[1u8; 32]));
+        tx
+    }
+
+    #[test]
+    fn test_transaction_kinds_follow_their_own_rules() {
+        let validator = Validator::new(create_test_storage()).with_max_data_bytes(1024);
+        let update = GovernanceAction::AddValidator { node_id: "validator-5".to_string(), public_key: [5u8; 32] }.encode();
+
+        // Transfers move value to someone else
+        let transfer = create_transaction_with(1000, vec![]);
+        assert_eq!(transfer.kind(), TransactionKind::Transfer);
+        assert!(validator.validate_transaction(&transfer).is_ok());
+        assert!(validator.validate_transaction(&create_transaction_with(0, vec![])).is_err());
+
+        // Pure data moves nothing, even to the sender itself
+        let data = create_transaction_with(0, b"notarized document".to_vec());
+        assert_eq!(data.kind(), TransactionKind::DataCarrier);
+        assert!(validator.validate_transaction(&data).is_ok());
+        assert!(validator.quick_validate_transaction(&data));
+        let mut to_self = data.clone();
+        to_self.to = to_self.from;
+        to_self.sign(&SigningKey::from_bytes(&[1u8; 32]));
+        assert!(validator.validate_transaction(&to_self).is_ok());
+        let with_value = create_transaction_with(5, b"notarized document".to_vec());
+        assert!(validator.validate_transaction(&with_value).is_err());
+        assert!(!validator.quick_validate_transaction(&with_value));
+
+        // Governance actions may carry value but must decode
+        let validator_update = create_transaction_with(1, update);
+        assert_eq!(validator_update.kind(), TransactionKind::ValidatorUpdate);
+        assert!(validator.validate_transaction(&validator_update).is_ok());
+        let garbled = create_transaction_with(1, b"gov:garbage".to_vec());
+        assert_eq!(garbled.kind(), TransactionKind::ValidatorUpdate);
+        assert!(validator.validate_transaction(&garbled).is_err());
+    }
+
+    #[test]
+    fn test_oversized_data_is_rejected() {
+        let validator = Validator::new(create_test_storage()).with_max_data_bytes(1024);
+
+        assert!(validator.validate_transaction(&create_transaction_with(0, vec![7; 1024])).is_ok());
+        let oversized = create_transaction_with(0, vec![7; 1025]);
+        let error = validator.validate_transaction(&oversized).unwrap_err();
+        assert!(error.to_string().contains("over the limit of 1024"), "{}", error);
+        assert!(!validator.quick_validate_transaction(&oversized));
+
+        // A block carrying it is rejected as a whole
+        let mut block = create_test_block();
+        block.transactions = vec![oversized];
+        block.header.merkle_root = block.calculate_merkle_root();
+        assert!(validator.validate_block_transactions(&block).is_err());
+    }
+
+    #[test]
+    fn test_block_cannot_spend_the_same_balance_twice() {
+        let storage = create_test_storage();
+        let sender = test_address(1);
+        // Enough for either transfer of 1000 + 10 fee, not both
+        storage.state().set_balance(&sender, 1500).unwrap();
+        let validator = Validator::new(storage.clone());
+
+        let first = create_test_transaction();
+        let mut second = create_test_transaction();
+        second.nonce = 2;
+        second.sign(&SigningKey::from_bytes(&[1u8; 32]));
+        assert!(validator.validate_transaction_against_state(&first).is_ok());
+        assert!(validator.validate_transaction_in_sequence(&second, 1, 0).is_ok());
+
+        let mut block = create_test_block();
+        block.transactions = vec![first.clone(), second];
+        block.header.merkle_root = block.calculate_merkle_root();
+        let error = validator.validate_block_transactions(&block).unwrap_err();
+        assert!(matches!(error.downcast_ref::<ValidationError>(), Some(ValidationError::InsufficientBalance)));
+
+        // Funds received earlier in the block can be spent later in it
+        let recipient_key = SigningKey::from_bytes(&[2u8; 32]);
+        let mut forward = create_transaction_from(2);
+        forward.amount = 500;
+        forward.sign(&recipient_key);
+        let mut to_recipient = first;
+        to_recipient.to = test_address(2);
+        to_recipient.sign(&SigningKey::from_bytes(&[1u8; 32]));
+        block.transactions = vec![to_recipient, forward];
+        block.header.merkle_root = block.calculate_merkle_root();
+        assert!(validator.validate_block_transactions(&block).is_ok());
+    }
+
+    #[test]
+    fn test_overflowing_spend_is_insufficient_balance() {
+        let storage = create_test_storage();
+        storage.state().set_balance(&test_address(1), u64::MAX).unwrap();
+        let validator = Validator::new(storage);
+
+        // Already spent plus this transfer wraps past u64::MAX to look affordable
+        let mut tx = create_test_transaction();
+        tx.nonce = 2;
+        let error = validator.validate_transaction_in_sequence(&tx, 1, u64::MAX - 5).unwrap_err();
+        assert!(matches!(error.downcast_ref::<ValidationError>(), Some(ValidationError::InsufficientBalance)));
+
+        // As does a single transfer whose amount and fee overflow together
+        let mut tx = create_test_transaction();
+        tx.amount = u64::MAX;
+        let error = validator.validate_transaction_against_state(&tx).unwrap_err();
+        assert!(matches!(error.downcast_ref::<ValidationError>(), Some(ValidationError::InsufficientBalance)));
+    }
+
+    /// Store blocks at heights 0.. with the given timestamps, returning the last one
+    fn store_chain(storage: &Storage, timestamps: &[u64]) -> Block {
+        let mut previous_hash = [0; 32];
+        let mut last = None;
+        for (height, timestamp) in timestamps.iter().enumerate() {
+            let mut block = create_test_block();
+            block.header.height = height as BlockHeight;
+            block.header.previous_hash = previous_hash;
+            block.header.timestamp = *timestamp;
+            storage.store_block(&block).unwrap();
+            previous_hash = block.hash();
+            last = Some(block);
+        }
+        last.unwrap()
+    }
+
+    #[test]
+    fn test_block_timestamp_must_exceed_median_time_past() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = Storage::new(temp_dir.path()).unwrap();
+        // A leader pushed height 4 far ahead; the median of the last five is still 3000
+        let tip = store_chain(&storage, &[1000, 2000, 3000, 4000, 90_000]);
+        let validator = Validator::new(storage.clone()).with_median_time_past_window(5);
+        assert_eq!(validator.median_time_past(4).unwrap(), Some(3000));
+
+        let mut block = create_test_block();
+        block.header.height = 5;
+        block.header.previous_hash = tip.hash();
+
+        // Earlier than the previous block, but past the median
+        block.header.timestamp = 3001;
+        assert!(validator.validate_block_chain(&block).is_ok());
+
+        block.header.timestamp = 3000;
+        let error = validator.validate_block_chain(&block).unwrap_err();
+        assert!(matches!(error.downcast_ref::<ValidationError>(), Some(ValidationError::InvalidTimestamp(_))));
+
+        block.header.timestamp = 2500;
+        assert!(validator.validate_block_chain(&block).is_err());
+
+        // A window of one is the previous block's timestamp alone
+        let strict = Validator::new(storage).with_median_time_past_window(1);
+        block.header.timestamp = 3001;
+        assert!(strict.validate_block_chain(&block).is_err());
+        block.header.timestamp = 90_001;
+        assert!(strict.validate_block_chain(&block).is_ok());
+    }
+
+    #[test]
+    fn test_sender_allowlist_from_hex() {
+        let allowlist = SenderAllowlist::from_hex(&[
+            "0x1111111111111111111111111111111111111111".to_string(),
+        ]).unwrap();
+        assert!(allowlist.is_allowed(&[0x11u8; 20]));
+        assert!(!allowlist.is_allowed(&[0x22u8; 20]));
+
+        assert!(SenderAllowlist::from_hex(&["0x1234".to_string()]).is_err());
+
+        // Forty bytes of multi-byte characters, and a doubled prefix, are errors rather than panics
+        assert!(SenderAllowlist::from_hex(&["é".repeat(20)]).is_err());
+        assert!(SenderAllowlist::from_hex(&[format!("0x0x{}", "11".repeat(20))]).is_err());
+        assert!(SenderAllowlist::default().is_allowed(&[0x22u8; 20]));
+    }
+
+    #[test]
+    fn test_dynamic_block_size_grows_when_full() {
+        let config = DynamicBlockSizeConfig {
+            min_size: 1000,
+            max_size: 10_000,
+            window: 10,
+            growth_percent: 125,
+        };
+
+        // Simulate a chain where every block is filled to the current limit
+        let mut sizes: Vec<usize> = Vec::new();
+        let mut limits = Vec::new();
+        for _ in 0..60 {
+            let window_start = sizes.len().saturating_sub(config.window as usize);
+            let limit = config.limit_from_sizes(&sizes[window_start..]);
+            limits.push(limit);
+            sizes.push(limit);
+        }
+
+        assert_eq!(limits[0], config.min_size);
+        assert!(limits.windows(2).all(|pair| pair[1] >= pair[0]));
+        assert!(limits[30] > config.min_size);
+        assert_eq!(*limits.last().unwrap(), config.max_size);
+    }
+
+    #[test]
+    fn test_dynamic_block_size_shrinks_when_underused() {
+        let config = DynamicBlockSizeConfig {
+            min_size: 1000,
+            max_size: 10_000,
+            window: 10,
+            growth_percent: 125,
+        };
+
+        assert_eq!(config.limit_from_sizes(&[8000; 10]), config.max_size);
+        assert_eq!(config.limit_from_sizes(&[100; 10]), config.min_size);
+        assert_eq!(config.limit_from_sizes(&[4000; 10]), 5000);
     }
 }
@@ -0,0 +1,193 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use anyhow::{Result, anyhow};
+use tokio::sync::mpsc;
+use tracing::{debug, warn};
+
+use crate::api::BlockInfo;
+use crate::types::Block;
+
+/// Webhook notification configuration
+#[derive(Debug, Clone)]
+pub struct WebhookConfig {
+    pub url: String,
+    pub queue_size: usize,
+    pub timeout: Duration,
+}
+
+impl WebhookConfig {
+    pub fn new(url: String) -> Self {
+        Self {
+            url,
+            queue_size: 256,
+            timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Webhook delivery statistics
+#[derive(Debug, Clone, Default)]
+pub struct WebhookStats {
+    pub delivered: u64,
+    pub failed: u64,
+    pub dropped: u64,
+}
+
+#[derive(Debug, Default)]
+struct WebhookCounters {
+    delivered: AtomicU64,
+    failed: AtomicU64,
+    dropped: AtomicU64,
+}
+
+/// Best-effort notifier that POSTs committed blocks to a configured URL
+#[derive(Clone)]
+pub struct WebhookNotifier {
+    sender: mpsc::Sender<BlockInfo>,
+    counters: Arc<WebhookCounters>,
+}
+
+impl WebhookNotifier {
+    /// Start the delivery worker for the configured webhook
+    pub fn spawn(config: WebhookConfig) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(config.timeout)
+            .build()
+            .map_err(|e| anyhow!("Failed to build webhook client: {}", e))?;
+
+        let (sender, mut receiver) = mpsc::channel::<BlockInfo>(config.queue_size);
+        let counters = Arc::new(WebhookCounters::default());
+
+        let worker_counters = counters.clone();
+        tokio::spawn(async move {
+            while let Some(info) = receiver.recv().await {
+                let result = client.post(&config.url).json(&info).send().await
+                    .and_then(|response| response.error_for_status());
+
+                match result {
+                    Ok(_) => {
+                        worker_counters.delivered.fetch_add(1, Ordering::Relaxed);
+                        debug!("Webhook delivered for block {}", info.height);
+                    }
+                    Err(e) => {
+                        worker_counters.failed.fetch_add(1, Ordering::Relaxed);
+                        warn!("Webhook delivery failed for block {}: {}", info.height, e);
+                    }
+                }
+            }
+        });
+
+        Ok(Self { sender, counters })
+    }
+
+    /// Queue a committed block for delivery without waiting on the endpoint
+    pub fn notify(&self, block: &Block) {
+        if self.sender.try_send(BlockInfo::from_block(block)).is_err() {
+            self.counters.dropped.fetch_add(1, Ordering::Relaxed);
+            warn!("Webhook queue full, dropping notification for block {}", block.header.height);
+        }
+    }
+
+    /// Get webhook delivery statistics
+    pub fn stats(&self) -> WebhookStats {
+        WebhookStats {
+            delivered: self.counters.delivered.load(Ordering::Relaxed),
+            failed: self.counters.failed.load(Ordering::Relaxed),
+            dropped: self.counters.dropped.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::BlockHeader;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    fn create_test_block(height: u64) -> Block {
+        Block {
+            header: BlockHeader {
+                height,
+                previous_hash: [0; 32],
+                merkle_root: [0; 32],
+                state_root: [0; 32],
+                timestamp: 1_000,
+                proposer: "validator-1".to_string(),
+                round: 0,
+                view: 0,
+                vrf_proof: None,
+            },
+            transactions: vec![],
+            signatures: vec![],
+        }
+    }
+
+    async fn wait_for<F: Fn(&WebhookStats) -> bool>(notifier: &WebhookNotifier, condition: F) -> WebhookStats {
+        for _ in 0..100 {
+            let stats = notifier.stats();
+            if condition(&stats) {
+                return stats;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        notifier.stats()
+    }
+
+    #[tokio::test]
+    async fn test_webhook_fires_on_commit() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0u8; 4096];
+            let n = socket.read(&mut buf).await.unwrap();
+            socket.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n").await.unwrap();
+            String::from_utf8_lossy(&buf[..n]).to_string()
+        });
+
+        let notifier = WebhookNotifier::spawn(WebhookConfig::new(url)).unwrap();
+        notifier.notify(&create_test_block(7));
+
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /hook"));
+        assert!(request.contains("\"height\":7"));
+
+        let stats = wait_for(&notifier, |stats| stats.delivered == 1).await;
+        assert_eq!(stats.delivered, 1);
+        assert_eq!(stats.failed, 0);
+    }
+
+    #[tokio::test]
+    async fn test_failing_webhook_does_not_block() {
+        // Accept connections but never answer, so every delivery times out
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut sockets = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                sockets.push(socket);
+            }
+        });
+
+        let config = WebhookConfig {
+            url,
+            queue_size: 2,
+            timeout: Duration::from_millis(100),
+        };
+        let notifier = WebhookNotifier::spawn(config).unwrap();
+
+        let start = std::time::Instant::now();
+        for height in 0..10 {
+            notifier.notify(&create_test_block(height));
+        }
+        assert!(start.elapsed() < Duration::from_millis(100));
+
+        let stats = wait_for(&notifier, |stats| stats.failed > 0).await;
+        assert!(stats.failed > 0);
+        assert!(stats.dropped > 0);
+        assert_eq!(stats.delivered, 0);
+    }
+}